@@ -0,0 +1,100 @@
+//! COM initialization for the threads that use this crate.
+
+use std::cell::RefCell;
+use std::marker::PhantomData;
+
+use windows::Win32::{
+    Foundation::RPC_E_CHANGED_MODE,
+    System::Com::{
+        CoInitializeEx, CoUninitialize, COINIT, COINIT_APARTMENTTHREADED, COINIT_MULTITHREADED,
+    },
+};
+
+/// The concurrency model that COM is initialized with on a thread.
+///
+/// See also: [`CoInitializeEx`](https://docs.microsoft.com/en-us/windows/win32/api/combaseapi/nf-combaseapi-coinitializeex)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ComApartment {
+    /// A single-threaded apartment (`COINIT_APARTMENTTHREADED`), as used by
+    /// UI threads.
+    SingleThreaded,
+    /// The multithreaded apartment (`COINIT_MULTITHREADED`).
+    MultiThreaded,
+}
+
+impl ComApartment {
+    fn to_raw(self) -> COINIT {
+        match self {
+            Self::SingleThreaded => COINIT_APARTMENTTHREADED,
+            Self::MultiThreaded => COINIT_MULTITHREADED,
+        }
+    }
+
+    /// Initialize COM on the current thread for as long as the returned guard
+    /// is alive.
+    ///
+    /// A thread that was already initialized with a different concurrency
+    /// model is tolerated: COM stays usable, and the guard leaves the existing
+    /// initialization alone when dropped.
+    pub fn initialize(self) -> crate::Result<ComGuard> {
+        match unsafe { CoInitializeEx(None, self.to_raw()) } {
+            Ok(()) => Ok(ComGuard::new(true)),
+            Err(error) if error.code() == RPC_E_CHANGED_MODE => Ok(ComGuard::new(false)),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
+
+/// Keeps COM initialized on the current thread.
+///
+/// Dropping the guard balances the initialization it performed with
+/// `CoUninitialize`. The guard is bound to the thread that created it.
+///
+/// See also: [`CoUninitialize`](https://docs.microsoft.com/en-us/windows/win32/api/combaseapi/nf-combaseapi-couninitialize)
+#[derive(Debug)]
+#[must_use = "COM will be uninitialized when the guard is dropped"]
+pub struct ComGuard {
+    uninitialize: bool,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl ComGuard {
+    fn new(uninitialize: bool) -> Self {
+        Self {
+            uninitialize,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Whether the thread was already initialized with a different
+    /// concurrency model than the one that was requested.
+    pub fn is_changed_mode(&self) -> bool {
+        !self.uninitialize
+    }
+}
+
+impl Drop for ComGuard {
+    fn drop(&mut self) {
+        if self.uninitialize {
+            unsafe { CoUninitialize() };
+        }
+    }
+}
+
+/// Make sure this is called at every static entrypoint to this crate.
+///
+/// COM is initialized in the multithreaded apartment unless the thread is
+/// already initialized. The guard is kept in a thread-local, so the
+/// initialization is balanced when the thread exits.
+pub(crate) fn ensure_thread_init() -> crate::Result<()> {
+    thread_local! {
+        static GUARD: RefCell<Option<ComGuard>> = const { RefCell::new(None) };
+    }
+    GUARD.with(|guard| {
+        let mut guard = guard.borrow_mut();
+        if guard.is_none() {
+            *guard = Some(ComApartment::MultiThreaded.initialize()?);
+        }
+        Ok(())
+    })
+}
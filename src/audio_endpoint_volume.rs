@@ -1,116 +1,81 @@
-use windows::core::GUID;
+use std::rc::Rc;
 
-use windows::Win32::Media::Audio::Endpoints::IAudioEndpointVolume;
-use windows::Win32::Media::Audio::Endpoints::IAudioEndpointVolumeCallback;
+use windows::core::GUID;
 
 use crate::{
-    audio_endpoint_volume_callback::{
-        AudioEndpointVolumeCallback, AudioEndpointVolumeCallbackWrapper,
-    },
+    audio_endpoint_volume_callback::AudioEndpointVolumeCallback,
+    backend::{AudioEndpointVolumeBackend, CallbackRegistration},
     bits::HardwareSupportMask,
-    device::Activate,
-    util::as_raw_or_null,
 };
 
-
 /// See also: [`IAudioEndpointVolume`](https://docs.microsoft.com/en-us/windows/win32/api/endpointvolume/nn-endpointvolume-iaudioendpointvolume)
 #[derive(Debug, Clone)]
 pub struct AudioEndpointVolume {
-    inner: IAudioEndpointVolume,
+    inner: Rc<dyn AudioEndpointVolumeBackend>,
 }
 
-impl Activate for AudioEndpointVolume {
-    type Raw = IAudioEndpointVolume;
-
-    fn from_raw(inner: Self::Raw) -> Self {
+impl AudioEndpointVolume {
+    pub(crate) fn new(inner: Rc<dyn AudioEndpointVolumeBackend>) -> Self {
         Self { inner }
     }
-}
 
-impl AudioEndpointVolume {
     /// See also: [`IAudioEndpointVolume::GetChannelCount`](https://docs.microsoft.com/en-us/windows/win32/api/endpointvolume/nf-endpointvolume-iaudioendpointvolume-getchannelcount)
-    pub fn get_channel_count(&self) -> windows::core::Result<u32> {
-        unsafe { self.inner.GetChannelCount() }
+    pub fn get_channel_count(&self) -> crate::Result<u32> {
+        self.inner.get_channel_count()
     }
 
     /// See also: [`IAudioEndpointVolume::GetChannelVolumeLevel`](https://docs.microsoft.com/en-us/windows/win32/api/endpointvolume/nf-endpointvolume-iaudioendpointvolume-getchannelvolumelevel)
-    pub fn get_channel_volume_level(&self, channel: u32) -> windows::core::Result<f32> {
-        unsafe { self.inner.GetChannelVolumeLevel(channel) }
+    pub fn get_channel_volume_level(&self, channel: u32) -> crate::Result<f32> {
+        self.inner.get_channel_volume_level(channel)
     }
 
     /// See also: [`IAudioEndpointVolume::GetChannelVolumeLevelScalar`](https://docs.microsoft.com/en-us/windows/win32/api/endpointvolume/nf-endpointvolume-iaudioendpointvolume-getchannelvolumelevelscalar)
-    pub fn get_channel_volume_level_scalar(&self, channel: u32) -> windows::core::Result<f32> {
-        unsafe { self.inner.GetChannelVolumeLevelScalar(channel) }
+    pub fn get_channel_volume_level_scalar(&self, channel: u32) -> crate::Result<f32> {
+        self.inner.get_channel_volume_level_scalar(channel)
     }
 
     /// See also: [`IAudioEndpointVolume::GetMasterVolumeLevel`](https://docs.microsoft.com/en-us/windows/win32/api/endpointvolume/nf-endpointvolume-iaudioendpointvolume-getmastervolumelevel)
-    pub fn get_master_volume_level(&self) -> windows::core::Result<f32> {
-        unsafe { self.inner.GetMasterVolumeLevel() }
+    pub fn get_master_volume_level(&self) -> crate::Result<f32> {
+        self.inner.get_master_volume_level()
     }
 
     /// See also: [`IAudioEndpointVolume::GetMasterVolumeLevelScalar`](https://docs.microsoft.com/en-us/windows/win32/api/endpointvolume/nf-endpointvolume-iaudioendpointvolume-getmastervolumelevelscalar)
-    pub fn get_master_volume_level_scalar(&self) -> windows::core::Result<f32> {
-        unsafe { self.inner.GetMasterVolumeLevelScalar() }
+    pub fn get_master_volume_level_scalar(&self) -> crate::Result<f32> {
+        self.inner.get_master_volume_level_scalar()
     }
 
     /// See also: [`IAudioEndpointVolume::GetMute`](https://docs.microsoft.com/en-us/windows/win32/api/endpointvolume/nf-endpointvolume-iaudioendpointvolume-getmute)
-    pub fn get_mute(&self) -> windows::core::Result<bool> {
-        unsafe { self.inner.GetMute().map(Into::into) }
+    pub fn get_mute(&self) -> crate::Result<bool> {
+        self.inner.get_mute()
     }
 
     /// See also: [`IAudioEndpointVolume::GetVolumeRange`](https://docs.microsoft.com/en-us/windows/win32/api/endpointvolume/nf-endpointvolume-iaudioendpointvolume-getvolumerange)
-    pub fn get_volume_range(&self) -> windows::core::Result<VolumeRange> {
-        let mut volume_range = VolumeRange {
-            min_db: 0.0,
-            max_db: 0.0,
-            increment_db: 0.0,
-        };
-        unsafe {
-            self.inner.GetVolumeRange(
-                &mut volume_range.min_db,
-                &mut volume_range.max_db,
-                &mut volume_range.increment_db,
-            )?
-        };
-        Ok(volume_range)
+    pub fn get_volume_range(&self) -> crate::Result<VolumeRange> {
+        self.inner.get_volume_range()
     }
 
     /// See also: [`IAudioEndpointVolume::GetVolumeStepInfo`](https://docs.microsoft.com/en-us/windows/win32/api/endpointvolume/nf-endpointvolume-iaudioendpointvolume-getvolumestepinfo)
-    pub fn get_volume_step_info(&self) -> windows::core::Result<VolumeStepInfo> {
-        let mut volume_step_info = VolumeStepInfo {
-            current_step: 0,
-            num_steps: 0,
-        };
-        unsafe {
-            self.inner.GetVolumeStepInfo(
-                &mut volume_step_info.current_step,
-                &mut volume_step_info.num_steps,
-            )?
-        };
-        Ok(volume_step_info)
+    pub fn get_volume_step_info(&self) -> crate::Result<VolumeStepInfo> {
+        self.inner.get_volume_step_info()
     }
 
     /// See also: [`IAudioEndpointVolume::QueryHardwareSupport`](https://docs.microsoft.com/en-us/windows/win32/api/endpointvolume/nf-endpointvolume-iaudioendpointvolume-queryhardwaresupport)
-    pub fn query_hardware_support(&self) -> windows::core::Result<HardwareSupportMask> {
-        let raw = unsafe { self.inner.QueryHardwareSupport()? };
-        Ok(HardwareSupportMask::from_bits(raw).expect("invalid mask"))
+    pub fn query_hardware_support(&self) -> crate::Result<HardwareSupportMask> {
+        self.inner.query_hardware_support()
     }
 
     /// See also: [`IAudioEndpointVolume::RegisterControlChangeNotify`](https://docs.microsoft.com/en-us/windows/win32/api/endpointvolume/nf-endpointvolume-iaudioendpointvolume-registercontrolchangenotify)
     pub fn register_control_change_notify<T>(
         &self,
         callback: T,
-    ) -> windows::core::Result<AudioEndpointVolumeCallbackHandle>
+    ) -> crate::Result<AudioEndpointVolumeCallbackHandle>
     where
         T: AudioEndpointVolumeCallback,
     {
-        let callback =
-            IAudioEndpointVolumeCallback::from(AudioEndpointVolumeCallbackWrapper::new(callback));
-        unsafe { self.inner.RegisterControlChangeNotify(&callback).unwrap() };
-        Ok(AudioEndpointVolumeCallbackHandle {
-            inner: callback,
-            parent: self.inner.clone(),
-        })
+        let inner = self
+            .inner
+            .register_control_change_notify(Box::new(callback))?;
+        Ok(AudioEndpointVolumeCallbackHandle { inner })
     }
 
     /// See also: [`IAudioEndpointVolume::SetChannelVolumeLevel`](https://docs.microsoft.com/en-us/windows/win32/api/endpointvolume/nf-endpointvolume-iaudioendpointvolume-setchannelvolumelevel)
@@ -119,11 +84,9 @@ impl AudioEndpointVolume {
         channel: u32,
         level_db: f32,
         event_context: Option<&GUID>,
-    ) -> windows::core::Result<()> {
-        unsafe {
-            self.inner
-                .SetChannelVolumeLevel(channel, level_db, as_raw_or_null(event_context))
-        }
+    ) -> crate::Result<()> {
+        self.inner
+            .set_channel_volume_level(channel, level_db, event_context)
     }
 
     /// See also: [`IAudioEndpointVolume::SetChannelVolumeLevelScalar`](https://docs.microsoft.com/en-us/windows/win32/api/endpointvolume/nf-endpointvolume-iaudioendpointvolume-setchannelvolumelevelscalar)
@@ -132,11 +95,9 @@ impl AudioEndpointVolume {
         channel: u32,
         level: f32,
         event_context: Option<&GUID>,
-    ) -> windows::core::Result<()> {
-        unsafe {
-            self.inner
-                .SetChannelVolumeLevelScalar(channel, level, as_raw_or_null(event_context))
-        }
+    ) -> crate::Result<()> {
+        self.inner
+            .set_channel_volume_level_scalar(channel, level, event_context)
     }
 
     /// See also: [`IAudioEndpointVolume::SetMasterVolumeLevel`](https://docs.microsoft.com/en-us/windows/win32/api/endpointvolume/nf-endpointvolume-iaudioendpointvolume-setmastervolumelevel)
@@ -144,11 +105,8 @@ impl AudioEndpointVolume {
         &self,
         level_db: f32,
         event_context: Option<&GUID>,
-    ) -> windows::core::Result<()> {
-        unsafe {
-            self.inner
-                .SetMasterVolumeLevel(level_db, as_raw_or_null(event_context))
-        }
+    ) -> crate::Result<()> {
+        self.inner.set_master_volume_level(level_db, event_context)
     }
 
     /// See also: [`IAudioEndpointVolume::SetMasterVolumeLevelScalar`](https://docs.microsoft.com/en-us/windows/win32/api/endpointvolume/nf-endpointvolume-iaudioendpointvolume-setmastervolumelevelscalar)
@@ -156,37 +114,29 @@ impl AudioEndpointVolume {
         &self,
         level: f32,
         event_context: Option<&GUID>,
-    ) -> windows::core::Result<()> {
-        unsafe {
-            self.inner
-                .SetMasterVolumeLevelScalar(level, as_raw_or_null(event_context))
-        }
+    ) -> crate::Result<()> {
+        self.inner
+            .set_master_volume_level_scalar(level, event_context)
     }
 
     /// See also: [`IAudioEndpointVolume::SetMute`](https://docs.microsoft.com/en-us/windows/win32/api/endpointvolume/nf-endpointvolume-iaudioendpointvolume-setmute)
-    pub fn set_mute(&self, mute: bool, event_context: Option<&GUID>) -> windows::core::Result<()> {
-        unsafe {
-            self.inner.SetMute(
-                mute,
-                event_context
-                    .map(|x| x as *const _)
-                    .unwrap_or(std::ptr::null()),
-            )
-        }
+    pub fn set_mute(&self, mute: bool, event_context: Option<&GUID>) -> crate::Result<()> {
+        self.inner.set_mute(mute, event_context)
     }
 
     /// See also: [`IAudioEndpointVolume::VolumeStepDown`](https://docs.microsoft.com/en-us/windows/win32/api/endpointvolume/nf-endpointvolume-iaudioendpointvolume-volumestepdown)
-    pub fn volume_step_down(&self, event_context: Option<&GUID>) -> windows::core::Result<()> {
-        unsafe { self.inner.VolumeStepDown(as_raw_or_null(event_context)) }
+    pub fn volume_step_down(&self, event_context: Option<&GUID>) -> crate::Result<()> {
+        self.inner.volume_step_down(event_context)
     }
 
     /// See also: [`IAudioEndpointVolume::VolumeStepUp`](https://docs.microsoft.com/en-us/windows/win32/api/endpointvolume/nf-endpointvolume-iaudioendpointvolume-volumestepup)
-    pub fn volume_step_up(&self, event_context: Option<&GUID>) -> windows::core::Result<()> {
-        unsafe { self.inner.VolumeStepUp(as_raw_or_null(event_context)) }
+    pub fn volume_step_up(&self, event_context: Option<&GUID>) -> crate::Result<()> {
+        self.inner.volume_step_up(event_context)
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[non_exhaustive]
 pub struct VolumeRange {
     pub min_db: f32,
@@ -194,28 +144,41 @@ pub struct VolumeRange {
     pub increment_db: f32,
 }
 
+impl VolumeRange {
+    pub fn new(min_db: f32, max_db: f32, increment_db: f32) -> Self {
+        Self {
+            min_db,
+            max_db,
+            increment_db,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[non_exhaustive]
 pub struct VolumeStepInfo {
     pub current_step: u32,
     pub num_steps: u32,
 }
 
-#[derive(Debug, Clone)]
+impl VolumeStepInfo {
+    pub fn new(current_step: u32, num_steps: u32) -> Self {
+        Self {
+            current_step,
+            num_steps,
+        }
+    }
+}
+
+#[derive(Debug)]
 #[must_use = "callback will be unregistered when the handle is dropped"]
 pub struct AudioEndpointVolumeCallbackHandle {
-    inner: IAudioEndpointVolumeCallback,
-    parent: IAudioEndpointVolume,
+    inner: Rc<dyn CallbackRegistration>,
 }
 
 impl AudioEndpointVolumeCallbackHandle {
     pub fn unregister(self) {
-        // Don't have to do anything, handled by the Drop impl
-    }
-}
-
-impl Drop for AudioEndpointVolumeCallbackHandle {
-    fn drop(&mut self) {
-        unsafe { self.parent.UnregisterControlChangeNotify(&self.inner).ok() };
+        drop(self.inner);
     }
 }
@@ -1,9 +1,9 @@
-use std::convert::TryInto;
 use std::slice;
 
+use crate::Result;
 use windows::core::implement;
-use windows::core::Result;
 use windows::core::GUID;
+use windows::Win32::Foundation::E_POINTER;
 use windows::Win32::Media::Audio::Endpoints::IAudioEndpointVolumeCallback;
 use windows::Win32::Media::Audio::Endpoints::IAudioEndpointVolumeCallback_Impl;
 use windows::Win32::Media::Audio::AUDIO_VOLUME_NOTIFICATION_DATA;
@@ -17,8 +17,26 @@ pub trait AudioEndpointVolumeCallback: 'static {
     }
 }
 
+impl<F> AudioEndpointVolumeCallback for F
+where
+    F: Fn(&NotificationData) + 'static,
+{
+    fn on_notify(&self, data: &NotificationData) -> Result<()> {
+        self(data);
+        Ok(())
+    }
+}
+
+/// With the `serde` feature, this can be serialized but not deserialized,
+/// because the channel volumes are borrowed from the notification. Convert
+/// it to an [`OwnedNotificationData`] to keep it or to deserialize it.
 #[non_exhaustive]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct NotificationData<'a> {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::guid::serialize_guid")
+    )]
     pub event_context: GUID,
     pub muted: bool,
     pub master_volume: f32,
@@ -32,13 +50,56 @@ impl<'a> NotificationData<'a> {
             muted: raw.bMuted.into(),
             master_volume: raw.fMasterVolume,
             channel_volumes: unsafe {
-                slice::from_raw_parts(
-                    raw.afChannelVolumes.as_ptr(),
-                    raw.nChannels.try_into().unwrap(),
-                )
+                slice::from_raw_parts(raw.afChannelVolumes.as_ptr(), raw.nChannels as usize)
             },
         }
     }
+
+    /// Copy the notification, so that it can outlive the callback.
+    pub fn to_owned_data(&self) -> OwnedNotificationData {
+        OwnedNotificationData {
+            event_context: self.event_context,
+            muted: self.muted,
+            master_volume: self.master_volume,
+            channel_volumes: self.channel_volumes.to_vec(),
+        }
+    }
+}
+
+/// An owned copy of [`NotificationData`], which can also be deserialized.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OwnedNotificationData {
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::guid::serialize_guid",
+            deserialize_with = "crate::guid::deserialize_guid"
+        )
+    )]
+    pub event_context: GUID,
+    pub muted: bool,
+    pub master_volume: f32,
+    pub channel_volumes: Vec<f32>,
+}
+
+impl OwnedNotificationData {
+    /// Borrow the notification, for example to replay it into an
+    /// [`AudioEndpointVolumeCallback`].
+    pub fn as_data(&self) -> NotificationData<'_> {
+        NotificationData {
+            event_context: self.event_context,
+            muted: self.muted,
+            master_volume: self.master_volume,
+            channel_volumes: &self.channel_volumes,
+        }
+    }
+}
+
+impl From<&NotificationData<'_>> for OwnedNotificationData {
+    fn from(data: &NotificationData<'_>) -> Self {
+        data.to_owned_data()
+    }
 }
 
 #[implement(IAudioEndpointVolumeCallback)]
@@ -47,13 +108,8 @@ pub(crate) struct AudioEndpointVolumeCallbackWrapper {
 }
 
 impl AudioEndpointVolumeCallbackWrapper {
-    pub(crate) fn new<T>(inner: T) -> Self
-    where
-        T: AudioEndpointVolumeCallback,
-    {
-        Self {
-            inner: Box::new(inner),
-        }
+    pub(crate) fn new(inner: Box<dyn AudioEndpointVolumeCallback>) -> Self {
+        Self { inner }
     }
 }
 
@@ -69,8 +125,10 @@ impl AudioEndpointVolumeCallbackWrapper {
 // Impl IMMNotificationClient
 #[allow(non_snake_case)]
 impl IAudioEndpointVolumeCallback_Impl for AudioEndpointVolumeCallbackWrapper {
-    fn OnNotify(&self, data: *mut AUDIO_VOLUME_NOTIFICATION_DATA) -> Result<()> {
+    fn OnNotify(&self, data: *mut AUDIO_VOLUME_NOTIFICATION_DATA) -> windows::core::Result<()> {
+        let data = unsafe { data.as_ref() }.ok_or(E_POINTER)?;
         self.inner
-            .on_notify(unsafe { &NotificationData::from_raw(&*data) })
+            .on_notify(unsafe { &NotificationData::from_raw(data) })
+            .map_err(Into::into)
     }
 }
@@ -1,75 +1,58 @@
 use crate::{
-    audio_session_events::{AudioSessionEvents, AudioSessionEventsWrapper},
+    audio_session_events::AudioSessionEvents,
+    backend::{AudioSessionControlBackend, CallbackRegistration},
     bits::AudioSessionState,
     string::{WinStr, WinString},
-    util::as_raw_or_null,
     SimpleAudioVolume,
 };
 use std::ops::Deref;
+use std::rc::Rc;
 
-use windows::core::ComInterface;
-use windows::{
-    core::GUID,
-    Win32::Media::Audio::{IAudioSessionControl, IAudioSessionControl2, IAudioSessionEvents},
-};
+use windows::core::GUID;
 
 /// See also: [`IAudioSessionControl`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nn-audiopolicy-iaudiosessioncontrol)
 #[derive(Debug, Clone)]
 pub struct AudioSessionControl {
-    inner: IAudioSessionControl,
+    inner: Rc<dyn AudioSessionControlBackend>,
 }
 
 impl AudioSessionControl {
-    pub(crate) fn new(inner: IAudioSessionControl) -> Self {
+    pub(crate) fn new(inner: Rc<dyn AudioSessionControlBackend>) -> Self {
         Self { inner }
     }
 
     /// See also: [`IAudioSessionControl::GetDisplayName`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessioncontrol-getdisplayname)
-    pub fn get_display_name(&self) -> windows::core::Result<WinString> {
-        unsafe {
-            self.inner
-                .GetDisplayName()
-                .map(|x| WinString::from_com_pwstr(x))
-        }
+    pub fn get_display_name(&self) -> crate::Result<WinString> {
+        self.inner.get_display_name()
     }
 
     /// See also: [`IAudioSessionControl::GetGroupingParam`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessioncontrol-getgroupingparam)
-    pub fn get_grouping_param(&self) -> windows::core::Result<GUID> {
-        unsafe { self.inner.GetGroupingParam() }
+    pub fn get_grouping_param(&self) -> crate::Result<GUID> {
+        self.inner.get_grouping_param()
     }
 
     /// See also: [`IAudioSessionControl::GetIconPath`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessioncontrol-geticonpath)
-    pub fn get_icon_path(&self) -> windows::core::Result<WinString> {
-        unsafe {
-            self.inner
-                .GetIconPath()
-                .map(|x| WinString::from_com_pwstr(x))
-        }
+    pub fn get_icon_path(&self) -> crate::Result<WinString> {
+        self.inner.get_icon_path()
     }
 
     /// See also: [`IAudioSessionControl::GetState`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessioncontrol-getstate)
-    pub fn get_state(&self) -> windows::core::Result<AudioSessionState> {
-        unsafe { self.inner.GetState().map(AudioSessionState::from_raw) }
+    pub fn get_state(&self) -> crate::Result<AudioSessionState> {
+        self.inner.get_state()
     }
 
     /// See also: [`IAudioSessionControl::RegisterAudioSessionNotification`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessioncontrol-registeraudiosessionnotification)
     pub fn register_audio_session_notification<T>(
         &self,
         session_notification: T,
-    ) -> windows::core::Result<AudioSessionEventsHandle>
+    ) -> crate::Result<AudioSessionEventsHandle>
     where
         T: AudioSessionEvents,
     {
-        let session_notification =
-            IAudioSessionEvents::from(AudioSessionEventsWrapper::new(session_notification));
-        unsafe {
-            self.inner
-                .RegisterAudioSessionNotification(&session_notification)?
-        };
-        Ok(AudioSessionEventsHandle {
-            inner: session_notification,
-            parent: self.inner.clone(),
-        })
+        let inner = self
+            .inner
+            .register_audio_session_notification(Box::new(session_notification))?;
+        Ok(AudioSessionEventsHandle { inner })
     }
 
     /// See also: [`IAudioSessionControl::SetDisplayName`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessioncontrol-setdisplayname)
@@ -77,11 +60,8 @@ impl AudioSessionControl {
         &self,
         value: &WinStr,
         event_context: Option<&GUID>,
-    ) -> windows::core::Result<()> {
-        unsafe {
-            self.inner
-                .SetDisplayName(value.as_pcwstr(), as_raw_or_null(event_context))
-        }
+    ) -> crate::Result<()> {
+        self.inner.set_display_name(value, event_context)
     }
 
     /// See also: [`IAudioSessionControl::SetGroupingParam`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessioncontrol-setgroupingparam)
@@ -89,78 +69,61 @@ impl AudioSessionControl {
         &self,
         value: &GUID,
         event_context: Option<&GUID>,
-    ) -> windows::core::Result<()> {
-        unsafe {
-            self.inner
-                .SetGroupingParam(value, as_raw_or_null(event_context))
-        }
+    ) -> crate::Result<()> {
+        self.inner.set_grouping_param(value, event_context)
     }
 
     /// See also: [`IAudioSessionControl::SetIconPath`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessioncontrol-seticonpath)
-    pub fn set_icon_path(
-        &self,
-        value: &WinStr,
-        event_context: Option<&GUID>,
-    ) -> windows::core::Result<()> {
-        unsafe {
-            self.inner
-                .SetIconPath(value.as_pcwstr(), as_raw_or_null(event_context))
-        }
+    pub fn set_icon_path(&self, value: &WinStr, event_context: Option<&GUID>) -> crate::Result<()> {
+        self.inner.set_icon_path(value, event_context)
     }
 
-    pub fn upgrade(&self) -> windows::core::Result<AudioSessionControl2> {
-        self.inner.cast().map(AudioSessionControl2::new)
+    pub fn upgrade(&self) -> crate::Result<AudioSessionControl2> {
+        Ok(AudioSessionControl2::new(self.inner.clone()))
     }
 
-    pub fn get_simple_audio_volume(&self) -> windows::core::Result<SimpleAudioVolume> {
-        self.inner.cast().map(SimpleAudioVolume::new)
+    pub fn get_simple_audio_volume(&self) -> crate::Result<SimpleAudioVolume> {
+        self.inner
+            .get_simple_audio_volume()
+            .map(SimpleAudioVolume::new)
     }
 }
 
 /// See also: [`IAudioSessionControl2`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nn-audiopolicy-iaudiosessioncontrol2)
 #[derive(Debug, Clone)]
 pub struct AudioSessionControl2 {
-    inner: IAudioSessionControl2,
     downgrade: AudioSessionControl,
 }
 
 impl AudioSessionControl2 {
-    pub(crate) fn new(inner: IAudioSessionControl2) -> Self {
-        let downgrade = AudioSessionControl::new(inner.cast().unwrap());
-        Self { inner, downgrade }
+    pub(crate) fn new(inner: Rc<dyn AudioSessionControlBackend>) -> Self {
+        let downgrade = AudioSessionControl::new(inner);
+        Self { downgrade }
     }
 
     /// See also: [`IAudioSessionControl2::GetProcessId`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessioncontrol2-getprocessid)
-    pub fn get_process_id(&self) -> windows::core::Result<u32> {
-        unsafe { self.inner.GetProcessId() }
+    pub fn get_process_id(&self) -> crate::Result<u32> {
+        self.downgrade.inner.get_process_id()
     }
 
     /// See also: [`IAudioSessionControl2::GetSessionIdentifier`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessioncontrol2-getsessionidentifier)
-    pub fn get_session_identifier(&self) -> windows::core::Result<WinString> {
-        unsafe {
-            self.inner
-                .GetSessionIdentifier()
-                .map(|x| WinString::from_com_pwstr(x))
-        }
+    pub fn get_session_identifier(&self) -> crate::Result<WinString> {
+        self.downgrade.inner.get_session_identifier()
     }
 
     /// See also: [`IAudioSessionControl2::GetSessionInstanceIdentifier`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessioncontrol2-getsessioninstanceidentifier)
-    pub fn get_session_instance_identifier(&self) -> windows::core::Result<WinString> {
-        unsafe {
-            self.inner
-                .GetSessionInstanceIdentifier()
-                .map(|x| WinString::from_com_pwstr(x))
-        }
+    pub fn get_session_instance_identifier(&self) -> crate::Result<WinString> {
+        self.downgrade.inner.get_session_instance_identifier()
     }
 
     /// See also: [`IAudioSessionControl2::IsSystemSoundsSession`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessioncontrol2-issystemsoundssession)
-    pub fn is_system_sounds_session(&self) -> bool {
-        todo!("cannot query IsSystemSoundsSession yet, see https://github.com/microsoft/windows-rs/issues/1065")
+    pub fn is_system_sounds_session(&self) -> crate::Result<bool> {
+        self.downgrade.inner.is_system_sounds_session()
     }
 
     /// See also: [`IAudioSessionControl2::SetDuckingPreference`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessioncontrol2-setduckingpreference)
-    pub fn set_ducking_preference(&self, opt_out: bool) -> windows::core::Result<()> {
-        unsafe { self.inner.SetDuckingPreference(opt_out) }
+    pub fn set_ducking_preference(&self, opt_out: bool) -> crate::Result<()> {
+        self.downgrade.inner.set_ducking_preference(opt_out)
     }
 }
 
@@ -172,25 +135,14 @@ impl Deref for AudioSessionControl2 {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 #[must_use = "callback will be unregistered when the handle is dropped"]
 pub struct AudioSessionEventsHandle {
-    inner: IAudioSessionEvents,
-    parent: IAudioSessionControl,
+    inner: Rc<dyn CallbackRegistration>,
 }
 
 impl AudioSessionEventsHandle {
     pub fn unregister(self) {
-        // Handled by the Drop impl
-    }
-}
-
-impl Drop for AudioSessionEventsHandle {
-    fn drop(&mut self) {
-        unsafe {
-            self.parent
-                .UnregisterAudioSessionNotification(&self.inner)
-                .ok()
-        };
+        drop(self.inner);
     }
 }
@@ -1,65 +1,95 @@
-use std::{iter::FusedIterator, ops::Range};
+use std::{iter::FusedIterator, ops::Range, rc::Rc};
 
-use windows::Win32::Media::Audio::IAudioSessionEnumerator;
-
-use crate::audio_session_control::AudioSessionControl;
+use crate::{
+    audio_session_control::AudioSessionControl, backend::AudioSessionEnumeratorBackend,
+    error::Error,
+};
 
 /// See also: [`IAudioSessionEnumerator`](https://docs.microsoft.com/en-us/windows/desktop/api/audiopolicy/nn-audiopolicy-iaudiosessionenumerator)
 #[derive(Debug, Clone)]
 pub struct AudioSessionEnumerator {
-    inner: IAudioSessionEnumerator,
+    inner: Rc<dyn AudioSessionEnumeratorBackend>,
 }
 
 impl AudioSessionEnumerator {
-    pub(crate) fn new(inner: IAudioSessionEnumerator) -> Self {
+    pub(crate) fn new(inner: Rc<dyn AudioSessionEnumeratorBackend>) -> Self {
         Self { inner }
     }
 
-    pub fn get_count(&self) -> windows::core::Result<i32> {
-        unsafe { self.inner.GetCount() }
+    pub fn get_count(&self) -> crate::Result<i32> {
+        self.inner.get_count()
     }
 
-    pub fn get_session(&self, session: i32) -> windows::core::Result<AudioSessionControl> {
-        unsafe { self.inner.GetSession(session).map(AudioSessionControl::new) }
+    /// Iterate over the sessions, yielding an error for every item that
+    /// could not be retrieved.
+    pub fn iter(&self) -> AudioSessionIter<'_> {
+        AudioSessionIter::new(self)
+    }
+
+    /// Collect every item, failing if any of them could not be retrieved.
+    pub fn to_vec(&self) -> crate::Result<Vec<AudioSessionControl>> {
+        self.iter().collect()
+    }
+
+    pub fn get_session(&self, session: i32) -> crate::Result<AudioSessionControl> {
+        self.inner
+            .get_session(session)
+            .map(AudioSessionControl::new)
     }
 }
 
 impl<'a> IntoIterator for &'a AudioSessionEnumerator {
     type IntoIter = AudioSessionIter<'a>;
-    type Item = AudioSessionControl;
+    type Item = crate::Result<AudioSessionControl>;
 
     fn into_iter(self) -> Self::IntoIter {
         AudioSessionIter::new(self)
     }
 }
 
+/// Iterator over the sessions of an [`AudioSessionEnumerator`].
+///
+/// Items are retrieved lazily, so an item that disappears while iterating
+/// yields an error instead of ending the iteration. If the number of items
+/// cannot be determined, the iterator yields that error once and then ends.
 #[derive(Debug, Clone)]
 pub struct AudioSessionIter<'a> {
     inner: &'a AudioSessionEnumerator,
     range: Range<i32>,
+    error: Option<Error>,
 }
 
 impl<'a> AudioSessionIter<'a> {
     pub(crate) fn new(inner: &'a AudioSessionEnumerator) -> Self {
-        let count = inner.get_count().unwrap();
+        let (range, error) = match inner.get_count() {
+            Ok(count) => (0..count, None),
+            Err(error) => (0..0, Some(error)),
+        };
         Self {
             inner,
-            range: 0..count,
+            range,
+            error,
         }
     }
+
+    /// Skip the items that could not be retrieved.
+    pub fn lossy(self) -> impl Iterator<Item = AudioSessionControl> + 'a {
+        self.filter_map(Result::ok)
+    }
 }
 
 impl<'a> Iterator for AudioSessionIter<'a> {
-    type Item = AudioSessionControl;
+    type Item = crate::Result<AudioSessionControl>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.range
-            .next()
-            .map(|index| self.inner.get_session(index).unwrap())
+        if let Some(error) = self.error.take() {
+            return Some(Err(error));
+        }
+        self.range.next().map(|index| self.inner.get_session(index))
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let len = self.range.len();
+        let len = self.range.len() + usize::from(self.error.is_some());
         (len, Some(len))
     }
 }
@@ -1,5 +1,3 @@
-use std::convert::TryInto;
-
 use crate::{
     bits::{AudioSessionDisconnectReason, AudioSessionState},
     string::WinStr,
@@ -21,7 +19,7 @@ pub trait AudioSessionEvents: 'static {
         new_volume_levels: &[f32],
         changed_channel: usize,
         event_context: Option<&GUID>,
-    ) -> windows::core::Result<()> {
+    ) -> crate::Result<()> {
         let _ = (new_volume_levels, changed_channel, event_context);
         Ok(())
     }
@@ -31,7 +29,7 @@ pub trait AudioSessionEvents: 'static {
         &self,
         new_display_name: &WinStr,
         event_context: Option<&GUID>,
-    ) -> windows::core::Result<()> {
+    ) -> crate::Result<()> {
         let _ = (new_display_name, event_context);
         Ok(())
     }
@@ -41,7 +39,7 @@ pub trait AudioSessionEvents: 'static {
         &self,
         new_grouping_param: Option<&GUID>,
         event_context: Option<&GUID>,
-    ) -> windows::core::Result<()> {
+    ) -> crate::Result<()> {
         let _ = (new_grouping_param, event_context);
         Ok(())
     }
@@ -51,7 +49,7 @@ pub trait AudioSessionEvents: 'static {
         &self,
         new_icon_path: &WinStr,
         event_context: Option<&GUID>,
-    ) -> windows::core::Result<()> {
+    ) -> crate::Result<()> {
         let _ = (new_icon_path, event_context);
         Ok(())
     }
@@ -60,7 +58,7 @@ pub trait AudioSessionEvents: 'static {
     fn on_session_disconnected(
         &self,
         disconnect_reason: AudioSessionDisconnectReason,
-    ) -> windows::core::Result<()> {
+    ) -> crate::Result<()> {
         let _ = disconnect_reason;
         Ok(())
     }
@@ -71,13 +69,13 @@ pub trait AudioSessionEvents: 'static {
         new_volume: f32,
         new_mute: bool,
         event_context: Option<&GUID>,
-    ) -> windows::core::Result<()> {
+    ) -> crate::Result<()> {
         let _ = (new_volume, new_mute, event_context);
         Ok(())
     }
 
     /// See also: [`IAudioSessionEvents::OnStateChanged`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessionevents-onstatechanged)
-    fn on_state_changed(&self, new_state: AudioSessionState) -> windows::core::Result<()> {
+    fn on_state_changed(&self, new_state: AudioSessionState) -> crate::Result<()> {
         let _ = new_state;
         Ok(())
     }
@@ -89,13 +87,8 @@ pub(crate) struct AudioSessionEventsWrapper {
 }
 
 impl AudioSessionEventsWrapper {
-    pub(crate) fn new<T>(inner: T) -> Self
-    where
-        T: AudioSessionEvents,
-    {
-        Self {
-            inner: Box::new(inner),
-        }
+    pub(crate) fn new(inner: Box<dyn AudioSessionEvents>) -> Self {
+        Self { inner }
     }
 }
 
@@ -109,16 +102,16 @@ impl IAudioSessionEvents_Impl for AudioSessionEventsWrapper {
         changed_channel: u32,
         event_context: *const GUID,
     ) -> windows::core::Result<()> {
-        self.inner.on_channel_volume_changed(
-            unsafe {
-                std::slice::from_raw_parts(
-                    channel_volume_array as *const f32,
-                    channel_count.try_into().unwrap(),
-                )
-            },
-            changed_channel.try_into().unwrap(),
-            unsafe { event_context.as_ref() },
-        )
+        let new_volume_levels = if channel_volume_array.is_null() {
+            &[]
+        } else {
+            unsafe { std::slice::from_raw_parts(channel_volume_array, channel_count as usize) }
+        };
+        self.inner
+            .on_channel_volume_changed(new_volume_levels, changed_channel as usize, unsafe {
+                event_context.as_ref()
+            })
+            .map_err(Into::into)
     }
 
     fn OnDisplayNameChanged(
@@ -130,6 +123,7 @@ impl IAudioSessionEvents_Impl for AudioSessionEventsWrapper {
             .on_display_name_changed(unsafe { WinStr::from_pcwstr(new_display_name) }, unsafe {
                 event_context.as_ref()
             })
+            .map_err(Into::into)
     }
 
     fn OnGroupingParamChanged(
@@ -141,6 +135,7 @@ impl IAudioSessionEvents_Impl for AudioSessionEventsWrapper {
             .on_grouping_param_changed(unsafe { new_grouping_param.as_ref() }, unsafe {
                 event_context.as_ref()
             })
+            .map_err(Into::into)
     }
 
     fn OnIconPathChanged(
@@ -152,6 +147,7 @@ impl IAudioSessionEvents_Impl for AudioSessionEventsWrapper {
             .on_icon_path_changed(unsafe { WinStr::from_pcwstr(new_icon_path) }, unsafe {
                 event_context.as_ref()
             })
+            .map_err(Into::into)
     }
 
     fn OnSessionDisconnected(
@@ -160,6 +156,7 @@ impl IAudioSessionEvents_Impl for AudioSessionEventsWrapper {
     ) -> windows::core::Result<()> {
         self.inner
             .on_session_disconnected(AudioSessionDisconnectReason::from_raw(disconnect_reason))
+            .map_err(Into::into)
     }
 
     fn OnSimpleVolumeChanged(
@@ -172,6 +169,7 @@ impl IAudioSessionEvents_Impl for AudioSessionEventsWrapper {
             .on_simple_volume_changed(new_volume, new_mute.into(), unsafe {
                 event_context.as_ref()
             })
+            .map_err(Into::into)
     }
 
     fn OnStateChanged(
@@ -180,5 +178,187 @@ impl IAudioSessionEvents_Impl for AudioSessionEventsWrapper {
     ) -> windows::core::Result<()> {
         self.inner
             .on_state_changed(AudioSessionState::from_raw(new_state))
+            .map_err(Into::into)
+    }
+}
+
+impl dyn AudioSessionEvents {
+    /// Build a handler from closures, the same as
+    /// [`AudioSessionEventsBuilder::new`], for example
+    /// `<dyn AudioSessionEvents>::builder().on_state_changed(|state| ..).build()`.
+    ///
+    /// Edition 2018 also accepts `AudioSessionEvents::builder()`.
+    pub fn builder() -> AudioSessionEventsBuilder {
+        AudioSessionEventsBuilder::new()
+    }
+}
+
+/// An [`AudioSessionEvents`] handler that calls the closures of an
+/// [`AudioSessionEventsBuilder`]. Events without a closure are ignored.
+#[allow(clippy::type_complexity)]
+#[derive(Default)]
+pub struct FnAudioSessionEvents {
+    on_channel_volume_changed:
+        Option<Box<dyn Fn(&[f32], usize, Option<&GUID>) -> crate::Result<()>>>,
+    on_display_name_changed: Option<Box<dyn Fn(&WinStr, Option<&GUID>) -> crate::Result<()>>>,
+    on_grouping_param_changed:
+        Option<Box<dyn Fn(Option<&GUID>, Option<&GUID>) -> crate::Result<()>>>,
+    on_icon_path_changed: Option<Box<dyn Fn(&WinStr, Option<&GUID>) -> crate::Result<()>>>,
+    on_session_disconnected: Option<Box<dyn Fn(AudioSessionDisconnectReason) -> crate::Result<()>>>,
+    on_simple_volume_changed: Option<Box<dyn Fn(f32, bool, Option<&GUID>) -> crate::Result<()>>>,
+    on_state_changed: Option<Box<dyn Fn(AudioSessionState) -> crate::Result<()>>>,
+}
+
+impl AudioSessionEvents for FnAudioSessionEvents {
+    fn on_channel_volume_changed(
+        &self,
+        new_volume_levels: &[f32],
+        changed_channel: usize,
+        event_context: Option<&GUID>,
+    ) -> crate::Result<()> {
+        match &self.on_channel_volume_changed {
+            Some(f) => f(new_volume_levels, changed_channel, event_context),
+            None => Ok(()),
+        }
+    }
+
+    fn on_display_name_changed(
+        &self,
+        new_display_name: &WinStr,
+        event_context: Option<&GUID>,
+    ) -> crate::Result<()> {
+        match &self.on_display_name_changed {
+            Some(f) => f(new_display_name, event_context),
+            None => Ok(()),
+        }
+    }
+
+    fn on_grouping_param_changed(
+        &self,
+        new_grouping_param: Option<&GUID>,
+        event_context: Option<&GUID>,
+    ) -> crate::Result<()> {
+        match &self.on_grouping_param_changed {
+            Some(f) => f(new_grouping_param, event_context),
+            None => Ok(()),
+        }
+    }
+
+    fn on_icon_path_changed(
+        &self,
+        new_icon_path: &WinStr,
+        event_context: Option<&GUID>,
+    ) -> crate::Result<()> {
+        match &self.on_icon_path_changed {
+            Some(f) => f(new_icon_path, event_context),
+            None => Ok(()),
+        }
+    }
+
+    fn on_session_disconnected(
+        &self,
+        disconnect_reason: AudioSessionDisconnectReason,
+    ) -> crate::Result<()> {
+        match &self.on_session_disconnected {
+            Some(f) => f(disconnect_reason),
+            None => Ok(()),
+        }
+    }
+
+    fn on_simple_volume_changed(
+        &self,
+        new_volume: f32,
+        new_mute: bool,
+        event_context: Option<&GUID>,
+    ) -> crate::Result<()> {
+        match &self.on_simple_volume_changed {
+            Some(f) => f(new_volume, new_mute, event_context),
+            None => Ok(()),
+        }
+    }
+
+    fn on_state_changed(&self, new_state: AudioSessionState) -> crate::Result<()> {
+        match &self.on_state_changed {
+            Some(f) => f(new_state),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Builder of a [`FnAudioSessionEvents`], which handles a few events without
+/// declaring a type.
+///
+/// Start from [`new`](Self::new) and set a closure for each event to handle.
+/// The closures return a [`Result`](crate::Result) like the methods of
+/// [`AudioSessionEvents`].
+#[derive(Default)]
+pub struct AudioSessionEventsBuilder {
+    events: FnAudioSessionEvents,
+}
+
+impl AudioSessionEventsBuilder {
+    /// A builder without closures.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn on_channel_volume_changed<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&[f32], usize, Option<&GUID>) -> crate::Result<()> + 'static,
+    {
+        self.events.on_channel_volume_changed = Some(Box::new(f));
+        self
+    }
+
+    pub fn on_display_name_changed<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&WinStr, Option<&GUID>) -> crate::Result<()> + 'static,
+    {
+        self.events.on_display_name_changed = Some(Box::new(f));
+        self
+    }
+
+    pub fn on_grouping_param_changed<F>(mut self, f: F) -> Self
+    where
+        F: Fn(Option<&GUID>, Option<&GUID>) -> crate::Result<()> + 'static,
+    {
+        self.events.on_grouping_param_changed = Some(Box::new(f));
+        self
+    }
+
+    pub fn on_icon_path_changed<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&WinStr, Option<&GUID>) -> crate::Result<()> + 'static,
+    {
+        self.events.on_icon_path_changed = Some(Box::new(f));
+        self
+    }
+
+    pub fn on_session_disconnected<F>(mut self, f: F) -> Self
+    where
+        F: Fn(AudioSessionDisconnectReason) -> crate::Result<()> + 'static,
+    {
+        self.events.on_session_disconnected = Some(Box::new(f));
+        self
+    }
+
+    pub fn on_simple_volume_changed<F>(mut self, f: F) -> Self
+    where
+        F: Fn(f32, bool, Option<&GUID>) -> crate::Result<()> + 'static,
+    {
+        self.events.on_simple_volume_changed = Some(Box::new(f));
+        self
+    }
+
+    pub fn on_state_changed<F>(mut self, f: F) -> Self
+    where
+        F: Fn(AudioSessionState) -> crate::Result<()> + 'static,
+    {
+        self.events.on_state_changed = Some(Box::new(f));
+        self
+    }
+
+    pub fn build(self) -> FnAudioSessionEvents {
+        self.events
     }
 }
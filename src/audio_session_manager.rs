@@ -1,90 +1,71 @@
 use std::ops::Deref;
+use std::rc::Rc;
 
 use crate::{
     audio_session_control::AudioSessionControl,
     audio_session_enumerator::AudioSessionEnumerator,
-    audio_session_notification::{AudioSessionNotification, AudioSessionNotificationWrapper},
-    audio_volume_duck_notification::{
-        AudioVolumeDuckNotification, AudioVolumeDuckNotificationWrapper,
-    },
-    device::Activate,
+    audio_session_notification::AudioSessionNotification,
+    audio_volume_duck_notification::AudioVolumeDuckNotification,
+    backend::{AudioSessionManagerBackend, CallbackRegistration},
     simple_audio_volume::SimpleAudioVolume,
     string::WinStr,
 };
-use windows::core::{ComInterface, GUID};
-use windows::Win32::Media::Audio::{
-    IAudioSessionManager, IAudioSessionManager2, IAudioSessionNotification,
-    IAudioVolumeDuckNotification,
-};
+use windows::core::GUID;
 
 /// See also: [`IAudioSessionManager`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nn-audiopolicy-iaudiosessionmanager)
 #[derive(Debug, Clone)]
 pub struct AudioSessionManager {
-    inner: IAudioSessionManager,
+    inner: Rc<dyn AudioSessionManagerBackend>,
 }
 
-impl Activate for AudioSessionManager {
-    type Raw = IAudioSessionManager;
-
-    fn from_raw(inner: Self::Raw) -> Self {
+impl AudioSessionManager {
+    pub(crate) fn new(inner: Rc<dyn AudioSessionManagerBackend>) -> Self {
         Self { inner }
     }
-}
 
-impl AudioSessionManager {
     /// See also: [`IAudioSessionManager::GetAudioSessionControl`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessionmanager-getaudiosessioncontrol)
     pub fn get_audio_session_control(
         &self,
         audio_session_guid: &GUID,
-    ) -> windows::core::Result<AudioSessionControl> {
-        unsafe {
-            self.inner
-                .GetAudioSessionControl(Some(audio_session_guid), 0)
-                .map(AudioSessionControl::new)
-        }
+    ) -> crate::Result<AudioSessionControl> {
+        self.inner
+            .get_audio_session_control(audio_session_guid)
+            .map(AudioSessionControl::new)
     }
 
     /// See also: [`IAudioSessionManager::GetSimpleAudioVolume`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessionmanager-getsimpleaudiovolume)
     pub fn get_simple_audio_volume(
         &self,
         audio_session_guid: &GUID,
-    ) -> windows::core::Result<SimpleAudioVolume> {
-        unsafe {
-            self.inner
-                .GetSimpleAudioVolume(Some(audio_session_guid), 0)
-                .map(SimpleAudioVolume::new)
-        }
+    ) -> crate::Result<SimpleAudioVolume> {
+        self.inner
+            .get_simple_audio_volume(audio_session_guid)
+            .map(SimpleAudioVolume::new)
     }
 
-    pub fn upgrade(&self) -> windows::core::Result<AudioSessionManager2> {
-        self.inner.cast().map(AudioSessionManager2::from_raw)
+    pub fn upgrade(&self) -> crate::Result<AudioSessionManager2> {
+        Ok(AudioSessionManager2::new(self.inner.clone()))
     }
 }
 
 /// See also: [`IAudioSessionManager2`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nn-audiopolicy-iaudiosessionmanager2)
 #[derive(Debug, Clone)]
 pub struct AudioSessionManager2 {
-    inner: IAudioSessionManager2,
     downgrade: AudioSessionManager,
 }
 
-impl Activate for AudioSessionManager2 {
-    type Raw = IAudioSessionManager2;
-
-    fn from_raw(inner: Self::Raw) -> Self {
-        let downgrade = AudioSessionManager::from_raw(inner.cast().unwrap());
-        Self { inner, downgrade }
+impl AudioSessionManager2 {
+    pub(crate) fn new(inner: Rc<dyn AudioSessionManagerBackend>) -> Self {
+        let downgrade = AudioSessionManager::new(inner);
+        Self { downgrade }
     }
-}
 
-impl AudioSessionManager2 {
     /// See also: [`IAudioSessionManager2::GetSessionEnumerator`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessionmanager2-getsessionenumerator)
-    pub fn get_session_enumerator(&self) -> windows::core::Result<AudioSessionEnumerator> {
-        unsafe {
-            self.inner
-                .GetSessionEnumerator()
-                .map(AudioSessionEnumerator::new)
-        }
+    pub fn get_session_enumerator(&self) -> crate::Result<AudioSessionEnumerator> {
+        self.downgrade
+            .inner
+            .get_session_enumerator()
+            .map(AudioSessionEnumerator::new)
     }
 
     /// See also: [`IAudioSessionManager2::RegisterDuckNotification`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessionmanager2-registerducknotification)
@@ -92,42 +73,30 @@ impl AudioSessionManager2 {
         &self,
         session_id: &WinStr,
         duck_notification: T,
-    ) -> windows::core::Result<AudioVolumeDuckNotificationHandle>
+    ) -> crate::Result<AudioVolumeDuckNotificationHandle>
     where
         T: AudioVolumeDuckNotification,
     {
-        let duck_notification = IAudioVolumeDuckNotification::from(
-            AudioVolumeDuckNotificationWrapper::new(duck_notification),
-        );
-        unsafe {
-            self.inner
-                .RegisterDuckNotification(session_id.as_pcwstr(), &duck_notification)?
-        };
-        Ok(AudioVolumeDuckNotificationHandle {
-            inner: duck_notification,
-            parent: self.inner.clone(),
-        })
+        let inner = self
+            .downgrade
+            .inner
+            .register_duck_notification(session_id, Box::new(duck_notification))?;
+        Ok(AudioVolumeDuckNotificationHandle { inner })
     }
 
     /// See also: [`IAudioSessionManager2::RegisterSessionNotification`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessionmanager2-registersessionnotification)
     pub fn register_session_notification<T>(
         &self,
         session_notification: T,
-    ) -> windows::core::Result<AudioSessionNotificationHandle>
+    ) -> crate::Result<AudioSessionNotificationHandle>
     where
         T: AudioSessionNotification,
     {
-        let session_notification = IAudioSessionNotification::from(
-            AudioSessionNotificationWrapper::new(session_notification),
-        );
-        unsafe {
-            self.inner
-                .RegisterSessionNotification(&session_notification)?;
-        }
-        Ok(AudioSessionNotificationHandle {
-            inner: session_notification,
-            parent: self.inner.clone(),
-        })
+        let inner = self
+            .downgrade
+            .inner
+            .register_session_notification(Box::new(session_notification))?;
+        Ok(AudioSessionNotificationHandle { inner })
     }
 }
 
@@ -139,40 +108,26 @@ impl Deref for AudioSessionManager2 {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 #[must_use = "callback will be unregistered when the handle is dropped"]
 pub struct AudioVolumeDuckNotificationHandle {
-    inner: IAudioVolumeDuckNotification,
-    parent: IAudioSessionManager2,
+    inner: Rc<dyn CallbackRegistration>,
 }
 
 impl AudioVolumeDuckNotificationHandle {
     pub fn unregister(self) {
-        // Handled by the Drop impl
-    }
-}
-
-impl Drop for AudioVolumeDuckNotificationHandle {
-    fn drop(&mut self) {
-        unsafe { self.parent.UnregisterDuckNotification(&self.inner).ok() };
+        drop(self.inner);
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 #[must_use = "callback will be unregistered when the handle is dropped"]
 pub struct AudioSessionNotificationHandle {
-    inner: IAudioSessionNotification,
-    parent: IAudioSessionManager2,
+    inner: Rc<dyn CallbackRegistration>,
 }
 
 impl AudioSessionNotificationHandle {
     pub fn unregister(self) {
-        // Handled by the Drop impl
-    }
-}
-
-impl Drop for AudioSessionNotificationHandle {
-    fn drop(&mut self) {
-        unsafe { self.parent.UnregisterSessionNotification(&self.inner).ok() };
+        drop(self.inner);
     }
 }
@@ -1,31 +1,40 @@
+use std::rc::Rc;
+
 use crate::audio_session_control::AudioSessionControl;
-use windows::core::implement;
+use windows::core::{implement, ComInterface};
+use windows::Win32::Foundation::E_POINTER;
 use windows::Win32::Media::Audio::IAudioSessionControl;
+use windows::Win32::Media::Audio::IAudioSessionControl2;
 use windows::Win32::Media::Audio::IAudioSessionNotification;
 use windows::Win32::Media::Audio::IAudioSessionNotification_Impl;
 
 /// See also: [`IAudioSessionNotification`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nn-audiopolicy-iaudiosessionnotification)
 pub trait AudioSessionNotification: 'static {
     /// See also: [`IAudioSessionNotification::OnSessionCreated`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiosessionnotification-onsessioncreated)
-    fn on_session_created(&self, new_session: AudioSessionControl) -> windows::core::Result<()> {
+    fn on_session_created(&self, new_session: AudioSessionControl) -> crate::Result<()> {
         let _ = new_session;
         Ok(())
     }
 }
 
+impl<F> AudioSessionNotification for F
+where
+    F: Fn(AudioSessionControl) + 'static,
+{
+    fn on_session_created(&self, new_session: AudioSessionControl) -> crate::Result<()> {
+        self(new_session);
+        Ok(())
+    }
+}
+
 #[implement(IAudioSessionNotification)]
 pub(crate) struct AudioSessionNotificationWrapper {
     inner: Box<dyn AudioSessionNotification>,
 }
 
 impl AudioSessionNotificationWrapper {
-    pub(crate) fn new<T>(inner: T) -> Self
-    where
-        T: AudioSessionNotification,
-    {
-        Self {
-            inner: Box::new(inner),
-        }
+    pub(crate) fn new(inner: Box<dyn AudioSessionNotification>) -> Self {
+        Self { inner }
     }
 }
 
@@ -36,7 +45,11 @@ impl IAudioSessionNotification_Impl for AudioSessionNotificationWrapper {
         &self,
         new_session: Option<&IAudioSessionControl>,
     ) -> windows::core::Result<()> {
+        let new_session = new_session
+            .ok_or(E_POINTER)?
+            .cast::<IAudioSessionControl2>()?;
         self.inner
-            .on_session_created(AudioSessionControl::new(new_session.unwrap().to_owned()))
+            .on_session_created(AudioSessionControl::new(Rc::new(new_session)))
+            .map_err(Into::into)
     }
 }
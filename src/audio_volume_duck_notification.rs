@@ -11,13 +11,13 @@ pub trait AudioVolumeDuckNotification: 'static {
         &self,
         session_id: &WinStr,
         num_communication_sessions: u32,
-    ) -> windows::core::Result<()> {
+    ) -> crate::Result<()> {
         let _ = (session_id, num_communication_sessions);
         Ok(())
     }
 
     /// See also: [`IAudioVolumeDuckNotification::OnVolumeUnduckNotification`](https://docs.microsoft.com/en-us/windows/win32/api/audiopolicy/nf-audiopolicy-iaudiovolumeducknotification-onvolumeunducknotification)
-    fn on_volume_unduck_notification(&self, session_id: &WinStr) -> windows::core::Result<()> {
+    fn on_volume_unduck_notification(&self, session_id: &WinStr) -> crate::Result<()> {
         let _ = session_id;
         Ok(())
     }
@@ -29,13 +29,8 @@ pub(crate) struct AudioVolumeDuckNotificationWrapper {
 }
 
 impl AudioVolumeDuckNotificationWrapper {
-    pub(crate) fn new<T>(inner: T) -> Self
-    where
-        T: AudioVolumeDuckNotification,
-    {
-        Self {
-            inner: Box::new(inner),
-        }
+    pub(crate) fn new(inner: Box<dyn AudioVolumeDuckNotification>) -> Self {
+        Self { inner }
     }
 }
 
@@ -47,14 +42,96 @@ impl IAudioVolumeDuckNotification_Impl for AudioVolumeDuckNotificationWrapper {
         session_id: &PCWSTR,
         num_communication_sessions: u32,
     ) -> windows::core::Result<()> {
-        self.inner.on_volume_duck_notification(
-            unsafe { WinStr::from_pcwstr(session_id) },
-            num_communication_sessions,
-        )
+        self.inner
+            .on_volume_duck_notification(
+                unsafe { WinStr::from_pcwstr(session_id) },
+                num_communication_sessions,
+            )
+            .map_err(Into::into)
     }
 
     fn OnVolumeUnduckNotification(&self, session_id: &PCWSTR) -> windows::core::Result<()> {
         self.inner
             .on_volume_unduck_notification(unsafe { WinStr::from_pcwstr(session_id) })
+            .map_err(Into::into)
+    }
+}
+
+impl dyn AudioVolumeDuckNotification {
+    /// Build a handler from closures, the same as
+    /// [`AudioVolumeDuckNotificationBuilder::new`], for example
+    /// `<dyn AudioVolumeDuckNotification>::builder().on_volume_unduck_notification(|id| ..).build()`.
+    ///
+    /// Edition 2018 also accepts `AudioVolumeDuckNotification::builder()`.
+    pub fn builder() -> AudioVolumeDuckNotificationBuilder {
+        AudioVolumeDuckNotificationBuilder::new()
+    }
+}
+
+/// An [`AudioVolumeDuckNotification`] handler that calls the closures of an
+/// [`AudioVolumeDuckNotificationBuilder`]. Notifications without a closure
+/// are ignored.
+#[allow(clippy::type_complexity)]
+#[derive(Default)]
+pub struct FnAudioVolumeDuckNotification {
+    on_volume_duck_notification: Option<Box<dyn Fn(&WinStr, u32) -> crate::Result<()>>>,
+    on_volume_unduck_notification: Option<Box<dyn Fn(&WinStr) -> crate::Result<()>>>,
+}
+
+impl AudioVolumeDuckNotification for FnAudioVolumeDuckNotification {
+    fn on_volume_duck_notification(
+        &self,
+        session_id: &WinStr,
+        num_communication_sessions: u32,
+    ) -> crate::Result<()> {
+        match &self.on_volume_duck_notification {
+            Some(f) => f(session_id, num_communication_sessions),
+            None => Ok(()),
+        }
+    }
+
+    fn on_volume_unduck_notification(&self, session_id: &WinStr) -> crate::Result<()> {
+        match &self.on_volume_unduck_notification {
+            Some(f) => f(session_id),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Builder of a [`FnAudioVolumeDuckNotification`], which handles ducking
+/// notifications without declaring a type.
+///
+/// Start from [`new`](Self::new) and set a closure for each notification to
+/// handle. The closures return a [`Result`](crate::Result) like the methods
+/// of [`AudioVolumeDuckNotification`].
+#[derive(Default)]
+pub struct AudioVolumeDuckNotificationBuilder {
+    notification: FnAudioVolumeDuckNotification,
+}
+
+impl AudioVolumeDuckNotificationBuilder {
+    /// A builder without closures.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn on_volume_duck_notification<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&WinStr, u32) -> crate::Result<()> + 'static,
+    {
+        self.notification.on_volume_duck_notification = Some(Box::new(f));
+        self
+    }
+
+    pub fn on_volume_unduck_notification<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&WinStr) -> crate::Result<()> + 'static,
+    {
+        self.notification.on_volume_unduck_notification = Some(Box::new(f));
+        self
+    }
+
+    pub fn build(self) -> FnAudioVolumeDuckNotification {
+        self.notification
     }
 }
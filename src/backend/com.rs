@@ -0,0 +1,715 @@
+//! Backend implementations that forward to the Windows Core Audio APIs.
+
+use std::rc::Rc;
+
+use windows::core::{ComInterface, GUID};
+use windows::Win32::{
+    Foundation::{E_NOINTERFACE, S_OK},
+    Media::Audio::{
+        Endpoints::{IAudioEndpointVolume, IAudioEndpointVolumeCallback},
+        IAudioAutoGainControl, IAudioLoudness, IAudioMute, IAudioSessionControl2,
+        IAudioSessionEnumerator, IAudioSessionEvents, IAudioSessionManager2,
+        IAudioSessionNotification, IAudioVolumeDuckNotification, IAudioVolumeLevel, IConnector,
+        IDeviceTopology, IMMDevice, IMMDeviceCollection, IMMDeviceEnumerator, IMMEndpoint,
+        IMMNotificationClient, IPart, IPartsList, ISimpleAudioVolume, MMDeviceEnumerator,
+    },
+    Media::KernelStreaming::{IKsJackDescription, KSJACK_DESCRIPTION},
+    System::Com::{CoCreateInstance, CLSCTX_ALL, STGM},
+    UI::Shell::PropertiesSystem::{IPropertyStore, PROPERTYKEY},
+};
+
+use crate::{
+    audio_endpoint_volume::{VolumeRange, VolumeStepInfo},
+    audio_endpoint_volume_callback::{
+        AudioEndpointVolumeCallback, AudioEndpointVolumeCallbackWrapper,
+    },
+    audio_session_events::{AudioSessionEvents, AudioSessionEventsWrapper},
+    audio_session_notification::{AudioSessionNotification, AudioSessionNotificationWrapper},
+    audio_volume_duck_notification::{
+        AudioVolumeDuckNotification, AudioVolumeDuckNotificationWrapper,
+    },
+    backend::{
+        AudioEndpointVolumeBackend, AudioSessionControlBackend, AudioSessionEnumeratorBackend,
+        AudioSessionManagerBackend, CallbackRegistration, ConnectorBackend, DeviceBackend,
+        DeviceCollectionBackend, DeviceEnumeratorBackend, DeviceTopologyBackend, PartBackend,
+        PartsListBackend, PolicyConfigBackend, PropertyStoreBackend, SimpleAudioVolumeBackend,
+    },
+    bits::{
+        AudioSessionState, ConnectorDataFlow, ConnectorType, DataFlow, DataFlowMask, DeviceRole,
+        DeviceState, DeviceStateMask, HardwareSupportMask, PartType, StorageAccessMode,
+    },
+    device_topology::{ChannelLevel, JackDescription, PartControl},
+    notification_client::{NotificationClient, NotificationClientWrapper},
+    policy_config::{IPolicyConfig, POLICY_CONFIG_CLIENT},
+    property_store::{OwnedPropVariant, Property, PropertyKey},
+    string::{WinStr, WinString},
+    util::as_raw_or_null,
+};
+
+impl DeviceEnumeratorBackend for IMMDeviceEnumerator {
+    fn enum_audio_endpoints(
+        &self,
+        data_flow_mask: DataFlowMask,
+        state_mask: DeviceStateMask,
+    ) -> crate::Result<Rc<dyn DeviceCollectionBackend>> {
+        let inner = unsafe { self.EnumAudioEndpoints(data_flow_mask.to_raw(), state_mask.bits())? };
+        Ok(Rc::new(inner))
+    }
+
+    fn get_default_audio_endpoint(
+        &self,
+        data_flow: DataFlow,
+        role: DeviceRole,
+    ) -> crate::Result<Rc<dyn DeviceBackend>> {
+        let inner = unsafe { self.GetDefaultAudioEndpoint(data_flow.to_raw(), role.to_raw())? };
+        Ok(Rc::new(inner))
+    }
+
+    fn get_device(&self, device_id: &WinStr) -> crate::Result<Rc<dyn DeviceBackend>> {
+        let inner = unsafe { self.GetDevice(device_id.as_pcwstr())? };
+        Ok(Rc::new(inner))
+    }
+
+    fn register_endpoint_notification(
+        &self,
+        notification_client: Box<dyn NotificationClient>,
+    ) -> crate::Result<Rc<dyn CallbackRegistration>> {
+        let wrapper =
+            IMMNotificationClient::from(NotificationClientWrapper::new(notification_client));
+        unsafe { self.RegisterEndpointNotificationCallback(&wrapper)? };
+
+        Ok(Rc::new(EndpointNotificationRegistration {
+            inner: wrapper,
+            parent: self.clone(),
+        }))
+    }
+
+    fn policy_config(&self) -> crate::Result<Rc<dyn PolicyConfigBackend>> {
+        let inner: IPolicyConfig =
+            unsafe { CoCreateInstance(&POLICY_CONFIG_CLIENT, None, CLSCTX_ALL)? };
+        Ok(Rc::new(inner))
+    }
+}
+
+impl PolicyConfigBackend for IPolicyConfig {
+    fn set_default_endpoint(&self, device_id: &WinStr, role: DeviceRole) -> crate::Result<()> {
+        unsafe {
+            self.SetDefaultEndpoint(device_id.as_pcwstr(), role.to_raw())
+                .ok()?
+        };
+        Ok(())
+    }
+}
+
+impl DeviceCollectionBackend for IMMDeviceCollection {
+    fn get_count(&self) -> crate::Result<u32> {
+        Ok(unsafe { self.GetCount()? })
+    }
+
+    fn item(&self, device: u32) -> crate::Result<Rc<dyn DeviceBackend>> {
+        let inner = unsafe { self.Item(device)? };
+        Ok(Rc::new(inner))
+    }
+}
+
+impl DeviceBackend for IMMDevice {
+    fn activate_audio_endpoint_volume(&self) -> crate::Result<Rc<dyn AudioEndpointVolumeBackend>> {
+        let inner = unsafe { self.Activate::<IAudioEndpointVolume>(CLSCTX_ALL, None)? };
+        Ok(Rc::new(inner))
+    }
+
+    fn activate_audio_session_manager(&self) -> crate::Result<Rc<dyn AudioSessionManagerBackend>> {
+        let inner = unsafe { self.Activate::<IAudioSessionManager2>(CLSCTX_ALL, None)? };
+        Ok(Rc::new(inner))
+    }
+
+    fn activate_device_topology(&self) -> crate::Result<Rc<dyn DeviceTopologyBackend>> {
+        let inner = unsafe { self.Activate::<IDeviceTopology>(CLSCTX_ALL, None)? };
+        Ok(Rc::new(inner))
+    }
+
+    fn get_data_flow(&self) -> crate::Result<DataFlow> {
+        let endpoint: IMMEndpoint = self.cast()?;
+        Ok(DataFlow::from_raw(unsafe { endpoint.GetDataFlow()? }))
+    }
+
+    fn get_id(&self) -> crate::Result<WinString> {
+        Ok(unsafe { WinString::from_com_pwstr(self.GetId()?) })
+    }
+
+    fn get_state(&self) -> crate::Result<DeviceState> {
+        Ok(DeviceState::from_raw(unsafe { self.GetState()? }))
+    }
+
+    fn enumerator(&self) -> crate::Result<Rc<dyn DeviceEnumeratorBackend>> {
+        let inner: IMMDeviceEnumerator =
+            unsafe { CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)? };
+        Ok(Rc::new(inner))
+    }
+
+    fn open_property_store(
+        &self,
+        storage_access_mode: StorageAccessMode,
+    ) -> crate::Result<Rc<dyn PropertyStoreBackend>> {
+        let inner = unsafe { self.OpenPropertyStore(STGM(storage_access_mode.to_raw() as _))? };
+        Ok(Rc::new(inner))
+    }
+}
+
+impl DeviceTopologyBackend for IDeviceTopology {
+    fn get_connector_count(&self) -> crate::Result<u32> {
+        Ok(unsafe { self.GetConnectorCount()? })
+    }
+
+    fn get_connector(&self, index: u32) -> crate::Result<Rc<dyn ConnectorBackend>> {
+        let inner = unsafe { self.GetConnector(index)? };
+        Ok(Rc::new(inner))
+    }
+
+    fn get_subunit_count(&self) -> crate::Result<u32> {
+        Ok(unsafe { self.GetSubunitCount()? })
+    }
+
+    fn get_subunit(&self, index: u32) -> crate::Result<Rc<dyn PartBackend>> {
+        let inner: IPart = unsafe { self.GetSubunit(index)? }.cast()?;
+        Ok(Rc::new(inner))
+    }
+
+    fn get_part_by_id(&self, local_id: u32) -> crate::Result<Rc<dyn PartBackend>> {
+        let inner = unsafe { self.GetPartById(local_id)? };
+        Ok(Rc::new(inner))
+    }
+
+    fn get_device_id(&self) -> crate::Result<WinString> {
+        Ok(unsafe { WinString::from_com_pwstr(self.GetDeviceId()?) })
+    }
+}
+
+impl PartBackend for IPart {
+    fn get_name(&self) -> crate::Result<WinString> {
+        Ok(unsafe { WinString::from_com_pwstr(self.GetName()?) })
+    }
+
+    fn get_local_id(&self) -> crate::Result<u32> {
+        Ok(unsafe { self.GetLocalId()? })
+    }
+
+    fn get_global_id(&self) -> crate::Result<WinString> {
+        Ok(unsafe { WinString::from_com_pwstr(self.GetGlobalId()?) })
+    }
+
+    fn get_part_type(&self) -> crate::Result<PartType> {
+        Ok(PartType::from_raw(unsafe { self.GetPartType()? }))
+    }
+
+    fn get_sub_type(&self) -> crate::Result<GUID> {
+        Ok(unsafe { self.GetSubType()? })
+    }
+
+    fn enum_parts_incoming(&self) -> crate::Result<Rc<dyn PartsListBackend>> {
+        let inner = unsafe { self.EnumPartsIncoming()? };
+        Ok(Rc::new(inner))
+    }
+
+    fn enum_parts_outgoing(&self) -> crate::Result<Rc<dyn PartsListBackend>> {
+        let inner = unsafe { self.EnumPartsOutgoing()? };
+        Ok(Rc::new(inner))
+    }
+
+    fn get_topology_object(&self) -> crate::Result<Rc<dyn DeviceTopologyBackend>> {
+        let inner = unsafe { self.GetTopologyObject()? };
+        Ok(Rc::new(inner))
+    }
+
+    fn as_connector(&self) -> crate::Result<Rc<dyn ConnectorBackend>> {
+        let inner: IConnector = self.cast()?;
+        Ok(Rc::new(inner))
+    }
+
+    fn controls(&self) -> crate::Result<Vec<PartControl>> {
+        let mut controls = Vec::new();
+        if let Some(volume) = activate_part_interface::<IAudioVolumeLevel>(self)? {
+            let mut levels = Vec::new();
+            for channel in 0..unsafe { volume.GetChannelCount()? } {
+                let (mut min_db, mut max_db, mut increment_db) = (0.0, 0.0, 0.0);
+                unsafe {
+                    volume.GetLevelRange(channel, &mut min_db, &mut max_db, &mut increment_db)?
+                };
+                levels.push(ChannelLevel::new(
+                    unsafe { volume.GetLevel(channel)? },
+                    VolumeRange::new(min_db, max_db, increment_db),
+                ));
+            }
+            controls.push(PartControl::Volume(levels));
+        }
+        if let Some(mute) = activate_part_interface::<IAudioMute>(self)? {
+            controls.push(PartControl::Mute(unsafe { mute.GetMute()? }.into()));
+        }
+        if let Some(agc) = activate_part_interface::<IAudioAutoGainControl>(self)? {
+            controls.push(PartControl::AutoGainControl(
+                unsafe { agc.GetEnabled()? }.into(),
+            ));
+        }
+        if let Some(loudness) = activate_part_interface::<IAudioLoudness>(self)? {
+            controls.push(PartControl::Loudness(
+                unsafe { loudness.GetEnabled()? }.into(),
+            ));
+        }
+        Ok(controls)
+    }
+
+    fn jack_descriptions(&self) -> crate::Result<Vec<JackDescription>> {
+        let jacks = match activate_part_interface::<IKsJackDescription>(self)? {
+            Some(jacks) => jacks,
+            None => return Ok(Vec::new()),
+        };
+        let mut descriptions = Vec::new();
+        for jack in 0..unsafe { jacks.GetJackCount()? } {
+            let mut raw = KSJACK_DESCRIPTION::default();
+            unsafe { jacks.GetJackDescription(jack, &mut raw)? };
+            descriptions.push(JackDescription::from_raw(&raw));
+        }
+        Ok(descriptions)
+    }
+}
+
+/// Activate a control interface of a part, or return `None` if the part does
+/// not have it.
+fn activate_part_interface<T: ComInterface>(part: &IPart) -> crate::Result<Option<T>> {
+    let mut raw = std::ptr::null_mut();
+    match unsafe { part.Activate(CLSCTX_ALL.0, &T::IID, Some(&mut raw)) } {
+        Ok(()) => Ok(Some(unsafe { T::from_raw(raw) })),
+        Err(error) if error.code() == E_NOINTERFACE => Ok(None),
+        Err(error) => Err(error.into()),
+    }
+}
+
+impl PartsListBackend for IPartsList {
+    fn get_count(&self) -> crate::Result<u32> {
+        Ok(unsafe { self.GetCount()? })
+    }
+
+    fn get_part(&self, index: u32) -> crate::Result<Rc<dyn PartBackend>> {
+        let inner = unsafe { self.GetPart(index)? };
+        Ok(Rc::new(inner))
+    }
+}
+
+impl ConnectorBackend for IConnector {
+    fn get_type(&self) -> crate::Result<ConnectorType> {
+        Ok(ConnectorType::from_raw(unsafe { self.GetType()? }))
+    }
+
+    fn get_data_flow(&self) -> crate::Result<ConnectorDataFlow> {
+        Ok(ConnectorDataFlow::from_raw(unsafe { self.GetDataFlow()? }))
+    }
+
+    fn is_connected(&self) -> crate::Result<bool> {
+        Ok(unsafe { self.IsConnected()? }.into())
+    }
+
+    fn get_connected_to(&self) -> crate::Result<Rc<dyn ConnectorBackend>> {
+        let inner = unsafe { self.GetConnectedTo()? };
+        Ok(Rc::new(inner))
+    }
+
+    fn get_connector_id_connected_to(&self) -> crate::Result<WinString> {
+        Ok(unsafe { WinString::from_com_pwstr(self.GetConnectorIdConnectedTo()?) })
+    }
+
+    fn get_device_id_connected_to(&self) -> crate::Result<WinString> {
+        Ok(unsafe { WinString::from_com_pwstr(self.GetDeviceIdConnectedTo()?) })
+    }
+
+    fn as_part(&self) -> crate::Result<Rc<dyn PartBackend>> {
+        let inner: IPart = self.cast()?;
+        Ok(Rc::new(inner))
+    }
+}
+
+impl PropertyStoreBackend for IPropertyStore {
+    fn commit(&self) -> crate::Result<()> {
+        unsafe { self.Commit()? };
+        Ok(())
+    }
+
+    fn get_at(&self, index: u32) -> crate::Result<PropertyKey> {
+        let mut pkey = PROPERTYKEY::default();
+        unsafe { self.GetAt(index, &mut pkey)? };
+        Ok(PropertyKey::from_raw(pkey))
+    }
+
+    fn get_count(&self) -> crate::Result<u32> {
+        Ok(unsafe { self.GetCount()? })
+    }
+
+    fn get_value(&self, key: PropertyKey) -> crate::Result<Property> {
+        let raw = unsafe { OwnedPropVariant::new(self.GetValue(key.as_raw() as _)?) };
+        Ok(Property::from_raw(raw.as_raw()))
+    }
+
+    fn set_value(&self, key: &PropertyKey, property: &Property) -> crate::Result<()> {
+        let raw = property.to_raw()?;
+        unsafe { self.SetValue(key.as_raw(), raw.as_raw())? };
+        Ok(())
+    }
+}
+
+impl AudioEndpointVolumeBackend for IAudioEndpointVolume {
+    fn get_channel_count(&self) -> crate::Result<u32> {
+        Ok(unsafe { self.GetChannelCount()? })
+    }
+
+    fn get_channel_volume_level(&self, channel: u32) -> crate::Result<f32> {
+        Ok(unsafe { self.GetChannelVolumeLevel(channel)? })
+    }
+
+    fn get_channel_volume_level_scalar(&self, channel: u32) -> crate::Result<f32> {
+        Ok(unsafe { self.GetChannelVolumeLevelScalar(channel)? })
+    }
+
+    fn get_master_volume_level(&self) -> crate::Result<f32> {
+        Ok(unsafe { self.GetMasterVolumeLevel()? })
+    }
+
+    fn get_master_volume_level_scalar(&self) -> crate::Result<f32> {
+        Ok(unsafe { self.GetMasterVolumeLevelScalar()? })
+    }
+
+    fn get_mute(&self) -> crate::Result<bool> {
+        Ok(unsafe { self.GetMute()? }.into())
+    }
+
+    fn get_volume_range(&self) -> crate::Result<VolumeRange> {
+        let mut volume_range = VolumeRange::new(0.0, 0.0, 0.0);
+        unsafe {
+            self.GetVolumeRange(
+                &mut volume_range.min_db,
+                &mut volume_range.max_db,
+                &mut volume_range.increment_db,
+            )?
+        };
+        Ok(volume_range)
+    }
+
+    fn get_volume_step_info(&self) -> crate::Result<VolumeStepInfo> {
+        let mut volume_step_info = VolumeStepInfo::new(0, 0);
+        unsafe {
+            self.GetVolumeStepInfo(
+                &mut volume_step_info.current_step,
+                &mut volume_step_info.num_steps,
+            )?
+        };
+        Ok(volume_step_info)
+    }
+
+    fn query_hardware_support(&self) -> crate::Result<HardwareSupportMask> {
+        let raw = unsafe { self.QueryHardwareSupport()? };
+        Ok(HardwareSupportMask::from_bits_truncate(raw))
+    }
+
+    fn register_control_change_notify(
+        &self,
+        callback: Box<dyn AudioEndpointVolumeCallback>,
+    ) -> crate::Result<Rc<dyn CallbackRegistration>> {
+        let callback =
+            IAudioEndpointVolumeCallback::from(AudioEndpointVolumeCallbackWrapper::new(callback));
+        unsafe { self.RegisterControlChangeNotify(&callback)? };
+        Ok(Rc::new(ControlChangeRegistration {
+            inner: callback,
+            parent: self.clone(),
+        }))
+    }
+
+    fn set_channel_volume_level(
+        &self,
+        channel: u32,
+        level_db: f32,
+        event_context: Option<&GUID>,
+    ) -> crate::Result<()> {
+        unsafe { self.SetChannelVolumeLevel(channel, level_db, as_raw_or_null(event_context))? };
+        Ok(())
+    }
+
+    fn set_channel_volume_level_scalar(
+        &self,
+        channel: u32,
+        level: f32,
+        event_context: Option<&GUID>,
+    ) -> crate::Result<()> {
+        unsafe { self.SetChannelVolumeLevelScalar(channel, level, as_raw_or_null(event_context))? };
+        Ok(())
+    }
+
+    fn set_master_volume_level(
+        &self,
+        level_db: f32,
+        event_context: Option<&GUID>,
+    ) -> crate::Result<()> {
+        unsafe { self.SetMasterVolumeLevel(level_db, as_raw_or_null(event_context))? };
+        Ok(())
+    }
+
+    fn set_master_volume_level_scalar(
+        &self,
+        level: f32,
+        event_context: Option<&GUID>,
+    ) -> crate::Result<()> {
+        unsafe { self.SetMasterVolumeLevelScalar(level, as_raw_or_null(event_context))? };
+        Ok(())
+    }
+
+    fn set_mute(&self, mute: bool, event_context: Option<&GUID>) -> crate::Result<()> {
+        unsafe { self.SetMute(mute, as_raw_or_null(event_context))? };
+        Ok(())
+    }
+
+    fn volume_step_down(&self, event_context: Option<&GUID>) -> crate::Result<()> {
+        unsafe { self.VolumeStepDown(as_raw_or_null(event_context))? };
+        Ok(())
+    }
+
+    fn volume_step_up(&self, event_context: Option<&GUID>) -> crate::Result<()> {
+        unsafe { self.VolumeStepUp(as_raw_or_null(event_context))? };
+        Ok(())
+    }
+}
+
+impl AudioSessionManagerBackend for IAudioSessionManager2 {
+    fn get_audio_session_control(
+        &self,
+        audio_session_guid: &GUID,
+    ) -> crate::Result<Rc<dyn AudioSessionControlBackend>> {
+        let inner = unsafe { self.GetAudioSessionControl(Some(audio_session_guid), 0)? };
+        Ok(Rc::new(inner.cast::<IAudioSessionControl2>()?))
+    }
+
+    fn get_simple_audio_volume(
+        &self,
+        audio_session_guid: &GUID,
+    ) -> crate::Result<Rc<dyn SimpleAudioVolumeBackend>> {
+        let inner = unsafe { self.GetSimpleAudioVolume(Some(audio_session_guid), 0)? };
+        Ok(Rc::new(inner))
+    }
+
+    fn get_session_enumerator(&self) -> crate::Result<Rc<dyn AudioSessionEnumeratorBackend>> {
+        let inner = unsafe { self.GetSessionEnumerator()? };
+        Ok(Rc::new(inner))
+    }
+
+    fn register_duck_notification(
+        &self,
+        session_id: &WinStr,
+        duck_notification: Box<dyn AudioVolumeDuckNotification>,
+    ) -> crate::Result<Rc<dyn CallbackRegistration>> {
+        let duck_notification = IAudioVolumeDuckNotification::from(
+            AudioVolumeDuckNotificationWrapper::new(duck_notification),
+        );
+        unsafe { self.RegisterDuckNotification(session_id.as_pcwstr(), &duck_notification)? };
+        Ok(Rc::new(DuckNotificationRegistration {
+            inner: duck_notification,
+            parent: self.clone(),
+        }))
+    }
+
+    fn register_session_notification(
+        &self,
+        session_notification: Box<dyn AudioSessionNotification>,
+    ) -> crate::Result<Rc<dyn CallbackRegistration>> {
+        let session_notification = IAudioSessionNotification::from(
+            AudioSessionNotificationWrapper::new(session_notification),
+        );
+        unsafe { self.RegisterSessionNotification(&session_notification)? };
+        Ok(Rc::new(SessionNotificationRegistration {
+            inner: session_notification,
+            parent: self.clone(),
+        }))
+    }
+}
+
+impl AudioSessionEnumeratorBackend for IAudioSessionEnumerator {
+    fn get_count(&self) -> crate::Result<i32> {
+        Ok(unsafe { self.GetCount()? })
+    }
+
+    fn get_session(&self, session: i32) -> crate::Result<Rc<dyn AudioSessionControlBackend>> {
+        let inner = unsafe { self.GetSession(session)? };
+        Ok(Rc::new(inner.cast::<IAudioSessionControl2>()?))
+    }
+}
+
+impl AudioSessionControlBackend for IAudioSessionControl2 {
+    fn get_display_name(&self) -> crate::Result<WinString> {
+        Ok(unsafe { WinString::from_com_pwstr(self.GetDisplayName()?) })
+    }
+
+    fn get_grouping_param(&self) -> crate::Result<GUID> {
+        Ok(unsafe { self.GetGroupingParam()? })
+    }
+
+    fn get_icon_path(&self) -> crate::Result<WinString> {
+        Ok(unsafe { WinString::from_com_pwstr(self.GetIconPath()?) })
+    }
+
+    fn get_state(&self) -> crate::Result<AudioSessionState> {
+        Ok(AudioSessionState::from_raw(unsafe { self.GetState()? }))
+    }
+
+    fn register_audio_session_notification(
+        &self,
+        session_notification: Box<dyn AudioSessionEvents>,
+    ) -> crate::Result<Rc<dyn CallbackRegistration>> {
+        let session_notification =
+            IAudioSessionEvents::from(AudioSessionEventsWrapper::new(session_notification));
+        unsafe { self.RegisterAudioSessionNotification(&session_notification)? };
+        Ok(Rc::new(AudioSessionEventsRegistration {
+            inner: session_notification,
+            parent: self.clone(),
+        }))
+    }
+
+    fn set_display_name(&self, value: &WinStr, event_context: Option<&GUID>) -> crate::Result<()> {
+        unsafe { self.SetDisplayName(value.as_pcwstr(), as_raw_or_null(event_context))? };
+        Ok(())
+    }
+
+    fn set_grouping_param(&self, value: &GUID, event_context: Option<&GUID>) -> crate::Result<()> {
+        unsafe { self.SetGroupingParam(value, as_raw_or_null(event_context))? };
+        Ok(())
+    }
+
+    fn set_icon_path(&self, value: &WinStr, event_context: Option<&GUID>) -> crate::Result<()> {
+        unsafe { self.SetIconPath(value.as_pcwstr(), as_raw_or_null(event_context))? };
+        Ok(())
+    }
+
+    fn get_simple_audio_volume(&self) -> crate::Result<Rc<dyn SimpleAudioVolumeBackend>> {
+        Ok(Rc::new(self.cast::<ISimpleAudioVolume>()?))
+    }
+
+    fn get_process_id(&self) -> crate::Result<u32> {
+        Ok(unsafe { self.GetProcessId()? })
+    }
+
+    fn get_session_identifier(&self) -> crate::Result<WinString> {
+        Ok(unsafe { WinString::from_com_pwstr(self.GetSessionIdentifier()?) })
+    }
+
+    fn get_session_instance_identifier(&self) -> crate::Result<WinString> {
+        Ok(unsafe { WinString::from_com_pwstr(self.GetSessionInstanceIdentifier()?) })
+    }
+
+    fn is_system_sounds_session(&self) -> crate::Result<bool> {
+        // Returns S_OK for the system sounds session and S_FALSE otherwise.
+        let hr = unsafe { self.IsSystemSoundsSession() };
+        hr.ok()?;
+        Ok(hr == S_OK)
+    }
+
+    fn set_ducking_preference(&self, opt_out: bool) -> crate::Result<()> {
+        unsafe { self.SetDuckingPreference(opt_out)? };
+        Ok(())
+    }
+}
+
+impl SimpleAudioVolumeBackend for ISimpleAudioVolume {
+    fn get_master_volume(&self) -> crate::Result<f32> {
+        Ok(unsafe { self.GetMasterVolume()? })
+    }
+
+    fn get_mute(&self) -> crate::Result<bool> {
+        Ok(unsafe { self.GetMute()? }.into())
+    }
+
+    fn set_master_volume(
+        &self,
+        volume_level: f32,
+        event_context: Option<&GUID>,
+    ) -> crate::Result<()> {
+        unsafe { self.SetMasterVolume(volume_level, as_raw_or_null(event_context))? };
+        Ok(())
+    }
+
+    fn set_mute(&self, mute: bool, event_context: Option<&GUID>) -> crate::Result<()> {
+        unsafe { self.SetMute(mute, as_raw_or_null(event_context))? };
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct EndpointNotificationRegistration {
+    inner: IMMNotificationClient,
+    parent: IMMDeviceEnumerator,
+}
+
+impl CallbackRegistration for EndpointNotificationRegistration {}
+
+impl Drop for EndpointNotificationRegistration {
+    fn drop(&mut self) {
+        unsafe {
+            self.parent
+                .UnregisterEndpointNotificationCallback(&self.inner)
+                .ok()
+        };
+    }
+}
+
+#[derive(Debug)]
+struct ControlChangeRegistration {
+    inner: IAudioEndpointVolumeCallback,
+    parent: IAudioEndpointVolume,
+}
+
+impl CallbackRegistration for ControlChangeRegistration {}
+
+impl Drop for ControlChangeRegistration {
+    fn drop(&mut self) {
+        unsafe { self.parent.UnregisterControlChangeNotify(&self.inner).ok() };
+    }
+}
+
+#[derive(Debug)]
+struct DuckNotificationRegistration {
+    inner: IAudioVolumeDuckNotification,
+    parent: IAudioSessionManager2,
+}
+
+impl CallbackRegistration for DuckNotificationRegistration {}
+
+impl Drop for DuckNotificationRegistration {
+    fn drop(&mut self) {
+        unsafe { self.parent.UnregisterDuckNotification(&self.inner).ok() };
+    }
+}
+
+#[derive(Debug)]
+struct SessionNotificationRegistration {
+    inner: IAudioSessionNotification,
+    parent: IAudioSessionManager2,
+}
+
+impl CallbackRegistration for SessionNotificationRegistration {}
+
+impl Drop for SessionNotificationRegistration {
+    fn drop(&mut self) {
+        unsafe { self.parent.UnregisterSessionNotification(&self.inner).ok() };
+    }
+}
+
+#[derive(Debug)]
+struct AudioSessionEventsRegistration {
+    inner: IAudioSessionEvents,
+    parent: IAudioSessionControl2,
+}
+
+impl CallbackRegistration for AudioSessionEventsRegistration {}
+
+impl Drop for AudioSessionEventsRegistration {
+    fn drop(&mut self) {
+        unsafe {
+            self.parent
+                .UnregisterAudioSessionNotification(&self.inner)
+                .ok()
+        };
+    }
+}
@@ -0,0 +1,354 @@
+//! Fake devices, property stores and endpoint volumes.
+
+use std::rc::Rc;
+
+use windows::core::GUID;
+use windows::Win32::Foundation::{E_INVALIDARG, STG_E_ACCESSDENIED};
+
+use crate::{
+    audio_endpoint_volume::{VolumeRange, VolumeStepInfo},
+    audio_endpoint_volume_callback::{AudioEndpointVolumeCallback, NotificationData},
+    backend::{
+        AudioEndpointVolumeBackend, AudioSessionManagerBackend, CallbackRegistration,
+        DeviceBackend, DeviceCollectionBackend, DeviceEnumeratorBackend, DeviceTopologyBackend,
+        PropertyStoreBackend,
+    },
+    bits::{DataFlow, DeviceState, HardwareSupportMask, StorageAccessMode},
+    error::Error,
+    property_store::{Property, PropertyKey},
+    string::WinString,
+};
+
+use super::{
+    session::FakeSessionManager, set_property, topology::FakeTopologyHandle, DeviceEntry,
+    FakeAudioSystem, FakeRegistration, Registration,
+};
+
+/// The devices are looked up when they are retrieved, so a device that was
+/// removed after the enumeration fails like it does on Windows.
+#[derive(Debug)]
+pub(super) struct FakeDeviceCollection {
+    pub(super) system: FakeAudioSystem,
+    pub(super) ids: Vec<WinString>,
+}
+
+impl DeviceCollectionBackend for FakeDeviceCollection {
+    fn get_count(&self) -> crate::Result<u32> {
+        Ok(self.ids.len() as u32)
+    }
+
+    fn item(&self, device: u32) -> crate::Result<Rc<dyn DeviceBackend>> {
+        let id = self
+            .ids
+            .get(device as usize)
+            .ok_or(Error::Other(E_INVALIDARG))?;
+        self.system.with_device(id, |_| ())?;
+        Ok(self.system.device(id.clone()))
+    }
+}
+
+#[derive(Debug)]
+pub(super) struct FakeDeviceHandle {
+    pub(super) system: FakeAudioSystem,
+    pub(super) id: WinString,
+}
+
+impl DeviceBackend for FakeDeviceHandle {
+    fn activate_audio_endpoint_volume(&self) -> crate::Result<Rc<dyn AudioEndpointVolumeBackend>> {
+        self.system.with_device(&self.id, |_| ())?;
+        Ok(Rc::new(FakeEndpointVolume {
+            system: self.system.clone(),
+            id: self.id.clone(),
+        }))
+    }
+
+    fn activate_audio_session_manager(&self) -> crate::Result<Rc<dyn AudioSessionManagerBackend>> {
+        self.system.with_device(&self.id, |_| ())?;
+        Ok(Rc::new(FakeSessionManager {
+            system: self.system.clone(),
+            id: self.id.clone(),
+        }))
+    }
+
+    fn activate_device_topology(&self) -> crate::Result<Rc<dyn DeviceTopologyBackend>> {
+        self.system.with_device(&self.id, |_| ())?;
+        Ok(Rc::new(FakeTopologyHandle {
+            system: self.system.clone(),
+            device_id: self.id.to_string_lossy(),
+        }))
+    }
+
+    fn get_data_flow(&self) -> crate::Result<DataFlow> {
+        self.system.with_device(&self.id, |device| device.data_flow)
+    }
+
+    fn get_id(&self) -> crate::Result<WinString> {
+        Ok(self.id.clone())
+    }
+
+    fn get_state(&self) -> crate::Result<DeviceState> {
+        self.system.with_device(&self.id, |device| device.state)
+    }
+
+    fn enumerator(&self) -> crate::Result<Rc<dyn DeviceEnumeratorBackend>> {
+        Ok(Rc::new(self.system.clone()))
+    }
+
+    fn open_property_store(
+        &self,
+        storage_access_mode: StorageAccessMode,
+    ) -> crate::Result<Rc<dyn PropertyStoreBackend>> {
+        self.system.with_device(&self.id, |_| ())?;
+        Ok(Rc::new(FakePropertyStore {
+            system: self.system.clone(),
+            id: self.id.clone(),
+            storage_access_mode,
+        }))
+    }
+}
+
+#[derive(Debug)]
+struct FakePropertyStore {
+    system: FakeAudioSystem,
+    id: WinString,
+    storage_access_mode: StorageAccessMode,
+}
+
+impl PropertyStoreBackend for FakePropertyStore {
+    fn commit(&self) -> crate::Result<()> {
+        Ok(())
+    }
+
+    fn get_at(&self, index: u32) -> crate::Result<PropertyKey> {
+        self.system
+            .with_device(&self.id, |device| {
+                device.properties.get(index as usize).map(|(key, _)| *key)
+            })?
+            .ok_or(Error::Other(E_INVALIDARG))
+    }
+
+    fn get_count(&self) -> crate::Result<u32> {
+        self.system
+            .with_device(&self.id, |device| device.properties.len() as u32)
+    }
+
+    fn get_value(&self, key: PropertyKey) -> crate::Result<Property> {
+        // Like Windows, a missing value is reported as an empty property.
+        self.system.with_device(&self.id, |device| {
+            device
+                .properties
+                .iter()
+                .find(|(x, _)| *x == key)
+                .map(|(_, value)| value.clone())
+                .unwrap_or(Property::Empty)
+        })
+    }
+
+    fn set_value(&self, key: &PropertyKey, property: &Property) -> crate::Result<()> {
+        if self.storage_access_mode == StorageAccessMode::Read {
+            return Err(Error::Other(STG_E_ACCESSDENIED));
+        }
+        self.system.with_device(&self.id, |device| {
+            set_property(&mut device.properties, *key, property.clone())
+        })?;
+        self.system.notify_property_changed(&self.id, *key);
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct FakeEndpointVolume {
+    system: FakeAudioSystem,
+    id: WinString,
+}
+
+impl FakeEndpointVolume {
+    /// Apply a change and notify the registered callbacks.
+    fn update(
+        &self,
+        event_context: Option<&GUID>,
+        f: impl FnOnce(&mut DeviceEntry) -> crate::Result<()>,
+    ) -> crate::Result<()> {
+        let (callbacks, muted, master_volume, channel_volumes) =
+            self.system.with_device(&self.id, |device| {
+                f(device)?;
+                Ok::<_, Error>((
+                    device
+                        .volume_callbacks
+                        .iter()
+                        .map(|(_, x)| x.clone())
+                        .collect::<Vec<_>>(),
+                    device.mute,
+                    device.master_volume,
+                    device.channel_volumes.clone(),
+                ))
+            })??;
+        let data = NotificationData {
+            event_context: event_context.copied().unwrap_or_else(GUID::zeroed),
+            muted,
+            master_volume,
+            channel_volumes: &channel_volumes,
+        };
+        for callback in callbacks {
+            let _ = callback.on_notify(&data);
+        }
+        Ok(())
+    }
+
+    fn channel<T>(&self, channel: u32, f: impl FnOnce(&DeviceEntry, f32) -> T) -> crate::Result<T> {
+        self.system
+            .with_device(&self.id, |device| {
+                let level = device.channel_volumes.get(channel as usize)?;
+                Some(f(device, *level))
+            })?
+            .ok_or(Error::Other(E_INVALIDARG))
+    }
+}
+
+impl AudioEndpointVolumeBackend for FakeEndpointVolume {
+    fn get_channel_count(&self) -> crate::Result<u32> {
+        self.system
+            .with_device(&self.id, |device| device.channel_volumes.len() as u32)
+    }
+
+    fn get_channel_volume_level(&self, channel: u32) -> crate::Result<f32> {
+        self.channel(channel, |device, level| device.scalar_to_db(level))
+    }
+
+    fn get_channel_volume_level_scalar(&self, channel: u32) -> crate::Result<f32> {
+        self.channel(channel, |_, level| level)
+    }
+
+    fn get_master_volume_level(&self) -> crate::Result<f32> {
+        self.system
+            .with_device(&self.id, |device| device.scalar_to_db(device.master_volume))
+    }
+
+    fn get_master_volume_level_scalar(&self) -> crate::Result<f32> {
+        self.system
+            .with_device(&self.id, |device| device.master_volume)
+    }
+
+    fn get_mute(&self) -> crate::Result<bool> {
+        self.system.with_device(&self.id, |device| device.mute)
+    }
+
+    fn get_volume_range(&self) -> crate::Result<VolumeRange> {
+        self.system
+            .with_device(&self.id, |device| device.volume_range.clone())
+    }
+
+    fn get_volume_step_info(&self) -> crate::Result<VolumeStepInfo> {
+        self.system.with_device(&self.id, |device| {
+            VolumeStepInfo::new(device.current_step(), device.num_steps)
+        })
+    }
+
+    fn query_hardware_support(&self) -> crate::Result<HardwareSupportMask> {
+        // Like Windows, the endpoint volume of a device that is not active
+        // does not work.
+        self.system.with_device(&self.id, |device| {
+            if device.state == DeviceState::Active {
+                Ok(device.hardware_support)
+            } else {
+                Err(Error::DeviceInvalidated)
+            }
+        })?
+    }
+
+    fn register_control_change_notify(
+        &self,
+        callback: Box<dyn AudioEndpointVolumeCallback>,
+    ) -> crate::Result<Rc<dyn CallbackRegistration>> {
+        let key = self.system.next_registration();
+        self.system.with_device(&self.id, |device| {
+            device.volume_callbacks.push((key, callback.into()))
+        })?;
+        Ok(Rc::new(FakeRegistration {
+            system: self.system.clone(),
+            registration: Some(Registration::Volume(self.id.clone(), key)),
+        }))
+    }
+
+    fn set_channel_volume_level(
+        &self,
+        channel: u32,
+        level_db: f32,
+        event_context: Option<&GUID>,
+    ) -> crate::Result<()> {
+        self.update(event_context, |device| {
+            let level = device.db_to_scalar(level_db);
+            *device
+                .channel_volumes
+                .get_mut(channel as usize)
+                .ok_or(Error::Other(E_INVALIDARG))? = level;
+            Ok(())
+        })
+    }
+
+    fn set_channel_volume_level_scalar(
+        &self,
+        channel: u32,
+        level: f32,
+        event_context: Option<&GUID>,
+    ) -> crate::Result<()> {
+        if !(0.0..=1.0).contains(&level) {
+            return Err(Error::Other(E_INVALIDARG));
+        }
+        self.update(event_context, |device| {
+            *device
+                .channel_volumes
+                .get_mut(channel as usize)
+                .ok_or(Error::Other(E_INVALIDARG))? = level;
+            Ok(())
+        })
+    }
+
+    fn set_master_volume_level(
+        &self,
+        level_db: f32,
+        event_context: Option<&GUID>,
+    ) -> crate::Result<()> {
+        self.update(event_context, |device| {
+            device.master_volume = device.db_to_scalar(level_db);
+            device.channel_volumes.fill(device.master_volume);
+            Ok(())
+        })
+    }
+
+    fn set_master_volume_level_scalar(
+        &self,
+        level: f32,
+        event_context: Option<&GUID>,
+    ) -> crate::Result<()> {
+        if !(0.0..=1.0).contains(&level) {
+            return Err(Error::Other(E_INVALIDARG));
+        }
+        self.update(event_context, |device| {
+            device.master_volume = level;
+            device.channel_volumes.fill(level);
+            Ok(())
+        })
+    }
+
+    fn set_mute(&self, mute: bool, event_context: Option<&GUID>) -> crate::Result<()> {
+        self.update(event_context, |device| {
+            device.mute = mute;
+            Ok(())
+        })
+    }
+
+    fn volume_step_down(&self, event_context: Option<&GUID>) -> crate::Result<()> {
+        self.update(event_context, |device| {
+            device.set_step(device.current_step().saturating_sub(1));
+            Ok(())
+        })
+    }
+
+    fn volume_step_up(&self, event_context: Option<&GUID>) -> crate::Result<()> {
+        self.update(event_context, |device| {
+            device.set_step(device.current_step() + 1);
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,882 @@
+//! An in-memory audio system that does not depend on the Windows APIs.
+//!
+//! [`FakeAudioSystem`] holds a list of devices and their sessions, and hands
+//! out a [`DeviceEnumerator`] that reads from it. Changing the fake system
+//! through its methods delivers the same notifications that Windows would,
+//! synchronously on the calling thread.
+
+use std::cell::RefCell;
+use std::fmt::{self, Debug, Formatter};
+use std::rc::Rc;
+
+use windows::core::GUID;
+use windows::Win32::Foundation::E_INVALIDARG;
+
+use crate::{
+    audio_endpoint_volume::VolumeRange,
+    audio_endpoint_volume_callback::AudioEndpointVolumeCallback,
+    audio_session_control::AudioSessionControl,
+    audio_session_events::AudioSessionEvents,
+    audio_session_notification::AudioSessionNotification,
+    audio_volume_duck_notification::AudioVolumeDuckNotification,
+    backend::{
+        CallbackRegistration, DeviceBackend, DeviceCollectionBackend, DeviceEnumeratorBackend,
+        PolicyConfigBackend,
+    },
+    bits::{
+        AudioSessionDisconnectReason, AudioSessionState, ConnectorDataFlow, ConnectorType,
+        DataFlow, DataFlowMask, DeviceRole, DeviceState, DeviceStateMask, HardwareSupportMask,
+    },
+    device_enumerator::DeviceEnumerator,
+    device_topology::{JackDescription, PartControl},
+    error::Error,
+    notification_client::NotificationClient,
+    property_store::{Property, PropertyKey},
+    string::{WinStr, WinString},
+};
+
+use self::{
+    device::{FakeDeviceCollection, FakeDeviceHandle},
+    session::FakeSessionControl,
+};
+
+mod device;
+mod session;
+mod topology;
+
+/// Description of a device to add to a [`FakeAudioSystem`].
+#[derive(Debug, Clone)]
+pub struct FakeDevice {
+    id: String,
+    data_flow: DataFlow,
+    state: DeviceState,
+    properties: Vec<(PropertyKey, Property)>,
+    channel_count: u32,
+    volume_range: (f32, f32, f32),
+    num_steps: u32,
+    hardware_support: HardwareSupportMask,
+}
+
+impl FakeDevice {
+    /// An active device with two channels and no properties.
+    pub fn new(id: &str, data_flow: DataFlow) -> Self {
+        Self {
+            id: id.to_owned(),
+            data_flow,
+            state: DeviceState::Active,
+            properties: Vec::new(),
+            channel_count: 2,
+            volume_range: (-65.25, 0.0, 0.03125),
+            num_steps: 101,
+            hardware_support: HardwareSupportMask::empty(),
+        }
+    }
+
+    pub fn state(mut self, state: DeviceState) -> Self {
+        self.state = state;
+        self
+    }
+
+    /// Add a value to the device's property store, replacing any previous
+    /// value of the same key.
+    pub fn property(mut self, key: PropertyKey, value: Property) -> Self {
+        set_property(&mut self.properties, key, value);
+        self
+    }
+
+    pub fn channel_count(mut self, channel_count: u32) -> Self {
+        self.channel_count = channel_count;
+        self
+    }
+
+    pub fn volume_range(mut self, min_db: f32, max_db: f32, increment_db: f32) -> Self {
+        self.volume_range = (min_db, max_db, increment_db);
+        self
+    }
+
+    pub fn num_steps(mut self, num_steps: u32) -> Self {
+        self.num_steps = num_steps.max(2);
+        self
+    }
+
+    pub fn hardware_support(mut self, hardware_support: HardwareSupportMask) -> Self {
+        self.hardware_support = hardware_support;
+        self
+    }
+}
+
+/// Description of an audio session to add to a [`FakeAudioSystem`].
+#[derive(Debug, Clone)]
+pub struct FakeSession {
+    instance_identifier: String,
+    identifier: String,
+    display_name: String,
+    icon_path: String,
+    grouping_param: GUID,
+    process_id: u32,
+    system_sounds: bool,
+    state: AudioSessionState,
+    volume: f32,
+    mute: bool,
+}
+
+impl FakeSession {
+    /// An inactive session at full volume.
+    ///
+    /// The instance identifier is used to address the session in the methods
+    /// of [`FakeAudioSystem`].
+    pub fn new(instance_identifier: &str) -> Self {
+        Self {
+            instance_identifier: instance_identifier.to_owned(),
+            identifier: instance_identifier.to_owned(),
+            display_name: String::new(),
+            icon_path: String::new(),
+            grouping_param: GUID::zeroed(),
+            process_id: 0,
+            system_sounds: false,
+            state: AudioSessionState::Inactive,
+            volume: 1.0,
+            mute: false,
+        }
+    }
+
+    pub fn identifier(mut self, identifier: &str) -> Self {
+        self.identifier = identifier.to_owned();
+        self
+    }
+
+    pub fn display_name(mut self, display_name: &str) -> Self {
+        self.display_name = display_name.to_owned();
+        self
+    }
+
+    pub fn icon_path(mut self, icon_path: &str) -> Self {
+        self.icon_path = icon_path.to_owned();
+        self
+    }
+
+    pub fn grouping_param(mut self, grouping_param: GUID) -> Self {
+        self.grouping_param = grouping_param;
+        self
+    }
+
+    pub fn process_id(mut self, process_id: u32) -> Self {
+        self.process_id = process_id;
+        self
+    }
+
+    pub fn system_sounds(mut self, system_sounds: bool) -> Self {
+        self.system_sounds = system_sounds;
+        self
+    }
+
+    pub fn state(mut self, state: AudioSessionState) -> Self {
+        self.state = state;
+        self
+    }
+
+    pub fn volume(mut self, volume: f32, mute: bool) -> Self {
+        self.volume = volume;
+        self.mute = mute;
+        self
+    }
+}
+
+/// Description of a part to add to a [`FakeTopology`].
+#[derive(Debug, Clone)]
+pub struct FakePart {
+    local_id: u32,
+    name: String,
+    kind: FakePartKind,
+    sub_type: GUID,
+    controls: Vec<PartControl>,
+    jacks: Vec<JackDescription>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum FakePartKind {
+    Connector(ConnectorType, ConnectorDataFlow),
+    Subunit,
+}
+
+impl FakePart {
+    /// A connector without jacks.
+    pub fn connector(
+        local_id: u32,
+        name: &str,
+        connector_type: ConnectorType,
+        data_flow: ConnectorDataFlow,
+    ) -> Self {
+        Self::new(
+            local_id,
+            name,
+            FakePartKind::Connector(connector_type, data_flow),
+        )
+    }
+
+    /// A subunit without controls.
+    pub fn subunit(local_id: u32, name: &str) -> Self {
+        Self::new(local_id, name, FakePartKind::Subunit)
+    }
+
+    fn new(local_id: u32, name: &str, kind: FakePartKind) -> Self {
+        Self {
+            local_id,
+            name: name.to_owned(),
+            kind,
+            sub_type: GUID::zeroed(),
+            controls: Vec::new(),
+            jacks: Vec::new(),
+        }
+    }
+
+    pub fn sub_type(mut self, sub_type: GUID) -> Self {
+        self.sub_type = sub_type;
+        self
+    }
+
+    /// Add a hardware control, which [`Part::controls`](crate::Part::controls)
+    /// reports in the order in which they were added.
+    pub fn control(mut self, control: PartControl) -> Self {
+        self.controls.push(control);
+        self
+    }
+
+    pub fn jack(mut self, jack: JackDescription) -> Self {
+        self.jacks.push(jack);
+        self
+    }
+}
+
+/// Description of the topology of a device to add to a [`FakeAudioSystem`].
+///
+/// The device can be an endpoint or an adapter that only appears in
+/// topologies.
+#[derive(Debug, Clone)]
+pub struct FakeTopology {
+    device_id: String,
+    parts: Vec<FakePart>,
+    links: Vec<(u32, u32)>,
+}
+
+impl FakeTopology {
+    pub fn new(device_id: &str) -> Self {
+        Self {
+            device_id: device_id.to_owned(),
+            parts: Vec::new(),
+            links: Vec::new(),
+        }
+    }
+
+    /// Add a part, replacing any previous part with the same local id.
+    pub fn part(mut self, part: FakePart) -> Self {
+        self.parts.retain(|x| x.local_id != part.local_id);
+        self.parts.push(part);
+        self
+    }
+
+    /// Link two parts by their local ids, with the signal flowing from `from`
+    /// to `to`.
+    pub fn link(mut self, from: u32, to: u32) -> Self {
+        if !self.links.contains(&(from, to)) {
+            self.links.push((from, to));
+        }
+        self
+    }
+}
+
+/// A shared handle to an in-memory audio system.
+///
+/// Clones refer to the same system.
+#[derive(Debug, Clone, Default)]
+pub struct FakeAudioSystem {
+    state: Rc<RefCell<SystemState>>,
+}
+
+impl FakeAudioSystem {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an enumerator that reads from this system.
+    pub fn device_enumerator(&self) -> DeviceEnumerator {
+        DeviceEnumerator::from_backend(self.clone())
+    }
+
+    /// Add a device and notify [`NotificationClient::on_device_added`].
+    ///
+    /// A device with the same id is replaced.
+    pub fn add_device(&self, device: FakeDevice) {
+        let id = WinString::from(&device.id);
+        let replaced = {
+            let mut state = self.state.borrow_mut();
+            let replaced = take_device(&mut state.devices, &id);
+            state.devices.push(DeviceEntry::new(id.clone(), device));
+            replaced
+        };
+        // Callbacks of the replaced device may call back into the system when
+        // they are dropped.
+        drop(replaced);
+        for client in self.notification_clients() {
+            let _ = client.on_device_added(&id);
+        }
+    }
+
+    /// Remove a device and notify [`NotificationClient::on_device_removed`].
+    ///
+    /// Handles to the device that are still alive fail with
+    /// [`Error::DeviceInvalidated`] afterwards.
+    pub fn remove_device(&self, device_id: &str) {
+        let id = WinString::from(device_id);
+        let removed = {
+            let mut state = self.state.borrow_mut();
+            state.defaults.retain(|(_, _, x)| *x != id);
+            take_device(&mut state.devices, &id)
+        };
+        if removed.is_some() {
+            drop(removed);
+            for client in self.notification_clients() {
+                let _ = client.on_device_removed(&id);
+            }
+        }
+    }
+
+    /// Change the state of a device and notify
+    /// [`NotificationClient::on_device_state_changed`].
+    pub fn set_device_state(&self, device_id: &str, device_state: DeviceState) {
+        let id = WinString::from(device_id);
+        let changed = self.with_device(&id, |device| {
+            let changed = device.state != device_state;
+            device.state = device_state;
+            changed
+        });
+        if changed == Ok(true) {
+            for client in self.notification_clients() {
+                let _ = client.on_device_state_changed(&id, device_state);
+            }
+        }
+    }
+
+    /// Make a device the default for the given data flow and role, and notify
+    /// [`NotificationClient::on_default_device_changed`].
+    pub fn set_default_device(&self, data_flow: DataFlow, role: DeviceRole, device_id: &str) {
+        let id = WinString::from(device_id);
+        {
+            let mut state = self.state.borrow_mut();
+            state
+                .defaults
+                .retain(|(flow, x, _)| !(*flow == data_flow && *x == role));
+            state.defaults.push((data_flow, role, id.clone()));
+        }
+        for client in self.notification_clients() {
+            let _ = client.on_default_device_changed(data_flow, role, &id);
+        }
+    }
+
+    /// Change a property of a device and notify
+    /// [`NotificationClient::on_property_value_changed`].
+    pub fn set_property(&self, device_id: &str, key: PropertyKey, value: Property) {
+        let id = WinString::from(device_id);
+        if self
+            .with_device(&id, |device| {
+                set_property(&mut device.properties, key, value)
+            })
+            .is_ok()
+        {
+            self.notify_property_changed(&id, key);
+        }
+    }
+
+    /// Set the topology of a device, replacing its previous topology.
+    ///
+    /// The topology of an endpoint without one is empty.
+    pub fn set_topology(&self, topology: FakeTopology) {
+        let mut state = self.state.borrow_mut();
+        state
+            .topologies
+            .retain(|x| x.device_id != topology.device_id);
+        state.topologies.push(topology);
+    }
+
+    /// Connect a connector of one device topology to a connector of another,
+    /// replacing their previous connections.
+    pub fn connect(
+        &self,
+        device_id: &str,
+        connector: u32,
+        other_device_id: &str,
+        other_connector: u32,
+    ) {
+        let ends = [
+            (device_id.to_owned(), connector),
+            (other_device_id.to_owned(), other_connector),
+        ];
+        let mut state = self.state.borrow_mut();
+        state
+            .connections
+            .retain(|[a, b]| !ends.contains(a) && !ends.contains(b));
+        state.connections.push(ends);
+    }
+
+    /// Add an audio session to a device and notify
+    /// [`AudioSessionNotification::on_session_created`].
+    pub fn add_session(&self, device_id: &str, session: FakeSession) {
+        let id = WinString::from(device_id);
+        let instance_identifier = WinString::from(&session.instance_identifier);
+        let notifications = self.with_device(&id, |device| {
+            device
+                .sessions
+                .retain(|x| x.instance_identifier != instance_identifier);
+            device.sessions.push(SessionEntry::new(session));
+            device
+                .session_notifications
+                .iter()
+                .map(|(_, x)| x.clone())
+                .collect::<Vec<_>>()
+        });
+        for notification in notifications.unwrap_or_default() {
+            let control = AudioSessionControl::new(Rc::new(FakeSessionControl {
+                system: self.clone(),
+                instance_identifier: instance_identifier.clone(),
+            }));
+            let _ = notification.on_session_created(control);
+        }
+    }
+
+    /// Change the state of a session and notify
+    /// [`AudioSessionEvents::on_state_changed`].
+    pub fn set_session_state(&self, instance_identifier: &str, session_state: AudioSessionState) {
+        let instance_identifier = WinString::from(instance_identifier);
+        let events = self.with_session(&instance_identifier, |session| {
+            session.state = session_state;
+            session.event_listeners()
+        });
+        for events in events.unwrap_or_default() {
+            let _ = events.on_state_changed(session_state);
+        }
+    }
+
+    /// Remove a session and notify
+    /// [`AudioSessionEvents::on_session_disconnected`].
+    pub fn disconnect_session(
+        &self,
+        instance_identifier: &str,
+        disconnect_reason: AudioSessionDisconnectReason,
+    ) {
+        let instance_identifier = WinString::from(instance_identifier);
+        let events = {
+            let mut state = self.state.borrow_mut();
+            let mut events = Vec::new();
+            for device in &mut state.devices {
+                if let Some(index) = device
+                    .sessions
+                    .iter()
+                    .position(|x| x.instance_identifier == instance_identifier)
+                {
+                    events = device.sessions.remove(index).event_listeners();
+                }
+            }
+            events
+        };
+        for events in events {
+            let _ = events.on_session_disconnected(disconnect_reason);
+        }
+    }
+
+    /// Notify [`AudioVolumeDuckNotification::on_volume_duck_notification`] on
+    /// every device that has a listener for the given session.
+    pub fn duck(&self, session_id: &str, num_communication_sessions: u32) {
+        let session_id = WinString::from(session_id);
+        for notification in self.duck_notifications(&session_id) {
+            let _ =
+                notification.on_volume_duck_notification(&session_id, num_communication_sessions);
+        }
+    }
+
+    /// Notify [`AudioVolumeDuckNotification::on_volume_unduck_notification`]
+    /// on every device that has a listener for the given session.
+    pub fn unduck(&self, session_id: &str) {
+        let session_id = WinString::from(session_id);
+        for notification in self.duck_notifications(&session_id) {
+            let _ = notification.on_volume_unduck_notification(&session_id);
+        }
+    }
+
+    fn notification_clients(&self) -> Vec<Rc<dyn NotificationClient>> {
+        let state = self.state.borrow();
+        state
+            .notification_clients
+            .iter()
+            .map(|(_, x)| x.clone())
+            .collect()
+    }
+
+    fn duck_notifications(&self, session_id: &WinStr) -> Vec<Rc<dyn AudioVolumeDuckNotification>> {
+        let state = self.state.borrow();
+        state
+            .devices
+            .iter()
+            .flat_map(|device| &device.duck_notifications)
+            .filter(|(_, id, _)| id == session_id)
+            .map(|(_, _, x)| x.clone())
+            .collect()
+    }
+
+    fn notify_property_changed(&self, device_id: &WinStr, key: PropertyKey) {
+        for client in self.notification_clients() {
+            let _ = client.on_property_value_changed(device_id, key);
+        }
+    }
+
+    fn next_registration(&self) -> usize {
+        let mut state = self.state.borrow_mut();
+        state.next_registration += 1;
+        state.next_registration
+    }
+
+    fn with_device<T>(
+        &self,
+        device_id: &WinStr,
+        f: impl FnOnce(&mut DeviceEntry) -> T,
+    ) -> crate::Result<T> {
+        let mut state = self.state.borrow_mut();
+        let device = state
+            .devices
+            .iter_mut()
+            .find(|x| x.id == *device_id)
+            .ok_or(Error::DeviceInvalidated)?;
+        Ok(f(device))
+    }
+
+    fn with_session<T>(
+        &self,
+        instance_identifier: &WinStr,
+        f: impl FnOnce(&mut SessionEntry) -> T,
+    ) -> crate::Result<T> {
+        let mut state = self.state.borrow_mut();
+        let session = state
+            .devices
+            .iter_mut()
+            .flat_map(|device| &mut device.sessions)
+            .find(|x| x.instance_identifier == *instance_identifier)
+            .ok_or(Error::DeviceInvalidated)?;
+        Ok(f(session))
+    }
+
+    fn device(&self, id: WinString) -> Rc<dyn DeviceBackend> {
+        Rc::new(FakeDeviceHandle {
+            system: self.clone(),
+            id,
+        })
+    }
+
+    fn unregister(&self, registration: Registration) {
+        // Take the callback out before dropping it, it may own other handles.
+        let removed: Option<Box<dyn std::any::Any>> = {
+            let mut state = self.state.borrow_mut();
+            match registration {
+                Registration::Endpoint(key) => take(&mut state.notification_clients, key),
+                Registration::Volume(ref id, key) => state
+                    .devices
+                    .iter_mut()
+                    .find(|x| x.id == *id)
+                    .and_then(|x| take(&mut x.volume_callbacks, key)),
+                Registration::Session(ref id, key) => state
+                    .devices
+                    .iter_mut()
+                    .find(|x| x.id == *id)
+                    .and_then(|x| take(&mut x.session_notifications, key)),
+                Registration::Duck(ref id, key) => state
+                    .devices
+                    .iter_mut()
+                    .find(|x| x.id == *id)
+                    .and_then(|x| {
+                        let index = x.duck_notifications.iter().position(|y| y.0 == key)?;
+                        Some(Box::new(x.duck_notifications.remove(index).2) as _)
+                    }),
+                Registration::Events(ref instance_identifier, key) => state
+                    .devices
+                    .iter_mut()
+                    .flat_map(|device| &mut device.sessions)
+                    .find(|x| x.instance_identifier == *instance_identifier)
+                    .and_then(|x| take(&mut x.events, key)),
+            }
+        };
+        drop(removed);
+    }
+}
+
+impl DeviceEnumeratorBackend for FakeAudioSystem {
+    fn enum_audio_endpoints(
+        &self,
+        data_flow_mask: DataFlowMask,
+        state_mask: DeviceStateMask,
+    ) -> crate::Result<Rc<dyn DeviceCollectionBackend>> {
+        if let DataFlowMask::Unknown(_) = data_flow_mask {
+            return Err(Error::Other(E_INVALIDARG));
+        }
+
+        let state = self.state.borrow();
+        let ids = state
+            .devices
+            .iter()
+            .filter(|x| match data_flow_mask {
+                DataFlowMask::Render => x.data_flow == DataFlow::Render,
+                DataFlowMask::Capture => x.data_flow == DataFlow::Capture,
+                DataFlowMask::All | DataFlowMask::Unknown(_) => true,
+            })
+            .filter(|x| state_mask.contains(DeviceStateMask::from_bits_truncate(x.state.to_raw())))
+            .map(|x| x.id.clone())
+            .collect();
+        Ok(Rc::new(FakeDeviceCollection {
+            system: self.clone(),
+            ids,
+        }))
+    }
+
+    fn get_default_audio_endpoint(
+        &self,
+        data_flow: DataFlow,
+        role: DeviceRole,
+    ) -> crate::Result<Rc<dyn DeviceBackend>> {
+        let state = self.state.borrow();
+        let (_, _, id) = state
+            .defaults
+            .iter()
+            .find(|(flow, x, _)| *flow == data_flow && *x == role)
+            .ok_or(Error::DeviceNotFound)?;
+        Ok(self.device(id.clone()))
+    }
+
+    fn get_device(&self, device_id: &WinStr) -> crate::Result<Rc<dyn DeviceBackend>> {
+        let state = self.state.borrow();
+        let device = state
+            .devices
+            .iter()
+            .find(|x| x.id == *device_id)
+            .ok_or(Error::DeviceNotFound)?;
+        Ok(self.device(device.id.clone()))
+    }
+
+    fn register_endpoint_notification(
+        &self,
+        notification_client: Box<dyn NotificationClient>,
+    ) -> crate::Result<Rc<dyn CallbackRegistration>> {
+        let key = self.next_registration();
+        self.state
+            .borrow_mut()
+            .notification_clients
+            .push((key, notification_client.into()));
+        Ok(Rc::new(FakeRegistration {
+            system: self.clone(),
+            registration: Some(Registration::Endpoint(key)),
+        }))
+    }
+
+    fn policy_config(&self) -> crate::Result<Rc<dyn PolicyConfigBackend>> {
+        Ok(Rc::new(self.clone()))
+    }
+}
+
+impl PolicyConfigBackend for FakeAudioSystem {
+    /// Make an active device the default, like
+    /// [`set_default_device`](Self::set_default_device).
+    fn set_default_endpoint(&self, device_id: &WinStr, role: DeviceRole) -> crate::Result<()> {
+        let (data_flow, state) = self
+            .with_device(device_id, |device| (device.data_flow, device.state))
+            .map_err(|_| Error::DeviceNotFound)?;
+        if state != DeviceState::Active {
+            return Err(Error::Other(E_INVALIDARG));
+        }
+        self.set_default_device(data_flow, role, &device_id.to_string_lossy());
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct SystemState {
+    devices: Vec<DeviceEntry>,
+    defaults: Vec<(DataFlow, DeviceRole, WinString)>,
+    topologies: Vec<FakeTopology>,
+    connections: Vec<[(String, u32); 2]>,
+    notification_clients: Vec<(usize, Rc<dyn NotificationClient>)>,
+    next_registration: usize,
+}
+
+impl Debug for SystemState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SystemState")
+            .field("devices", &self.devices)
+            .field("defaults", &self.defaults)
+            .field("topologies", &self.topologies)
+            .field("connections", &self.connections)
+            .finish_non_exhaustive()
+    }
+}
+
+struct DeviceEntry {
+    id: WinString,
+    data_flow: DataFlow,
+    state: DeviceState,
+    properties: Vec<(PropertyKey, Property)>,
+    channel_volumes: Vec<f32>,
+    master_volume: f32,
+    mute: bool,
+    volume_range: VolumeRange,
+    num_steps: u32,
+    hardware_support: HardwareSupportMask,
+    volume_callbacks: Vec<(usize, Rc<dyn AudioEndpointVolumeCallback>)>,
+    sessions: Vec<SessionEntry>,
+    session_notifications: Vec<(usize, Rc<dyn AudioSessionNotification>)>,
+    duck_notifications: Vec<(usize, WinString, Rc<dyn AudioVolumeDuckNotification>)>,
+}
+
+impl DeviceEntry {
+    fn new(id: WinString, device: FakeDevice) -> Self {
+        let (min_db, max_db, increment_db) = device.volume_range;
+        Self {
+            id,
+            data_flow: device.data_flow,
+            state: device.state,
+            properties: device.properties,
+            channel_volumes: vec![1.0; device.channel_count as usize],
+            master_volume: 1.0,
+            mute: false,
+            volume_range: VolumeRange::new(min_db, max_db, increment_db),
+            num_steps: device.num_steps,
+            hardware_support: device.hardware_support,
+            volume_callbacks: Vec::new(),
+            sessions: Vec::new(),
+            session_notifications: Vec::new(),
+            duck_notifications: Vec::new(),
+        }
+    }
+
+    fn scalar_to_db(&self, level: f32) -> f32 {
+        let range = &self.volume_range;
+        range.min_db + level * (range.max_db - range.min_db)
+    }
+
+    fn db_to_scalar(&self, level_db: f32) -> f32 {
+        let range = &self.volume_range;
+        if range.max_db > range.min_db {
+            ((level_db - range.min_db) / (range.max_db - range.min_db)).clamp(0.0, 1.0)
+        } else {
+            1.0
+        }
+    }
+
+    fn current_step(&self) -> u32 {
+        (self.master_volume * (self.num_steps - 1) as f32).round() as u32
+    }
+
+    fn set_step(&mut self, step: u32) {
+        self.master_volume = step.min(self.num_steps - 1) as f32 / (self.num_steps - 1) as f32;
+        self.channel_volumes.fill(self.master_volume);
+    }
+}
+
+impl Debug for DeviceEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeviceEntry")
+            .field("id", &self.id)
+            .field("data_flow", &self.data_flow)
+            .field("state", &self.state)
+            .field("properties", &self.properties)
+            .field("sessions", &self.sessions)
+            .finish_non_exhaustive()
+    }
+}
+
+struct SessionEntry {
+    instance_identifier: WinString,
+    identifier: WinString,
+    display_name: WinString,
+    icon_path: WinString,
+    grouping_param: GUID,
+    process_id: u32,
+    system_sounds: bool,
+    state: AudioSessionState,
+    volume: f32,
+    mute: bool,
+    ducking_opt_out: bool,
+    events: Vec<(usize, Rc<dyn AudioSessionEvents>)>,
+}
+
+impl SessionEntry {
+    fn new(session: FakeSession) -> Self {
+        Self {
+            instance_identifier: WinString::from(&session.instance_identifier),
+            identifier: WinString::from(&session.identifier),
+            display_name: WinString::from(&session.display_name),
+            icon_path: WinString::from(&session.icon_path),
+            grouping_param: session.grouping_param,
+            process_id: session.process_id,
+            system_sounds: session.system_sounds,
+            state: session.state,
+            volume: session.volume,
+            mute: session.mute,
+            ducking_opt_out: false,
+            events: Vec::new(),
+        }
+    }
+
+    fn event_listeners(&self) -> Vec<Rc<dyn AudioSessionEvents>> {
+        self.events.iter().map(|(_, x)| x.clone()).collect()
+    }
+}
+
+impl Debug for SessionEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SessionEntry")
+            .field("instance_identifier", &self.instance_identifier)
+            .field("display_name", &self.display_name)
+            .field("state", &self.state)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug)]
+enum Registration {
+    Endpoint(usize),
+    Volume(WinString, usize),
+    Session(WinString, usize),
+    Duck(WinString, usize),
+    Events(WinString, usize),
+}
+
+#[derive(Debug)]
+struct FakeRegistration {
+    system: FakeAudioSystem,
+    registration: Option<Registration>,
+}
+
+impl CallbackRegistration for FakeRegistration {}
+
+impl Drop for FakeRegistration {
+    fn drop(&mut self) {
+        if let Some(registration) = self.registration.take() {
+            self.system.unregister(registration);
+        }
+    }
+}
+
+fn take<T: ?Sized + 'static>(
+    list: &mut Vec<(usize, Rc<T>)>,
+    key: usize,
+) -> Option<Box<dyn std::any::Any>> {
+    let index = list.iter().position(|(x, _)| *x == key)?;
+    Some(Box::new(list.remove(index).1))
+}
+
+fn take_device(devices: &mut Vec<DeviceEntry>, device_id: &WinStr) -> Option<DeviceEntry> {
+    let index = devices.iter().position(|x| x.id == *device_id)?;
+    Some(devices.remove(index))
+}
+
+fn set_property(properties: &mut Vec<(PropertyKey, Property)>, key: PropertyKey, value: Property) {
+    match properties.iter_mut().find(|(x, _)| *x == key) {
+        Some((_, x)) => *x = value,
+        None => properties.push((key, value)),
+    }
+}
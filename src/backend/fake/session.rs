@@ -0,0 +1,290 @@
+//! Fake audio sessions.
+
+use std::convert::TryFrom;
+use std::rc::Rc;
+
+use windows::core::GUID;
+use windows::Win32::Foundation::E_INVALIDARG;
+
+use crate::{
+    audio_session_events::AudioSessionEvents,
+    audio_session_notification::AudioSessionNotification,
+    audio_volume_duck_notification::AudioVolumeDuckNotification,
+    backend::{
+        AudioSessionControlBackend, AudioSessionEnumeratorBackend, AudioSessionManagerBackend,
+        CallbackRegistration, SimpleAudioVolumeBackend,
+    },
+    bits::AudioSessionState,
+    error::Error,
+    string::{WinStr, WinString},
+};
+
+use super::{FakeAudioSystem, FakeRegistration, Registration, SessionEntry};
+
+#[derive(Debug)]
+pub(super) struct FakeSessionManager {
+    pub(super) system: FakeAudioSystem,
+    pub(super) id: WinString,
+}
+
+impl FakeSessionManager {
+    fn session(&self, instance_identifier: WinString) -> FakeSessionControl {
+        FakeSessionControl {
+            system: self.system.clone(),
+            instance_identifier,
+        }
+    }
+
+    /// Sessions are looked up by their grouping parameter, which is how the
+    /// fake identifies the session GUID passed to Windows.
+    fn find_session(&self, audio_session_guid: &GUID) -> crate::Result<WinString> {
+        self.system
+            .with_device(&self.id, |device| {
+                device
+                    .sessions
+                    .iter()
+                    .find(|x| x.grouping_param == *audio_session_guid)
+                    .map(|x| x.instance_identifier.clone())
+            })?
+            .ok_or(Error::DeviceNotFound)
+    }
+}
+
+impl AudioSessionManagerBackend for FakeSessionManager {
+    fn get_audio_session_control(
+        &self,
+        audio_session_guid: &GUID,
+    ) -> crate::Result<Rc<dyn AudioSessionControlBackend>> {
+        let instance_identifier = self.find_session(audio_session_guid)?;
+        Ok(Rc::new(self.session(instance_identifier)))
+    }
+
+    fn get_simple_audio_volume(
+        &self,
+        audio_session_guid: &GUID,
+    ) -> crate::Result<Rc<dyn SimpleAudioVolumeBackend>> {
+        let instance_identifier = self.find_session(audio_session_guid)?;
+        Ok(Rc::new(self.session(instance_identifier)))
+    }
+
+    fn get_session_enumerator(&self) -> crate::Result<Rc<dyn AudioSessionEnumeratorBackend>> {
+        let sessions = self.system.with_device(&self.id, |device| {
+            device
+                .sessions
+                .iter()
+                .map(|x| x.instance_identifier.clone())
+                .collect::<Vec<_>>()
+        })?;
+        Ok(Rc::new(FakeSessionEnumerator {
+            system: self.system.clone(),
+            sessions,
+        }))
+    }
+
+    fn register_duck_notification(
+        &self,
+        session_id: &WinStr,
+        duck_notification: Box<dyn AudioVolumeDuckNotification>,
+    ) -> crate::Result<Rc<dyn CallbackRegistration>> {
+        let key = self.system.next_registration();
+        self.system.with_device(&self.id, |device| {
+            device.duck_notifications.push((
+                key,
+                session_id.to_winstring(),
+                duck_notification.into(),
+            ))
+        })?;
+        Ok(Rc::new(FakeRegistration {
+            system: self.system.clone(),
+            registration: Some(Registration::Duck(self.id.clone(), key)),
+        }))
+    }
+
+    fn register_session_notification(
+        &self,
+        session_notification: Box<dyn AudioSessionNotification>,
+    ) -> crate::Result<Rc<dyn CallbackRegistration>> {
+        let key = self.system.next_registration();
+        self.system.with_device(&self.id, |device| {
+            device
+                .session_notifications
+                .push((key, session_notification.into()))
+        })?;
+        Ok(Rc::new(FakeRegistration {
+            system: self.system.clone(),
+            registration: Some(Registration::Session(self.id.clone(), key)),
+        }))
+    }
+}
+
+/// Like [`FakeDeviceCollection`](super::device::FakeDeviceCollection), the
+/// sessions are looked up when they are retrieved.
+#[derive(Debug)]
+struct FakeSessionEnumerator {
+    system: FakeAudioSystem,
+    sessions: Vec<WinString>,
+}
+
+impl AudioSessionEnumeratorBackend for FakeSessionEnumerator {
+    fn get_count(&self) -> crate::Result<i32> {
+        Ok(self.sessions.len() as i32)
+    }
+
+    fn get_session(&self, session: i32) -> crate::Result<Rc<dyn AudioSessionControlBackend>> {
+        let instance_identifier = usize::try_from(session)
+            .ok()
+            .and_then(|x| self.sessions.get(x))
+            .ok_or(Error::Other(E_INVALIDARG))?;
+        self.system.with_session(instance_identifier, |_| ())?;
+        Ok(Rc::new(FakeSessionControl {
+            system: self.system.clone(),
+            instance_identifier: instance_identifier.clone(),
+        }))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct FakeSessionControl {
+    pub(super) system: FakeAudioSystem,
+    pub(super) instance_identifier: WinString,
+}
+
+impl FakeSessionControl {
+    fn get<T>(&self, f: impl FnOnce(&SessionEntry) -> T) -> crate::Result<T> {
+        self.system
+            .with_session(&self.instance_identifier, |session| f(session))
+    }
+
+    /// Apply a change and notify the registered session events.
+    fn update(
+        &self,
+        f: impl FnOnce(&mut SessionEntry),
+        notify: impl Fn(&dyn AudioSessionEvents) -> crate::Result<()>,
+    ) -> crate::Result<()> {
+        let events = self
+            .system
+            .with_session(&self.instance_identifier, |session| {
+                f(session);
+                session.event_listeners()
+            })?;
+        for events in events {
+            let _ = notify(&*events);
+        }
+        Ok(())
+    }
+}
+
+impl AudioSessionControlBackend for FakeSessionControl {
+    fn get_display_name(&self) -> crate::Result<WinString> {
+        self.get(|session| session.display_name.clone())
+    }
+
+    fn get_grouping_param(&self) -> crate::Result<GUID> {
+        self.get(|session| session.grouping_param)
+    }
+
+    fn get_icon_path(&self) -> crate::Result<WinString> {
+        self.get(|session| session.icon_path.clone())
+    }
+
+    fn get_state(&self) -> crate::Result<AudioSessionState> {
+        self.get(|session| session.state)
+    }
+
+    fn register_audio_session_notification(
+        &self,
+        session_notification: Box<dyn AudioSessionEvents>,
+    ) -> crate::Result<Rc<dyn CallbackRegistration>> {
+        let key = self.system.next_registration();
+        self.system
+            .with_session(&self.instance_identifier, |session| {
+                session.events.push((key, session_notification.into()))
+            })?;
+        Ok(Rc::new(FakeRegistration {
+            system: self.system.clone(),
+            registration: Some(Registration::Events(self.instance_identifier.clone(), key)),
+        }))
+    }
+
+    fn set_display_name(&self, value: &WinStr, event_context: Option<&GUID>) -> crate::Result<()> {
+        self.update(
+            |session| session.display_name = value.to_winstring(),
+            |events| events.on_display_name_changed(value, event_context),
+        )
+    }
+
+    fn set_grouping_param(&self, value: &GUID, event_context: Option<&GUID>) -> crate::Result<()> {
+        self.update(
+            |session| session.grouping_param = *value,
+            |events| events.on_grouping_param_changed(Some(value), event_context),
+        )
+    }
+
+    fn set_icon_path(&self, value: &WinStr, event_context: Option<&GUID>) -> crate::Result<()> {
+        self.update(
+            |session| session.icon_path = value.to_winstring(),
+            |events| events.on_icon_path_changed(value, event_context),
+        )
+    }
+
+    fn get_simple_audio_volume(&self) -> crate::Result<Rc<dyn SimpleAudioVolumeBackend>> {
+        self.get(|_| ())?;
+        Ok(Rc::new(self.clone()))
+    }
+
+    fn get_process_id(&self) -> crate::Result<u32> {
+        self.get(|session| session.process_id)
+    }
+
+    fn get_session_identifier(&self) -> crate::Result<WinString> {
+        self.get(|session| session.identifier.clone())
+    }
+
+    fn get_session_instance_identifier(&self) -> crate::Result<WinString> {
+        self.get(|session| session.instance_identifier.clone())
+    }
+
+    fn is_system_sounds_session(&self) -> crate::Result<bool> {
+        self.get(|session| session.system_sounds)
+    }
+
+    fn set_ducking_preference(&self, opt_out: bool) -> crate::Result<()> {
+        self.system
+            .with_session(&self.instance_identifier, |session| {
+                session.ducking_opt_out = opt_out
+            })
+    }
+}
+
+impl SimpleAudioVolumeBackend for FakeSessionControl {
+    fn get_master_volume(&self) -> crate::Result<f32> {
+        self.get(|session| session.volume)
+    }
+
+    fn get_mute(&self) -> crate::Result<bool> {
+        self.get(|session| session.mute)
+    }
+
+    fn set_master_volume(
+        &self,
+        volume_level: f32,
+        event_context: Option<&GUID>,
+    ) -> crate::Result<()> {
+        if !(0.0..=1.0).contains(&volume_level) {
+            return Err(Error::Other(E_INVALIDARG));
+        }
+        let mute = self.get(|session| session.mute)?;
+        self.update(
+            |session| session.volume = volume_level,
+            |events| events.on_simple_volume_changed(volume_level, mute, event_context),
+        )
+    }
+
+    fn set_mute(&self, mute: bool, event_context: Option<&GUID>) -> crate::Result<()> {
+        let volume = self.get(|session| session.volume)?;
+        self.update(
+            |session| session.mute = mute,
+            |events| events.on_simple_volume_changed(volume, mute, event_context),
+        )
+    }
+}
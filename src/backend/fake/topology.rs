@@ -0,0 +1,299 @@
+//! Fake device topologies, parts and connectors.
+
+use std::rc::Rc;
+
+use windows::core::GUID;
+use windows::Win32::Foundation::{E_INVALIDARG, E_NOINTERFACE};
+
+use crate::{
+    backend::{ConnectorBackend, DeviceTopologyBackend, PartBackend, PartsListBackend},
+    bits::{ConnectorDataFlow, ConnectorType, PartType},
+    device_topology::{JackDescription, PartControl},
+    error::Error,
+    string::WinString,
+};
+
+use super::{FakeAudioSystem, FakePart, FakePartKind};
+
+impl FakeAudioSystem {
+    /// Read the parts of a topology. A device without a topology has no
+    /// parts.
+    fn with_parts<T>(&self, device_id: &str, f: impl FnOnce(&[FakePart]) -> T) -> T {
+        let state = self.state.borrow();
+        let parts = state
+            .topologies
+            .iter()
+            .find(|x| x.device_id == device_id)
+            .map_or(&[][..], |x| &x.parts);
+        f(parts)
+    }
+
+    fn with_part<T>(
+        &self,
+        device_id: &str,
+        local_id: u32,
+        f: impl FnOnce(&FakePart) -> T,
+    ) -> crate::Result<T> {
+        self.with_parts(device_id, |parts| {
+            parts
+                .iter()
+                .find(|x| x.local_id == local_id)
+                .map(f)
+                .ok_or(Error::DeviceInvalidated)
+        })
+    }
+
+    /// The local ids of the parts that are linked to `local_id`, downstream
+    /// if `outgoing` is set and upstream otherwise.
+    fn linked_parts(&self, device_id: &str, local_id: u32, outgoing: bool) -> Vec<u32> {
+        let state = self.state.borrow();
+        state
+            .topologies
+            .iter()
+            .filter(|x| x.device_id == device_id)
+            .flat_map(|x| &x.links)
+            .filter_map(|&(from, to)| match outgoing {
+                true if from == local_id => Some(to),
+                false if to == local_id => Some(from),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn connected_to(&self, device_id: &str, local_id: u32) -> Option<(String, u32)> {
+        let state = self.state.borrow();
+        state.connections.iter().find_map(|[a, b]| {
+            if a.0 == device_id && a.1 == local_id {
+                Some(b.clone())
+            } else if b.0 == device_id && b.1 == local_id {
+                Some(a.clone())
+            } else {
+                None
+            }
+        })
+    }
+
+    fn part(&self, device_id: &str, local_id: u32) -> FakePartHandle {
+        FakePartHandle {
+            system: self.clone(),
+            device_id: device_id.to_owned(),
+            local_id,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(super) struct FakeTopologyHandle {
+    pub(super) system: FakeAudioSystem,
+    pub(super) device_id: String,
+}
+
+impl FakeTopologyHandle {
+    /// The local ids of the connectors or of the subunits.
+    fn local_ids(&self, connectors: bool) -> Vec<u32> {
+        self.system.with_parts(&self.device_id, |parts| {
+            parts
+                .iter()
+                .filter(|x| matches!(x.kind, FakePartKind::Connector(..)) == connectors)
+                .map(|x| x.local_id)
+                .collect()
+        })
+    }
+}
+
+impl DeviceTopologyBackend for FakeTopologyHandle {
+    fn get_connector_count(&self) -> crate::Result<u32> {
+        Ok(self.local_ids(true).len() as u32)
+    }
+
+    fn get_connector(&self, index: u32) -> crate::Result<Rc<dyn ConnectorBackend>> {
+        let local_id = *self
+            .local_ids(true)
+            .get(index as usize)
+            .ok_or(Error::Other(E_INVALIDARG))?;
+        Ok(Rc::new(self.system.part(&self.device_id, local_id)))
+    }
+
+    fn get_subunit_count(&self) -> crate::Result<u32> {
+        Ok(self.local_ids(false).len() as u32)
+    }
+
+    fn get_subunit(&self, index: u32) -> crate::Result<Rc<dyn PartBackend>> {
+        let local_id = *self
+            .local_ids(false)
+            .get(index as usize)
+            .ok_or(Error::Other(E_INVALIDARG))?;
+        Ok(Rc::new(self.system.part(&self.device_id, local_id)))
+    }
+
+    fn get_part_by_id(&self, local_id: u32) -> crate::Result<Rc<dyn PartBackend>> {
+        self.system
+            .with_part(&self.device_id, local_id, |_| ())
+            .map_err(|_| Error::Other(E_INVALIDARG))?;
+        Ok(Rc::new(self.system.part(&self.device_id, local_id)))
+    }
+
+    fn get_device_id(&self) -> crate::Result<WinString> {
+        Ok(WinString::from(&self.device_id))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct FakePartHandle {
+    system: FakeAudioSystem,
+    device_id: String,
+    local_id: u32,
+}
+
+impl FakePartHandle {
+    fn connector(&self) -> crate::Result<(ConnectorType, ConnectorDataFlow)> {
+        match self
+            .system
+            .with_part(&self.device_id, self.local_id, |x| x.kind)?
+        {
+            FakePartKind::Connector(connector_type, data_flow) => Ok((connector_type, data_flow)),
+            FakePartKind::Subunit => Err(Error::Other(E_NOINTERFACE)),
+        }
+    }
+
+    fn linked_parts(&self, outgoing: bool) -> crate::Result<Rc<dyn PartsListBackend>> {
+        self.system
+            .with_part(&self.device_id, self.local_id, |_| ())?;
+        let parts: Vec<Rc<dyn PartBackend>> = self
+            .system
+            .linked_parts(&self.device_id, self.local_id, outgoing)
+            .into_iter()
+            .map(|local_id| Rc::new(self.system.part(&self.device_id, local_id)) as _)
+            .collect();
+        // Like Windows, a part without links fails with `E_NOTFOUND`.
+        if parts.is_empty() {
+            return Err(Error::DeviceNotFound);
+        }
+        Ok(Rc::new(FakePartsList { parts }))
+    }
+
+    fn peer(&self) -> crate::Result<(String, u32)> {
+        self.connector()?;
+        self.system
+            .connected_to(&self.device_id, self.local_id)
+            .ok_or(Error::DeviceNotFound)
+    }
+}
+
+/// The global id that a fake part is known by across topologies.
+fn global_id(device_id: &str, local_id: u32) -> WinString {
+    WinString::from(format!("{}/{:08x}", device_id, local_id))
+}
+
+impl PartBackend for FakePartHandle {
+    fn get_name(&self) -> crate::Result<WinString> {
+        self.system
+            .with_part(&self.device_id, self.local_id, |x| WinString::from(&x.name))
+    }
+
+    fn get_local_id(&self) -> crate::Result<u32> {
+        Ok(self.local_id)
+    }
+
+    fn get_global_id(&self) -> crate::Result<WinString> {
+        Ok(global_id(&self.device_id, self.local_id))
+    }
+
+    fn get_part_type(&self) -> crate::Result<PartType> {
+        self.system
+            .with_part(&self.device_id, self.local_id, |x| match x.kind {
+                FakePartKind::Connector(..) => PartType::Connector,
+                FakePartKind::Subunit => PartType::Subunit,
+            })
+    }
+
+    fn get_sub_type(&self) -> crate::Result<GUID> {
+        self.system
+            .with_part(&self.device_id, self.local_id, |x| x.sub_type)
+    }
+
+    fn enum_parts_incoming(&self) -> crate::Result<Rc<dyn PartsListBackend>> {
+        self.linked_parts(false)
+    }
+
+    fn enum_parts_outgoing(&self) -> crate::Result<Rc<dyn PartsListBackend>> {
+        self.linked_parts(true)
+    }
+
+    fn get_topology_object(&self) -> crate::Result<Rc<dyn DeviceTopologyBackend>> {
+        Ok(Rc::new(FakeTopologyHandle {
+            system: self.system.clone(),
+            device_id: self.device_id.clone(),
+        }))
+    }
+
+    fn as_connector(&self) -> crate::Result<Rc<dyn ConnectorBackend>> {
+        self.connector()?;
+        Ok(Rc::new(self.clone()))
+    }
+
+    fn controls(&self) -> crate::Result<Vec<PartControl>> {
+        self.system
+            .with_part(&self.device_id, self.local_id, |x| x.controls.clone())
+    }
+
+    fn jack_descriptions(&self) -> crate::Result<Vec<JackDescription>> {
+        self.system
+            .with_part(&self.device_id, self.local_id, |x| x.jacks.clone())
+    }
+}
+
+impl ConnectorBackend for FakePartHandle {
+    fn get_type(&self) -> crate::Result<ConnectorType> {
+        Ok(self.connector()?.0)
+    }
+
+    fn get_data_flow(&self) -> crate::Result<ConnectorDataFlow> {
+        Ok(self.connector()?.1)
+    }
+
+    fn is_connected(&self) -> crate::Result<bool> {
+        self.connector()?;
+        Ok(self
+            .system
+            .connected_to(&self.device_id, self.local_id)
+            .is_some())
+    }
+
+    fn get_connected_to(&self) -> crate::Result<Rc<dyn ConnectorBackend>> {
+        let (device_id, local_id) = self.peer()?;
+        Ok(Rc::new(self.system.part(&device_id, local_id)))
+    }
+
+    fn get_connector_id_connected_to(&self) -> crate::Result<WinString> {
+        let (device_id, local_id) = self.peer()?;
+        Ok(global_id(&device_id, local_id))
+    }
+
+    fn get_device_id_connected_to(&self) -> crate::Result<WinString> {
+        let (device_id, _) = self.peer()?;
+        Ok(WinString::from(device_id))
+    }
+
+    fn as_part(&self) -> crate::Result<Rc<dyn PartBackend>> {
+        Ok(Rc::new(self.clone()))
+    }
+}
+
+#[derive(Debug)]
+struct FakePartsList {
+    parts: Vec<Rc<dyn PartBackend>>,
+}
+
+impl PartsListBackend for FakePartsList {
+    fn get_count(&self) -> crate::Result<u32> {
+        Ok(self.parts.len() as u32)
+    }
+
+    fn get_part(&self, index: u32) -> crate::Result<Rc<dyn PartBackend>> {
+        self.parts
+            .get(index as usize)
+            .cloned()
+            .ok_or(Error::Other(E_INVALIDARG))
+    }
+}
@@ -0,0 +1,330 @@
+//! Backend traits that the public wrapper types dispatch to.
+//!
+//! Every wrapper in this crate, like [`DeviceEnumerator`] or [`Device`], holds
+//! a reference-counted backend object instead of a raw COM interface. On
+//! Windows, [`DeviceEnumerator::new`] creates the backend that forwards to the
+//! Core Audio APIs. The [`fake`] module provides an in-memory implementation
+//! that runs on any platform, and custom backends can be plugged in through
+//! [`DeviceEnumerator::from_backend`].
+//!
+//! [`DeviceEnumerator`]: crate::DeviceEnumerator
+//! [`DeviceEnumerator::new`]: crate::DeviceEnumerator::new
+//! [`DeviceEnumerator::from_backend`]: crate::DeviceEnumerator::from_backend
+//! [`Device`]: crate::Device
+
+use std::fmt::Debug;
+use std::rc::Rc;
+
+use windows::core::GUID;
+
+use crate::{
+    audio_endpoint_volume::{VolumeRange, VolumeStepInfo},
+    audio_endpoint_volume_callback::AudioEndpointVolumeCallback,
+    audio_session_events::AudioSessionEvents,
+    audio_session_notification::AudioSessionNotification,
+    audio_volume_duck_notification::AudioVolumeDuckNotification,
+    bits::{
+        AudioSessionState, ConnectorDataFlow, ConnectorType, DataFlow, DataFlowMask, DeviceRole,
+        DeviceState, DeviceStateMask, HardwareSupportMask, PartType, StorageAccessMode,
+    },
+    device_topology::{JackDescription, PartControl},
+    notification_client::NotificationClient,
+    property_store::{Property, PropertyKey},
+    string::{WinStr, WinString},
+};
+
+pub(crate) mod com;
+pub mod fake;
+
+/// Keeps a callback registered for as long as it is alive.
+///
+/// Implementations unregister the callback when dropped.
+pub trait CallbackRegistration: Debug {}
+
+/// Backend of [`DeviceEnumerator`](crate::DeviceEnumerator).
+pub trait DeviceEnumeratorBackend: Debug {
+    fn enum_audio_endpoints(
+        &self,
+        data_flow_mask: DataFlowMask,
+        state_mask: DeviceStateMask,
+    ) -> crate::Result<Rc<dyn DeviceCollectionBackend>>;
+
+    fn get_default_audio_endpoint(
+        &self,
+        data_flow: DataFlow,
+        role: DeviceRole,
+    ) -> crate::Result<Rc<dyn DeviceBackend>>;
+
+    fn get_device(&self, device_id: &WinStr) -> crate::Result<Rc<dyn DeviceBackend>>;
+
+    fn register_endpoint_notification(
+        &self,
+        notification_client: Box<dyn NotificationClient>,
+    ) -> crate::Result<Rc<dyn CallbackRegistration>>;
+
+    /// Called once by a [`DeviceEnumerator`](crate::DeviceEnumerator), which
+    /// shares the result with its clones.
+    fn policy_config(&self) -> crate::Result<Rc<dyn PolicyConfigBackend>>;
+}
+
+/// Changes the default endpoints, see
+/// [`DeviceEnumerator::set_default_audio_endpoint`](crate::DeviceEnumerator::set_default_audio_endpoint).
+///
+/// On Windows this forwards to the undocumented `IPolicyConfig` interface.
+pub trait PolicyConfigBackend: Debug {
+    fn set_default_endpoint(&self, device_id: &WinStr, role: DeviceRole) -> crate::Result<()>;
+}
+
+/// Backend of [`DeviceCollection`](crate::DeviceCollection).
+pub trait DeviceCollectionBackend: Debug {
+    fn get_count(&self) -> crate::Result<u32>;
+
+    fn item(&self, device: u32) -> crate::Result<Rc<dyn DeviceBackend>>;
+}
+
+/// Backend of [`Device`](crate::Device).
+pub trait DeviceBackend: Debug {
+    fn activate_audio_endpoint_volume(&self) -> crate::Result<Rc<dyn AudioEndpointVolumeBackend>>;
+
+    fn activate_audio_session_manager(&self) -> crate::Result<Rc<dyn AudioSessionManagerBackend>>;
+
+    fn activate_device_topology(&self) -> crate::Result<Rc<dyn DeviceTopologyBackend>>;
+
+    fn get_data_flow(&self) -> crate::Result<DataFlow>;
+
+    fn get_id(&self) -> crate::Result<WinString>;
+
+    fn get_state(&self) -> crate::Result<DeviceState>;
+
+    /// An enumerator of the system that the device belongs to.
+    fn enumerator(&self) -> crate::Result<Rc<dyn DeviceEnumeratorBackend>>;
+
+    fn open_property_store(
+        &self,
+        storage_access_mode: StorageAccessMode,
+    ) -> crate::Result<Rc<dyn PropertyStoreBackend>>;
+}
+
+/// Backend of [`DeviceTopology`](crate::DeviceTopology).
+pub trait DeviceTopologyBackend: Debug {
+    fn get_connector_count(&self) -> crate::Result<u32>;
+
+    fn get_connector(&self, index: u32) -> crate::Result<Rc<dyn ConnectorBackend>>;
+
+    fn get_subunit_count(&self) -> crate::Result<u32>;
+
+    fn get_subunit(&self, index: u32) -> crate::Result<Rc<dyn PartBackend>>;
+
+    fn get_part_by_id(&self, local_id: u32) -> crate::Result<Rc<dyn PartBackend>>;
+
+    fn get_device_id(&self) -> crate::Result<WinString>;
+}
+
+/// Backend of [`Part`](crate::Part) and [`Subunit`](crate::Subunit).
+pub trait PartBackend: Debug {
+    fn get_name(&self) -> crate::Result<WinString>;
+
+    fn get_local_id(&self) -> crate::Result<u32>;
+
+    fn get_global_id(&self) -> crate::Result<WinString>;
+
+    fn get_part_type(&self) -> crate::Result<PartType>;
+
+    fn get_sub_type(&self) -> crate::Result<GUID>;
+
+    /// Fails with [`Error::DeviceNotFound`](crate::Error::DeviceNotFound) if
+    /// no part links to this one.
+    fn enum_parts_incoming(&self) -> crate::Result<Rc<dyn PartsListBackend>>;
+
+    /// Fails with [`Error::DeviceNotFound`](crate::Error::DeviceNotFound) if
+    /// this part links to no other part.
+    fn enum_parts_outgoing(&self) -> crate::Result<Rc<dyn PartsListBackend>>;
+
+    fn get_topology_object(&self) -> crate::Result<Rc<dyn DeviceTopologyBackend>>;
+
+    fn as_connector(&self) -> crate::Result<Rc<dyn ConnectorBackend>>;
+
+    /// The current values of the control interfaces that the part has.
+    fn controls(&self) -> crate::Result<Vec<PartControl>>;
+
+    fn jack_descriptions(&self) -> crate::Result<Vec<JackDescription>>;
+}
+
+/// Backend of [`PartsList`](crate::PartsList).
+pub trait PartsListBackend: Debug {
+    fn get_count(&self) -> crate::Result<u32>;
+
+    fn get_part(&self, index: u32) -> crate::Result<Rc<dyn PartBackend>>;
+}
+
+/// Backend of [`Connector`](crate::Connector).
+pub trait ConnectorBackend: Debug {
+    fn get_type(&self) -> crate::Result<ConnectorType>;
+
+    fn get_data_flow(&self) -> crate::Result<ConnectorDataFlow>;
+
+    fn is_connected(&self) -> crate::Result<bool>;
+
+    fn get_connected_to(&self) -> crate::Result<Rc<dyn ConnectorBackend>>;
+
+    fn get_connector_id_connected_to(&self) -> crate::Result<WinString>;
+
+    fn get_device_id_connected_to(&self) -> crate::Result<WinString>;
+
+    fn as_part(&self) -> crate::Result<Rc<dyn PartBackend>>;
+}
+
+/// Backend of [`PropertyStore`](crate::PropertyStore).
+pub trait PropertyStoreBackend: Debug {
+    fn commit(&self) -> crate::Result<()>;
+
+    fn get_at(&self, index: u32) -> crate::Result<PropertyKey>;
+
+    fn get_count(&self) -> crate::Result<u32>;
+
+    fn get_value(&self, key: PropertyKey) -> crate::Result<Property>;
+
+    fn set_value(&self, key: &PropertyKey, property: &Property) -> crate::Result<()>;
+}
+
+/// Backend of [`AudioEndpointVolume`](crate::AudioEndpointVolume).
+pub trait AudioEndpointVolumeBackend: Debug {
+    fn get_channel_count(&self) -> crate::Result<u32>;
+
+    fn get_channel_volume_level(&self, channel: u32) -> crate::Result<f32>;
+
+    fn get_channel_volume_level_scalar(&self, channel: u32) -> crate::Result<f32>;
+
+    fn get_master_volume_level(&self) -> crate::Result<f32>;
+
+    fn get_master_volume_level_scalar(&self) -> crate::Result<f32>;
+
+    fn get_mute(&self) -> crate::Result<bool>;
+
+    fn get_volume_range(&self) -> crate::Result<VolumeRange>;
+
+    fn get_volume_step_info(&self) -> crate::Result<VolumeStepInfo>;
+
+    fn query_hardware_support(&self) -> crate::Result<HardwareSupportMask>;
+
+    fn register_control_change_notify(
+        &self,
+        callback: Box<dyn AudioEndpointVolumeCallback>,
+    ) -> crate::Result<Rc<dyn CallbackRegistration>>;
+
+    fn set_channel_volume_level(
+        &self,
+        channel: u32,
+        level_db: f32,
+        event_context: Option<&GUID>,
+    ) -> crate::Result<()>;
+
+    fn set_channel_volume_level_scalar(
+        &self,
+        channel: u32,
+        level: f32,
+        event_context: Option<&GUID>,
+    ) -> crate::Result<()>;
+
+    fn set_master_volume_level(
+        &self,
+        level_db: f32,
+        event_context: Option<&GUID>,
+    ) -> crate::Result<()>;
+
+    fn set_master_volume_level_scalar(
+        &self,
+        level: f32,
+        event_context: Option<&GUID>,
+    ) -> crate::Result<()>;
+
+    fn set_mute(&self, mute: bool, event_context: Option<&GUID>) -> crate::Result<()>;
+
+    fn volume_step_down(&self, event_context: Option<&GUID>) -> crate::Result<()>;
+
+    fn volume_step_up(&self, event_context: Option<&GUID>) -> crate::Result<()>;
+}
+
+/// Backend of [`AudioSessionManager`](crate::AudioSessionManager) and
+/// [`AudioSessionManager2`](crate::AudioSessionManager2).
+pub trait AudioSessionManagerBackend: Debug {
+    fn get_audio_session_control(
+        &self,
+        audio_session_guid: &GUID,
+    ) -> crate::Result<Rc<dyn AudioSessionControlBackend>>;
+
+    fn get_simple_audio_volume(
+        &self,
+        audio_session_guid: &GUID,
+    ) -> crate::Result<Rc<dyn SimpleAudioVolumeBackend>>;
+
+    fn get_session_enumerator(&self) -> crate::Result<Rc<dyn AudioSessionEnumeratorBackend>>;
+
+    fn register_duck_notification(
+        &self,
+        session_id: &WinStr,
+        duck_notification: Box<dyn AudioVolumeDuckNotification>,
+    ) -> crate::Result<Rc<dyn CallbackRegistration>>;
+
+    fn register_session_notification(
+        &self,
+        session_notification: Box<dyn AudioSessionNotification>,
+    ) -> crate::Result<Rc<dyn CallbackRegistration>>;
+}
+
+/// Backend of [`AudioSessionEnumerator`](crate::AudioSessionEnumerator).
+pub trait AudioSessionEnumeratorBackend: Debug {
+    fn get_count(&self) -> crate::Result<i32>;
+
+    fn get_session(&self, session: i32) -> crate::Result<Rc<dyn AudioSessionControlBackend>>;
+}
+
+/// Backend of [`AudioSessionControl`](crate::AudioSessionControl) and
+/// [`AudioSessionControl2`](crate::AudioSessionControl2).
+pub trait AudioSessionControlBackend: Debug {
+    fn get_display_name(&self) -> crate::Result<WinString>;
+
+    fn get_grouping_param(&self) -> crate::Result<GUID>;
+
+    fn get_icon_path(&self) -> crate::Result<WinString>;
+
+    fn get_state(&self) -> crate::Result<AudioSessionState>;
+
+    fn register_audio_session_notification(
+        &self,
+        session_notification: Box<dyn AudioSessionEvents>,
+    ) -> crate::Result<Rc<dyn CallbackRegistration>>;
+
+    fn set_display_name(&self, value: &WinStr, event_context: Option<&GUID>) -> crate::Result<()>;
+
+    fn set_grouping_param(&self, value: &GUID, event_context: Option<&GUID>) -> crate::Result<()>;
+
+    fn set_icon_path(&self, value: &WinStr, event_context: Option<&GUID>) -> crate::Result<()>;
+
+    fn get_simple_audio_volume(&self) -> crate::Result<Rc<dyn SimpleAudioVolumeBackend>>;
+
+    fn get_process_id(&self) -> crate::Result<u32>;
+
+    fn get_session_identifier(&self) -> crate::Result<WinString>;
+
+    fn get_session_instance_identifier(&self) -> crate::Result<WinString>;
+
+    fn is_system_sounds_session(&self) -> crate::Result<bool>;
+
+    fn set_ducking_preference(&self, opt_out: bool) -> crate::Result<()>;
+}
+
+/// Backend of [`SimpleAudioVolume`](crate::SimpleAudioVolume).
+pub trait SimpleAudioVolumeBackend: Debug {
+    fn get_master_volume(&self) -> crate::Result<f32>;
+
+    fn get_mute(&self) -> crate::Result<bool>;
+
+    fn set_master_volume(
+        &self,
+        volume_level: f32,
+        event_context: Option<&GUID>,
+    ) -> crate::Result<()>;
+
+    fn set_mute(&self, mute: bool, event_context: Option<&GUID>) -> crate::Result<()>;
+}
@@ -1,17 +1,30 @@
 //! Collection of translated bitflag and enumeration types.
 
+use std::convert::TryFrom;
+
 use windows::Win32::Media::Audio::{
-    eAll, eCapture, eCommunications, eConsole, eMultimedia, eRender,
+    self as audio, eAll, eCapture, eCommunications, eConsole, eMultimedia, eRender,
     AudioSessionDisconnectReason as EAudioSessionDisconnectReason,
     AudioSessionState as EAudioSessionState, AudioSessionStateActive, AudioSessionStateExpired,
     AudioSessionStateInactive, DisconnectReasonDeviceRemoval,
     DisconnectReasonExclusiveModeOverride, DisconnectReasonFormatChanged,
     DisconnectReasonServerShutdown, DisconnectReasonSessionDisconnected,
-    DisconnectReasonSessionLogoff, EDataFlow, ERole, DEVICE_STATEMASK_ALL, DEVICE_STATE_ACTIVE,
-    DEVICE_STATE_DISABLED, DEVICE_STATE_NOTPRESENT, DEVICE_STATE_UNPLUGGED,
-    ENDPOINT_HARDWARE_SUPPORT_METER, ENDPOINT_HARDWARE_SUPPORT_MUTE,
+    DisconnectReasonSessionLogoff, EDataFlow, ERole, EndpointFormFactor as EEndpointFormFactor,
+    DEVICE_STATEMASK_ALL, DEVICE_STATE_ACTIVE, DEVICE_STATE_DISABLED, DEVICE_STATE_NOTPRESENT,
+    DEVICE_STATE_UNPLUGGED, ENDPOINT_HARDWARE_SUPPORT_METER, ENDPOINT_HARDWARE_SUPPORT_MUTE,
     ENDPOINT_HARDWARE_SUPPORT_VOLUME,
 };
+use windows::Win32::Media::Audio::{
+    ConnectorType as EConnectorType, DataFlow as EConnectorDataFlow, PartType as EPartType,
+};
+use windows::Win32::Media::KernelStreaming::{
+    self as ks, EPcxConnectionType, EPcxGenLocation, EPcxGeoLocation, EPxcPortConnection,
+    SPEAKER_BACK_CENTER, SPEAKER_BACK_LEFT, SPEAKER_BACK_RIGHT, SPEAKER_FRONT_CENTER,
+    SPEAKER_FRONT_LEFT, SPEAKER_FRONT_LEFT_OF_CENTER, SPEAKER_FRONT_RIGHT,
+    SPEAKER_FRONT_RIGHT_OF_CENTER, SPEAKER_LOW_FREQUENCY, SPEAKER_SIDE_LEFT, SPEAKER_SIDE_RIGHT,
+    SPEAKER_TOP_BACK_CENTER, SPEAKER_TOP_BACK_LEFT, SPEAKER_TOP_BACK_RIGHT, SPEAKER_TOP_CENTER,
+    SPEAKER_TOP_FRONT_CENTER, SPEAKER_TOP_FRONT_LEFT, SPEAKER_TOP_FRONT_RIGHT,
+};
 
 macro_rules! map_enum {
     ($(
@@ -29,37 +42,153 @@ macro_rules! map_enum {
                 $(#[$varmeta])*
                 $varname,
             )*
+            /// A raw value that has no matching variant, for example one that
+            /// was introduced by a newer version of Windows.
+            Unknown($maptype),
         }
 
         impl $name {
+            /// Convert a raw value, falling back to `Unknown` for values
+            /// without a matching variant.
             #[allow(dead_code)]
             pub(crate) fn from_raw(raw: $maptype) -> Self {
                 match raw {
                     $(x if x == $varmap => Self::$varname,)*
-                    _ => panic!("invalid raw value {:?}", raw)
+                    _ => Self::Unknown(raw),
                 }
             }
 
             #[allow(dead_code)]
-            pub(crate) fn to_raw(&self) -> $maptype {
+            pub(crate) fn to_raw(self) -> $maptype {
+                match self {
+                    $(Self::$varname => $varmap,)*
+                    Self::Unknown(raw) => raw,
+                }
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl serde::Serialize for $name {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
                 match self {
-                    $(Self::$varname => $varmap),*
+                    $(Self::$varname => serializer.serialize_str(stringify!($varname)),)*
+                    Self::Unknown(raw) => serializer.serialize_i64(RawValue::to_i64(*raw)),
+                }
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                deserializer.deserialize_any(EnumVisitor {
+                    variants: &[$(stringify!($varname)),*],
+                    from_name: |name| match name {
+                        $(stringify!($varname) => Some(Self::$varname),)*
+                        _ => None,
+                    },
+                    from_raw: |raw| RawValue::from_i64(raw).map(Self::from_raw),
+                })
+            }
+        }
+
+        impl TryFrom<$maptype> for $name {
+            /// The raw value that has no matching variant.
+            type Error = $maptype;
+
+            fn try_from(raw: $maptype) -> Result<Self, Self::Error> {
+                match Self::from_raw(raw) {
+                    Self::Unknown(raw) => Err(raw),
+                    known => Ok(known),
                 }
             }
         }
     )*}
 }
 
+/// Raw enumeration values, which are serialized as integers when they have no
+/// matching variant.
+#[cfg(feature = "serde")]
+trait RawValue: Sized {
+    fn to_i64(self) -> i64;
+
+    fn from_i64(value: i64) -> Option<Self>;
+}
+
+#[cfg(feature = "serde")]
+macro_rules! raw_value {
+    ($($ty:ty),*; newtypes: $($newtype:ty),*) => {
+        $(impl RawValue for $ty {
+            fn to_i64(self) -> i64 {
+                self.into()
+            }
+
+            fn from_i64(value: i64) -> Option<Self> {
+                Self::try_from(value).ok()
+            }
+        })*
+
+        $(impl RawValue for $newtype {
+            fn to_i64(self) -> i64 {
+                self.0.into()
+            }
+
+            fn from_i64(value: i64) -> Option<Self> {
+                i32::try_from(value).ok().map(Self)
+            }
+        })*
+    };
+}
+
+#[cfg(feature = "serde")]
+raw_value!(
+    i32, u32;
+    newtypes: EDataFlow, ERole, EAudioSessionState, EAudioSessionDisconnectReason, EEndpointFormFactor,
+        EConnectorType, EConnectorDataFlow, EPartType, EPcxConnectionType, EPcxGenLocation,
+        EPcxGeoLocation, EPxcPortConnection
+);
+
+/// Deserializes a mapped enum from a variant name or a raw value.
+#[cfg(feature = "serde")]
+struct EnumVisitor<T> {
+    variants: &'static [&'static str],
+    from_name: fn(&str) -> Option<T>,
+    from_raw: fn(i64) -> Option<T>,
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T> serde::de::Visitor<'de> for EnumVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "one of {:?} or a raw value", self.variants)
+    }
+
+    fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<T, E> {
+        (self.from_name)(v).ok_or_else(|| E::unknown_variant(v, self.variants))
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<T, E> {
+        (self.from_raw)(v).ok_or_else(|| E::invalid_value(serde::de::Unexpected::Signed(v), &self))
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<T, E> {
+        i64::try_from(v)
+            .ok()
+            .and_then(self.from_raw)
+            .ok_or_else(|| E::invalid_value(serde::de::Unexpected::Unsigned(v), &self))
+    }
+}
+
 map_enum! {
     /// See also: [`EDataFlow`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/ne-mmdeviceapi-edataflow)
-    #[derive(Debug, Clone, Copy, PartialEq)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub enum DataFlow: EDataFlow {
         Render = eRender,
         Capture = eCapture,
     }
 
     /// See also: [`EDataFlow`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/ne-mmdeviceapi-edataflow)
-    #[derive(Debug, Clone, Copy, PartialEq)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub enum DataFlowMask: EDataFlow {
         Render = eRender,
         Capture = eCapture,
@@ -67,7 +196,7 @@ map_enum! {
     }
 
     /// See also: [`DEVICE_STATE_XXXX Constants`](https://docs.microsoft.com/en-us/windows/win32/coreaudio/device-state-xxx-constants)
-    #[derive(Debug, Clone, Copy, PartialEq)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub enum DeviceState: u32 {
         Active = DEVICE_STATE_ACTIVE,
         Disabled = DEVICE_STATE_DISABLED,
@@ -76,7 +205,7 @@ map_enum! {
     }
 
     /// See also: [`ERole`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/ne-mmdeviceapi-erole)
-    #[derive(Debug, Clone, Copy, PartialEq)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub enum DeviceRole: ERole {
         Console = eConsole,
         Multimedia = eMultimedia,
@@ -84,7 +213,7 @@ map_enum! {
     }
 
     /// See also: [`STGM Constants`](https://docs.microsoft.com/en-us/windows/win32/stg/stgm-constants)
-    #[derive(Debug, Clone, Copy, PartialEq)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub enum StorageAccessMode: i32 {
         Read = 0,
         Write = 1,
@@ -92,14 +221,14 @@ map_enum! {
     }
 
     /// See also: [`AudioSessionState`](https://docs.microsoft.com/en-us/windows/win32/api/audiosessiontypes/ne-audiosessiontypes-audiosessionstate)
-    #[derive(Debug, Clone, Copy, PartialEq)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub enum AudioSessionState: EAudioSessionState {
         Active = AudioSessionStateActive,
         Expired = AudioSessionStateExpired,
         Inactive = AudioSessionStateInactive,
     }
 
-    #[derive(Debug, Clone, Copy, PartialEq)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub enum AudioSessionDisconnectReason: EAudioSessionDisconnectReason {
         DeviceRemoval = DisconnectReasonDeviceRemoval,
         ServerShutdown = DisconnectReasonServerShutdown,
@@ -108,6 +237,111 @@ map_enum! {
         SessionDisconnected = DisconnectReasonSessionDisconnected,
         ExclusiveModeOverride = DisconnectReasonExclusiveModeOverride,
     }
+
+    /// See also: [`EndpointFormFactor`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/ne-mmdeviceapi-endpointformfactor)
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum EndpointFormFactor: EEndpointFormFactor {
+        RemoteNetworkDevice = audio::RemoteNetworkDevice,
+        Speakers = audio::Speakers,
+        LineLevel = audio::LineLevel,
+        Headphones = audio::Headphones,
+        Microphone = audio::Microphone,
+        Headset = audio::Headset,
+        Handset = audio::Handset,
+        UnknownDigitalPassthrough = audio::UnknownDigitalPassthrough,
+        Spdif = audio::SPDIF,
+        DigitalAudioDisplayDevice = audio::DigitalAudioDisplayDevice,
+        UnknownFormFactor = audio::UnknownFormFactor,
+    }
+
+    /// See also: [`ConnectorType`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/ne-devicetopology-connectortype)
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum ConnectorType: EConnectorType {
+        UnknownConnector = EConnectorType::Unknown_Connector,
+        PhysicalInternal = EConnectorType::Physical_Internal,
+        PhysicalExternal = EConnectorType::Physical_External,
+        SoftwareIo = EConnectorType::Software_IO,
+        SoftwareFixed = EConnectorType::Software_Fixed,
+        Network = EConnectorType::Network,
+    }
+
+    /// The direction in which data flows through a connector, as seen from
+    /// the device that the connector belongs to.
+    ///
+    /// See also: [`DataFlow`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/ne-devicetopology-dataflow)
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum ConnectorDataFlow: EConnectorDataFlow {
+        In = audio::In,
+        Out = audio::Out,
+    }
+
+    /// See also: [`PartType`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/ne-devicetopology-parttype)
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum PartType: EPartType {
+        Connector = audio::Connector,
+        Subunit = audio::Subunit,
+    }
+
+    /// See also: [`EPcxConnectionType`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/ne-devicetopology-epcxconnectiontype)
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum JackConnectionType: EPcxConnectionType {
+        UnknownConnectionType = ks::eConnTypeUnknown,
+        /// A 3.5 mm jack.
+        Mini = ks::eConnType3Point5mm,
+        /// A 6.3 mm jack.
+        Quarter = ks::eConnTypeQuarter,
+        AtapiInternal = ks::eConnTypeAtapiInternal,
+        Rca = ks::eConnTypeRCA,
+        Optical = ks::eConnTypeOptical,
+        OtherDigital = ks::eConnTypeOtherDigital,
+        OtherAnalog = ks::eConnTypeOtherAnalog,
+        MultichannelAnalogDin = ks::eConnTypeMultichannelAnalogDIN,
+        XlrProfessional = ks::eConnTypeXlrProfessional,
+        Rj11Modem = ks::eConnTypeRJ11Modem,
+        Combination = ks::eConnTypeCombination,
+    }
+
+    /// Where a jack is located on its enclosure.
+    ///
+    /// See also: [`EPcxGeoLocation`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/ne-devicetopology-epcxgeolocation)
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum JackGeoLocation: EPcxGeoLocation {
+        Rear = ks::eGeoLocRear,
+        Front = ks::eGeoLocFront,
+        Left = ks::eGeoLocLeft,
+        Right = ks::eGeoLocRight,
+        Top = ks::eGeoLocTop,
+        Bottom = ks::eGeoLocBottom,
+        RearPanel = ks::eGeoLocRearPanel,
+        Riser = ks::eGeoLocRiser,
+        InsideMobileLid = ks::eGeoLocInsideMobileLid,
+        Drivebay = ks::eGeoLocDrivebay,
+        Hdmi = ks::eGeoLocHDMI,
+        OutsideMobileLid = ks::eGeoLocOutsideMobileLid,
+        Atapi = ks::eGeoLocATAPI,
+        NotApplicable = ks::eGeoLocNotApplicable,
+        Reserved6 = ks::eGeoLocReserved6,
+    }
+
+    /// Which enclosure a jack is located on.
+    ///
+    /// See also: [`EPcxGenLocation`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/ne-devicetopology-epcxgenlocation)
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum JackGenLocation: EPcxGenLocation {
+        PrimaryBox = ks::eGenLocPrimaryBox,
+        Internal = ks::eGenLocInternal,
+        Separate = ks::eGenLocSeparate,
+        Other = ks::eGenLocOther,
+    }
+
+    /// See also: [`EPxcPortConnection`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/ne-devicetopology-epxcportconnection)
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum JackPortConnection: EPxcPortConnection {
+        Jack = ks::ePortConnJack,
+        IntegratedDevice = ks::ePortConnIntegratedDevice,
+        BothIntegratedAndJack = ks::ePortConnBothIntegratedAndJack,
+        UnknownPortConnection = ks::ePortConnUnknown,
+    }
 }
 
 bitflags::bitflags! {
@@ -126,4 +360,77 @@ bitflags::bitflags! {
         const METER = ENDPOINT_HARDWARE_SUPPORT_METER;
         const VOLUME = ENDPOINT_HARDWARE_SUPPORT_VOLUME;
     }
+
+    /// Speaker positions, as used by `WAVEFORMATEXTENSIBLE::dwChannelMask` and
+    /// `PKEY_AudioEndpoint_PhysicalSpeakers`.
+    ///
+    /// See also: [`KSAUDIO_CHANNEL_CONFIG`](https://docs.microsoft.com/en-us/windows/win32/api/ksmedia/ns-ksmedia-ksaudio_channel_config)
+    pub struct ChannelMask: u32 {
+        const FRONT_LEFT = SPEAKER_FRONT_LEFT;
+        const FRONT_RIGHT = SPEAKER_FRONT_RIGHT;
+        const FRONT_CENTER = SPEAKER_FRONT_CENTER;
+        const LOW_FREQUENCY = SPEAKER_LOW_FREQUENCY;
+        const BACK_LEFT = SPEAKER_BACK_LEFT;
+        const BACK_RIGHT = SPEAKER_BACK_RIGHT;
+        const FRONT_LEFT_OF_CENTER = SPEAKER_FRONT_LEFT_OF_CENTER;
+        const FRONT_RIGHT_OF_CENTER = SPEAKER_FRONT_RIGHT_OF_CENTER;
+        const BACK_CENTER = SPEAKER_BACK_CENTER;
+        const SIDE_LEFT = SPEAKER_SIDE_LEFT;
+        const SIDE_RIGHT = SPEAKER_SIDE_RIGHT;
+        const TOP_CENTER = SPEAKER_TOP_CENTER;
+        const TOP_FRONT_LEFT = SPEAKER_TOP_FRONT_LEFT;
+        const TOP_FRONT_CENTER = SPEAKER_TOP_FRONT_CENTER;
+        const TOP_FRONT_RIGHT = SPEAKER_TOP_FRONT_RIGHT;
+        const TOP_BACK_LEFT = SPEAKER_TOP_BACK_LEFT;
+        const TOP_BACK_CENTER = SPEAKER_TOP_BACK_CENTER;
+        const TOP_BACK_RIGHT = SPEAKER_TOP_BACK_RIGHT;
+
+        const MONO = Self::FRONT_CENTER.bits;
+        const STEREO = Self::FRONT_LEFT.bits | Self::FRONT_RIGHT.bits;
+        const QUAD = Self::STEREO.bits | Self::BACK_LEFT.bits | Self::BACK_RIGHT.bits;
+        const SURROUND = Self::STEREO.bits | Self::FRONT_CENTER.bits | Self::BACK_CENTER.bits;
+        const FIVE_POINT_ONE = Self::STEREO.bits
+            | Self::FRONT_CENTER.bits
+            | Self::LOW_FREQUENCY.bits
+            | Self::SIDE_LEFT.bits
+            | Self::SIDE_RIGHT.bits;
+        const SEVEN_POINT_ONE = Self::FIVE_POINT_ONE.bits
+            | Self::BACK_LEFT.bits
+            | Self::BACK_RIGHT.bits;
+    }
+}
+
+impl ChannelMask {
+    /// Keep every bit of a raw mask, including reserved bits and
+    /// `SPEAKER_ALL`, so that the mask can be written back unchanged.
+    pub fn from_bits_retain(bits: u32) -> Self {
+        // Safety: the mask is a plain `u32`, bits without a constant only
+        // show up in `bits()` and `Debug`.
+        unsafe { Self::from_bits_unchecked(bits) }
+    }
+
+    /// The number of speaker positions in the mask.
+    pub fn channel_count(self) -> u32 {
+        self.bits().count_ones()
+    }
+}
+
+/// Serialize channel masks as their raw bits, for use with
+/// `#[serde(with = "...")]`.
+#[cfg(feature = "serde")]
+pub(crate) mod channel_mask_bits {
+    use super::ChannelMask;
+
+    pub(crate) fn serialize<S: serde::Serializer>(
+        mask: &ChannelMask,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(mask.bits())
+    }
+
+    pub(crate) fn deserialize<'de, D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<ChannelMask, D::Error> {
+        <u32 as serde::Deserialize>::deserialize(deserializer).map(ChannelMask::from_bits_retain)
+    }
 }
@@ -0,0 +1,345 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crate::{
+    bits::{DataFlow, DeviceRole, DeviceState},
+    clock::{Clock, SystemClock},
+    notification_client::NotificationClient,
+    property_store::PropertyKey,
+    string::{WinStr, WinString},
+};
+
+/// Whether a device was added or removed, see
+/// [`DeviceChangeBatch::presence`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Presence {
+    Added,
+    Removed,
+}
+
+/// The notifications for one device that a [`CoalescingClient`] collected
+/// during one window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceChangeBatch {
+    pub device_id: String,
+    /// The last time the device was added or removed, if it was.
+    pub presence: Option<Presence>,
+    /// The last state that the device changed to, if it changed.
+    pub state: Option<DeviceState>,
+    /// The keys of the properties that changed, each listed once in the
+    /// order in which they first changed.
+    pub property_keys: Vec<PropertyKey>,
+    /// The number of notifications that were coalesced into the batch.
+    pub event_count: usize,
+}
+
+impl DeviceChangeBatch {
+    fn new(device_id: String) -> Self {
+        Self {
+            device_id,
+            presence: None,
+            state: None,
+            property_keys: Vec::new(),
+            event_count: 0,
+        }
+    }
+
+    /// Call the per-notification methods of `client` with the summary of the
+    /// batch: an addition first, then the state change, the property changes
+    /// and finally a removal.
+    pub fn replay<C>(&self, client: &C) -> crate::Result<()>
+    where
+        C: NotificationClient + ?Sized,
+    {
+        let device_id = WinString::from(self.device_id.as_str());
+        if self.presence == Some(Presence::Added) {
+            client.on_device_added(&device_id)?;
+        }
+        if let Some(state) = self.state {
+            client.on_device_state_changed(&device_id, state)?;
+        }
+        for &key in &self.property_keys {
+            client.on_property_value_changed(&device_id, key)?;
+        }
+        if self.presence == Some(Presence::Removed) {
+            client.on_device_removed(&device_id)?;
+        }
+        Ok(())
+    }
+}
+
+/// Receives the batches of a [`CoalescingClient`].
+///
+/// Batches are delivered on the thread that calls
+/// [`flush_due`](CoalescingClient::flush_due) or
+/// [`flush_all`](CoalescingClient::flush_all), or on the thread of the
+/// notification that finds them due, which for a registered client is a COM
+/// worker thread. A [`CoalescingClient`] is `Send` and `Sync` when its
+/// handler is.
+///
+/// Closures taking a [`DeviceChangeBatch`] implement this trait and ignore
+/// default device changes. To handle the batches with a
+/// [`NotificationClient`] instead, wrap it in [`Replay`].
+pub trait BatchHandler: 'static {
+    /// Called with the coalesced notifications of one device.
+    fn on_device_changes(&self, batch: &DeviceChangeBatch) -> crate::Result<()>;
+
+    /// Called immediately for every default device change, which is not
+    /// coalesced.
+    fn on_default_device_changed(
+        &self,
+        data_flow: DataFlow,
+        role: DeviceRole,
+        device_id: &WinStr,
+    ) -> crate::Result<()> {
+        let _ = (data_flow, role, device_id);
+        Ok(())
+    }
+}
+
+impl<F> BatchHandler for F
+where
+    F: Fn(&DeviceChangeBatch) -> crate::Result<()> + 'static,
+{
+    fn on_device_changes(&self, batch: &DeviceChangeBatch) -> crate::Result<()> {
+        self(batch)
+    }
+}
+
+/// A [`BatchHandler`] that [replays](DeviceChangeBatch::replay) every batch
+/// into a [`NotificationClient`] and passes default device changes through,
+/// see [`CoalescingClient::wrapping`].
+#[derive(Debug, Clone, Default)]
+pub struct Replay<C>(pub C);
+
+impl<C> BatchHandler for Replay<C>
+where
+    C: NotificationClient,
+{
+    fn on_device_changes(&self, batch: &DeviceChangeBatch) -> crate::Result<()> {
+        batch.replay(&self.0)
+    }
+
+    fn on_default_device_changed(
+        &self,
+        data_flow: DataFlow,
+        role: DeviceRole,
+        device_id: &WinStr,
+    ) -> crate::Result<()> {
+        self.0.on_default_device_changed(data_flow, role, device_id)
+    }
+}
+
+struct PendingBatch {
+    started: Instant,
+    batch: DeviceChangeBatch,
+}
+
+struct Shared<H> {
+    handler: H,
+    clock: Box<dyn Clock>,
+    window: Duration,
+    pending: Mutex<BTreeMap<String, PendingBatch>>,
+}
+
+/// A [`NotificationClient`] that coalesces the notifications of each device
+/// over a window and delivers them to a [`BatchHandler`] as one
+/// [`DeviceChangeBatch`].
+///
+/// A window starts with the first notification for a device and the batch is
+/// delivered once it has elapsed. There is no timer: due batches are
+/// delivered when the next notification arrives and when
+/// [`flush_due`](Self::flush_due) is called, for example on a timer set to
+/// [`next_deadline`](Self::next_deadline). Default device changes are not
+/// tied to one device and are passed through immediately.
+///
+/// Clones share the same batches, so a clone can be registered while
+/// another one is kept to flush, on any thread if the handler is `Send` and
+/// `Sync`.
+pub struct CoalescingClient<H> {
+    shared: Arc<Shared<H>>,
+}
+
+impl<H> Clone for CoalescingClient<H> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<H> Debug for CoalescingClient<H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CoalescingClient")
+            .field("window", &self.shared.window)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<H> CoalescingClient<H>
+where
+    H: BatchHandler,
+{
+    pub fn new(handler: H, window: Duration) -> Self {
+        Self::with_clock(handler, window, SystemClock)
+    }
+
+    pub fn with_clock<K>(handler: H, window: Duration, clock: K) -> Self
+    where
+        K: Clock,
+    {
+        Self {
+            shared: Arc::new(Shared {
+                handler,
+                clock: Box::new(clock),
+                window,
+                pending: Mutex::new(BTreeMap::new()),
+            }),
+        }
+    }
+
+    pub fn handler(&self) -> &H {
+        &self.shared.handler
+    }
+
+    pub fn window(&self) -> Duration {
+        self.shared.window
+    }
+
+    /// When the oldest pending batch becomes due, or `None` if no batch is
+    /// pending.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.pending()
+            .values()
+            .map(|pending| pending.started + self.shared.window)
+            .min()
+    }
+
+    /// Deliver every batch whose window has elapsed.
+    ///
+    /// All due batches are delivered even if one fails, and the first error
+    /// is returned.
+    pub fn flush_due(&self) -> crate::Result<()> {
+        let now = self.shared.clock.now();
+        let window = self.shared.window;
+        self.deliver(|pending| now >= pending.started + window)
+    }
+
+    /// Deliver every pending batch, regardless of its window.
+    pub fn flush_all(&self) -> crate::Result<()> {
+        self.deliver(|_| true)
+    }
+
+    fn pending(&self) -> MutexGuard<'_, BTreeMap<String, PendingBatch>> {
+        // Every update leaves the batches consistent.
+        self.shared
+            .pending
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+    }
+
+    fn deliver<F>(&self, mut is_due: F) -> crate::Result<()>
+    where
+        F: FnMut(&PendingBatch) -> bool,
+    {
+        let mut due = Vec::new();
+        {
+            let mut pending = self.pending();
+            let ids: Vec<String> = pending
+                .iter()
+                .filter(|(_, x)| is_due(x))
+                .map(|(id, _)| id.clone())
+                .collect();
+            for id in ids {
+                due.extend(pending.remove(&id));
+            }
+        }
+        // Deliver without holding the lock, so that the handler may flush.
+        due.sort_by_key(|pending| pending.started);
+        let mut result = Ok(());
+        for pending in due {
+            let delivered = self.shared.handler.on_device_changes(&pending.batch);
+            if result.is_ok() {
+                result = delivered;
+            }
+        }
+        result
+    }
+
+    /// Deliver due batches, then add a notification to the batch of
+    /// `device_id`.
+    fn record<F>(&self, device_id: &WinStr, update: F) -> crate::Result<()>
+    where
+        F: FnOnce(&mut DeviceChangeBatch),
+    {
+        let result = self.flush_due();
+        let now = self.shared.clock.now();
+        let device_id = device_id.to_string_lossy();
+        let mut pending = self.pending();
+        let pending = pending
+            .entry(device_id.clone())
+            .or_insert_with(|| PendingBatch {
+                started: now,
+                batch: DeviceChangeBatch::new(device_id),
+            });
+        pending.batch.event_count += 1;
+        update(&mut pending.batch);
+        result
+    }
+}
+
+impl<C> CoalescingClient<Replay<C>>
+where
+    C: NotificationClient,
+{
+    /// Coalesce the notifications for `client`, which receives each batch as
+    /// the notifications that [`replay`](DeviceChangeBatch::replay) makes of
+    /// it.
+    pub fn wrapping(client: C, window: Duration) -> Self {
+        Self::new(Replay(client), window)
+    }
+}
+
+impl<H> NotificationClient for CoalescingClient<H>
+where
+    H: BatchHandler,
+{
+    fn on_default_device_changed(
+        &self,
+        data_flow: DataFlow,
+        role: DeviceRole,
+        device_id: &WinStr,
+    ) -> crate::Result<()> {
+        let result = self.flush_due();
+        self.shared
+            .handler
+            .on_default_device_changed(data_flow, role, device_id)?;
+        result
+    }
+
+    fn on_device_added(&self, device_id: &WinStr) -> crate::Result<()> {
+        self.record(device_id, |batch| batch.presence = Some(Presence::Added))
+    }
+
+    fn on_device_removed(&self, device_id: &WinStr) -> crate::Result<()> {
+        self.record(device_id, |batch| batch.presence = Some(Presence::Removed))
+    }
+
+    fn on_device_state_changed(&self, device_id: &WinStr, state: DeviceState) -> crate::Result<()> {
+        self.record(device_id, |batch| batch.state = Some(state))
+    }
+
+    fn on_property_value_changed(
+        &self,
+        device_id: &WinStr,
+        property_key: PropertyKey,
+    ) -> crate::Result<()> {
+        self.record(device_id, |batch| {
+            if !batch.property_keys.contains(&property_key) {
+                batch.property_keys.push(property_key);
+            }
+        })
+    }
+}
@@ -1,83 +1,238 @@
+use std::rc::Rc;
+
+use windows::{core::GUID, Win32::Media::Audio::EndpointFormFactor as EEndpointFormFactor};
+
 use crate::{
     audio_endpoint_volume::AudioEndpointVolume,
     audio_session_manager::AudioSessionManager,
-    bits::{DeviceState, StorageAccessMode},
-    property_store::{PropertyKey, PropertyStore},
+    backend::DeviceBackend,
+    bits::{ChannelMask, DataFlow, DeviceRole, DeviceState, EndpointFormFactor, StorageAccessMode},
+    device_enumerator::DeviceEnumerator,
+    device_topology::DeviceTopology,
+    guid::parse_guid,
+    property_keys,
+    property_store::{Property, PropertyKey, PropertyStore},
     string::WinString,
-    AudioSessionManager2,
-};
-use windows::Win32::{
-    Devices::FunctionDiscovery::{
-        PKEY_DeviceInterface_FriendlyName, PKEY_Device_DeviceDesc, PKEY_Device_FriendlyName,
-    },
-    Media::Audio::IMMDevice,
-    System::Com::{StructuredStorage::PROPVARIANT, CLSCTX_ALL, STGM},
+    wave_format::WaveFormat,
+    AudioSessionManager2, Error,
 };
 
 /// See also: [`IMMDevice`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/nn-mmdeviceapi-immdevice)
 #[derive(Debug, Clone)]
 pub struct Device {
-    inner: IMMDevice,
+    inner: Rc<dyn DeviceBackend>,
 }
 
 impl Device {
-    pub(crate) fn new(inner: IMMDevice) -> Self {
+    pub(crate) fn new(inner: Rc<dyn DeviceBackend>) -> Self {
         Self { inner }
     }
 
-    pub(crate) unsafe fn activate<T>(&self, params: *mut PROPVARIANT) -> windows::core::Result<T>
-    where
-        T: Activate,
-    {
-        let raw = None;
-        unsafe {
-            self.inner.Activate::<T::Raw>(CLSCTX_ALL, Some(params))?;
-        }
-        Ok(<T as Activate>::from_raw(raw.unwrap()))
+    /// See also: [`IMMDevice::Activate`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/nf-mmdeviceapi-immdevice-activate)
+    pub fn activate_audio_endpoint_volume(&self) -> crate::Result<AudioEndpointVolume> {
+        self.inner
+            .activate_audio_endpoint_volume()
+            .map(AudioEndpointVolume::new)
     }
 
-    pub fn activate_audio_endpoint_volume(&self) -> windows::core::Result<AudioEndpointVolume> {
-        unsafe { self.activate(std::ptr::null_mut()) }
+    /// See also: [`IMMDevice::Activate`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/nf-mmdeviceapi-immdevice-activate)
+    pub fn activate_audio_session_manager(&self) -> crate::Result<AudioSessionManager> {
+        self.inner
+            .activate_audio_session_manager()
+            .map(AudioSessionManager::new)
     }
 
-    pub fn activate_audio_session_manager(&self) -> windows::core::Result<AudioSessionManager> {
-        unsafe { self.activate(std::ptr::null_mut()) }
+    /// See also: [`IMMDevice::Activate`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/nf-mmdeviceapi-immdevice-activate)
+    pub fn activate_audio_session_manager2(&self) -> crate::Result<AudioSessionManager2> {
+        self.inner
+            .activate_audio_session_manager()
+            .map(AudioSessionManager2::new)
     }
 
-    pub fn activate_audio_session_manager2(&self) -> windows::core::Result<AudioSessionManager2> {
-        unsafe { self.activate(std::ptr::null_mut()) }
+    /// See also: [`IMMDevice::Activate`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/nf-mmdeviceapi-immdevice-activate)
+    pub fn activate_device_topology(&self) -> crate::Result<DeviceTopology> {
+        self.inner
+            .activate_device_topology()
+            .map(DeviceTopology::new)
+    }
+
+    /// Whether the device is a render or a capture endpoint.
+    ///
+    /// See also: [`IMMEndpoint::GetDataFlow`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/nf-mmdeviceapi-immendpoint-getdataflow)
+    pub fn data_flow(&self) -> crate::Result<DataFlow> {
+        self.inner.get_data_flow()
+    }
+
+    /// Whether the device is currently the default endpoint of its data flow
+    /// for `role`, as reported by `enumerator`.
+    ///
+    /// The enumerator is passed in so that checking many devices or roles
+    /// does not create a new one for every call.
+    pub fn is_default_for(
+        &self,
+        enumerator: &DeviceEnumerator,
+        role: DeviceRole,
+    ) -> crate::Result<bool> {
+        match enumerator.get_default_audio_endpoint(self.data_flow()?, role) {
+            Ok(default) => Ok(default.get_id()? == self.get_id()?),
+            Err(Error::DeviceNotFound) => Ok(false),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// An enumerator of the system that the device belongs to. On Windows,
+    /// this creates a new `IMMDeviceEnumerator`.
+    pub(crate) fn enumerator(&self) -> crate::Result<DeviceEnumerator> {
+        self.inner.enumerator().map(DeviceEnumerator::from_inner)
     }
 
     /// See also: [`IMMDevice::GetId`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/nf-mmdeviceapi-immdevice-getid)
-    pub fn get_id(&self) -> windows::core::Result<WinString> {
-        Ok(unsafe { WinString::from_com_pwstr(self.inner.GetId()?) })
+    pub fn get_id(&self) -> crate::Result<WinString> {
+        self.inner.get_id()
     }
 
     /// See also: [`IMMDevice::GetState`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/nf-mmdeviceapi-immdevice-getstate)
-    pub fn get_state(&self) -> windows::core::Result<DeviceState> {
-        Ok(DeviceState::from_raw(unsafe { self.inner.GetState()? }))
+    pub fn get_state(&self) -> crate::Result<DeviceState> {
+        self.inner.get_state()
     }
 
     /// See also: [`IMMDevice::OpenPropertyStore`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/nf-mmdeviceapi-immdevice-openpropertystore)
     pub fn open_property_store(
         &self,
         storage_access_mode: StorageAccessMode,
-    ) -> windows::core::Result<PropertyStore> {
-        unsafe {
-            self.inner
-                .OpenPropertyStore(STGM(storage_access_mode.to_raw() as _))
-                .map(PropertyStore::new)
+    ) -> crate::Result<PropertyStore> {
+        self.inner
+            .open_property_store(storage_access_mode)
+            .map(PropertyStore::new)
+    }
+
+    /// Read a property, failing with [`Error::PropertyNotFound`] if it has
+    /// no value.
+    pub fn property(&self, key: PropertyKey) -> crate::Result<Property> {
+        match self
+            .open_property_store(StorageAccessMode::Read)?
+            .get_value(key)?
+        {
+            Property::Empty => Err(Error::PropertyNotFound(key)),
+            value => Ok(value),
         }
     }
-}
 
-pub(crate) trait Activate {
-    type Raw: windows::core::ComInterface;
+    fn str_property(&self, key: PropertyKey) -> crate::Result<WinString> {
+        match self.property(key)? {
+            Property::Str(value) => Ok(value),
+            _ => Err(Error::PropertyTypeMismatch(key)),
+        }
+    }
+
+    fn u32_property(&self, key: PropertyKey) -> crate::Result<u32> {
+        match self.property(key)? {
+            Property::U32(value) => Ok(value),
+            _ => Err(Error::PropertyTypeMismatch(key)),
+        }
+    }
+
+    fn wave_format_property(&self, key: PropertyKey) -> crate::Result<WaveFormat> {
+        match self.property(key)? {
+            Property::Blob(value) => {
+                WaveFormat::from_bytes(&value).map_err(|_| Error::PropertyTypeMismatch(key))
+            }
+            _ => Err(Error::PropertyTypeMismatch(key)),
+        }
+    }
+
+    /// GUIDs are stored either as `VT_CLSID` or as a string in braces.
+    fn guid_property(&self, key: PropertyKey) -> crate::Result<GUID> {
+        match self.property(key)? {
+            Property::Guid(value) => Ok(value),
+            Property::Str(value) => value
+                .to_string()
+                .ok()
+                .and_then(|value| parse_guid(&value))
+                .ok_or(Error::PropertyTypeMismatch(key)),
+            _ => Err(Error::PropertyTypeMismatch(key)),
+        }
+    }
+
+    /// The name of the endpoint, for example "Speakers (Realtek Audio)".
+    pub fn friendly_name(&self) -> crate::Result<WinString> {
+        self.str_property(property_keys::DEVICE_FRIENDLY_NAME)
+    }
+
+    /// The name of the endpoint without the adapter, for example "Speakers".
+    pub fn description(&self) -> crate::Result<WinString> {
+        self.str_property(property_keys::DEVICE_DESCRIPTION)
+    }
+
+    /// The name of the audio adapter, for example "Realtek Audio".
+    pub fn interface_name(&self) -> crate::Result<WinString> {
+        self.str_property(property_keys::DEVICE_INTERFACE_FRIENDLY_NAME)
+    }
+
+    /// See also: [`PKEY_AudioEndpoint_FormFactor`](https://docs.microsoft.com/en-us/windows/win32/coreaudio/pkey-audioendpoint-formfactor)
+    pub fn form_factor(&self) -> crate::Result<EndpointFormFactor> {
+        let raw = self.u32_property(property_keys::AUDIO_ENDPOINT_FORM_FACTOR)?;
+        Ok(EndpointFormFactor::from_raw(EEndpointFormFactor(
+            raw as i32,
+        )))
+    }
+
+    /// The `KSNODETYPE_XXX` GUID of the jack or connector.
+    ///
+    /// See also: [`PKEY_AudioEndpoint_JackSubType`](https://docs.microsoft.com/en-us/windows/win32/coreaudio/pkey-audioendpoint-jacksubtype)
+    pub fn jack_subtype(&self) -> crate::Result<GUID> {
+        self.guid_property(property_keys::AUDIO_ENDPOINT_JACK_SUB_TYPE)
+    }
+
+    /// See also: [`PKEY_AudioEndpoint_PhysicalSpeakers`](https://docs.microsoft.com/en-us/windows/win32/coreaudio/pkey-audioendpoint-physicalspeakers)
+    pub fn physical_speakers(&self) -> crate::Result<ChannelMask> {
+        let raw = self.u32_property(property_keys::AUDIO_ENDPOINT_PHYSICAL_SPEAKERS)?;
+        Ok(ChannelMask::from_bits_retain(raw))
+    }
 
-    fn from_raw(raw: Self::Raw) -> Self;
+    /// Identifies the physical device that the endpoint belongs to. Endpoints
+    /// of the same headset share a container id.
+    pub fn container_id(&self) -> crate::Result<GUID> {
+        self.guid_property(property_keys::DEVICE_CONTAINER_ID)
+    }
+
+    /// The icon of the endpoint, in the form `path,-resource id`.
+    pub fn icon_path(&self) -> crate::Result<WinString> {
+        self.str_property(property_keys::DEVICE_CLASS_ICON_PATH)
+    }
+
+    /// The hardware ids of the device that the endpoint belongs to, most
+    /// specific first.
+    pub fn hardware_ids(&self) -> crate::Result<Vec<WinString>> {
+        let key = property_keys::DEVICE_HARDWARE_IDS;
+        match self.property(key)? {
+            Property::StrVec(value) => Ok(value),
+            Property::Str(value) => Ok(vec![value]),
+            _ => Err(Error::PropertyTypeMismatch(key)),
+        }
+    }
+
+    /// The format that the audio engine uses for the endpoint in shared mode.
+    ///
+    /// See also: [`PKEY_AudioEngine_DeviceFormat`](https://docs.microsoft.com/en-us/windows/win32/coreaudio/pkey-audioengine-deviceformat)
+    pub fn device_format(&self) -> crate::Result<WaveFormat> {
+        self.wave_format_property(property_keys::AUDIO_ENGINE_DEVICE_FORMAT)
+    }
+
+    /// The default format of the endpoint as specified by the hardware
+    /// vendor.
+    ///
+    /// See also: [`PKEY_AudioEngine_OEMFormat`](https://docs.microsoft.com/en-us/windows/win32/coreaudio/pkey-audioengine-oemformat)
+    pub fn oem_format(&self) -> crate::Result<WaveFormat> {
+        self.wave_format_property(property_keys::AUDIO_ENGINE_OEM_FORMAT)
+    }
 }
 
-pub const DEVICE_INTERFACE_FRIENDLY_NAME: PropertyKey =
-    PropertyKey::from_raw(PKEY_DeviceInterface_FriendlyName);
-pub const DEVICE_DESCRIPTION: PropertyKey = PropertyKey::from_raw(PKEY_Device_DeviceDesc);
-pub const DEVICE_FRIENDLY_NAME: PropertyKey = PropertyKey::from_raw(PKEY_Device_FriendlyName);
+/// Treat a missing or malformed property as absent.
+pub(crate) fn optional_property<T>(result: crate::Result<T>) -> crate::Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(Error::PropertyNotFound(_)) | Err(Error::PropertyTypeMismatch(_)) => Ok(None),
+        Err(error) => Err(error),
+    }
+}
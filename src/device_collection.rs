@@ -1,65 +1,90 @@
-use std::{iter::FusedIterator, ops::Range};
+use std::{iter::FusedIterator, ops::Range, rc::Rc};
 
-use windows::Win32::Media::Audio::IMMDeviceCollection;
-
-use crate::device::Device;
+use crate::{backend::DeviceCollectionBackend, device::Device, error::Error};
 
 /// See also: [`IMMDeviceCollection`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/nn-mmdeviceapi-immdevicecollection)
 #[derive(Debug, Clone)]
 pub struct DeviceCollection {
-    inner: IMMDeviceCollection,
+    inner: Rc<dyn DeviceCollectionBackend>,
 }
 
 impl DeviceCollection {
-    pub(crate) fn new(inner: IMMDeviceCollection) -> Self {
+    pub(crate) fn new(inner: Rc<dyn DeviceCollectionBackend>) -> Self {
         Self { inner }
     }
 
-    pub fn get_count(&self) -> windows::core::Result<u32> {
-        unsafe { self.inner.GetCount() }
+    pub fn get_count(&self) -> crate::Result<u32> {
+        self.inner.get_count()
     }
 
-    pub fn item(&self, device: u32) -> windows::core::Result<Device> {
-        unsafe { self.inner.Item(device).map(Device::new) }
+    /// Iterate over the devices, yielding an error for every item that
+    /// could not be retrieved.
+    pub fn iter(&self) -> DeviceIter<'_> {
+        DeviceIter::new(self)
+    }
+
+    /// Collect every item, failing if any of them could not be retrieved.
+    pub fn to_vec(&self) -> crate::Result<Vec<Device>> {
+        self.iter().collect()
+    }
+
+    pub fn item(&self, device: u32) -> crate::Result<Device> {
+        self.inner.item(device).map(Device::new)
     }
 }
 
 impl<'a> IntoIterator for &'a DeviceCollection {
     type IntoIter = DeviceIter<'a>;
-    type Item = Device;
+    type Item = crate::Result<Device>;
 
     fn into_iter(self) -> Self::IntoIter {
         DeviceIter::new(self)
     }
 }
 
+/// Iterator over the devices of a [`DeviceCollection`].
+///
+/// Items are retrieved lazily, so an item that disappears while iterating
+/// yields an error instead of ending the iteration. If the number of items
+/// cannot be determined, the iterator yields that error once and then ends.
 #[derive(Debug, Clone)]
 pub struct DeviceIter<'a> {
     inner: &'a DeviceCollection,
     range: Range<u32>,
+    error: Option<Error>,
 }
 
 impl<'a> DeviceIter<'a> {
     pub(crate) fn new(inner: &'a DeviceCollection) -> Self {
-        let count = inner.get_count().unwrap();
+        let (range, error) = match inner.get_count() {
+            Ok(count) => (0..count, None),
+            Err(error) => (0..0, Some(error)),
+        };
         Self {
             inner,
-            range: 0..count,
+            range,
+            error,
         }
     }
+
+    /// Skip the items that could not be retrieved.
+    pub fn lossy(self) -> impl Iterator<Item = Device> + 'a {
+        self.filter_map(Result::ok)
+    }
 }
 
 impl<'a> Iterator for DeviceIter<'a> {
-    type Item = Device;
+    type Item = crate::Result<Device>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.range
-            .next()
-            .map(|index| self.inner.item(index).unwrap())
+        if let Some(error) = self.error.take() {
+            return Some(Err(error));
+        }
+        self.range.next().map(|index| self.inner.item(index))
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let len = self.range.len();
+        let len = self.range.len() + usize::from(self.error.is_some());
         (len, Some(len))
     }
 }
@@ -1,29 +1,65 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
 use windows::Win32::{
-    Media::Audio::{IMMDeviceEnumerator, IMMNotificationClient, MMDeviceEnumerator},
+    Media::Audio::{IMMDeviceEnumerator, MMDeviceEnumerator},
     System::Com::{CoCreateInstance, CLSCTX_ALL},
 };
 
 use crate::{
+    backend::{CallbackRegistration, DeviceEnumeratorBackend, PolicyConfigBackend},
     bits::{DataFlow, DataFlowMask, DeviceRole, DeviceStateMask},
     device::Device,
     device_collection::DeviceCollection,
-    notification_client::{NotificationClient, NotificationClientWrapper},
+    device_info::{self, DeviceInfo, ROLES},
+    device_query::{self, DeviceMatch, DeviceQuery},
+    notification_client::NotificationClient,
     string::WinStr,
 };
 
 /// See also: [`IMMDeviceEnumerator`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/nn-mmdeviceapi-immdeviceenumerator)
 #[derive(Debug, Clone)]
 pub struct DeviceEnumerator {
-    inner: IMMDeviceEnumerator,
+    inner: Rc<dyn DeviceEnumeratorBackend>,
+    // Created on first use and shared by clones, because creating it is a
+    // `CoCreateInstance` call.
+    policy_config: Rc<RefCell<Option<Rc<dyn PolicyConfigBackend>>>>,
 }
 
 impl DeviceEnumerator {
-    pub fn new() -> windows::core::Result<Self> {
+    /// Create an enumerator, initializing COM in the multithreaded apartment
+    /// first if this thread has not been initialized yet.
+    pub fn new() -> crate::Result<Self> {
         // Static entrypoint:
-        crate::ensure_thread_init();
+        crate::ensure_thread_init()?;
+
+        Self::without_com_init()
+    }
+
+    /// Create an enumerator on a thread where COM has already been
+    /// initialized, for example with [`ComApartment::initialize`].
+    ///
+    /// [`ComApartment::initialize`]: crate::ComApartment::initialize
+    pub fn without_com_init() -> crate::Result<Self> {
+        let inner: IMMDeviceEnumerator =
+            unsafe { CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)? };
+        Ok(Self::from_backend(inner))
+    }
+
+    /// Create an enumerator that is backed by a custom implementation, such as
+    /// [`FakeAudioSystem`](crate::backend::fake::FakeAudioSystem).
+    pub fn from_backend<T>(backend: T) -> Self
+    where
+        T: DeviceEnumeratorBackend + 'static,
+    {
+        Self::from_inner(Rc::new(backend))
+    }
 
-        let inner = unsafe { CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)? };
-        Ok(Self { inner })
+    pub(crate) fn from_inner(inner: Rc<dyn DeviceEnumeratorBackend>) -> Self {
+        Self {
+            inner,
+            policy_config: Rc::default(),
+        }
     }
 
     /// See also: [`IMMDeviceEnumerator::EnumAudioEndpoints`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/nf-mmdeviceapi-immdeviceenumerator-enumaudioendpoints)
@@ -31,12 +67,10 @@ impl DeviceEnumerator {
         &self,
         data_flow_mask: DataFlowMask,
         state_mask: DeviceStateMask,
-    ) -> windows::core::Result<DeviceCollection> {
-        let inner = unsafe {
-            self.inner
-                .EnumAudioEndpoints(data_flow_mask.to_raw(), state_mask.bits())?
-        };
-        Ok(DeviceCollection::new(inner))
+    ) -> crate::Result<DeviceCollection> {
+        self.inner
+            .enum_audio_endpoints(data_flow_mask, state_mask)
+            .map(DeviceCollection::new)
     }
 
     /// See also: [`IMMDeviceEnumerator::GetDefaultAudioEndpoint`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/nf-mmdeviceapi-immdeviceenumerator-getdefaultaudioendpoint)
@@ -44,57 +78,99 @@ impl DeviceEnumerator {
         &self,
         data_flow: DataFlow,
         role: DeviceRole,
-    ) -> windows::core::Result<Device> {
-        unsafe {
-            self.inner
-                .GetDefaultAudioEndpoint(data_flow.to_raw(), role.to_raw())
-                .map(Device::new)
-        }
+    ) -> crate::Result<Device> {
+        self.inner
+            .get_default_audio_endpoint(data_flow, role)
+            .map(Device::new)
     }
 
     /// See also: [`IMMDeviceEnumerator::GetDevice`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/nf-mmdeviceapi-immdeviceenumerator-getdevice)
-    pub fn get_device(&self, device_id: &WinStr) -> windows::core::Result<Device> {
-        unsafe { self.inner.GetDevice(device_id.as_pcwstr()).map(Device::new) }
+    pub fn get_device(&self, device_id: &WinStr) -> crate::Result<Device> {
+        self.inner.get_device(device_id).map(Device::new)
+    }
+
+    /// Take a snapshot of every device that matches the masks, including the
+    /// roles that each of them is the default endpoint for.
+    ///
+    /// Properties that a device does not report are left empty instead of
+    /// failing the whole inventory.
+    pub fn inventory(
+        &self,
+        data_flow_mask: DataFlowMask,
+        state_mask: DeviceStateMask,
+    ) -> crate::Result<Vec<DeviceInfo>> {
+        device_info::inventory(self, data_flow_mask, state_mask)
+    }
+
+    /// Find the devices that match `query`, best match first.
+    ///
+    /// This makes configurations portable between machines, where device ids
+    /// differ but names and hardware ids stay the same.
+    pub fn find_devices(&self, query: &DeviceQuery) -> crate::Result<Vec<DeviceMatch>> {
+        device_query::find_devices(self, query)
+    }
+
+    /// Make a device the default endpoint of its data flow for `role`.
+    ///
+    /// Windows has no documented API for this, so it goes through the
+    /// `IPolicyConfig` interface that the sound settings use. The interface is
+    /// created by the first call and reused by later calls on this enumerator
+    /// and its clones.
+    ///
+    /// Fails if the device does not exist or is not active. On success,
+    /// registered notification clients are told that the default changed.
+    pub fn set_default_audio_endpoint(
+        &self,
+        device_id: &WinStr,
+        role: DeviceRole,
+    ) -> crate::Result<()> {
+        self.policy_config()?.set_default_endpoint(device_id, role)
+    }
+
+    /// Make a device the default endpoint of its data flow for every role.
+    ///
+    /// The roles are changed one by one, so if changing one fails, the roles
+    /// before it have already been changed.
+    pub fn set_default_audio_endpoint_all_roles(&self, device_id: &WinStr) -> crate::Result<()> {
+        let policy_config = self.policy_config()?;
+        for &role in &ROLES {
+            policy_config.set_default_endpoint(device_id, role)?;
+        }
+        Ok(())
+    }
+
+    fn policy_config(&self) -> crate::Result<Rc<dyn PolicyConfigBackend>> {
+        if let Some(policy_config) = &*self.policy_config.borrow() {
+            return Ok(policy_config.clone());
+        }
+        let policy_config = self.inner.policy_config()?;
+        *self.policy_config.borrow_mut() = Some(policy_config.clone());
+        Ok(policy_config)
     }
 
     /// See also: [`IMMDeviceEnumerator::RegisterEndpointNotificationCallback`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/nf-mmdeviceapi-immdeviceenumerator-registerendpointnotificationcallback)
     pub fn register_endpoint_notification<T>(
         &self,
         notification_client: T,
-    ) -> windows::core::Result<NotificationClientHandle>
+    ) -> crate::Result<NotificationClientHandle>
     where
         T: NotificationClient,
     {
-        let wrapper =
-            IMMNotificationClient::from(NotificationClientWrapper::new(notification_client));
-        unsafe { self.inner.RegisterEndpointNotificationCallback(&wrapper)? };
-
-        Ok(NotificationClientHandle {
-            inner: wrapper,
-            parent: self.inner.clone(),
-        })
+        let inner = self
+            .inner
+            .register_endpoint_notification(Box::new(notification_client))?;
+        Ok(NotificationClientHandle { inner })
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 #[must_use = "callback will be unregistered when the handle is dropped"]
 pub struct NotificationClientHandle {
-    inner: IMMNotificationClient,
-    parent: IMMDeviceEnumerator,
+    inner: Rc<dyn CallbackRegistration>,
 }
 
 impl NotificationClientHandle {
     pub fn unregister(self) {
-        // Handled by Drop impl
-    }
-}
-
-impl Drop for NotificationClientHandle {
-    fn drop(&mut self) {
-        unsafe {
-            self.parent
-                .UnregisterEndpointNotificationCallback(&self.inner)
-                .ok()
-        };
+        drop(self.inner);
     }
 }
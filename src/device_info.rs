@@ -0,0 +1,137 @@
+use windows::Win32::Foundation::E_INVALIDARG;
+
+use crate::{
+    bits::{DataFlow, DataFlowMask, DeviceRole, DeviceState, DeviceStateMask, EndpointFormFactor},
+    device::{optional_property, Device},
+    device_enumerator::DeviceEnumerator,
+    error::Error,
+    string::WinString,
+    HardwareSupportMask,
+};
+
+/// An owned snapshot of the state of an endpoint device, as returned by
+/// [`DeviceEnumerator::inventory`].
+///
+/// Unlike [`Device`], a snapshot holds no COM pointers, so it can be sent to
+/// other threads, stored and compared with later snapshots.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceInfo {
+    pub id: String,
+    /// `None` if the device has no friendly name.
+    pub friendly_name: Option<String>,
+    pub state: DeviceState,
+    pub data_flow: DataFlow,
+    /// `None` if the device does not report a form factor.
+    pub form_factor: Option<EndpointFormFactor>,
+    /// The roles that the device is the default endpoint for, in the order
+    /// console, multimedia, communications.
+    pub default_roles: Vec<DeviceRole>,
+    /// `None` if the endpoint volume cannot be activated, which is the case
+    /// for devices that are not active.
+    pub hardware_support: Option<HardwareSupportMask>,
+}
+
+impl DeviceInfo {
+    /// Take a snapshot of `device`.
+    ///
+    /// This looks up the default endpoints of the device's data flow. Use
+    /// [`DeviceEnumerator::inventory`] to take snapshots of many devices, which
+    /// looks them up only once.
+    pub fn from_device(device: &Device) -> crate::Result<Self> {
+        let data_flow = device.data_flow()?;
+        let defaults = default_ids(&device.enumerator()?, data_flow)?;
+        snapshot(device, data_flow, &defaults)
+    }
+
+    /// Whether the device is the default endpoint for `role`.
+    pub fn is_default_for(&self, role: DeviceRole) -> bool {
+        self.default_roles.contains(&role)
+    }
+}
+
+pub(crate) const ROLES: [DeviceRole; 3] = [
+    DeviceRole::Console,
+    DeviceRole::Multimedia,
+    DeviceRole::Communications,
+];
+
+pub(crate) fn inventory(
+    enumerator: &DeviceEnumerator,
+    data_flow_mask: DataFlowMask,
+    state_mask: DeviceStateMask,
+) -> crate::Result<Vec<DeviceInfo>> {
+    // Enumerate each data flow separately, so that the flow of every device
+    // is known without querying it.
+    let data_flows: &[(DataFlow, DataFlowMask)] = match data_flow_mask {
+        DataFlowMask::Render => &[(DataFlow::Render, DataFlowMask::Render)],
+        DataFlowMask::Capture => &[(DataFlow::Capture, DataFlowMask::Capture)],
+        DataFlowMask::All => &[
+            (DataFlow::Render, DataFlowMask::Render),
+            (DataFlow::Capture, DataFlowMask::Capture),
+        ],
+        DataFlowMask::Unknown(_) => return Err(Error::Other(E_INVALIDARG)),
+    };
+
+    // The defaults are looked up once per data flow and compared by id,
+    // instead of once per device and role.
+    let mut devices = Vec::new();
+    for &(data_flow, mask) in data_flows {
+        let defaults = default_ids(enumerator, data_flow)?;
+        for device in &enumerator.enum_audio_endpoints(mask, state_mask)? {
+            match device.and_then(|device| snapshot(&device, data_flow, &defaults)) {
+                Ok(info) => devices.push(info),
+                // The device was removed after it was enumerated.
+                Err(Error::DeviceNotFound) | Err(Error::DeviceInvalidated) => {}
+                Err(error) => return Err(error),
+            }
+        }
+    }
+    Ok(devices)
+}
+
+/// The ids of the default endpoints of `data_flow`, by role. Roles without a
+/// default are left out.
+fn default_ids(
+    enumerator: &DeviceEnumerator,
+    data_flow: DataFlow,
+) -> crate::Result<Vec<(DeviceRole, WinString)>> {
+    let mut defaults = Vec::with_capacity(ROLES.len());
+    for &role in &ROLES {
+        match enumerator.get_default_audio_endpoint(data_flow, role) {
+            Ok(device) => defaults.push((role, device.get_id()?)),
+            Err(Error::DeviceNotFound) => {}
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(defaults)
+}
+
+fn snapshot(
+    device: &Device,
+    data_flow: DataFlow,
+    defaults: &[(DeviceRole, WinString)],
+) -> crate::Result<DeviceInfo> {
+    let id = device.get_id()?;
+    let default_roles = defaults
+        .iter()
+        .filter(|(_, default_id)| *default_id == id)
+        .map(|(role, _)| *role)
+        .collect();
+    Ok(DeviceInfo {
+        id: id.to_string_lossy(),
+        friendly_name: optional_property(device.friendly_name())?
+            .map(|name| name.to_string_lossy()),
+        state: device.get_state()?,
+        data_flow,
+        form_factor: optional_property(device.form_factor())?,
+        default_roles,
+        hardware_support: hardware_support(device),
+    })
+}
+
+fn hardware_support(device: &Device) -> Option<HardwareSupportMask> {
+    device
+        .activate_audio_endpoint_volume()
+        .and_then(|volume| volume.query_hardware_support())
+        .ok()
+}
@@ -0,0 +1,353 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    bits::{DataFlow, DataFlowMask, DeviceRole, DeviceState, DeviceStateMask, EndpointFormFactor},
+    device_enumerator::DeviceEnumerator,
+    device_info::DeviceInfo,
+    device_query::TextMatch,
+    device_watcher::DeviceEvent,
+    error::Error,
+    property_keys,
+    string::WinString,
+};
+
+/// Selects the devices that one entry of a [`DevicePolicy`] prefers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceSelector {
+    /// The device with this endpoint id.
+    Id(String),
+    /// Devices whose friendly name matches.
+    FriendlyName(TextMatch),
+    /// Devices with this form factor, for example the built-in speakers.
+    FormFactor(EndpointFormFactor),
+}
+
+impl DeviceSelector {
+    pub fn matches(&self, device: &DeviceInfo) -> bool {
+        match self {
+            Self::Id(id) => device.id == *id,
+            Self::FriendlyName(pattern) => matches!(
+                device.friendly_name.as_deref(),
+                Some(name) if pattern.matches(name)
+            ),
+            Self::FormFactor(form_factor) => device.form_factor == Some(*form_factor),
+        }
+    }
+}
+
+/// Priority lists of devices per data flow and role, for example "the USB
+/// headset if it is plugged in, else the dock speakers, else the laptop
+/// speakers".
+///
+/// Only active devices of the data flow of a list are candidates.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DevicePolicy {
+    lists: Vec<(DataFlow, DeviceRole, Vec<DeviceSelector>)>,
+}
+
+impl DevicePolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append `selector` to the list of `data_flow` and `role`, with a lower
+    /// priority than the selectors that were added before.
+    pub fn prefer(
+        mut self,
+        data_flow: DataFlow,
+        role: DeviceRole,
+        selector: DeviceSelector,
+    ) -> Self {
+        match self
+            .lists
+            .iter_mut()
+            .find(|(flow, x, _)| *flow == data_flow && *x == role)
+        {
+            Some((_, _, selectors)) => selectors.push(selector),
+            None => self.lists.push((data_flow, role, vec![selector])),
+        }
+        self
+    }
+
+    /// The priority list of `data_flow` and `role`, highest priority first.
+    pub fn preferences(&self, data_flow: DataFlow, role: DeviceRole) -> &[DeviceSelector] {
+        self.lists
+            .iter()
+            .find(|(flow, x, _)| *flow == data_flow && *x == role)
+            .map_or(&[], |(_, _, selectors)| selectors)
+    }
+
+    /// Decide for every list which of `devices` should be the default.
+    pub fn evaluate(&self, devices: &[DeviceInfo]) -> Vec<PolicyDecision> {
+        self.decide_all(devices)
+    }
+
+    fn decide_all<'a, I>(&self, devices: I) -> Vec<PolicyDecision>
+    where
+        I: IntoIterator<Item = &'a DeviceInfo> + Clone,
+    {
+        self.lists
+            .iter()
+            .map(|(data_flow, role, selectors)| {
+                decide(*data_flow, *role, selectors, devices.clone())
+            })
+            .collect()
+    }
+}
+
+/// Which device a [`DevicePolicy`] wants as the default for one data flow and
+/// role.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyDecision {
+    pub data_flow: DataFlow,
+    pub role: DeviceRole,
+    /// The preferred device, or `None` if no active device matches the list.
+    pub device_id: Option<String>,
+    /// The position in the list of the selector that chose the device.
+    pub rank: Option<usize>,
+    /// Whether the preferred device already is the default.
+    pub is_default: bool,
+}
+
+impl PolicyDecision {
+    /// Whether the default has to be changed to follow the decision.
+    pub fn needs_switch(&self) -> bool {
+        self.device_id.is_some() && !self.is_default
+    }
+}
+
+fn decide<'a, I>(
+    data_flow: DataFlow,
+    role: DeviceRole,
+    selectors: &[DeviceSelector],
+    devices: I,
+) -> PolicyDecision
+where
+    I: IntoIterator<Item = &'a DeviceInfo>,
+{
+    let candidates: Vec<&DeviceInfo> = devices
+        .into_iter()
+        .filter(|device| device.data_flow == data_flow && device.state == DeviceState::Active)
+        .collect();
+    for (rank, selector) in selectors.iter().enumerate() {
+        let mut matches = candidates.iter().filter(|device| selector.matches(device));
+        let first = matches.next();
+        // Among equally preferred devices keep the current default, so that
+        // the decision does not flap.
+        let chosen = first
+            .into_iter()
+            .chain(matches)
+            .find(|device| device.is_default_for(role))
+            .or(first);
+        if let Some(device) = chosen {
+            return PolicyDecision {
+                data_flow,
+                role,
+                device_id: Some(device.id.clone()),
+                rank: Some(rank),
+                is_default: device.is_default_for(role),
+            };
+        }
+    }
+    PolicyDecision {
+        data_flow,
+        role,
+        device_id: None,
+        rank: None,
+        is_default: false,
+    }
+}
+
+/// A change to the devices known to a [`PolicyEngine`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyEvent {
+    /// A device was added, or its snapshot was taken again.
+    DeviceUpdated(DeviceInfo),
+    DeviceRemoved {
+        id: String,
+    },
+    StateChanged {
+        id: String,
+        state: DeviceState,
+    },
+    /// `id` is `None` if there is no longer a default device for the role.
+    DefaultChanged {
+        data_flow: DataFlow,
+        role: DeviceRole,
+        id: Option<String>,
+    },
+}
+
+/// Applies a [`DevicePolicy`] to a table of device snapshots that is kept up
+/// to date with [`PolicyEvent`]s.
+///
+/// The engine does not query any device, so it can be driven by a scripted
+/// list of events. [`PolicyMonitor`] drives it from endpoint notifications.
+#[derive(Debug, Clone)]
+pub struct PolicyEngine {
+    policy: DevicePolicy,
+    devices: BTreeMap<String, DeviceInfo>,
+    decisions: Vec<PolicyDecision>,
+}
+
+impl PolicyEngine {
+    pub fn new(policy: DevicePolicy, devices: Vec<DeviceInfo>) -> Self {
+        let mut engine = Self {
+            policy,
+            devices: devices
+                .into_iter()
+                .map(|device| (device.id.clone(), device))
+                .collect(),
+            decisions: Vec::new(),
+        };
+        engine.decisions = engine.evaluate();
+        engine
+    }
+
+    pub fn policy(&self) -> &DevicePolicy {
+        &self.policy
+    }
+
+    pub fn devices(&self) -> impl Iterator<Item = &DeviceInfo> + '_ {
+        self.devices.values()
+    }
+
+    /// The current decision of every list of the policy.
+    pub fn decisions(&self) -> &[PolicyDecision] {
+        &self.decisions
+    }
+
+    pub fn decision(&self, data_flow: DataFlow, role: DeviceRole) -> Option<&PolicyDecision> {
+        self.decisions
+            .iter()
+            .find(|x| x.data_flow == data_flow && x.role == role)
+    }
+
+    /// Apply `event` and return the decisions that changed.
+    pub fn handle(&mut self, event: PolicyEvent) -> Vec<PolicyDecision> {
+        match event {
+            PolicyEvent::DeviceUpdated(device) => {
+                for &role in &device.default_roles {
+                    self.clear_default(device.data_flow, role);
+                }
+                self.devices.insert(device.id.clone(), device);
+            }
+            PolicyEvent::DeviceRemoved { id } => {
+                self.devices.remove(&id);
+            }
+            PolicyEvent::StateChanged { id, state } => {
+                if let Some(device) = self.devices.get_mut(&id) {
+                    device.state = state;
+                }
+            }
+            PolicyEvent::DefaultChanged {
+                data_flow,
+                role,
+                id,
+            } => {
+                self.clear_default(data_flow, role);
+                if let Some(device) = id.and_then(|id| self.devices.get_mut(&id)) {
+                    device.default_roles.push(role);
+                }
+            }
+        }
+
+        let decisions = self.evaluate();
+        let changed = decisions
+            .iter()
+            .filter(|decision| !self.decisions.contains(decision))
+            .cloned()
+            .collect();
+        self.decisions = decisions;
+        changed
+    }
+
+    fn clear_default(&mut self, data_flow: DataFlow, role: DeviceRole) {
+        for device in self.devices.values_mut() {
+            if device.data_flow == data_flow {
+                device.default_roles.retain(|x| *x != role);
+            }
+        }
+    }
+
+    fn evaluate(&self) -> Vec<PolicyDecision> {
+        self.policy.decide_all(self.devices.values())
+    }
+}
+
+/// Drives a [`PolicyEngine`] with the devices of a [`DeviceEnumerator`].
+///
+/// Feed it the events of a [`DeviceWatcher`](crate::DeviceWatcher) on the
+/// thread that created it: devices that were added or changed are queried
+/// again before the policy is applied.
+#[derive(Debug)]
+pub struct PolicyMonitor {
+    enumerator: DeviceEnumerator,
+    engine: PolicyEngine,
+}
+
+impl PolicyMonitor {
+    pub fn new(enumerator: &DeviceEnumerator, policy: DevicePolicy) -> crate::Result<Self> {
+        let devices = enumerator.inventory(DataFlowMask::All, DeviceStateMask::ALL)?;
+        Ok(Self {
+            enumerator: enumerator.clone(),
+            engine: PolicyEngine::new(policy, devices),
+        })
+    }
+
+    pub fn engine(&self) -> &PolicyEngine {
+        &self.engine
+    }
+
+    /// The current decision of every list of the policy.
+    pub fn decisions(&self) -> &[PolicyDecision] {
+        self.engine.decisions()
+    }
+
+    /// Apply `event` and return the decisions that changed.
+    pub fn handle(&mut self, event: &DeviceEvent) -> crate::Result<Vec<PolicyDecision>> {
+        let event = match event {
+            DeviceEvent::Added { id } => self.query(id)?,
+            DeviceEvent::Removed { id } => PolicyEvent::DeviceRemoved { id: id.clone() },
+            DeviceEvent::StateChanged { id, state } if self.engine.devices.contains_key(id) => {
+                PolicyEvent::StateChanged {
+                    id: id.clone(),
+                    state: *state,
+                }
+            }
+            DeviceEvent::StateChanged { id, .. } => self.query(id)?,
+            DeviceEvent::DefaultChanged {
+                data_flow,
+                role,
+                id,
+            } => PolicyEvent::DefaultChanged {
+                data_flow: *data_flow,
+                role: *role,
+                id: id.clone(),
+            },
+            // Only the properties that selectors look at are relevant.
+            DeviceEvent::PropertyChanged { id, key }
+                if *key == property_keys::DEVICE_FRIENDLY_NAME
+                    || *key == property_keys::AUDIO_ENDPOINT_FORM_FACTOR =>
+            {
+                self.query(id)?
+            }
+            DeviceEvent::PropertyChanged { .. } => return Ok(Vec::new()),
+        };
+        Ok(self.engine.handle(event))
+    }
+
+    fn query(&self, id: &str) -> crate::Result<PolicyEvent> {
+        let device = self
+            .enumerator
+            .get_device(&WinString::from(id))
+            .and_then(|device| DeviceInfo::from_device(&device));
+        match device {
+            Ok(device) => Ok(PolicyEvent::DeviceUpdated(device)),
+            Err(Error::DeviceNotFound) | Err(Error::DeviceInvalidated) => {
+                Ok(PolicyEvent::DeviceRemoved { id: id.to_owned() })
+            }
+            Err(error) => Err(error),
+        }
+    }
+}
@@ -0,0 +1,282 @@
+use std::cmp::Reverse;
+
+use windows::core::GUID;
+
+use crate::{
+    bits::{DataFlowMask, DeviceStateMask},
+    device::{optional_property, Device},
+    device_enumerator::DeviceEnumerator,
+    error::Error,
+};
+
+/// How a [`TextMatch`] compares its pattern with a property.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MatchKind {
+    /// The whole text equals the pattern.
+    Exact,
+    /// The text contains the pattern.
+    Substring,
+    /// The whole text matches the pattern, where `*` matches any sequence of
+    /// characters and `?` matches a single character.
+    Glob,
+}
+
+/// A pattern for a text property of a device.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TextMatch {
+    pattern: String,
+    kind: MatchKind,
+    ignore_case: bool,
+}
+
+/// How closely a text matched, from best to worst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Closeness {
+    Substring,
+    Prefix,
+    Glob,
+    EqualIgnoringCase,
+    Equal,
+}
+
+impl TextMatch {
+    pub fn new(pattern: &str, kind: MatchKind) -> Self {
+        Self {
+            pattern: pattern.to_owned(),
+            kind,
+            ignore_case: false,
+        }
+    }
+
+    pub fn exact(pattern: &str) -> Self {
+        Self::new(pattern, MatchKind::Exact)
+    }
+
+    pub fn substring(pattern: &str) -> Self {
+        Self::new(pattern, MatchKind::Substring)
+    }
+
+    pub fn glob(pattern: &str) -> Self {
+        Self::new(pattern, MatchKind::Glob)
+    }
+
+    /// Compare without regard to case.
+    pub fn ignore_case(mut self) -> Self {
+        self.ignore_case = true;
+        self
+    }
+
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    pub fn kind(&self) -> MatchKind {
+        self.kind
+    }
+
+    pub fn is_ignore_case(&self) -> bool {
+        self.ignore_case
+    }
+
+    /// Whether `text` matches the pattern.
+    pub fn matches(&self, text: &str) -> bool {
+        self.score(text).is_some()
+    }
+
+    /// Score a match: how closely the text matched, then the share of the
+    /// text in per mille that the pattern covers.
+    fn score(&self, text: &str) -> Option<u32> {
+        let closeness = if text == self.pattern {
+            Closeness::Equal
+        } else {
+            let (text, pattern) = if self.ignore_case {
+                (text.to_lowercase(), self.pattern.to_lowercase())
+            } else {
+                (text.to_owned(), self.pattern.clone())
+            };
+            match self.kind {
+                _ if self.ignore_case && text == pattern => Closeness::EqualIgnoringCase,
+                MatchKind::Exact => return None,
+                MatchKind::Substring if text.starts_with(&pattern) => Closeness::Prefix,
+                MatchKind::Substring if text.contains(&pattern) => Closeness::Substring,
+                MatchKind::Glob if glob_matches(&pattern, &text) => Closeness::Glob,
+                MatchKind::Substring | MatchKind::Glob => return None,
+            }
+        };
+        let literal_len = self
+            .pattern
+            .chars()
+            .filter(|c| !matches!(c, '*' | '?'))
+            .count();
+        let text_len = text.chars().count().max(1);
+        let coverage = (literal_len.min(text_len) * 1000 / text_len) as u32;
+        Some(closeness as u32 * 1000 + coverage)
+    }
+}
+
+/// Match `text` against a glob `pattern` with `*` and `?` wildcards.
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and the text position it was tried at.
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, star_t)) => {
+                    p = star + 1;
+                    t = star_t + 1;
+                    backtrack = Some((star, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Criteria for [`DeviceEnumerator::find_devices`].
+///
+/// A device has to match every criterion that is set. Devices are active
+/// endpoints of either data flow unless configured otherwise.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceQuery {
+    data_flow_mask: DataFlowMask,
+    state_mask: DeviceStateMask,
+    friendly_name: Option<TextMatch>,
+    description: Option<TextMatch>,
+    container_id: Option<GUID>,
+    hardware_id: Option<TextMatch>,
+}
+
+impl Default for DeviceQuery {
+    fn default() -> Self {
+        Self {
+            data_flow_mask: DataFlowMask::All,
+            state_mask: DeviceStateMask::ACTIVE,
+            friendly_name: None,
+            description: None,
+            container_id: None,
+            hardware_id: None,
+        }
+    }
+}
+
+impl DeviceQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn data_flow(mut self, data_flow_mask: DataFlowMask) -> Self {
+        self.data_flow_mask = data_flow_mask;
+        self
+    }
+
+    pub fn state(mut self, state_mask: DeviceStateMask) -> Self {
+        self.state_mask = state_mask;
+        self
+    }
+
+    /// Match the friendly name, for example "Headset Earphone (Jabra Evolve
+    /// 65)".
+    pub fn friendly_name(mut self, friendly_name: TextMatch) -> Self {
+        self.friendly_name = Some(friendly_name);
+        self
+    }
+
+    /// Match the description, for example "Headset Earphone".
+    pub fn description(mut self, description: TextMatch) -> Self {
+        self.description = Some(description);
+        self
+    }
+
+    pub fn container_id(mut self, container_id: GUID) -> Self {
+        self.container_id = Some(container_id);
+        self
+    }
+
+    /// Match any of the hardware ids of the device.
+    pub fn hardware_id(mut self, hardware_id: TextMatch) -> Self {
+        self.hardware_id = Some(hardware_id);
+        self
+    }
+
+    /// Score `device`, or `None` if it does not match.
+    fn score(&self, device: &Device) -> crate::Result<Option<u32>> {
+        let mut score = 0;
+        if let Some(pattern) = &self.friendly_name {
+            let name = optional_property(device.friendly_name())?;
+            match name.and_then(|name| pattern.score(&name.to_string_lossy())) {
+                Some(x) => score += x,
+                None => return Ok(None),
+            }
+        }
+        if let Some(pattern) = &self.description {
+            let description = optional_property(device.description())?;
+            match description.and_then(|x| pattern.score(&x.to_string_lossy())) {
+                Some(x) => score += x,
+                None => return Ok(None),
+            }
+        }
+        if let Some(container_id) = &self.container_id {
+            match optional_property(device.container_id())? {
+                Some(x) if x == *container_id => score += Closeness::Equal as u32 * 1000 + 1000,
+                _ => return Ok(None),
+            }
+        }
+        if let Some(pattern) = &self.hardware_id {
+            let hardware_ids = optional_property(device.hardware_ids())?.unwrap_or_default();
+            match hardware_ids
+                .iter()
+                .filter_map(|id| pattern.score(&id.to_string_lossy()))
+                .max()
+            {
+                Some(x) => score += x,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(score))
+    }
+}
+
+/// A device found by [`DeviceEnumerator::find_devices`].
+#[derive(Debug, Clone)]
+pub struct DeviceMatch {
+    pub device: Device,
+    /// How closely the device matches the query. Higher is better.
+    ///
+    /// For every text criterion, an exact match ranks above a
+    /// case-insensitive one, which ranks above a glob match, a substring at
+    /// the start of the text and then a substring anywhere else. Ties are
+    /// broken by how much of the text the pattern covers.
+    pub score: u32,
+}
+
+pub(crate) fn find_devices(
+    enumerator: &DeviceEnumerator,
+    query: &DeviceQuery,
+) -> crate::Result<Vec<DeviceMatch>> {
+    let mut matches = Vec::new();
+    for device in &enumerator.enum_audio_endpoints(query.data_flow_mask, query.state_mask)? {
+        let scored = device.and_then(|device| Ok((query.score(&device)?, device)));
+        match scored {
+            Ok((Some(score), device)) => matches.push(DeviceMatch { device, score }),
+            Ok((None, _)) => {}
+            // The device went away after it was enumerated.
+            Err(Error::DeviceInvalidated) | Err(Error::DeviceNotFound) => {}
+            Err(error) => return Err(error),
+        }
+    }
+    // Stable, so equally good matches stay in enumeration order.
+    matches.sort_by_key(|x| Reverse(x.score));
+    Ok(matches)
+}
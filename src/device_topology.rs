@@ -0,0 +1,395 @@
+use std::rc::Rc;
+
+use windows::core::GUID;
+use windows::Win32::Foundation::E_NOINTERFACE;
+use windows::Win32::Media::KernelStreaming::KSJACK_DESCRIPTION;
+
+use crate::{
+    audio_endpoint_volume::VolumeRange,
+    backend::{ConnectorBackend, DeviceTopologyBackend, PartBackend, PartsListBackend},
+    bits::{
+        ChannelMask, ConnectorDataFlow, ConnectorType, JackConnectionType, JackGenLocation,
+        JackGeoLocation, JackPortConnection, PartType,
+    },
+    error::Error,
+    string::WinString,
+};
+
+/// The topology of a device, which is either an endpoint or the audio
+/// adapter that it belongs to.
+///
+/// See also: [`IDeviceTopology`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/nn-devicetopology-idevicetopology)
+#[derive(Debug, Clone)]
+pub struct DeviceTopology {
+    inner: Rc<dyn DeviceTopologyBackend>,
+}
+
+impl DeviceTopology {
+    pub(crate) fn new(inner: Rc<dyn DeviceTopologyBackend>) -> Self {
+        Self { inner }
+    }
+
+    /// See also: [`IDeviceTopology::GetConnectorCount`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/nf-devicetopology-idevicetopology-getconnectorcount)
+    pub fn get_connector_count(&self) -> crate::Result<u32> {
+        self.inner.get_connector_count()
+    }
+
+    /// See also: [`IDeviceTopology::GetConnector`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/nf-devicetopology-idevicetopology-getconnector)
+    pub fn get_connector(&self, index: u32) -> crate::Result<Connector> {
+        self.inner.get_connector(index).map(Connector::new)
+    }
+
+    /// See also: [`IDeviceTopology::GetSubunitCount`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/nf-devicetopology-idevicetopology-getsubunitcount)
+    pub fn get_subunit_count(&self) -> crate::Result<u32> {
+        self.inner.get_subunit_count()
+    }
+
+    /// See also: [`IDeviceTopology::GetSubunit`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/nf-devicetopology-idevicetopology-getsubunit)
+    pub fn get_subunit(&self, index: u32) -> crate::Result<Subunit> {
+        self.inner.get_subunit(index).map(Subunit::new)
+    }
+
+    /// See also: [`IDeviceTopology::GetPartById`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/nf-devicetopology-idevicetopology-getpartbyid)
+    pub fn get_part_by_id(&self, local_id: u32) -> crate::Result<Part> {
+        self.inner.get_part_by_id(local_id).map(Part::new)
+    }
+
+    /// The id of the device, which is an endpoint id for the topology of an
+    /// endpoint and a device interface path for an adapter.
+    ///
+    /// See also: [`IDeviceTopology::GetDeviceId`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/nf-devicetopology-idevicetopology-getdeviceid)
+    pub fn get_device_id(&self) -> crate::Result<WinString> {
+        self.inner.get_device_id()
+    }
+
+    /// Collect every connector.
+    pub fn connectors(&self) -> crate::Result<Vec<Connector>> {
+        (0..self.get_connector_count()?)
+            .map(|index| self.get_connector(index))
+            .collect()
+    }
+
+    /// Collect every subunit.
+    pub fn subunits(&self) -> crate::Result<Vec<Subunit>> {
+        (0..self.get_subunit_count()?)
+            .map(|index| self.get_subunit(index))
+            .collect()
+    }
+}
+
+/// A part of a device topology, which is either a [`Connector`] or a
+/// [`Subunit`].
+///
+/// See also: [`IPart`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/nn-devicetopology-ipart)
+#[derive(Debug, Clone)]
+pub struct Part {
+    inner: Rc<dyn PartBackend>,
+}
+
+impl Part {
+    pub(crate) fn new(inner: Rc<dyn PartBackend>) -> Self {
+        Self { inner }
+    }
+
+    /// See also: [`IPart::GetName`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/nf-devicetopology-ipart-getname)
+    pub fn get_name(&self) -> crate::Result<WinString> {
+        self.inner.get_name()
+    }
+
+    /// The id of the part within its device topology.
+    ///
+    /// See also: [`IPart::GetLocalId`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/nf-devicetopology-ipart-getlocalid)
+    pub fn get_local_id(&self) -> crate::Result<u32> {
+        self.inner.get_local_id()
+    }
+
+    /// The id of the part across all device topologies.
+    ///
+    /// See also: [`IPart::GetGlobalId`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/nf-devicetopology-ipart-getglobalid)
+    pub fn get_global_id(&self) -> crate::Result<WinString> {
+        self.inner.get_global_id()
+    }
+
+    /// See also: [`IPart::GetPartType`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/nf-devicetopology-ipart-getparttype)
+    pub fn get_part_type(&self) -> crate::Result<PartType> {
+        self.inner.get_part_type()
+    }
+
+    /// The `KSNODETYPE_XXX` GUID of the part, for example
+    /// `KSNODETYPE_VOLUME` for a volume control or `KSNODETYPE_SPEAKER` for
+    /// a speaker jack.
+    ///
+    /// See also: [`IPart::GetSubType`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/nf-devicetopology-ipart-getsubtype)
+    pub fn get_sub_type(&self) -> crate::Result<GUID> {
+        self.inner.get_sub_type()
+    }
+
+    /// Fails with [`Error::DeviceNotFound`] if no part links to this one.
+    ///
+    /// See also: [`IPart::EnumPartsIncoming`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/nf-devicetopology-ipart-enumpartsincoming)
+    pub fn enum_parts_incoming(&self) -> crate::Result<PartsList> {
+        self.inner.enum_parts_incoming().map(PartsList::new)
+    }
+
+    /// Fails with [`Error::DeviceNotFound`] if this part links to no other
+    /// part.
+    ///
+    /// See also: [`IPart::EnumPartsOutgoing`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/nf-devicetopology-ipart-enumpartsoutgoing)
+    pub fn enum_parts_outgoing(&self) -> crate::Result<PartsList> {
+        self.inner.enum_parts_outgoing().map(PartsList::new)
+    }
+
+    /// The parts that link to this one, upstream in the signal path.
+    pub fn incoming_parts(&self) -> crate::Result<Vec<Part>> {
+        parts_or_empty(self.enum_parts_incoming())
+    }
+
+    /// The parts that this one links to, downstream in the signal path.
+    pub fn outgoing_parts(&self) -> crate::Result<Vec<Part>> {
+        parts_or_empty(self.enum_parts_outgoing())
+    }
+
+    /// The topology of the device that the part belongs to.
+    ///
+    /// See also: [`IPart::GetTopologyObject`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/nf-devicetopology-ipart-gettopologyobject)
+    pub fn get_topology_object(&self) -> crate::Result<DeviceTopology> {
+        self.inner.get_topology_object().map(DeviceTopology::new)
+    }
+
+    /// Fails with `E_NOINTERFACE` if the part is a subunit.
+    pub fn as_connector(&self) -> crate::Result<Connector> {
+        self.inner.as_connector().map(Connector::new)
+    }
+
+    /// Fails with `E_NOINTERFACE` if the part is a connector.
+    pub fn as_subunit(&self) -> crate::Result<Subunit> {
+        match self.get_part_type()? {
+            PartType::Subunit => Ok(Subunit::new(self.inner.clone())),
+            _ => Err(Error::Other(E_NOINTERFACE)),
+        }
+    }
+
+    /// Read the hardware controls of the part, in the order volume, mute,
+    /// automatic gain control and loudness. Controls that the part does not
+    /// have are left out.
+    ///
+    /// See also: [`IPart::Activate`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/nf-devicetopology-ipart-activate)
+    pub fn controls(&self) -> crate::Result<Vec<PartControl>> {
+        self.inner.controls()
+    }
+
+    /// Read the jack descriptions of the part, which is empty unless the part
+    /// is a connector with jack information.
+    ///
+    /// See also: [`IKsJackDescription`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/nn-devicetopology-iksjackdescription)
+    pub fn jack_descriptions(&self) -> crate::Result<Vec<JackDescription>> {
+        self.inner.jack_descriptions()
+    }
+}
+
+fn parts_or_empty(list: crate::Result<PartsList>) -> crate::Result<Vec<Part>> {
+    match list {
+        Ok(list) => list.to_vec(),
+        Err(Error::DeviceNotFound) => Ok(Vec::new()),
+        Err(error) => Err(error),
+    }
+}
+
+/// See also: [`IPartsList`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/nn-devicetopology-ipartslist)
+#[derive(Debug, Clone)]
+pub struct PartsList {
+    inner: Rc<dyn PartsListBackend>,
+}
+
+impl PartsList {
+    pub(crate) fn new(inner: Rc<dyn PartsListBackend>) -> Self {
+        Self { inner }
+    }
+
+    /// See also: [`IPartsList::GetCount`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/nf-devicetopology-ipartslist-getcount)
+    pub fn get_count(&self) -> crate::Result<u32> {
+        self.inner.get_count()
+    }
+
+    /// See also: [`IPartsList::GetPart`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/nf-devicetopology-ipartslist-getpart)
+    pub fn get_part(&self, index: u32) -> crate::Result<Part> {
+        self.inner.get_part(index).map(Part::new)
+    }
+
+    /// Collect every part, failing if any of them could not be retrieved.
+    pub fn to_vec(&self) -> crate::Result<Vec<Part>> {
+        (0..self.get_count()?)
+            .map(|index| self.get_part(index))
+            .collect()
+    }
+}
+
+/// A point where a device topology connects to another device, or to the
+/// outside world like a jack.
+///
+/// See also: [`IConnector`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/nn-devicetopology-iconnector)
+#[derive(Debug, Clone)]
+pub struct Connector {
+    inner: Rc<dyn ConnectorBackend>,
+}
+
+impl Connector {
+    pub(crate) fn new(inner: Rc<dyn ConnectorBackend>) -> Self {
+        Self { inner }
+    }
+
+    /// See also: [`IConnector::GetType`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/nf-devicetopology-iconnector-gettype)
+    pub fn get_type(&self) -> crate::Result<ConnectorType> {
+        self.inner.get_type()
+    }
+
+    /// See also: [`IConnector::GetDataFlow`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/nf-devicetopology-iconnector-getdataflow)
+    pub fn get_data_flow(&self) -> crate::Result<ConnectorDataFlow> {
+        self.inner.get_data_flow()
+    }
+
+    /// See also: [`IConnector::IsConnected`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/nf-devicetopology-iconnector-isconnected)
+    pub fn is_connected(&self) -> crate::Result<bool> {
+        self.inner.is_connected()
+    }
+
+    /// The connector of the other device, failing with
+    /// [`Error::DeviceNotFound`] if this one is not connected.
+    ///
+    /// See also: [`IConnector::GetConnectedTo`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/nf-devicetopology-iconnector-getconnectedto)
+    pub fn get_connected_to(&self) -> crate::Result<Connector> {
+        self.inner.get_connected_to().map(Connector::new)
+    }
+
+    /// The global id of the connector of the other device.
+    ///
+    /// See also: [`IConnector::GetConnectorIdConnectedTo`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/nf-devicetopology-iconnector-getconnectoridconnectedto)
+    pub fn get_connector_id_connected_to(&self) -> crate::Result<WinString> {
+        self.inner.get_connector_id_connected_to()
+    }
+
+    /// See also: [`IConnector::GetDeviceIdConnectedTo`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/nf-devicetopology-iconnector-getdeviceidconnectedto)
+    pub fn get_device_id_connected_to(&self) -> crate::Result<WinString> {
+        self.inner.get_device_id_connected_to()
+    }
+
+    /// The connector as a part, to read its name and follow its links.
+    pub fn part(&self) -> crate::Result<Part> {
+        self.inner.as_part().map(Part::new)
+    }
+}
+
+/// A processing step of a device topology, like a volume control or a mixer.
+///
+/// See also: [`ISubunit`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/nn-devicetopology-isubunit)
+#[derive(Debug, Clone)]
+pub struct Subunit {
+    inner: Rc<dyn PartBackend>,
+}
+
+impl Subunit {
+    pub(crate) fn new(inner: Rc<dyn PartBackend>) -> Self {
+        Self { inner }
+    }
+
+    /// The subunit as a part, to read its name, controls and links.
+    pub fn part(&self) -> Part {
+        Part::new(self.inner.clone())
+    }
+}
+
+/// The level of one channel of an `IAudioVolumeLevel` control.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct ChannelLevel {
+    pub level_db: f32,
+    pub range: VolumeRange,
+}
+
+impl ChannelLevel {
+    pub fn new(level_db: f32, range: VolumeRange) -> Self {
+        Self { level_db, range }
+    }
+}
+
+/// The current value of a hardware control of a [`Part`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PartControl {
+    /// See also: [`IAudioVolumeLevel`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/nn-devicetopology-iaudiovolumelevel)
+    Volume(Vec<ChannelLevel>),
+    /// Whether the signal is muted.
+    ///
+    /// See also: [`IAudioMute`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/nn-devicetopology-iaudiomute)
+    Mute(bool),
+    /// Whether automatic gain control is enabled.
+    ///
+    /// See also: [`IAudioAutoGainControl`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/nn-devicetopology-iaudioautogaincontrol)
+    AutoGainControl(bool),
+    /// Whether loudness compensation is enabled.
+    ///
+    /// See also: [`IAudioLoudness`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/nn-devicetopology-iaudioloudness)
+    Loudness(bool),
+}
+
+/// Describes one jack of a connector.
+///
+/// See also: [`KSJACK_DESCRIPTION`](https://docs.microsoft.com/en-us/windows/win32/api/devicetopology/ns-devicetopology-ksjack_description)
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct JackDescription {
+    /// The speaker positions that the jack carries.
+    #[cfg_attr(feature = "serde", serde(with = "crate::bits::channel_mask_bits"))]
+    pub channel_mapping: ChannelMask,
+    /// The color of the jack as `0x00RRGGBB`, or 0 if it is unknown.
+    pub color: u32,
+    pub connection_type: JackConnectionType,
+    pub geo_location: JackGeoLocation,
+    pub gen_location: JackGenLocation,
+    pub port_connection: JackPortConnection,
+    /// Whether something is plugged into the jack. Always `true` for jacks
+    /// that cannot detect a connection.
+    pub is_connected: bool,
+}
+
+impl JackDescription {
+    pub fn new(
+        channel_mapping: ChannelMask,
+        color: u32,
+        connection_type: JackConnectionType,
+        geo_location: JackGeoLocation,
+        gen_location: JackGenLocation,
+        port_connection: JackPortConnection,
+        is_connected: bool,
+    ) -> Self {
+        Self {
+            channel_mapping,
+            color,
+            connection_type,
+            geo_location,
+            gen_location,
+            port_connection,
+            is_connected,
+        }
+    }
+
+    pub(crate) fn from_raw(raw: &KSJACK_DESCRIPTION) -> Self {
+        Self {
+            channel_mapping: ChannelMask::from_bits_retain(raw.ChannelMapping),
+            color: raw.Color,
+            connection_type: JackConnectionType::from_raw(raw.ConnectionType),
+            geo_location: JackGeoLocation::from_raw(raw.GeoLocation),
+            gen_location: JackGenLocation::from_raw(raw.GenLocation),
+            port_connection: JackPortConnection::from_raw(raw.PortConnection),
+            is_connected: raw.IsConnected.into(),
+        }
+    }
+
+    /// The red, green and blue components of [`color`](Self::color).
+    pub fn rgb(&self) -> (u8, u8, u8) {
+        let [_, r, g, b] = self.color.to_be_bytes();
+        (r, g, b)
+    }
+}
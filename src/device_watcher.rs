@@ -0,0 +1,392 @@
+use std::collections::BTreeMap;
+use std::sync::{
+    mpsc::{self, Receiver, Sender},
+    Arc, Mutex, MutexGuard,
+};
+
+use crate::{
+    bits::{DataFlow, DataFlowMask, DeviceRole, DeviceState, DeviceStateMask},
+    device_enumerator::{DeviceEnumerator, NotificationClientHandle},
+    device_info::ROLES,
+    error::Error,
+    notification_client::NotificationClient,
+    property_store::PropertyKey,
+    string::{WinStr, WinString},
+};
+
+/// A change reported by a [`DeviceWatcher`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceEvent {
+    Added {
+        id: String,
+    },
+    Removed {
+        id: String,
+    },
+    StateChanged {
+        id: String,
+        state: DeviceState,
+    },
+    /// `id` is `None` if there is no longer a default device for the role.
+    DefaultChanged {
+        data_flow: DataFlow,
+        role: DeviceRole,
+        id: Option<String>,
+    },
+    PropertyChanged {
+        id: String,
+        key: PropertyKey,
+    },
+}
+
+impl DeviceEvent {
+    /// The id of the device whose presence or state the event changes.
+    fn device_id(&self) -> Option<&String> {
+        match self {
+            Self::Added { id } | Self::Removed { id } | Self::StateChanged { id, .. } => Some(id),
+            Self::DefaultChanged { .. } | Self::PropertyChanged { .. } => None,
+        }
+    }
+
+    /// The system passes an empty id if there is no default device left.
+    pub(crate) fn default_changed(
+        data_flow: DataFlow,
+        role: DeviceRole,
+        device_id: &WinStr,
+    ) -> Self {
+        let id = Some(device_id.to_string_lossy()).filter(|id| !id.is_empty());
+        Self::DefaultChanged {
+            data_flow,
+            role,
+            id,
+        }
+    }
+}
+
+/// The devices and defaults known to a [`DeviceWatcher`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceTable {
+    devices: BTreeMap<String, Option<DeviceState>>,
+    defaults: Vec<(DataFlow, DeviceRole, String)>,
+}
+
+impl DeviceTable {
+    /// The ids of all known devices, in any state.
+    pub fn ids(&self) -> impl Iterator<Item = &str> + '_ {
+        self.devices.keys().map(String::as_str)
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.devices.contains_key(id)
+    }
+
+    /// The state of a device, or `None` if the device is unknown or its
+    /// state has not been queried since it was added, see
+    /// [`DeviceWatcher::update_added`].
+    pub fn state(&self, id: &str) -> Option<DeviceState> {
+        self.devices.get(id).copied().flatten()
+    }
+
+    /// The id of the default device for `data_flow` and `role`.
+    pub fn default_device(&self, data_flow: DataFlow, role: DeviceRole) -> Option<&str> {
+        self.defaults
+            .iter()
+            .find(|(flow, x, _)| *flow == data_flow && *x == role)
+            .map(|(_, _, id)| id.as_str())
+    }
+
+    fn apply(&mut self, event: &DeviceEvent) {
+        match event {
+            DeviceEvent::Added { id } => {
+                self.devices.entry(id.clone()).or_insert(None);
+            }
+            DeviceEvent::Removed { id } => {
+                self.devices.remove(id);
+                self.defaults.retain(|(_, _, x)| x != id);
+            }
+            DeviceEvent::StateChanged { id, state } => {
+                self.devices.insert(id.clone(), Some(*state));
+            }
+            DeviceEvent::DefaultChanged {
+                data_flow,
+                role,
+                id,
+            } => {
+                self.defaults
+                    .retain(|(flow, x, _)| !(flow == data_flow && x == role));
+                if let Some(id) = id {
+                    self.defaults.push((*data_flow, *role, id.clone()));
+                }
+            }
+            DeviceEvent::PropertyChanged { .. } => {}
+        }
+    }
+}
+
+/// Keeps a [`DeviceTable`] up to date with endpoint notifications and
+/// forwards them as [`DeviceEvent`]s.
+///
+/// Notifications arrive on COM worker threads, where calling back into the
+/// device enumerator can deadlock. The table is therefore only updated with
+/// the data that comes with each notification, and devices that were added
+/// have an unknown state until [`update_added`](Self::update_added) or
+/// [`refresh`](Self::refresh) queries them from the thread that owns the
+/// watcher.
+///
+/// While the watcher queries devices, notifications are applied to the
+/// current table and recorded as well. The recorded notifications are
+/// replayed onto the queried table before it replaces the current one, so
+/// that no change is lost.
+///
+/// The watcher stops listening when it is dropped.
+#[derive(Debug)]
+pub struct DeviceWatcher {
+    enumerator: DeviceEnumerator,
+    table: Arc<Mutex<DeviceTable>>,
+    log: Arc<Mutex<EventLog>>,
+    sender: Sender<DeviceEvent>,
+    _handle: NotificationClientHandle,
+}
+
+impl DeviceWatcher {
+    /// Start watching the devices of `enumerator`.
+    ///
+    /// Events are sent to the returned receiver until the watcher is dropped.
+    pub fn new(enumerator: &DeviceEnumerator) -> crate::Result<(Self, Receiver<DeviceEvent>)> {
+        let table = Arc::new(Mutex::new(DeviceTable::default()));
+        let log = Arc::new(Mutex::new(EventLog::default()));
+        let (sender, receiver) = mpsc::channel();
+        // Register first, so that no change between the query and the
+        // registration is lost.
+        let handle = enumerator.register_endpoint_notification(WatcherClient {
+            table: table.clone(),
+            log: log.clone(),
+            sender: sender.clone(),
+        })?;
+        let watcher = Self {
+            enumerator: enumerator.clone(),
+            table,
+            log,
+            sender,
+            _handle: handle,
+        };
+        watcher.refresh()?;
+        Ok((watcher, receiver))
+    }
+
+    /// Query every device and default again.
+    ///
+    /// No events are sent for the differences to the previous table.
+    pub fn refresh(&self) -> crate::Result<()> {
+        self.record(
+            || self.query(),
+            |current, mut table, events| {
+                for event in events {
+                    table.apply(event);
+                }
+                *current = table;
+            },
+        )
+    }
+
+    /// Query the state of the devices that were added since the last
+    /// [`refresh`](Self::refresh), and send a [`DeviceEvent::StateChanged`]
+    /// for each of them.
+    ///
+    /// Call this from the thread that owns the watcher after receiving a
+    /// [`DeviceEvent::Added`]. Devices that are gone by then are skipped.
+    pub fn update_added(&self) -> crate::Result<()> {
+        let added: Vec<String> = lock(&self.table)
+            .devices
+            .iter()
+            .filter(|(_, state)| state.is_none())
+            .map(|(id, _)| id.clone())
+            .collect();
+        if added.is_empty() {
+            return Ok(());
+        }
+        self.record(
+            || {
+                let mut states = Vec::with_capacity(added.len());
+                for id in &added {
+                    let state = self
+                        .enumerator
+                        .get_device(&WinString::from(id))
+                        .and_then(|device| device.get_state());
+                    match state {
+                        Ok(state) => states.push((id, state)),
+                        Err(Error::DeviceNotFound) | Err(Error::DeviceInvalidated) => {}
+                        Err(error) => return Err(error),
+                    }
+                }
+                Ok(states)
+            },
+            |current, states, events| {
+                for (id, state) in states {
+                    // A notification about the device that arrived during the
+                    // query is newer than the queried state.
+                    if events.iter().any(|event| event.device_id() == Some(id)) {
+                        continue;
+                    }
+                    if let Some(entry @ None) = current.devices.get_mut(id) {
+                        *entry = Some(state);
+                        let _ = self.sender.send(DeviceEvent::StateChanged {
+                            id: id.clone(),
+                            state,
+                        });
+                    }
+                }
+            },
+        )
+    }
+
+    /// A copy of the current table.
+    pub fn table(&self) -> DeviceTable {
+        lock(&self.table).clone()
+    }
+
+    /// A handle to the table that can be read from other threads.
+    pub fn shared_table(&self) -> Arc<Mutex<DeviceTable>> {
+        self.table.clone()
+    }
+
+    /// Run `query` while recording notifications, then hand its result and
+    /// the recorded notifications to `finish` under the table lock.
+    fn record<T>(
+        &self,
+        query: impl FnOnce() -> crate::Result<T>,
+        finish: impl FnOnce(&mut DeviceTable, T, &[DeviceEvent]),
+    ) -> crate::Result<()> {
+        {
+            let _table = lock(&self.table);
+            let mut log = lock(&self.log);
+            log.generation += 1;
+            log.events.clear();
+        }
+        let result = query();
+
+        let mut table = lock(&self.table);
+        let mut log = lock(&self.log);
+        log.generation += 1;
+        let events = std::mem::take(&mut log.events);
+        finish(&mut table, result?, &events);
+        Ok(())
+    }
+
+    fn query(&self) -> crate::Result<DeviceTable> {
+        let mut table = DeviceTable::default();
+        for device in &self
+            .enumerator
+            .enum_audio_endpoints(DataFlowMask::All, DeviceStateMask::ALL)?
+        {
+            let entry = device.and_then(|device| Ok((device.get_id()?, device.get_state()?)));
+            match entry {
+                Ok((id, state)) => {
+                    table.devices.insert(id.to_string_lossy(), Some(state));
+                }
+                // The device was removed after it was enumerated.
+                Err(Error::DeviceNotFound) | Err(Error::DeviceInvalidated) => {}
+                Err(error) => return Err(error),
+            }
+        }
+        for &data_flow in &[DataFlow::Render, DataFlow::Capture] {
+            for &role in &ROLES {
+                match self.enumerator.get_default_audio_endpoint(data_flow, role) {
+                    Ok(device) => {
+                        let id = device.get_id()?.to_string_lossy();
+                        table.defaults.push((data_flow, role, id));
+                    }
+                    Err(Error::DeviceNotFound) => {}
+                    Err(error) => return Err(error),
+                }
+            }
+        }
+        Ok(table)
+    }
+}
+
+/// The notifications that arrived while the watcher was querying devices.
+///
+/// The generation is odd while a query is running, and the notifications
+/// are only recorded then.
+#[derive(Debug, Default)]
+struct EventLog {
+    generation: u64,
+    events: Vec<DeviceEvent>,
+}
+
+impl EventLog {
+    fn is_recording(&self) -> bool {
+        self.generation % 2 == 1
+    }
+}
+
+/// Lock a mutex, ignoring poisoning: every update leaves the data consistent.
+///
+/// The table is always locked before the log.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|error| error.into_inner())
+}
+
+struct WatcherClient {
+    table: Arc<Mutex<DeviceTable>>,
+    log: Arc<Mutex<EventLog>>,
+    sender: Sender<DeviceEvent>,
+}
+
+impl WatcherClient {
+    fn publish(&self, event: DeviceEvent) -> crate::Result<()> {
+        {
+            let mut table = lock(&self.table);
+            table.apply(&event);
+            let mut log = lock(&self.log);
+            if log.is_recording() {
+                log.events.push(event.clone());
+            }
+        }
+        // The receiver may have been dropped while the table is still in use.
+        let _ = self.sender.send(event);
+        Ok(())
+    }
+}
+
+impl NotificationClient for WatcherClient {
+    fn on_default_device_changed(
+        &self,
+        data_flow: DataFlow,
+        role: DeviceRole,
+        device_id: &WinStr,
+    ) -> crate::Result<()> {
+        self.publish(DeviceEvent::default_changed(data_flow, role, device_id))
+    }
+
+    fn on_device_added(&self, device_id: &WinStr) -> crate::Result<()> {
+        self.publish(DeviceEvent::Added {
+            id: device_id.to_string_lossy(),
+        })
+    }
+
+    fn on_device_removed(&self, device_id: &WinStr) -> crate::Result<()> {
+        self.publish(DeviceEvent::Removed {
+            id: device_id.to_string_lossy(),
+        })
+    }
+
+    fn on_device_state_changed(&self, device_id: &WinStr, state: DeviceState) -> crate::Result<()> {
+        self.publish(DeviceEvent::StateChanged {
+            id: device_id.to_string_lossy(),
+            state,
+        })
+    }
+
+    fn on_property_value_changed(
+        &self,
+        device_id: &WinStr,
+        property_key: PropertyKey,
+    ) -> crate::Result<()> {
+        self.publish(DeviceEvent::PropertyChanged {
+            id: device_id.to_string_lossy(),
+            key: property_key,
+        })
+    }
+}
@@ -0,0 +1,151 @@
+//! Error type shared by every fallible call in this crate.
+
+use std::fmt::{self, Display, Formatter};
+
+use windows::core::HRESULT;
+use windows::Win32::{
+    Foundation::{CO_E_NOTINITIALIZED, ERROR_NOT_FOUND, RPC_E_CHANGED_MODE, TYPE_E_TYPEMISMATCH},
+    Media::Audio::{
+        AUDCLNT_E_DEVICE_INVALIDATED, AUDCLNT_E_ENDPOINT_CREATE_FAILED,
+        AUDCLNT_E_SERVICE_NOT_RUNNING, AUDCLNT_E_UNSUPPORTED_FORMAT,
+    },
+};
+
+use crate::{property_keys, property_store::PropertyKey};
+
+/// `HRESULT_FROM_WIN32(ERROR_NOT_FOUND)`, which is how `mmdeviceapi.h`
+/// defines `E_NOTFOUND`.
+const E_NOTFOUND: HRESULT = ERROR_NOT_FOUND.to_hresult();
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// A failed Core Audio call, classified by its `HRESULT`.
+///
+/// See also: [`Core Audio Error Codes`](https://docs.microsoft.com/en-us/windows/win32/coreaudio/audclnt-e-xxx-constants)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The endpoint device was unplugged, disabled or otherwise made
+    /// unavailable (`AUDCLNT_E_DEVICE_INVALIDATED`).
+    DeviceInvalidated,
+    /// No device matches the requested id or role (`E_NOTFOUND`).
+    DeviceNotFound,
+    /// The Windows audio service is not running
+    /// (`AUDCLNT_E_SERVICE_NOT_RUNNING`).
+    ServiceNotRunning,
+    /// The audio engine does not support the requested format
+    /// (`AUDCLNT_E_UNSUPPORTED_FORMAT`).
+    UnsupportedFormat,
+    /// The device has no value for the property.
+    PropertyNotFound(PropertyKey),
+    /// The property value does not have the type that was expected for it.
+    PropertyTypeMismatch(PropertyKey),
+    /// COM is not initialized on this thread, or was initialized with a
+    /// different concurrency model.
+    ComInit(HRESULT),
+    /// Any other failure.
+    Other(HRESULT),
+}
+
+impl Error {
+    /// Classify a failed `HRESULT`.
+    pub fn from_hresult(code: HRESULT) -> Self {
+        match code {
+            AUDCLNT_E_DEVICE_INVALIDATED => Self::DeviceInvalidated,
+            E_NOTFOUND => Self::DeviceNotFound,
+            AUDCLNT_E_SERVICE_NOT_RUNNING => Self::ServiceNotRunning,
+            AUDCLNT_E_UNSUPPORTED_FORMAT => Self::UnsupportedFormat,
+            CO_E_NOTINITIALIZED | RPC_E_CHANGED_MODE => Self::ComInit(code),
+            _ => Self::Other(code),
+        }
+    }
+
+    /// The `HRESULT` that describes this error.
+    ///
+    /// [`from_hresult`](Self::from_hresult) turns the code back into the same
+    /// error, except for the property errors: `PropertyNotFound` shares
+    /// `E_NOTFOUND` with `DeviceNotFound` and comes back as that, and
+    /// `PropertyTypeMismatch` comes back as `Other(TYPE_E_TYPEMISMATCH)`.
+    pub fn code(&self) -> HRESULT {
+        match self {
+            Self::DeviceInvalidated => AUDCLNT_E_DEVICE_INVALIDATED,
+            Self::DeviceNotFound => E_NOTFOUND,
+            Self::ServiceNotRunning => AUDCLNT_E_SERVICE_NOT_RUNNING,
+            Self::UnsupportedFormat => AUDCLNT_E_UNSUPPORTED_FORMAT,
+            Self::PropertyNotFound(_) => E_NOTFOUND,
+            Self::PropertyTypeMismatch(_) => TYPE_E_TYPEMISMATCH,
+            Self::ComInit(code) | Self::Other(code) => *code,
+        }
+    }
+
+    /// Whether the same call may succeed if it is repeated later.
+    ///
+    /// This is the case when the device went away and a new one has to be
+    /// looked up, or when the audio service is restarting.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::DeviceInvalidated | Self::ServiceNotRunning => true,
+            Self::Other(code) => *code == AUDCLNT_E_ENDPOINT_CREATE_FAILED,
+            Self::DeviceNotFound
+            | Self::UnsupportedFormat
+            | Self::PropertyNotFound(_)
+            | Self::PropertyTypeMismatch(_)
+            | Self::ComInit(_) => false,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DeviceInvalidated => f.write_str("the audio device is no longer available"),
+            Self::DeviceNotFound => f.write_str("the audio device was not found"),
+            Self::ServiceNotRunning => f.write_str("the Windows audio service is not running"),
+            Self::UnsupportedFormat => f.write_str("the audio format is not supported"),
+            Self::PropertyNotFound(key) => {
+                write!(
+                    f,
+                    "the device has no value for property {}",
+                    PropertyName(key)
+                )
+            }
+            Self::PropertyTypeMismatch(key) => {
+                write!(f, "property {} has an unexpected type", PropertyName(key))
+            }
+            Self::ComInit(code) => write!(f, "COM is not initialized correctly ({})", code),
+            Self::Other(code) => write!(f, "Core Audio call failed ({})", code),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Displays a property key by its catalog name when it has one.
+struct PropertyName<'a>(&'a PropertyKey);
+
+impl Display for PropertyName<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match property_keys::lookup(*self.0) {
+            Some(info) => write!(f, "{} ({})", info.name, self.0),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+impl From<HRESULT> for Error {
+    fn from(code: HRESULT) -> Self {
+        Self::from_hresult(code)
+    }
+}
+
+impl From<windows::core::Error> for Error {
+    fn from(error: windows::core::Error) -> Self {
+        Self::from_hresult(error.code())
+    }
+}
+
+impl From<Error> for windows::core::Error {
+    fn from(error: Error) -> Self {
+        error.code().into()
+    }
+}
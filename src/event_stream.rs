@@ -0,0 +1,535 @@
+//! [`Stream`] adapters for the notification callbacks, available with the
+//! `async` feature.
+//!
+//! Every adapter registers a callback that copies each notification into an
+//! owned event and pushes it into a bounded buffer, from which the returned
+//! [`EventStream`] yields it. The callback stays registered until the
+//! [`EventStreamHandle`] returned with the stream is dropped, after which the
+//! stream yields the events that are still buffered and then ends.
+//!
+//! The events are owned and `Send`, and so is the stream, which can be moved
+//! to any executor, such as a multi-threaded tokio runtime. The handle holds
+//! the registration and, like the interface that it was created from, stays
+//! on the thread that created it.
+//!
+//! Dropping the stream stops the callback: later notifications are
+//! discarded, and the handle reports
+//! [`is_stream_dropped`](EventStreamHandle::is_stream_dropped) so that its
+//! owner can unregister. Dropping the handle unregisters right away.
+
+use std::collections::VecDeque;
+use std::fmt::{self, Debug};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+
+use futures_core::Stream;
+use windows::core::GUID;
+
+use crate::{
+    audio_endpoint_volume::AudioEndpointVolume,
+    audio_endpoint_volume_callback::{
+        AudioEndpointVolumeCallback, NotificationData, OwnedNotificationData,
+    },
+    audio_session_control::AudioSessionControl,
+    audio_session_events::AudioSessionEvents,
+    audio_session_manager::AudioSessionManager2,
+    audio_session_notification::AudioSessionNotification,
+    audio_volume_duck_notification::AudioVolumeDuckNotification,
+    bits::{AudioSessionDisconnectReason, AudioSessionState, DataFlow, DeviceRole, DeviceState},
+    device_enumerator::DeviceEnumerator,
+    device_watcher::DeviceEvent,
+    notification_client::NotificationClient,
+    property_store::PropertyKey,
+    string::WinStr,
+};
+
+/// What to do with an event that arrives while the buffer of an
+/// [`EventStream`] is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OverflowPolicy {
+    /// Discard the oldest buffered event to make room for the new one.
+    DropOldest,
+    /// Discard the new event.
+    DropNewest,
+}
+
+/// Buffering of an [`EventStream`].
+///
+/// The default buffers 64 events and drops the oldest on overflow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StreamOptions {
+    capacity: usize,
+    overflow: OverflowPolicy,
+}
+
+impl Default for StreamOptions {
+    fn default() -> Self {
+        Self {
+            capacity: 64,
+            overflow: OverflowPolicy::DropOldest,
+        }
+    }
+}
+
+impl StreamOptions {
+    /// Buffer up to `capacity` events, which is raised to 1 if it is 0.
+    pub fn new(capacity: usize, overflow: OverflowPolicy) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            overflow,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn overflow(&self) -> OverflowPolicy {
+        self.overflow
+    }
+}
+
+struct Buffer<T> {
+    events: VecDeque<T>,
+    options: StreamOptions,
+    dropped: u64,
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+type SharedBuffer<T> = Arc<Mutex<Buffer<T>>>;
+
+/// Lock the buffer, ignoring poisoning: every update leaves it consistent.
+fn lock<T>(buffer: &Mutex<Buffer<T>>) -> MutexGuard<'_, Buffer<T>> {
+    buffer.lock().unwrap_or_else(|error| error.into_inner())
+}
+
+/// The callback side of an [`EventStream`].
+struct EventSink<T> {
+    buffer: SharedBuffer<T>,
+    stream_dropped: Arc<AtomicBool>,
+}
+
+impl<T> EventSink<T> {
+    fn push(&self, event: T) -> crate::Result<()> {
+        if self.stream_dropped.load(Ordering::Acquire) {
+            return Ok(());
+        }
+        let mut buffer = lock(&self.buffer);
+        if buffer.events.len() >= buffer.options.capacity {
+            buffer.dropped += 1;
+            match buffer.options.overflow {
+                OverflowPolicy::DropOldest => {
+                    buffer.events.pop_front();
+                }
+                OverflowPolicy::DropNewest => return Ok(()),
+            }
+        }
+        buffer.events.push_back(event);
+        if let Some(waker) = buffer.waker.take() {
+            waker.wake();
+        }
+        Ok(())
+    }
+}
+
+impl<T> Drop for EventSink<T> {
+    // The callback was released, so no more events will arrive.
+    fn drop(&mut self) {
+        let mut buffer = lock(&self.buffer);
+        buffer.closed = true;
+        if let Some(waker) = buffer.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// A stream of owned notification events.
+///
+/// The stream ends once its [`EventStreamHandle`] is dropped and the
+/// buffered events are taken. Dropping the stream stops the callback from
+/// buffering events, see the [module documentation](self).
+pub struct EventStream<T> {
+    buffer: SharedBuffer<T>,
+    stream_dropped: Arc<AtomicBool>,
+}
+
+impl<T> EventStream<T> {
+    /// Register a callback created from the sink with `register`.
+    fn register<H, F>(
+        options: StreamOptions,
+        register: F,
+    ) -> crate::Result<(Self, EventStreamHandle)>
+    where
+        H: Debug + 'static,
+        F: FnOnce(EventSink<T>) -> crate::Result<H>,
+    {
+        let buffer = Arc::new(Mutex::new(Buffer {
+            events: VecDeque::with_capacity(options.capacity),
+            options,
+            dropped: 0,
+            closed: false,
+            waker: None,
+        }));
+        let stream_dropped = Arc::new(AtomicBool::new(false));
+        let handle = register(EventSink {
+            buffer: buffer.clone(),
+            stream_dropped: stream_dropped.clone(),
+        })?;
+        let handle = EventStreamHandle {
+            inner: Box::new(handle),
+            stream_dropped: stream_dropped.clone(),
+        };
+        let stream = Self {
+            buffer,
+            stream_dropped,
+        };
+        Ok((stream, handle))
+    }
+
+    /// The number of events that were discarded because the buffer was
+    /// full.
+    pub fn dropped_count(&self) -> u64 {
+        lock(&self.buffer).dropped
+    }
+}
+
+impl<T> Drop for EventStream<T> {
+    fn drop(&mut self) {
+        self.stream_dropped.store(true, Ordering::Release);
+        lock(&self.buffer).events.clear();
+    }
+}
+
+impl<T> Stream for EventStream<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let mut buffer = lock(&self.buffer);
+        match buffer.events.pop_front() {
+            Some(event) => Poll::Ready(Some(event)),
+            None if buffer.closed => Poll::Ready(None),
+            None => {
+                buffer.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (lock(&self.buffer).events.len(), None)
+    }
+}
+
+impl<T> Debug for EventStream<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let buffer = lock(&self.buffer);
+        f.debug_struct("EventStream")
+            .field("buffered", &buffer.events.len())
+            .field("options", &buffer.options)
+            .field("dropped", &buffer.dropped)
+            .field("closed", &buffer.closed)
+            .finish()
+    }
+}
+
+/// The registration of the callback behind an [`EventStream`].
+#[derive(Debug)]
+#[must_use = "callback will be unregistered when the handle is dropped"]
+pub struct EventStreamHandle {
+    inner: Box<dyn Debug>,
+    stream_dropped: Arc<AtomicBool>,
+}
+
+impl EventStreamHandle {
+    /// Whether the stream was dropped, after which the callback discards
+    /// every notification and the handle can be dropped as well.
+    pub fn is_stream_dropped(&self) -> bool {
+        self.stream_dropped.load(Ordering::Acquire)
+    }
+
+    /// Unregister the callback, which ends the stream after the buffered
+    /// events.
+    pub fn unregister(self) {
+        drop(self.inner);
+    }
+}
+
+/// An owned [`AudioSessionEvents`] notification.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionEvent {
+    ChannelVolumeChanged {
+        volume_levels: Vec<f32>,
+        changed_channel: usize,
+        event_context: Option<GUID>,
+    },
+    DisplayNameChanged {
+        display_name: String,
+        event_context: Option<GUID>,
+    },
+    GroupingParamChanged {
+        grouping_param: Option<GUID>,
+        event_context: Option<GUID>,
+    },
+    IconPathChanged {
+        icon_path: String,
+        event_context: Option<GUID>,
+    },
+    Disconnected {
+        reason: AudioSessionDisconnectReason,
+    },
+    SimpleVolumeChanged {
+        volume: f32,
+        mute: bool,
+        event_context: Option<GUID>,
+    },
+    StateChanged {
+        state: AudioSessionState,
+    },
+}
+
+/// An owned [`AudioVolumeDuckNotification`] notification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DuckEvent {
+    Ducked {
+        session_id: String,
+        num_communication_sessions: u32,
+    },
+    Unducked {
+        session_id: String,
+    },
+}
+
+/// A session reported by [`AudioSessionNotification::on_session_created`].
+///
+/// Find its [`AudioSessionControl`] by comparing
+/// [`get_session_instance_identifier`](crate::AudioSessionControl2::get_session_instance_identifier)
+/// with the sessions of
+/// [`get_session_enumerator`](AudioSessionManager2::get_session_enumerator).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SessionCreated {
+    /// Identifies this session and no other.
+    pub instance_identifier: String,
+    /// Identifies the application stream, which may have several sessions.
+    pub session_identifier: String,
+}
+
+impl SessionCreated {
+    fn new(session: &AudioSessionControl) -> crate::Result<Self> {
+        let session = session.upgrade()?;
+        Ok(Self {
+            instance_identifier: session.get_session_instance_identifier()?.to_string_lossy(),
+            session_identifier: session.get_session_identifier()?.to_string_lossy(),
+        })
+    }
+}
+
+/// An owned copy of [`NotificationData`].
+pub type VolumeEvent = OwnedNotificationData;
+
+impl NotificationClient for EventSink<DeviceEvent> {
+    fn on_default_device_changed(
+        &self,
+        data_flow: DataFlow,
+        role: DeviceRole,
+        device_id: &WinStr,
+    ) -> crate::Result<()> {
+        self.push(DeviceEvent::default_changed(data_flow, role, device_id))
+    }
+
+    fn on_device_added(&self, device_id: &WinStr) -> crate::Result<()> {
+        self.push(DeviceEvent::Added {
+            id: device_id.to_string_lossy(),
+        })
+    }
+
+    fn on_device_removed(&self, device_id: &WinStr) -> crate::Result<()> {
+        self.push(DeviceEvent::Removed {
+            id: device_id.to_string_lossy(),
+        })
+    }
+
+    fn on_device_state_changed(&self, device_id: &WinStr, state: DeviceState) -> crate::Result<()> {
+        self.push(DeviceEvent::StateChanged {
+            id: device_id.to_string_lossy(),
+            state,
+        })
+    }
+
+    fn on_property_value_changed(
+        &self,
+        device_id: &WinStr,
+        property_key: PropertyKey,
+    ) -> crate::Result<()> {
+        self.push(DeviceEvent::PropertyChanged {
+            id: device_id.to_string_lossy(),
+            key: property_key,
+        })
+    }
+}
+
+impl AudioSessionEvents for EventSink<SessionEvent> {
+    fn on_channel_volume_changed(
+        &self,
+        new_volume_levels: &[f32],
+        changed_channel: usize,
+        event_context: Option<&GUID>,
+    ) -> crate::Result<()> {
+        self.push(SessionEvent::ChannelVolumeChanged {
+            volume_levels: new_volume_levels.to_vec(),
+            changed_channel,
+            event_context: event_context.copied(),
+        })
+    }
+
+    fn on_display_name_changed(
+        &self,
+        new_display_name: &WinStr,
+        event_context: Option<&GUID>,
+    ) -> crate::Result<()> {
+        self.push(SessionEvent::DisplayNameChanged {
+            display_name: new_display_name.to_string_lossy(),
+            event_context: event_context.copied(),
+        })
+    }
+
+    fn on_grouping_param_changed(
+        &self,
+        new_grouping_param: Option<&GUID>,
+        event_context: Option<&GUID>,
+    ) -> crate::Result<()> {
+        self.push(SessionEvent::GroupingParamChanged {
+            grouping_param: new_grouping_param.copied(),
+            event_context: event_context.copied(),
+        })
+    }
+
+    fn on_icon_path_changed(
+        &self,
+        new_icon_path: &WinStr,
+        event_context: Option<&GUID>,
+    ) -> crate::Result<()> {
+        self.push(SessionEvent::IconPathChanged {
+            icon_path: new_icon_path.to_string_lossy(),
+            event_context: event_context.copied(),
+        })
+    }
+
+    fn on_session_disconnected(
+        &self,
+        disconnect_reason: AudioSessionDisconnectReason,
+    ) -> crate::Result<()> {
+        self.push(SessionEvent::Disconnected {
+            reason: disconnect_reason,
+        })
+    }
+
+    fn on_simple_volume_changed(
+        &self,
+        new_volume: f32,
+        new_mute: bool,
+        event_context: Option<&GUID>,
+    ) -> crate::Result<()> {
+        self.push(SessionEvent::SimpleVolumeChanged {
+            volume: new_volume,
+            mute: new_mute,
+            event_context: event_context.copied(),
+        })
+    }
+
+    fn on_state_changed(&self, new_state: AudioSessionState) -> crate::Result<()> {
+        self.push(SessionEvent::StateChanged { state: new_state })
+    }
+}
+
+impl AudioSessionNotification for EventSink<SessionCreated> {
+    fn on_session_created(&self, new_session: AudioSessionControl) -> crate::Result<()> {
+        self.push(SessionCreated::new(&new_session)?)
+    }
+}
+
+impl AudioVolumeDuckNotification for EventSink<DuckEvent> {
+    fn on_volume_duck_notification(
+        &self,
+        session_id: &WinStr,
+        num_communication_sessions: u32,
+    ) -> crate::Result<()> {
+        self.push(DuckEvent::Ducked {
+            session_id: session_id.to_string_lossy(),
+            num_communication_sessions,
+        })
+    }
+
+    fn on_volume_unduck_notification(&self, session_id: &WinStr) -> crate::Result<()> {
+        self.push(DuckEvent::Unducked {
+            session_id: session_id.to_string_lossy(),
+        })
+    }
+}
+
+impl AudioEndpointVolumeCallback for EventSink<VolumeEvent> {
+    fn on_notify(&self, data: &NotificationData) -> crate::Result<()> {
+        self.push(data.to_owned_data())
+    }
+}
+
+impl DeviceEnumerator {
+    /// Stream the endpoint notifications of
+    /// [`register_endpoint_notification`](Self::register_endpoint_notification).
+    pub fn endpoint_notification_stream(
+        &self,
+        options: StreamOptions,
+    ) -> crate::Result<(EventStream<DeviceEvent>, EventStreamHandle)> {
+        EventStream::register(options, |sink| self.register_endpoint_notification(sink))
+    }
+}
+
+impl AudioSessionControl {
+    /// Stream the session events of
+    /// [`register_audio_session_notification`](Self::register_audio_session_notification).
+    pub fn session_event_stream(
+        &self,
+        options: StreamOptions,
+    ) -> crate::Result<(EventStream<SessionEvent>, EventStreamHandle)> {
+        EventStream::register(options, |sink| {
+            self.register_audio_session_notification(sink)
+        })
+    }
+}
+
+impl AudioSessionManager2 {
+    /// Stream the sessions that
+    /// [`register_session_notification`](Self::register_session_notification)
+    /// reports as created.
+    pub fn session_notification_stream(
+        &self,
+        options: StreamOptions,
+    ) -> crate::Result<(EventStream<SessionCreated>, EventStreamHandle)> {
+        EventStream::register(options, |sink| self.register_session_notification(sink))
+    }
+
+    /// Stream the ducking notifications of
+    /// [`register_duck_notification`](Self::register_duck_notification).
+    pub fn duck_notification_stream(
+        &self,
+        session_id: &WinStr,
+        options: StreamOptions,
+    ) -> crate::Result<(EventStream<DuckEvent>, EventStreamHandle)> {
+        EventStream::register(options, |sink| {
+            self.register_duck_notification(session_id, sink)
+        })
+    }
+}
+
+impl AudioEndpointVolume {
+    /// Stream the volume notifications of
+    /// [`register_control_change_notify`](Self::register_control_change_notify).
+    pub fn control_change_stream(
+        &self,
+        options: StreamOptions,
+    ) -> crate::Result<(EventStream<VolumeEvent>, EventStreamHandle)> {
+        EventStream::register(options, |sink| self.register_control_change_notify(sink))
+    }
+}
@@ -0,0 +1,88 @@
+//! Formatting and parsing of GUIDs in their canonical
+//! `{XXXXXXXX-XXXX-XXXX-XXXX-XXXXXXXXXXXX}` form.
+
+use std::fmt::{self, Display, Formatter};
+
+use windows::core::GUID;
+
+/// Displays a GUID in braces with uppercase hex digits, the way the registry
+/// and `StringFromGUID2` format it.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct GuidDisplay<'a>(pub(crate) &'a GUID);
+
+impl Display for GuidDisplay<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let GUID {
+            data1,
+            data2,
+            data3,
+            data4,
+        } = self.0;
+        write!(
+            f,
+            "{{{:08X}-{:04X}-{:04X}-{:02X}{:02X}-{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}}}",
+            data1,
+            data2,
+            data3,
+            data4[0],
+            data4[1],
+            data4[2],
+            data4[3],
+            data4[4],
+            data4[5],
+            data4[6],
+            data4[7],
+        )
+    }
+}
+
+/// Parse a GUID with or without braces. Hex digits may be in either case.
+pub(crate) fn parse_guid(s: &str) -> Option<GUID> {
+    let s = match s.strip_prefix('{') {
+        Some(s) => s.strip_suffix('}')?,
+        None => s,
+    };
+    let bytes = s.as_bytes();
+    if bytes.len() != 36 || [8, 13, 18, 23].iter().any(|&i| bytes[i] != b'-') {
+        return None;
+    }
+
+    let hex = |range: std::ops::Range<usize>| {
+        let digits = s.get(range)?;
+        if !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        u64::from_str_radix(digits, 16).ok()
+    };
+    let mut data4 = [0; 8];
+    data4[0] = hex(19..21)? as u8;
+    data4[1] = hex(21..23)? as u8;
+    for (i, byte) in data4[2..].iter_mut().enumerate() {
+        *byte = hex(24 + 2 * i..26 + 2 * i)? as u8;
+    }
+    Some(GUID {
+        data1: hex(0..8)? as u32,
+        data2: hex(9..13)? as u16,
+        data3: hex(14..18)? as u16,
+        data4,
+    })
+}
+
+/// Serialize GUIDs as strings, for use with `#[serde(serialize_with = "...")]`.
+#[cfg(feature = "serde")]
+pub(crate) fn serialize_guid<S: serde::Serializer>(
+    guid: &GUID,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&GuidDisplay(guid))
+}
+
+/// Deserialize GUIDs from strings, for use with
+/// `#[serde(deserialize_with = "...")]`.
+#[cfg(feature = "serde")]
+pub(crate) fn deserialize_guid<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<GUID, D::Error> {
+    let s = <String as serde::Deserialize>::deserialize(deserializer)?;
+    parse_guid(&s).ok_or_else(|| serde::de::Error::custom("invalid GUID"))
+}
@@ -1,5 +1,6 @@
 #![warn(unsafe_op_in_unsafe_fn)]
 
+mod apartment;
 mod audio_endpoint_volume;
 mod audio_endpoint_volume_callback;
 mod audio_session_control;
@@ -8,49 +9,91 @@ mod audio_session_events;
 mod audio_session_manager;
 mod audio_session_notification;
 mod audio_volume_duck_notification;
+pub mod backend;
 mod bits;
+mod clock;
+mod coalescing_client;
 mod device;
 mod device_collection;
 mod device_enumerator;
+mod device_info;
+mod device_policy;
+mod device_query;
+mod device_topology;
+mod device_watcher;
+mod error;
+#[cfg(feature = "async")]
+mod event_stream;
+mod guid;
 mod notification_client;
+mod policy_config;
+pub mod property_keys;
 mod property_store;
+mod propvariant;
 mod simple_audio_volume;
 pub mod string;
+mod topology_graph;
 pub(crate) mod util;
+mod wave_format;
 
 pub use self::{
-    audio_endpoint_volume::{AudioEndpointVolume, AudioEndpointVolumeCallbackHandle},
-    audio_endpoint_volume_callback::{AudioEndpointVolumeCallback, NotificationData},
+    apartment::{ComApartment, ComGuard},
+    audio_endpoint_volume::{
+        AudioEndpointVolume, AudioEndpointVolumeCallbackHandle, VolumeRange, VolumeStepInfo,
+    },
+    audio_endpoint_volume_callback::{
+        AudioEndpointVolumeCallback, NotificationData, OwnedNotificationData,
+    },
     audio_session_control::{AudioSessionControl, AudioSessionControl2, AudioSessionEventsHandle},
     audio_session_enumerator::{AudioSessionEnumerator, AudioSessionIter},
-    audio_session_events::AudioSessionEvents,
+    audio_session_events::{AudioSessionEvents, AudioSessionEventsBuilder, FnAudioSessionEvents},
     audio_session_manager::{
         AudioSessionManager, AudioSessionManager2, AudioSessionNotificationHandle,
         AudioVolumeDuckNotificationHandle,
     },
     audio_session_notification::AudioSessionNotification,
-    audio_volume_duck_notification::AudioVolumeDuckNotification,
+    audio_volume_duck_notification::{
+        AudioVolumeDuckNotification, AudioVolumeDuckNotificationBuilder,
+        FnAudioVolumeDuckNotification,
+    },
     bits::{
-        AudioSessionDisconnectReason, AudioSessionState, DataFlow, DataFlowMask, DeviceRole,
-        DeviceState, DeviceStateMask, HardwareSupportMask, StorageAccessMode,
+        AudioSessionDisconnectReason, AudioSessionState, ChannelMask, ConnectorDataFlow,
+        ConnectorType, DataFlow, DataFlowMask, DeviceRole, DeviceState, DeviceStateMask,
+        EndpointFormFactor, HardwareSupportMask, JackConnectionType, JackGenLocation,
+        JackGeoLocation, JackPortConnection, PartType, StorageAccessMode,
     },
-    device::{Device, DEVICE_DESCRIPTION, DEVICE_FRIENDLY_NAME, DEVICE_INTERFACE_FRIENDLY_NAME},
+    clock::{Clock, ManualClock, SystemClock},
+    coalescing_client::{BatchHandler, CoalescingClient, DeviceChangeBatch, Presence, Replay},
+    device::Device,
     device_collection::{DeviceCollection, DeviceIter},
     device_enumerator::{DeviceEnumerator, NotificationClientHandle},
-    notification_client::NotificationClient,
-    property_store::{Property, PropertyKey, PropertyStore},
+    device_info::DeviceInfo,
+    device_policy::{
+        DevicePolicy, DeviceSelector, PolicyDecision, PolicyEngine, PolicyEvent, PolicyMonitor,
+    },
+    device_query::{DeviceMatch, DeviceQuery, MatchKind, TextMatch},
+    device_topology::{
+        ChannelLevel, Connector, DeviceTopology, JackDescription, Part, PartControl, PartsList,
+        Subunit,
+    },
+    device_watcher::{DeviceEvent, DeviceTable, DeviceWatcher},
+    error::{Error, Result},
+    notification_client::{FnNotificationClient, NotificationClient, NotificationClientBuilder},
+    property_keys::{DEVICE_DESCRIPTION, DEVICE_FRIENDLY_NAME, DEVICE_INTERFACE_FRIENDLY_NAME},
+    property_store::{
+        ParsePropertyKeyError, Property, PropertyChange, PropertyIter, PropertyKey, PropertyStore,
+    },
     simple_audio_volume::SimpleAudioVolume,
+    topology_graph::{
+        ConnectorDetails, EdgeKind, TopologyEdge, TopologyGraph, TopologyNode, WalkDirection,
+    },
+    wave_format::{WaveEncoding, WaveFormat},
 };
 
-use std::sync::Once;
-use windows::Win32::System::Com::{CoInitializeEx, COINIT_MULTITHREADED};
+#[cfg(feature = "async")]
+pub use self::event_stream::{
+    DuckEvent, EventStream, EventStreamHandle, OverflowPolicy, SessionCreated, SessionEvent,
+    StreamOptions, VolumeEvent,
+};
 
-/// Make sure this is called at every static entrypoint to this crate.
-pub(crate) fn ensure_thread_init() {
-    thread_local! {
-        static INIT_ONCE: Once = Once::new();
-    }
-    INIT_ONCE.with(|init_once| {
-        init_once.call_once(|| unsafe { CoInitializeEx(None, COINIT_MULTITHREADED).unwrap() })
-    })
-}
+pub(crate) use self::apartment::ensure_thread_init;
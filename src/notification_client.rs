@@ -15,29 +15,25 @@ pub trait NotificationClient: 'static {
         data_flow: DataFlow,
         role: DeviceRole,
         device_id: &WinStr,
-    ) -> windows::core::Result<()> {
+    ) -> crate::Result<()> {
         let _ = (data_flow, role, device_id);
         Ok(())
     }
 
     /// See also: [`IMMNotificationClient::OnDeviceAdded`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/nf-mmdeviceapi-immnotificationclient-ondeviceadded)
-    fn on_device_added(&self, device_id: &WinStr) -> windows::core::Result<()> {
+    fn on_device_added(&self, device_id: &WinStr) -> crate::Result<()> {
         let _ = device_id;
         Ok(())
     }
 
     /// See also: [`IMMNotificationClient::OnDeviceRemoved`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/nf-mmdeviceapi-immnotificationclient-ondeviceremoved)
-    fn on_device_removed(&self, device_id: &WinStr) -> windows::core::Result<()> {
+    fn on_device_removed(&self, device_id: &WinStr) -> crate::Result<()> {
         let _ = device_id;
         Ok(())
     }
 
     /// See also: [`IMMNotificationClient::OnDeviceStateChanged`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/nf-mmdeviceapi-immnotificationclient-ondevicestatechanged)
-    fn on_device_state_changed(
-        &self,
-        device_id: &WinStr,
-        state: DeviceState,
-    ) -> windows::core::Result<()> {
+    fn on_device_state_changed(&self, device_id: &WinStr, state: DeviceState) -> crate::Result<()> {
         let _ = (device_id, state);
         Ok(())
     }
@@ -47,7 +43,7 @@ pub trait NotificationClient: 'static {
         &self,
         device_id: &WinStr,
         property_key: PropertyKey,
-    ) -> windows::core::Result<()> {
+    ) -> crate::Result<()> {
         let _ = (device_id, property_key);
         Ok(())
     }
@@ -59,13 +55,8 @@ pub(crate) struct NotificationClientWrapper {
 }
 
 impl NotificationClientWrapper {
-    pub(crate) fn new<T>(inner: T) -> Self
-    where
-        T: NotificationClient,
-    {
-        Self {
-            inner: Box::new(inner),
-        }
+    pub(crate) fn new(inner: Box<dyn NotificationClient>) -> Self {
+        Self { inner }
     }
 }
 
@@ -78,21 +69,25 @@ impl IMMNotificationClient_Impl for NotificationClientWrapper {
         role: ERole,
         device_id: &PCWSTR,
     ) -> windows::core::Result<()> {
-        self.inner.on_default_device_changed(
-            DataFlow::from_raw(flow),
-            DeviceRole::from_raw(role),
-            unsafe { WinStr::from_pcwstr(device_id) },
-        )
+        self.inner
+            .on_default_device_changed(
+                DataFlow::from_raw(flow),
+                DeviceRole::from_raw(role),
+                unsafe { WinStr::from_pcwstr(device_id) },
+            )
+            .map_err(Into::into)
     }
 
     fn OnDeviceAdded(&self, device_id: &PCWSTR) -> windows::core::Result<()> {
         self.inner
             .on_device_added(unsafe { WinStr::from_pcwstr(device_id) })
+            .map_err(Into::into)
     }
 
     fn OnDeviceRemoved(&self, device_id: &PCWSTR) -> windows::core::Result<()> {
         self.inner
             .on_device_removed(unsafe { WinStr::from_pcwstr(device_id) })
+            .map_err(Into::into)
     }
 
     fn OnDeviceStateChanged(
@@ -100,10 +95,12 @@ impl IMMNotificationClient_Impl for NotificationClientWrapper {
         device_id: &PCWSTR,
         new_state: u32,
     ) -> windows::core::Result<()> {
-        self.inner.on_device_state_changed(
-            unsafe { WinStr::from_pcwstr(device_id) },
-            DeviceState::from_raw(new_state),
-        )
+        self.inner
+            .on_device_state_changed(
+                unsafe { WinStr::from_pcwstr(device_id) },
+                DeviceState::from_raw(new_state),
+            )
+            .map_err(Into::into)
     }
 
     fn OnPropertyValueChanged(
@@ -111,9 +108,161 @@ impl IMMNotificationClient_Impl for NotificationClientWrapper {
         device_id: &PCWSTR,
         key: &PROPERTYKEY,
     ) -> windows::core::Result<()> {
-        self.inner.on_property_value_changed(
-            unsafe { WinStr::from_pcwstr(device_id) },
-            PropertyKey::from_raw(key.to_owned()),
-        )
+        self.inner
+            .on_property_value_changed(
+                unsafe { WinStr::from_pcwstr(device_id) },
+                PropertyKey::from_raw(key.to_owned()),
+            )
+            .map_err(Into::into)
+    }
+}
+
+impl dyn NotificationClient {
+    /// Build a client from closures, the same as
+    /// [`NotificationClientBuilder::new`], for example
+    /// `<dyn NotificationClient>::builder().on_device_added(|id| ..).build()`.
+    ///
+    /// Edition 2018 also accepts `NotificationClient::builder()`.
+    pub fn builder() -> NotificationClientBuilder {
+        NotificationClientBuilder::new()
+    }
+}
+
+/// A [`NotificationClient`] that calls the closures of a
+/// [`NotificationClientBuilder`]. Notifications without a closure are
+/// ignored.
+#[allow(clippy::type_complexity)]
+#[derive(Default)]
+pub struct FnNotificationClient {
+    on_default_device_changed:
+        Option<Box<dyn Fn(DataFlow, DeviceRole, &WinStr) -> crate::Result<()>>>,
+    on_device_added: Option<Box<dyn Fn(&WinStr) -> crate::Result<()>>>,
+    on_device_removed: Option<Box<dyn Fn(&WinStr) -> crate::Result<()>>>,
+    on_device_state_changed: Option<Box<dyn Fn(&WinStr, DeviceState) -> crate::Result<()>>>,
+    on_property_value_changed: Option<Box<dyn Fn(&WinStr, PropertyKey) -> crate::Result<()>>>,
+}
+
+impl NotificationClient for FnNotificationClient {
+    fn on_default_device_changed(
+        &self,
+        data_flow: DataFlow,
+        role: DeviceRole,
+        device_id: &WinStr,
+    ) -> crate::Result<()> {
+        match &self.on_default_device_changed {
+            Some(f) => f(data_flow, role, device_id),
+            None => Ok(()),
+        }
+    }
+
+    fn on_device_added(&self, device_id: &WinStr) -> crate::Result<()> {
+        match &self.on_device_added {
+            Some(f) => f(device_id),
+            None => Ok(()),
+        }
+    }
+
+    fn on_device_removed(&self, device_id: &WinStr) -> crate::Result<()> {
+        match &self.on_device_removed {
+            Some(f) => f(device_id),
+            None => Ok(()),
+        }
+    }
+
+    fn on_device_state_changed(&self, device_id: &WinStr, state: DeviceState) -> crate::Result<()> {
+        match &self.on_device_state_changed {
+            Some(f) => f(device_id, state),
+            None => Ok(()),
+        }
+    }
+
+    fn on_property_value_changed(
+        &self,
+        device_id: &WinStr,
+        property_key: PropertyKey,
+    ) -> crate::Result<()> {
+        match &self.on_property_value_changed {
+            Some(f) => f(device_id, property_key),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Builder of a [`FnNotificationClient`], which handles a few notifications
+/// without declaring a type.
+///
+/// Start from [`new`](Self::new) and set a closure for each notification to
+/// handle. Like the methods of [`NotificationClient`], the closures return a
+/// [`Result`](crate::Result), which is passed back to the caller of the
+/// notification.
+///
+/// ```
+/// use win32_coreaudio::{backend::fake::FakeAudioSystem, NotificationClientBuilder};
+///
+/// let system = FakeAudioSystem::new();
+/// let client = NotificationClientBuilder::new()
+///     .on_device_added(|id| {
+///         println!("added {}", id);
+///         Ok(())
+///     })
+///     .build();
+/// let _handle = system
+///     .device_enumerator()
+///     .register_endpoint_notification(client)?;
+/// # Ok::<(), win32_coreaudio::Error>(())
+/// ```
+#[derive(Default)]
+pub struct NotificationClientBuilder {
+    client: FnNotificationClient,
+}
+
+impl NotificationClientBuilder {
+    /// A builder without closures.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn on_default_device_changed<F>(mut self, f: F) -> Self
+    where
+        F: Fn(DataFlow, DeviceRole, &WinStr) -> crate::Result<()> + 'static,
+    {
+        self.client.on_default_device_changed = Some(Box::new(f));
+        self
+    }
+
+    pub fn on_device_added<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&WinStr) -> crate::Result<()> + 'static,
+    {
+        self.client.on_device_added = Some(Box::new(f));
+        self
+    }
+
+    pub fn on_device_removed<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&WinStr) -> crate::Result<()> + 'static,
+    {
+        self.client.on_device_removed = Some(Box::new(f));
+        self
+    }
+
+    pub fn on_device_state_changed<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&WinStr, DeviceState) -> crate::Result<()> + 'static,
+    {
+        self.client.on_device_state_changed = Some(Box::new(f));
+        self
+    }
+
+    pub fn on_property_value_changed<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&WinStr, PropertyKey) -> crate::Result<()> + 'static,
+    {
+        self.client.on_property_value_changed = Some(Box::new(f));
+        self
+    }
+
+    pub fn build(self) -> FnNotificationClient {
+        self.client
     }
 }
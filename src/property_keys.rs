@@ -0,0 +1,208 @@
+//! Catalog of well-known property keys of audio endpoint devices.
+//!
+//! Every key is listed in [`CATALOG`] together with a human-readable name and
+//! the kind of [`Property`] that Windows stores for it, so that diagnostics
+//! can print `FormFactor = Headphones` instead of an opaque key.
+//!
+//! See also: [`Audio Endpoint Properties`](https://docs.microsoft.com/en-us/windows/win32/coreaudio/audio-endpoint-properties)
+
+use windows::Win32::{
+    Devices::FunctionDiscovery::{
+        PKEY_DeviceClass_IconPath, PKEY_DeviceInterface_FriendlyName, PKEY_Device_Class,
+        PKEY_Device_ContainerId, PKEY_Device_DeviceDesc, PKEY_Device_Driver,
+        PKEY_Device_DriverDate, PKEY_Device_DriverVersion, PKEY_Device_EnumeratorName,
+        PKEY_Device_FriendlyName, PKEY_Device_HardwareIds, PKEY_Device_InstanceId,
+        PKEY_Device_Manufacturer,
+    },
+    Media::Audio::{
+        EndpointFormFactor as EEndpointFormFactor, PKEY_AudioEndpointLogo_IconPath,
+        PKEY_AudioEndpoint_Association, PKEY_AudioEndpoint_ControlPanelPageProvider,
+        PKEY_AudioEndpoint_Default_VolumeInDb, PKEY_AudioEndpoint_Disable_SysFx,
+        PKEY_AudioEndpoint_FormFactor, PKEY_AudioEndpoint_FullRangeSpeakers,
+        PKEY_AudioEndpoint_GUID, PKEY_AudioEndpoint_JackSubType,
+        PKEY_AudioEndpoint_PhysicalSpeakers, PKEY_AudioEndpoint_Supports_EventDriven_Mode,
+        PKEY_AudioEngine_DeviceFormat, PKEY_AudioEngine_OEMFormat,
+    },
+};
+
+use crate::{bits::EndpointFormFactor, property_store::Property, PropertyKey};
+
+pub const DEVICE_FRIENDLY_NAME: PropertyKey = PropertyKey::from_raw(PKEY_Device_FriendlyName);
+pub const DEVICE_DESCRIPTION: PropertyKey = PropertyKey::from_raw(PKEY_Device_DeviceDesc);
+pub const DEVICE_INTERFACE_FRIENDLY_NAME: PropertyKey =
+    PropertyKey::from_raw(PKEY_DeviceInterface_FriendlyName);
+pub const DEVICE_CLASS: PropertyKey = PropertyKey::from_raw(PKEY_Device_Class);
+pub const DEVICE_CLASS_ICON_PATH: PropertyKey = PropertyKey::from_raw(PKEY_DeviceClass_IconPath);
+pub const DEVICE_CONTAINER_ID: PropertyKey = PropertyKey::from_raw(PKEY_Device_ContainerId);
+pub const DEVICE_DRIVER: PropertyKey = PropertyKey::from_raw(PKEY_Device_Driver);
+pub const DEVICE_DRIVER_DATE: PropertyKey = PropertyKey::from_raw(PKEY_Device_DriverDate);
+pub const DEVICE_DRIVER_VERSION: PropertyKey = PropertyKey::from_raw(PKEY_Device_DriverVersion);
+pub const DEVICE_ENUMERATOR_NAME: PropertyKey = PropertyKey::from_raw(PKEY_Device_EnumeratorName);
+pub const DEVICE_HARDWARE_IDS: PropertyKey = PropertyKey::from_raw(PKEY_Device_HardwareIds);
+pub const DEVICE_INSTANCE_ID: PropertyKey = PropertyKey::from_raw(PKEY_Device_InstanceId);
+pub const DEVICE_MANUFACTURER: PropertyKey = PropertyKey::from_raw(PKEY_Device_Manufacturer);
+
+pub const AUDIO_ENDPOINT_ASSOCIATION: PropertyKey =
+    PropertyKey::from_raw(PKEY_AudioEndpoint_Association);
+pub const AUDIO_ENDPOINT_CONTROL_PANEL_PAGE_PROVIDER: PropertyKey =
+    PropertyKey::from_raw(PKEY_AudioEndpoint_ControlPanelPageProvider);
+pub const AUDIO_ENDPOINT_DEFAULT_VOLUME_IN_DB: PropertyKey =
+    PropertyKey::from_raw(PKEY_AudioEndpoint_Default_VolumeInDb);
+pub const AUDIO_ENDPOINT_DISABLE_SYS_FX: PropertyKey =
+    PropertyKey::from_raw(PKEY_AudioEndpoint_Disable_SysFx);
+pub const AUDIO_ENDPOINT_FORM_FACTOR: PropertyKey =
+    PropertyKey::from_raw(PKEY_AudioEndpoint_FormFactor);
+pub const AUDIO_ENDPOINT_FULL_RANGE_SPEAKERS: PropertyKey =
+    PropertyKey::from_raw(PKEY_AudioEndpoint_FullRangeSpeakers);
+pub const AUDIO_ENDPOINT_GUID: PropertyKey = PropertyKey::from_raw(PKEY_AudioEndpoint_GUID);
+pub const AUDIO_ENDPOINT_JACK_SUB_TYPE: PropertyKey =
+    PropertyKey::from_raw(PKEY_AudioEndpoint_JackSubType);
+pub const AUDIO_ENDPOINT_LOGO_ICON_PATH: PropertyKey =
+    PropertyKey::from_raw(PKEY_AudioEndpointLogo_IconPath);
+pub const AUDIO_ENDPOINT_PHYSICAL_SPEAKERS: PropertyKey =
+    PropertyKey::from_raw(PKEY_AudioEndpoint_PhysicalSpeakers);
+pub const AUDIO_ENDPOINT_SUPPORTS_EVENT_DRIVEN_MODE: PropertyKey =
+    PropertyKey::from_raw(PKEY_AudioEndpoint_Supports_EventDriven_Mode);
+pub const AUDIO_ENGINE_DEVICE_FORMAT: PropertyKey =
+    PropertyKey::from_raw(PKEY_AudioEngine_DeviceFormat);
+pub const AUDIO_ENGINE_OEM_FORMAT: PropertyKey = PropertyKey::from_raw(PKEY_AudioEngine_OEMFormat);
+
+/// The kind of [`Property`] that a well-known key is expected to hold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum PropertyKind {
+    Bool,
+    U32,
+    Str,
+    StrVec,
+    Guid,
+    Blob,
+    FileTime,
+}
+
+/// Describes a well-known property key.
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct PropertyInfo {
+    pub key: PropertyKey,
+    /// The name of the key without its `PKEY_` prefix and category, for
+    /// example `FormFactor` for `PKEY_AudioEndpoint_FormFactor`.
+    pub name: &'static str,
+    pub kind: PropertyKind,
+}
+
+impl PropertyInfo {
+    const fn new(key: PropertyKey, name: &'static str, kind: PropertyKind) -> Self {
+        Self { key, name, kind }
+    }
+
+    /// The symbolic name of `value`, for properties that hold an enumeration.
+    ///
+    /// Returns `None` for other properties and for values that are not part
+    /// of the enumeration.
+    pub fn value_name(&self, value: &Property) -> Option<&'static str> {
+        match value {
+            Property::U32(raw) if self.key == AUDIO_ENDPOINT_FORM_FACTOR => form_factor_name(
+                EndpointFormFactor::from_raw(EEndpointFormFactor(*raw as i32)),
+            ),
+            _ => None,
+        }
+    }
+}
+
+fn form_factor_name(form_factor: EndpointFormFactor) -> Option<&'static str> {
+    Some(match form_factor {
+        EndpointFormFactor::RemoteNetworkDevice => "RemoteNetworkDevice",
+        EndpointFormFactor::Speakers => "Speakers",
+        EndpointFormFactor::LineLevel => "LineLevel",
+        EndpointFormFactor::Headphones => "Headphones",
+        EndpointFormFactor::Microphone => "Microphone",
+        EndpointFormFactor::Headset => "Headset",
+        EndpointFormFactor::Handset => "Handset",
+        EndpointFormFactor::UnknownDigitalPassthrough => "UnknownDigitalPassthrough",
+        EndpointFormFactor::Spdif => "SPDIF",
+        EndpointFormFactor::DigitalAudioDisplayDevice => "DigitalAudioDisplayDevice",
+        EndpointFormFactor::UnknownFormFactor => "UnknownFormFactor",
+        EndpointFormFactor::Unknown(_) => return None,
+    })
+}
+
+/// All keys that are known to this crate.
+pub static CATALOG: &[PropertyInfo] = &[
+    PropertyInfo::new(DEVICE_FRIENDLY_NAME, "FriendlyName", PropertyKind::Str),
+    PropertyInfo::new(DEVICE_DESCRIPTION, "DeviceDesc", PropertyKind::Str),
+    PropertyInfo::new(
+        DEVICE_INTERFACE_FRIENDLY_NAME,
+        "InterfaceFriendlyName",
+        PropertyKind::Str,
+    ),
+    PropertyInfo::new(DEVICE_CLASS, "Class", PropertyKind::Str),
+    PropertyInfo::new(DEVICE_CLASS_ICON_PATH, "IconPath", PropertyKind::Str),
+    PropertyInfo::new(DEVICE_CONTAINER_ID, "ContainerId", PropertyKind::Guid),
+    PropertyInfo::new(DEVICE_DRIVER, "Driver", PropertyKind::Str),
+    PropertyInfo::new(DEVICE_DRIVER_DATE, "DriverDate", PropertyKind::FileTime),
+    PropertyInfo::new(DEVICE_DRIVER_VERSION, "DriverVersion", PropertyKind::Str),
+    PropertyInfo::new(DEVICE_ENUMERATOR_NAME, "EnumeratorName", PropertyKind::Str),
+    PropertyInfo::new(DEVICE_HARDWARE_IDS, "HardwareIds", PropertyKind::StrVec),
+    PropertyInfo::new(DEVICE_INSTANCE_ID, "InstanceId", PropertyKind::Str),
+    PropertyInfo::new(DEVICE_MANUFACTURER, "Manufacturer", PropertyKind::Str),
+    PropertyInfo::new(AUDIO_ENDPOINT_ASSOCIATION, "Association", PropertyKind::Str),
+    PropertyInfo::new(
+        AUDIO_ENDPOINT_CONTROL_PANEL_PAGE_PROVIDER,
+        "ControlPanelPageProvider",
+        PropertyKind::Str,
+    ),
+    PropertyInfo::new(
+        AUDIO_ENDPOINT_DEFAULT_VOLUME_IN_DB,
+        "Default_VolumeInDb",
+        PropertyKind::U32,
+    ),
+    PropertyInfo::new(
+        AUDIO_ENDPOINT_DISABLE_SYS_FX,
+        "Disable_SysFx",
+        PropertyKind::U32,
+    ),
+    PropertyInfo::new(AUDIO_ENDPOINT_FORM_FACTOR, "FormFactor", PropertyKind::U32),
+    PropertyInfo::new(
+        AUDIO_ENDPOINT_FULL_RANGE_SPEAKERS,
+        "FullRangeSpeakers",
+        PropertyKind::U32,
+    ),
+    PropertyInfo::new(AUDIO_ENDPOINT_GUID, "GUID", PropertyKind::Str),
+    PropertyInfo::new(
+        AUDIO_ENDPOINT_JACK_SUB_TYPE,
+        "JackSubType",
+        PropertyKind::Str,
+    ),
+    PropertyInfo::new(
+        AUDIO_ENDPOINT_LOGO_ICON_PATH,
+        "LogoIconPath",
+        PropertyKind::Str,
+    ),
+    PropertyInfo::new(
+        AUDIO_ENDPOINT_PHYSICAL_SPEAKERS,
+        "PhysicalSpeakers",
+        PropertyKind::U32,
+    ),
+    PropertyInfo::new(
+        AUDIO_ENDPOINT_SUPPORTS_EVENT_DRIVEN_MODE,
+        "Supports_EventDriven_Mode",
+        PropertyKind::U32,
+    ),
+    PropertyInfo::new(
+        AUDIO_ENGINE_DEVICE_FORMAT,
+        "DeviceFormat",
+        PropertyKind::Blob,
+    ),
+    PropertyInfo::new(AUDIO_ENGINE_OEM_FORMAT, "OEMFormat", PropertyKind::Blob),
+];
+
+/// Look up a key in the [`CATALOG`].
+pub fn lookup(key: PropertyKey) -> Option<&'static PropertyInfo> {
+    CATALOG.iter().find(|info| info.key == key)
+}
+
+/// Look up a key in the [`CATALOG`] by its [`name`](PropertyInfo::name).
+pub fn lookup_name(name: &str) -> Option<&'static PropertyInfo> {
+    CATALOG.iter().find(|info| info.name == name)
+}
@@ -1,23 +1,47 @@
-use std::mem::ManuallyDrop;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::ffi::{c_void, CString};
+use std::fmt::{self, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::iter::FusedIterator;
+use std::rc::Rc;
+use std::str::FromStr;
 
+use windows::core::GUID;
 use windows::Win32::{
-    Foundation::VARIANT_BOOL,
-    System::Com::StructuredStorage::{
-        PropVariantToStringAlloc, PROPVARIANT, PROPVARIANT_0, PROPVARIANT_0_0, PROPVARIANT_0_0_0,
+    System::Com::{
+        CoTaskMemFree,
+        StructuredStorage::{
+            PropVariantClear, StgDeserializePropVariant, StgSerializePropVariant, PROPVARIANT,
+            SERIALIZEDPROPERTYVALUE,
+        },
     },
-    System::Variant::{
-        VT_BOOL, VT_EMPTY, VT_I1, VT_I2, VT_I4, VT_I8, VT_INT, VT_LPWSTR, VT_NULL, VT_R4, VT_R8,
-        VT_UI1, VT_UI2, VT_UI4, VT_UI8, VT_UINT,
-    },
-    UI::Shell::PropertiesSystem::{IPropertyStore, PROPERTYKEY},
+    UI::Shell::PropertiesSystem::PROPERTYKEY,
 };
 
-use crate::string::WinString;
+use crate::{
+    backend::PropertyStoreBackend,
+    guid::{parse_guid, GuidDisplay},
+    propvariant,
+    string::WinString,
+};
 
-#[derive(Debug, Clone, Copy)]
+/// Identifies a property in a [`PropertyStore`].
+///
+/// Keys are displayed and parsed in the `{fmtid} pid` form that is used by
+/// the registry and Device Manager, for example
+/// `{A45C254E-DF1C-4EFD-8020-67D146A850E0} 14`. Parsing accepts hex digits
+/// in either case and GUIDs without braces.
+///
+/// See also: [`PROPERTYKEY`](https://docs.microsoft.com/en-us/windows/win32/api/wtypes/ns-wtypes-propertykey)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct PropertyKey(PROPERTYKEY);
 
 impl PropertyKey {
+    pub const fn new(fmtid: GUID, pid: u32) -> Self {
+        Self(PROPERTYKEY { fmtid, pid })
+    }
+
     pub(crate) const fn from_raw(raw: PROPERTYKEY) -> Self {
         Self(raw)
     }
@@ -25,10 +49,88 @@ impl PropertyKey {
     pub(crate) fn as_raw(&self) -> &PROPERTYKEY {
         &self.0
     }
+
+    /// The format identifier, which groups related properties.
+    pub fn fmtid(&self) -> GUID {
+        self.0.fmtid
+    }
+
+    /// The property identifier within the format.
+    pub fn pid(&self) -> u32 {
+        self.0.pid
+    }
+}
+
+impl Hash for PropertyKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.fmtid.hash(state);
+        self.0.pid.hash(state);
+    }
+}
+
+impl PartialOrd for PropertyKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PropertyKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.0.fmtid.to_u128(), self.0.pid).cmp(&(other.0.fmtid.to_u128(), other.0.pid))
+    }
+}
+
+impl Display for PropertyKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", GuidDisplay(&self.0.fmtid), self.0.pid)
+    }
+}
+
+impl FromStr for PropertyKey {
+    type Err = ParsePropertyKeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (fmtid, pid) = s
+            .trim()
+            .rsplit_once(char::is_whitespace)
+            .ok_or(ParsePropertyKeyError(()))?;
+        let fmtid = parse_guid(fmtid.trim_end()).ok_or(ParsePropertyKeyError(()))?;
+        let pid = pid.parse().map_err(|_| ParsePropertyKeyError(()))?;
+        Ok(Self::new(fmtid, pid))
+    }
+}
+
+/// The error returned when parsing a [`PropertyKey`] fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsePropertyKeyError(());
+
+impl Display for ParsePropertyKeyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid property key, expected the form `{fmtid} pid`")
+    }
+}
+
+impl std::error::Error for ParsePropertyKeyError {}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for PropertyKey {
+    /// Serialized in the `{fmtid} pid` form.
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for PropertyKey {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
 }
 
 /// See also: [`PROPVARIANT`](https://docs.microsoft.com/en-us/windows/win32/api/propidlbase/ns-propidlbase-propvariant)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Property {
     Empty,
     Null,
@@ -44,140 +146,293 @@ pub enum Property {
     F64(f64),
     Bool(bool),
     Str(WinString),
+    /// A null-terminated string in the system code page (`VT_LPSTR`).
+    AnsiStr(CString),
+    /// Binary data (`VT_BLOB`), such as a `WAVEFORMATEX` device format.
+    Blob(Vec<u8>),
+    /// A class identifier (`VT_CLSID`).
+    Guid(
+        #[cfg_attr(
+            feature = "serde",
+            serde(
+                serialize_with = "crate::guid::serialize_guid",
+                deserialize_with = "crate::guid::deserialize_guid"
+            )
+        )]
+        GUID,
+    ),
+    /// A `FILETIME`: the number of 100-nanosecond intervals since
+    /// January 1, 1601 (UTC).
+    FileTime(u64),
+    /// A vector of strings (`VT_VECTOR | VT_LPWSTR`), such as hardware ids.
+    StrVec(Vec<WinString>),
+    /// A vector of signed integers (`VT_VECTOR | VT_I4`).
+    I32Vec(Vec<i32>),
+    /// A vector of unsigned integers (`VT_VECTOR | VT_UI4`).
+    U32Vec(Vec<u32>),
     /// The property contains a type that is not yet supported by this crate.
-    Unsupported,
+    ///
+    /// `vt` is the `VARTYPE` tag of the value. Unsupported properties cannot
+    /// be written back to a [`PropertyStore`].
+    Unsupported {
+        vt: u16,
+    },
 }
 
 impl Property {
-    pub(crate) fn from_raw(raw: PROPVARIANT) -> Self {
-        let raw_raw = raw.clone();
-        let raw_inner = unsafe { raw.Anonymous.Anonymous };
-        let tag = raw_inner.vt;
-        let value = &raw_inner.Anonymous;
-        if tag == VT_EMPTY {
-            Self::Empty
-        } else if tag == VT_NULL {
-            Self::Null
-        } else if tag == VT_I1 {
-            Self::I8(unsafe { value.cVal as _ })
-        } else if tag == VT_UI1 {
-            Self::U8(unsafe { value.bVal })
-        } else if tag == VT_I2 {
-            Self::I16(unsafe { value.iVal })
-        } else if tag == VT_UI2 {
-            Self::U16(unsafe { value.uiVal })
-        } else if tag == VT_I4 {
-            Self::I32(unsafe { value.lVal })
-        } else if tag == VT_UI4 {
-            Self::U32(unsafe { value.ulVal })
-        } else if tag == VT_INT {
-            Self::I32(unsafe { value.intVal })
-        } else if tag == VT_UINT {
-            Self::U32(unsafe { value.uintVal })
-        } else if tag == VT_I8 {
-            Self::I64(unsafe { value.hVal })
-        } else if tag == VT_UI8 {
-            Self::U64(unsafe { value.uhVal })
-        } else if tag == VT_R4 {
-            Self::F32(unsafe { value.fltVal })
-        } else if tag == VT_R8 {
-            Self::F64(unsafe { value.dblVal })
-        } else if tag == VT_BOOL {
-            Self::Bool(unsafe { value.boolVal != true })
-        } else if tag == VT_LPWSTR {
-            Self::Str(unsafe {
-                WinString::from_com_pwstr(PropVariantToStringAlloc(&raw_raw).unwrap())
-            })
-        } else {
-            Self::Unsupported
+    /// Encode the value in the platform-independent `TypedPropertyValue`
+    /// form of the MS-OLEPS specification, as used by
+    /// `StgSerializePropVariant`.
+    ///
+    /// Fails for [`Property::Unsupported`].
+    pub fn to_bytes(&self) -> crate::Result<Vec<u8>> {
+        propvariant::encode(self)
+    }
+
+    /// Decode a value in the form produced by [`to_bytes`](Self::to_bytes).
+    ///
+    /// Types that this crate does not support decode to
+    /// [`Property::Unsupported`]. Fails if the data is truncated or
+    /// malformed.
+    pub fn from_bytes(bytes: &[u8]) -> crate::Result<Self> {
+        propvariant::decode(bytes)
+    }
+
+    /// Copy the value out of `raw`, which stays owned by the caller.
+    pub(crate) fn from_raw(raw: &PROPVARIANT) -> Self {
+        let vt = unsafe { raw.Anonymous.Anonymous.vt.0 };
+        let mut serialized = std::ptr::null_mut();
+        let mut len = 0;
+        if unsafe { StgSerializePropVariant(raw, &mut serialized, &mut len) }.is_err() {
+            return Self::Unsupported { vt };
         }
+        let bytes = unsafe { std::slice::from_raw_parts(serialized as *const u8, len as usize) };
+        let property = Self::from_bytes(bytes).unwrap_or(Self::Unsupported { vt });
+        unsafe { CoTaskMemFree(Some(serialized as *const c_void)) };
+        property
     }
 
-    pub(crate) fn to_raw(&self) -> PROPVARIANT {
-        type Data = PROPVARIANT_0_0_0;
-        let (tag, data) = match self {
-            Property::Empty => (VT_EMPTY, Data { bVal: 0 }),
-            Property::Null => (VT_NULL, Data { bVal: 0 }),
-            Property::I8(x) => (VT_I1, Data { cVal: *x as _ }),
-            Property::U8(x) => (VT_UI1, Data { bVal: *x }),
-            Property::I16(x) => (VT_I2, Data { iVal: *x }),
-            Property::U16(x) => (VT_UI2, Data { uiVal: *x }),
-            Property::I32(x) => (VT_I4, Data { lVal: *x }),
-            Property::U32(x) => (VT_UI4, Data { ulVal: *x }),
-            Property::I64(x) => (VT_I8, Data { hVal: *x }),
-            Property::U64(x) => (VT_UI8, Data { uhVal: *x }),
-            Property::F32(x) => (VT_R4, Data { fltVal: *x }),
-            Property::F64(x) => (VT_R8, Data { dblVal: *x }),
-            Property::Bool(x) => (
-                VT_BOOL,
-                Data {
-                    boolVal: VARIANT_BOOL::from(*x),
-                },
-            ),
-            Property::Str(x) => (
-                VT_LPWSTR,
-                Data {
-                    pwszVal: x.as_pwstr(),
-                },
-            ),
-            Property::Unsupported => panic!("cannot convert unsupported"),
+    /// Convert to a `PROPVARIANT` whose data is allocated by COM, so that it
+    /// can be handed to APIs that take ownership or copy it.
+    pub(crate) fn to_raw(&self) -> crate::Result<OwnedPropVariant> {
+        let bytes = self.to_bytes()?;
+        // `SERIALIZEDPROPERTYVALUE` starts with a `u32`, so copy the bytes to
+        // an aligned buffer. The encoding is always padded to whole `u32`s.
+        let aligned: Vec<u32> = bytes
+            .chunks_exact(4)
+            .map(|chunk| u32::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect();
+        let raw = unsafe {
+            StgDeserializePropVariant(
+                aligned.as_ptr() as *const SERIALIZEDPROPERTYVALUE,
+                bytes.len() as u32,
+            )?
         };
-        PROPVARIANT {
-            Anonymous: PROPVARIANT_0 {
-                Anonymous: ManuallyDrop::new(PROPVARIANT_0_0 {
-                    vt: tag,
-                    wReserved1: 0,
-                    wReserved2: 0,
-                    wReserved3: 0,
-                    Anonymous: data,
-                }),
-            },
+        Ok(unsafe { OwnedPropVariant::new(raw) })
+    }
+
+    /// Like `==`, but comparing floating point values by their bits.
+    fn same_bits(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::F32(a), Self::F32(b)) => a.to_bits() == b.to_bits(),
+            (Self::F64(a), Self::F64(b)) => a.to_bits() == b.to_bits(),
+            _ => self == other,
         }
     }
 }
 
+/// A `PROPVARIANT` whose data was allocated by COM and is released with
+/// `PropVariantClear` when dropped.
+pub(crate) struct OwnedPropVariant(PROPVARIANT);
+
+impl OwnedPropVariant {
+    /// Take ownership of `raw`.
+    ///
+    /// # Safety
+    ///
+    /// - `raw` must have been initialized by COM, for example by
+    ///   `IPropertyStore::GetValue`, and must not be cleared elsewhere.
+    pub(crate) unsafe fn new(raw: PROPVARIANT) -> Self {
+        Self(raw)
+    }
+
+    pub(crate) fn as_raw(&self) -> &PROPVARIANT {
+        &self.0
+    }
+}
+
+impl Drop for OwnedPropVariant {
+    fn drop(&mut self) {
+        let _ = unsafe { PropVariantClear(&mut self.0) };
+    }
+}
+
 /// See also: [`IPropertyStore`](https://docs.microsoft.com/en-us/windows/win32/api/propsys/nn-propsys-ipropertystore)
 #[derive(Debug, Clone)]
 pub struct PropertyStore {
-    inner: IPropertyStore,
+    inner: Rc<dyn PropertyStoreBackend>,
 }
 
 impl PropertyStore {
-    pub(crate) fn new(inner: IPropertyStore) -> Self {
+    pub(crate) fn new(inner: Rc<dyn PropertyStoreBackend>) -> Self {
         Self { inner }
     }
 
     /// See also: [`IPropertyStore::Commit`](https://docs.microsoft.com/en-us/windows/win32/api/propsys/nf-propsys-ipropertystore-commit)
-    pub fn commit(&self) -> windows::core::Result<()> {
-        unsafe { self.inner.Commit() }
+    pub fn commit(&self) -> crate::Result<()> {
+        self.inner.commit()
     }
 
     /// See also: [`IPropertyStore::GetAt`](https://docs.microsoft.com/en-us/windows/win32/api/propsys/nf-propsys-ipropertystore-getat)
-    pub fn get_at(&self, index: u32) -> windows::core::Result<PropertyKey> {
-        let pkey: *mut PROPERTYKEY = std::ptr::null_mut();
-
-        unsafe {
-            self.inner.GetAt(index, pkey)?;
-            Ok(PropertyKey::from_raw(*pkey.to_owned()))
-        }
+    pub fn get_at(&self, index: u32) -> crate::Result<PropertyKey> {
+        self.inner.get_at(index)
     }
 
     /// See also: [`IPropertyStore::GetCount`](https://docs.microsoft.com/en-us/windows/win32/api/propsys/nf-propsys-ipropertystore-getcount)
-    pub fn get_count(&self) -> windows::core::Result<u32> {
-        unsafe { self.inner.GetCount() }
+    pub fn get_count(&self) -> crate::Result<u32> {
+        self.inner.get_count()
     }
 
     /// See also: [`IPropertyStore::GetValue`](https://docs.microsoft.com/en-us/windows/win32/api/propsys/nf-propsys-ipropertystore-getvalue)
-    pub fn get_value(&self, key: PropertyKey) -> windows::core::Result<Property> {
-        unsafe {
-            self.inner
-                .GetValue(key.as_raw() as _)
-                .map(Property::from_raw)
-        }
+    pub fn get_value(&self, key: PropertyKey) -> crate::Result<Property> {
+        self.inner.get_value(key)
     }
 
     /// See also: [`IPropertyStore::SetValue`](https://docs.microsoft.com/en-us/windows/win32/api/propsys/nf-propsys-ipropertystore-setvalue)
-    pub fn set_value(&self, key: &PropertyKey, property: &Property) -> windows::core::Result<()> {
-        unsafe { self.inner.SetValue(key.as_raw(), &property.to_raw()) }
+    pub fn set_value(&self, key: &PropertyKey, property: &Property) -> crate::Result<()> {
+        self.inner.set_value(key, property)
+    }
+
+    /// The keys of every property in the store, in store order.
+    pub fn keys(&self) -> crate::Result<Vec<PropertyKey>> {
+        (0..self.get_count()?)
+            .map(|index| self.get_at(index))
+            .collect()
+    }
+
+    /// Iterate over the properties in store order.
+    ///
+    /// The keys are retrieved up front, so failing to enumerate them is
+    /// reported here. Values are read lazily, and a value that cannot be read
+    /// yields an error next to its key.
+    pub fn iter(&self) -> crate::Result<PropertyIter<'_>> {
+        Ok(PropertyIter {
+            inner: self,
+            keys: self.keys()?.into_iter(),
+        })
+    }
+
+    /// Read every property into a map ordered by key, failing if any of them
+    /// could not be read.
+    pub fn snapshot(&self) -> crate::Result<BTreeMap<PropertyKey, Property>> {
+        self.iter()?
+            .map(|(key, value)| value.map(|value| (key, value)))
+            .collect()
+    }
+
+    /// Compare two snapshots taken with [`snapshot`](Self::snapshot), for
+    /// example before and after a driver update.
+    ///
+    /// The changes are ordered by key. Floating point values are compared by
+    /// their bits, so an unchanged NaN is not reported as a change, while a
+    /// change between `0.0` and `-0.0` is.
+    pub fn diff(
+        old: &BTreeMap<PropertyKey, Property>,
+        new: &BTreeMap<PropertyKey, Property>,
+    ) -> Vec<PropertyChange> {
+        let mut changes = Vec::new();
+        let mut old = old.iter().peekable();
+        let mut new = new.iter().peekable();
+        loop {
+            let order = match (old.peek(), new.peek()) {
+                (Some((old_key, _)), Some((new_key, _))) => old_key.cmp(new_key),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => break,
+            };
+            match order {
+                Ordering::Less => {
+                    let (key, value) = old.next().unwrap();
+                    changes.push(PropertyChange::Removed {
+                        key: *key,
+                        old: value.clone(),
+                    });
+                }
+                Ordering::Greater => {
+                    let (key, value) = new.next().unwrap();
+                    changes.push(PropertyChange::Added {
+                        key: *key,
+                        new: value.clone(),
+                    });
+                }
+                Ordering::Equal => {
+                    let ((key, old_value), (_, new_value)) =
+                        (old.next().unwrap(), new.next().unwrap());
+                    if !old_value.same_bits(new_value) {
+                        changes.push(PropertyChange::Changed {
+                            key: *key,
+                            old: old_value.clone(),
+                            new: new_value.clone(),
+                        });
+                    }
+                }
+            }
+        }
+        changes
+    }
+}
+
+/// Iterator over the properties of a [`PropertyStore`].
+#[derive(Debug, Clone)]
+pub struct PropertyIter<'a> {
+    inner: &'a PropertyStore,
+    keys: std::vec::IntoIter<PropertyKey>,
+}
+
+impl<'a> PropertyIter<'a> {
+    /// Skip the values that could not be read.
+    pub fn lossy(self) -> impl Iterator<Item = (PropertyKey, Property)> + 'a {
+        self.filter_map(|(key, value)| value.ok().map(|value| (key, value)))
+    }
+}
+
+impl<'a> Iterator for PropertyIter<'a> {
+    type Item = (PropertyKey, crate::Result<Property>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.keys.next().map(|key| (key, self.inner.get_value(key)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.keys.size_hint()
+    }
+}
+
+impl<'a> ExactSizeIterator for PropertyIter<'a> {}
+impl<'a> FusedIterator for PropertyIter<'a> {}
+
+/// A difference between two property snapshots, as returned by
+/// [`PropertyStore::diff`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PropertyChange {
+    /// The property only exists in the new snapshot.
+    Added { key: PropertyKey, new: Property },
+    /// The property only exists in the old snapshot.
+    Removed { key: PropertyKey, old: Property },
+    /// The property has a different value in the new snapshot.
+    Changed {
+        key: PropertyKey,
+        old: Property,
+        new: Property,
+    },
+}
+
+impl PropertyChange {
+    /// The key of the property that changed.
+    pub fn key(&self) -> PropertyKey {
+        match self {
+            Self::Added { key, .. } | Self::Removed { key, .. } | Self::Changed { key, .. } => *key,
+        }
     }
 }
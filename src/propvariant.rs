@@ -0,0 +1,278 @@
+//! Platform-independent encoding of [`Property`] values.
+//!
+//! Values are stored as a `TypedPropertyValue` from the
+//! [MS-OLEPS](https://docs.microsoft.com/en-us/openspecs/windows_protocols/ms-oleps/bf7aeae8-c47a-4939-9f45-700158dac3bc)
+//! specification: a little-endian `u16` type tag, two bytes of padding and
+//! the value itself, padded to a multiple of four bytes. This is the layout
+//! that `StgSerializePropVariant` produces, so the conversions between
+//! [`Property`] and `PROPVARIANT` only move bytes between this module and
+//! the system.
+
+use std::convert::TryFrom;
+use std::ffi::CString;
+
+use windows::core::GUID;
+use windows::Win32::{
+    Foundation::{DISP_E_BADVARTYPE, E_INVALIDARG},
+    System::Variant::{
+        VARENUM, VT_BLOB, VT_BOOL, VT_CLSID, VT_EMPTY, VT_FILETIME, VT_I1, VT_I2, VT_I4, VT_I8,
+        VT_INT, VT_LPSTR, VT_LPWSTR, VT_NULL, VT_R4, VT_R8, VT_UI1, VT_UI2, VT_UI4, VT_UI8,
+        VT_UINT, VT_VECTOR,
+    },
+};
+
+use crate::{property_store::Property, string::WinString, Error};
+
+const VT_VECTOR_LPWSTR: VARENUM = VARENUM(VT_VECTOR.0 | VT_LPWSTR.0);
+const VT_VECTOR_I4: VARENUM = VARENUM(VT_VECTOR.0 | VT_I4.0);
+const VT_VECTOR_UI4: VARENUM = VARENUM(VT_VECTOR.0 | VT_UI4.0);
+
+/// `VARIANT_TRUE`, as stored in a `VT_BOOL` value.
+const VARIANT_TRUE: u16 = 0xffff;
+
+/// Encode `property` as a `TypedPropertyValue`.
+///
+/// Fails with `DISP_E_BADVARTYPE` for [`Property::Unsupported`], and with
+/// `E_INVALIDARG` for values that do not fit in the format.
+pub(crate) fn encode(property: &Property) -> crate::Result<Vec<u8>> {
+    let mut writer = Writer::default();
+    match property {
+        Property::Empty => writer.tag(VT_EMPTY),
+        Property::Null => writer.tag(VT_NULL),
+        Property::I8(x) => writer.tag(VT_I1).bytes(&x.to_le_bytes()),
+        Property::U8(x) => writer.tag(VT_UI1).bytes(&x.to_le_bytes()),
+        Property::I16(x) => writer.tag(VT_I2).bytes(&x.to_le_bytes()),
+        Property::U16(x) => writer.tag(VT_UI2).bytes(&x.to_le_bytes()),
+        Property::I32(x) => writer.tag(VT_I4).bytes(&x.to_le_bytes()),
+        Property::U32(x) => writer.tag(VT_UI4).bytes(&x.to_le_bytes()),
+        Property::I64(x) => writer.tag(VT_I8).bytes(&x.to_le_bytes()),
+        Property::U64(x) => writer.tag(VT_UI8).bytes(&x.to_le_bytes()),
+        Property::F32(x) => writer.tag(VT_R4).bytes(&x.to_le_bytes()),
+        Property::F64(x) => writer.tag(VT_R8).bytes(&x.to_le_bytes()),
+        Property::Bool(x) => {
+            let value: u16 = if *x { VARIANT_TRUE } else { 0 };
+            writer.tag(VT_BOOL).bytes(&value.to_le_bytes())
+        }
+        Property::Str(x) => writer.tag(VT_LPWSTR).unicode_string(x)?,
+        Property::AnsiStr(x) => writer
+            .tag(VT_LPSTR)
+            .len(x.as_bytes_with_nul())?
+            .bytes(x.as_bytes_with_nul()),
+        Property::Blob(x) => writer.tag(VT_BLOB).len(x)?.bytes(x),
+        Property::Guid(x) => writer
+            .tag(VT_CLSID)
+            .bytes(&x.data1.to_le_bytes())
+            .bytes(&x.data2.to_le_bytes())
+            .bytes(&x.data3.to_le_bytes())
+            .bytes(&x.data4),
+        Property::FileTime(x) => writer.tag(VT_FILETIME).bytes(&x.to_le_bytes()),
+        Property::StrVec(x) => {
+            writer.tag(VT_VECTOR_LPWSTR).len(x)?;
+            for s in x {
+                writer.unicode_string(s)?;
+            }
+            &mut writer
+        }
+        Property::I32Vec(x) => {
+            writer.tag(VT_VECTOR_I4).len(x)?;
+            for i in x {
+                writer.bytes(&i.to_le_bytes());
+            }
+            &mut writer
+        }
+        Property::U32Vec(x) => {
+            writer.tag(VT_VECTOR_UI4).len(x)?;
+            for i in x {
+                writer.bytes(&i.to_le_bytes());
+            }
+            &mut writer
+        }
+        Property::Unsupported { .. } => return Err(Error::Other(DISP_E_BADVARTYPE)),
+    };
+    Ok(writer.finish())
+}
+
+/// Decode a `TypedPropertyValue`. Bytes after the value are ignored.
+///
+/// Types that [`Property`] cannot represent decode to
+/// [`Property::Unsupported`]. Fails with `E_INVALIDARG` if the value is
+/// truncated or malformed.
+pub(crate) fn decode(bytes: &[u8]) -> crate::Result<Property> {
+    let mut reader = Reader { bytes };
+    let tag = VARENUM(u16::from_le_bytes(reader.array()?));
+    reader.skip(2)?;
+    let property = if tag == VT_EMPTY {
+        Property::Empty
+    } else if tag == VT_NULL {
+        Property::Null
+    } else if tag == VT_I1 {
+        Property::I8(i8::from_le_bytes(reader.array()?))
+    } else if tag == VT_UI1 {
+        Property::U8(u8::from_le_bytes(reader.array()?))
+    } else if tag == VT_I2 {
+        Property::I16(i16::from_le_bytes(reader.array()?))
+    } else if tag == VT_UI2 {
+        Property::U16(u16::from_le_bytes(reader.array()?))
+    } else if tag == VT_I4 || tag == VT_INT {
+        Property::I32(i32::from_le_bytes(reader.array()?))
+    } else if tag == VT_UI4 || tag == VT_UINT {
+        Property::U32(u32::from_le_bytes(reader.array()?))
+    } else if tag == VT_I8 {
+        Property::I64(i64::from_le_bytes(reader.array()?))
+    } else if tag == VT_UI8 {
+        Property::U64(u64::from_le_bytes(reader.array()?))
+    } else if tag == VT_R4 {
+        Property::F32(f32::from_le_bytes(reader.array()?))
+    } else if tag == VT_R8 {
+        Property::F64(f64::from_le_bytes(reader.array()?))
+    } else if tag == VT_BOOL {
+        // Any non-zero value is true, although only `VARIANT_TRUE` is valid.
+        Property::Bool(u16::from_le_bytes(reader.array()?) != 0)
+    } else if tag == VT_LPWSTR {
+        Property::Str(reader.unicode_string()?)
+    } else if tag == VT_LPSTR {
+        let len = reader.len()?;
+        let bytes = reader.take(len)?;
+        let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        Property::AnsiStr(CString::new(&bytes[..end]).map_err(|_| invalid())?)
+    } else if tag == VT_BLOB {
+        let len = reader.len()?;
+        Property::Blob(reader.take(len)?.to_vec())
+    } else if tag == VT_CLSID {
+        Property::Guid(GUID {
+            data1: u32::from_le_bytes(reader.array()?),
+            data2: u16::from_le_bytes(reader.array()?),
+            data3: u16::from_le_bytes(reader.array()?),
+            data4: reader.array()?,
+        })
+    } else if tag == VT_FILETIME {
+        Property::FileTime(u64::from_le_bytes(reader.array()?))
+    } else if tag == VT_VECTOR_LPWSTR {
+        let len = reader.len()?;
+        let mut strings = Vec::with_capacity(len.min(reader.bytes.len() / 4));
+        for _ in 0..len {
+            strings.push(reader.unicode_string()?);
+        }
+        Property::StrVec(strings)
+    } else if tag == VT_VECTOR_I4 {
+        let len = reader.len()?;
+        let elems = reader.take(len.checked_mul(4).ok_or_else(invalid)?)?;
+        Property::I32Vec(
+            elems
+                .chunks_exact(4)
+                .map(|chunk| i32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+                .collect(),
+        )
+    } else if tag == VT_VECTOR_UI4 {
+        let len = reader.len()?;
+        let elems = reader.take(len.checked_mul(4).ok_or_else(invalid)?)?;
+        Property::U32Vec(
+            elems
+                .chunks_exact(4)
+                .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+                .collect(),
+        )
+    } else {
+        Property::Unsupported { vt: tag.0 }
+    };
+    Ok(property)
+}
+
+fn invalid() -> Error {
+    Error::Other(E_INVALIDARG)
+}
+
+#[derive(Default)]
+struct Writer {
+    buf: Vec<u8>,
+}
+
+impl Writer {
+    fn tag(&mut self, tag: VARENUM) -> &mut Self {
+        self.bytes(&tag.0.to_le_bytes()).bytes(&[0, 0])
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) -> &mut Self {
+        self.buf.extend_from_slice(bytes);
+        self
+    }
+
+    /// Write the element count of a counted array.
+    fn len<T>(&mut self, elems: &[T]) -> crate::Result<&mut Self> {
+        let len = u32::try_from(elems.len()).map_err(|_| invalid())?;
+        Ok(self.bytes(&len.to_le_bytes()))
+    }
+
+    /// Pad the buffer to a multiple of four bytes.
+    fn pad(&mut self) -> &mut Self {
+        let padding = (4 - self.buf.len() % 4) % 4;
+        self.buf.resize(self.buf.len() + padding, 0);
+        self
+    }
+
+    /// Write a `UnicodeString`: the length in code units including the null
+    /// terminator, followed by the code units.
+    fn unicode_string(&mut self, s: &WinString) -> crate::Result<&mut Self> {
+        let wide = s.as_slice();
+        let len = u32::try_from(wide.len() + 1).map_err(|_| invalid())?;
+        self.bytes(&len.to_le_bytes());
+        for unit in wide.iter().chain(Some(&0)) {
+            self.bytes(&unit.to_le_bytes());
+        }
+        Ok(self.pad())
+    }
+
+    fn finish(&mut self) -> Vec<u8> {
+        self.pad();
+        std::mem::take(&mut self.buf)
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> crate::Result<&'a [u8]> {
+        if len > self.bytes.len() {
+            return Err(invalid());
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn skip(&mut self, len: usize) -> crate::Result<()> {
+        self.take(len).map(|_| ())
+    }
+
+    fn array<const N: usize>(&mut self) -> crate::Result<[u8; N]> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    /// Read the element count of a counted array.
+    fn len(&mut self) -> crate::Result<usize> {
+        Ok(u32::from_le_bytes(self.array()?) as usize)
+    }
+
+    /// Skip the padding after a value of `len` bytes.
+    fn pad(&mut self, len: usize) {
+        let padding = (4 - len % 4) % 4;
+        self.bytes = &self.bytes[padding.min(self.bytes.len())..];
+    }
+
+    /// Read a `UnicodeString`, stopping at the first null terminator.
+    fn unicode_string(&mut self) -> crate::Result<WinString> {
+        let len = self.len()?;
+        let bytes = self.take(len.checked_mul(2).ok_or_else(invalid)?)?;
+        self.pad(bytes.len());
+        let wide: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|chunk| u16::from_le_bytes([chunk[0], chunk[1]]))
+            .take_while(|&unit| unit != 0)
+            .collect();
+        Ok(WinString::from_wide(&wide))
+    }
+}
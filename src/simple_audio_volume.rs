@@ -1,27 +1,28 @@
+use std::rc::Rc;
+
 use windows::core::GUID;
 
-use windows::Win32::Media::Audio::ISimpleAudioVolume;
+use crate::backend::SimpleAudioVolumeBackend;
 
-use crate::util::as_raw_or_null;
 /// See also: [`ISimpleAudioVolume`](https://docs.microsoft.com/en-us/windows/win32/api/audioclient/nn-audioclient-isimpleaudiovolume)
 #[derive(Debug, Clone)]
 pub struct SimpleAudioVolume {
-    inner: ISimpleAudioVolume,
+    inner: Rc<dyn SimpleAudioVolumeBackend>,
 }
 
 impl SimpleAudioVolume {
-    pub(crate) fn new(inner: ISimpleAudioVolume) -> Self {
+    pub(crate) fn new(inner: Rc<dyn SimpleAudioVolumeBackend>) -> Self {
         Self { inner }
     }
 
     /// See also: [`ISimpleAudioVolume::GetMasterVolume`](https://docs.microsoft.com/en-us/windows/win32/api/audioclient/nf-audioclient-isimpleaudiovolume-getmastervolume)
-    pub fn get_master_volume(&self) -> windows::core::Result<f32> {
-        unsafe { self.inner.GetMasterVolume() }
+    pub fn get_master_volume(&self) -> crate::Result<f32> {
+        self.inner.get_master_volume()
     }
 
     /// See also: [`ISimpleAudioVolume::GetMute`](https://docs.microsoft.com/en-us/windows/win32/api/audioclient/nf-audioclient-isimpleaudiovolume-getmute)
-    pub fn get_mute(&self) -> windows::core::Result<bool> {
-        unsafe { self.inner.GetMute().map(Into::into) }
+    pub fn get_mute(&self) -> crate::Result<bool> {
+        self.inner.get_mute()
     }
 
     /// See also: [`ISimpleAudioVolume::SetMasterVolume`](https://docs.microsoft.com/en-us/windows/win32/api/audioclient/nf-audioclient-isimpleaudiovolume-setmastervolume)
@@ -29,15 +30,12 @@ impl SimpleAudioVolume {
         &self,
         volume_level: f32,
         event_context: Option<&GUID>,
-    ) -> windows::core::Result<()> {
-        unsafe {
-            self.inner
-                .SetMasterVolume(volume_level, as_raw_or_null(event_context))
-        }
+    ) -> crate::Result<()> {
+        self.inner.set_master_volume(volume_level, event_context)
     }
 
     /// See also: [`ISimpleAudioVolume::SetMute`](https://docs.microsoft.com/en-us/windows/win32/api/audioclient/nf-audioclient-isimpleaudiovolume-setmute)
-    pub fn set_mute(&self, mute: bool, event_context: Option<&GUID>) -> windows::core::Result<()> {
-        unsafe { self.inner.SetMute(mute, as_raw_or_null(event_context)) }
+    pub fn set_mute(&self, mute: bool, event_context: Option<&GUID>) -> crate::Result<()> {
+        self.inner.set_mute(mute, event_context)
     }
 }
@@ -2,14 +2,21 @@
 
 use std::borrow::Borrow;
 use std::char::{decode_utf16, REPLACEMENT_CHARACTER};
-
-use std::fmt::{self, Debug};
+use std::error::Error;
+use std::ffi::{OsStr, OsString};
+use std::fmt::{self, Debug, Display};
 use std::fmt::{Formatter, Write};
 use std::hash::{Hash, Hasher};
 use std::ops::Deref;
+#[cfg(windows)]
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
+use std::str::FromStr;
+use std::string::FromUtf16Error;
 
 use windows::core::{PCWSTR, PWSTR};
+#[cfg(windows)]
 use windows::Win32::Foundation::{LocalFree, HLOCAL};
+#[cfg(windows)]
 use windows::Win32::System::Com::CoTaskMemFree;
 
 // impl IntoParam<PCWSTR, CopyType> for PWSTR {
@@ -31,15 +38,52 @@ impl WinStr {
     /// # Safety
     ///
     /// - `pwstr` must point to a valid, null-terminated string.
-    pub(crate) unsafe fn from_pwstr<'a>(pwstr: &'a PWSTR) -> &'a Self {
-        let _len = unsafe { pwstr.to_string().unwrap().chars().count() };
-        let slice = unsafe { std::slice::from_raw_parts(pwstr.0 as *const u16, 2) };
+    pub(crate) unsafe fn from_pwstr(pwstr: &PWSTR) -> &Self {
+        unsafe { Self::from_ptr(pwstr.0) }
+    }
+
+    /// Wrap a wide string that is followed by a null terminator.
+    ///
+    /// This is used by the [`wstr!`](crate::wstr) macro.
+    ///
+    /// # Safety
+    ///
+    /// - `wide` must end with a null terminator, which is not part of the
+    ///   returned string.
+    #[doc(hidden)]
+    pub unsafe fn from_wide_with_nul(wide: &[u16]) -> &Self {
+        debug_assert_eq!(wide.last(), Some(&0));
+        let slice = &wide[..wide.len() - 1];
         unsafe { &*(slice as *const [u16] as *const Self) }
     }
 
-    pub(crate) unsafe fn from_pcwstr<'a>(pcwstr: &'a PCWSTR) -> &'a Self {
-        let _len = unsafe { pcwstr.to_string().unwrap().chars().count() };
-        let slice = unsafe { std::slice::from_raw_parts(pcwstr.0 as *const u16, 2) };
+    /// Wrap the given string pointer.
+    ///
+    /// # Safety
+    ///
+    /// - `pcwstr` must point to a valid, null-terminated string.
+    pub(crate) unsafe fn from_pcwstr(pcwstr: &PCWSTR) -> &Self {
+        unsafe { Self::from_ptr(pcwstr.0) }
+    }
+
+    /// Wrap the string up to, but not including, its null terminator. A null
+    /// pointer is treated as an empty string.
+    ///
+    /// # Safety
+    ///
+    /// - `ptr` must be null or point to a valid, null-terminated string that
+    ///   outlives `'a`.
+    unsafe fn from_ptr<'a>(ptr: *const u16) -> &'a Self {
+        static EMPTY: [u16; 1] = [0];
+        if ptr.is_null() {
+            return unsafe { Self::from_wide_with_nul(&EMPTY) };
+        }
+
+        let mut len = 0;
+        while unsafe { *ptr.add(len) } != 0 {
+            len += 1;
+        }
+        let slice = unsafe { std::slice::from_raw_parts(ptr, len) };
         unsafe { &*(slice as *const [u16] as *const Self) }
     }
 
@@ -71,6 +115,10 @@ impl WinStr {
         self.slice.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.slice.is_empty()
+    }
+
     pub fn as_slice(&self) -> &[u16] {
         &self.slice
     }
@@ -86,25 +134,88 @@ impl WinStr {
     }
 
     pub fn to_winstring(&self) -> WinString {
-        let pwstr = self.as_pwstr();
-        if pwstr.is_null() {
-            panic!("unable to copy string");
-        }
-        unsafe { WinString::from_local_pwstr(pwstr) }
+        WinString::from_wide(self.as_slice())
+    }
+
+    /// Convert to a `String`, failing if the string is not valid UTF-16.
+    pub fn to_string(&self) -> Result<String, FromUtf16Error> {
+        String::from_utf16(self.as_slice())
     }
 
     pub fn to_string_lossy(&self) -> String {
         String::from_utf16_lossy(self.as_slice())
     }
+
+    /// Convert to an `OsString` without loss on Windows.
+    ///
+    /// On other targets, invalid UTF-16 is replaced with
+    /// [`U+FFFD REPLACEMENT CHARACTER`](REPLACEMENT_CHARACTER).
+    pub fn to_os_string(&self) -> OsString {
+        #[cfg(windows)]
+        return OsString::from_wide(self.as_slice());
+        #[cfg(not(windows))]
+        return OsString::from(self.to_string_lossy());
+    }
+
+    /// Iterate over the characters of the string, replacing invalid UTF-16
+    /// with [`U+FFFD REPLACEMENT CHARACTER`](REPLACEMENT_CHARACTER).
+    pub fn chars(&self) -> impl Iterator<Item = char> + '_ {
+        decode_utf16(self.slice.iter().copied()).map(|r| r.unwrap_or(REPLACEMENT_CHARACTER))
+    }
+
+    /// Whether the string starts with `prefix`.
+    pub fn starts_with(&self, prefix: &str) -> bool {
+        let mut slice = self.slice.iter().copied();
+        prefix
+            .encode_utf16()
+            .all(|expected| slice.next() == Some(expected))
+    }
+
+    /// Whether the string ends with `suffix`.
+    pub fn ends_with(&self, suffix: &str) -> bool {
+        let suffix: Vec<u16> = suffix.encode_utf16().collect();
+        self.slice.ends_with(&suffix)
+    }
+
+    /// Compare with `other`, ignoring case.
+    ///
+    /// Device ids and GUIDs are not consistently cased across Windows APIs, so
+    /// this is the comparison to use for ids that come from configuration.
+    pub fn eq_ignore_case(&self, other: &str) -> bool {
+        self.chars()
+            .flat_map(char::to_lowercase)
+            .eq(other.chars().flat_map(char::to_lowercase))
+    }
+}
+
+impl Display for WinStr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.chars().try_for_each(|c| f.write_char(c))
+    }
+}
+
+impl PartialEq<str> for WinStr {
+    fn eq(&self, other: &str) -> bool {
+        self.slice.iter().copied().eq(other.encode_utf16())
+    }
+}
+
+impl PartialEq<&str> for WinStr {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+impl PartialEq<WinStr> for str {
+    fn eq(&self, other: &WinStr) -> bool {
+        other == self
+    }
 }
 
 impl Debug for WinStr {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         f.write_char('"')?;
-        for c in decode_utf16(self.slice.iter().copied())
-            .map(|r| r.unwrap_or(REPLACEMENT_CHARACTER))
-            .flat_map(|c| c.escape_debug())
-        {
+        for c in self.chars().flat_map(|c| c.escape_debug()) {
             f.write_char(c)?;
         }
         f.write_char('"')?;
@@ -147,9 +258,9 @@ impl WinString {
     ///
     /// - Expect to call `CoTaskMemFree(pwstr)` when the string is no longer
     ///   used. This is usually the case for strings that are return values of
-    /// API calls, and should be mentioned in the function documentation. For
-    /// example, see [`IMMDevice::GetId`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/nf-mmdeviceapi-immdevice-getid).
-    /// If this is not the case, create a borrowed [`WinStr`] instead.
+    ///   API calls, and should be mentioned in the function documentation. For
+    ///   example, see [`IMMDevice::GetId`](https://docs.microsoft.com/en-us/windows/win32/api/mmdeviceapi/nf-mmdeviceapi-immdevice-getid).
+    ///   If this is not the case, create a borrowed [`WinStr`] instead.
     pub(crate) unsafe fn from_com_pwstr(pwstr: PWSTR) -> Self {
         Self {
             winstr: unsafe { WinStr::from_pwstr(&pwstr) },
@@ -165,9 +276,10 @@ impl WinString {
     ///
     /// - Expect to call `LocalFree(pwstr)` when the string is no longer
     ///   used. This is usually the case for strings that are return values of
-    /// API calls, and should be mentioned in the function documentation. For
-    /// example, see [`StrDupW`](https://docs.microsoft.com/en-us/windows/win32/api/shlwapi/nf-shlwapi-strdupw).
-    /// If this is not the case, create a borrowed [`WinStr`] instead.
+    ///   API calls, and should be mentioned in the function documentation. For
+    ///   example, see [`StrDupW`](https://docs.microsoft.com/en-us/windows/win32/api/shlwapi/nf-shlwapi-strdupw).
+    ///   If this is not the case, create a borrowed [`WinStr`] instead.
+    #[allow(dead_code)]
     pub(crate) unsafe fn from_local_pwstr(pwstr: PWSTR) -> Self {
         Self {
             winstr: unsafe { WinStr::from_pwstr(&pwstr) },
@@ -175,12 +287,117 @@ impl WinString {
         }
     }
 
+    /// Copy the given wide string into a new buffer owned by Rust.
+    ///
+    /// `wide` should not contain the null terminator, it is appended here.
+    pub(crate) fn from_wide(wide: &[u16]) -> Self {
+        let mut buf = Vec::with_capacity(wide.len() + 1);
+        buf.extend_from_slice(wide);
+        buf.push(0);
+        let buf = Box::into_raw(buf.into_boxed_slice());
+        let slice = std::ptr::slice_from_raw_parts(buf as *const u16, wide.len());
+        Self {
+            winstr: slice as *const WinStr,
+            alloc: StringAlloc::Rust,
+        }
+    }
+
     /// Borrow this string as a `WinStr`.
     pub fn as_winstr(&self) -> &WinStr {
         unsafe { &*self.winstr }
     }
+
+    /// Convert to a `String`, failing if the string is not valid UTF-16.
+    ///
+    /// This shadows [`ToString::to_string`] so that it behaves the same as
+    /// [`WinStr::to_string`].
+    pub fn to_string(&self) -> Result<String, FromUtf16Error> {
+        self.as_winstr().to_string()
+    }
+}
+
+impl From<&str> for WinString {
+    /// Windows reads strings up to their first null character, so the string
+    /// is truncated there. Use [`str::parse`] to reject interior nulls
+    /// instead.
+    fn from(s: &str) -> Self {
+        let wide: Vec<u16> = s.encode_utf16().take_while(|&x| x != 0).collect();
+        Self::from_wide(&wide)
+    }
 }
 
+impl From<String> for WinString {
+    /// The string is truncated at its first null character, like
+    /// `From<&str>`.
+    fn from(s: String) -> Self {
+        Self::from(s.as_str())
+    }
+}
+
+impl From<&String> for WinString {
+    fn from(s: &String) -> Self {
+        Self::from(s.as_str())
+    }
+}
+
+impl From<&OsStr> for WinString {
+    /// On targets other than Windows, invalid Unicode is replaced with
+    /// [`U+FFFD REPLACEMENT CHARACTER`](REPLACEMENT_CHARACTER). The string is
+    /// truncated at its first null character.
+    fn from(s: &OsStr) -> Self {
+        #[cfg(windows)]
+        let wide: Vec<u16> = s.encode_wide().take_while(|&x| x != 0).collect();
+        #[cfg(not(windows))]
+        let wide: Vec<u16> = s
+            .to_string_lossy()
+            .encode_utf16()
+            .take_while(|&x| x != 0)
+            .collect();
+        Self::from_wide(&wide)
+    }
+}
+
+impl From<&WinStr> for WinString {
+    fn from(s: &WinStr) -> Self {
+        s.to_winstring()
+    }
+}
+
+impl FromStr for WinString {
+    type Err = InteriorNulError;
+
+    /// Convert `s`, failing if it contains a null character that Windows
+    /// would stop reading at.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.find('\0') {
+            Some(position) => Err(InteriorNulError { position }),
+            None => Ok(Self::from(s)),
+        }
+    }
+}
+
+/// The error of parsing a [`WinString`] from a string that contains a null
+/// character.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InteriorNulError {
+    position: usize,
+}
+
+impl InteriorNulError {
+    /// The byte offset of the first null character.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl Display for InteriorNulError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "null character at byte {}", self.position)
+    }
+}
+
+impl Error for InteriorNulError {}
+
 impl Deref for WinString {
     type Target = WinStr;
 
@@ -201,6 +418,12 @@ impl Borrow<WinStr> for WinString {
     }
 }
 
+impl Display for WinString {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(self.as_winstr(), f)
+    }
+}
+
 impl Debug for WinString {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(f, "{:?}", self.as_winstr())
@@ -225,11 +448,23 @@ impl PartialEq<WinStr> for WinString {
     }
 }
 
+impl PartialEq<str> for WinString {
+    fn eq(&self, other: &str) -> bool {
+        self.as_winstr() == other
+    }
+}
+
+impl PartialEq<&str> for WinString {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_winstr() == *other
+    }
+}
+
 impl Eq for WinString {}
 
 impl PartialOrd for WinString {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.as_winstr().partial_cmp(other.as_winstr())
+        Some(self.cmp(other))
     }
 }
 
@@ -254,16 +489,52 @@ impl Hash for WinString {
 impl Drop for WinString {
     fn drop(&mut self) {
         match &self.alloc {
+            // Only strings handed out by Windows use these allocators. They are
+            // gated so that binaries using a fake backend link on other targets.
+            #[cfg(windows)]
             StringAlloc::Com => unsafe {
                 CoTaskMemFree(Some(self.as_pwstr().0 as _));
             },
+            #[cfg(windows)]
             StringAlloc::Local => unsafe {
                 LocalFree(HLOCAL(self.as_pwstr().0 as _)).unwrap();
             },
+            #[cfg(not(windows))]
+            StringAlloc::Com | StringAlloc::Local => unreachable!("no Windows allocator"),
+            StringAlloc::Rust => unsafe {
+                let len = self.len() + 1;
+                drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(
+                    self.as_pwstr().0,
+                    len,
+                )));
+            },
         }
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for WinStr {
+    /// Serialized as a string, replacing invalid UTF-16 with
+    /// [`U+FFFD REPLACEMENT CHARACTER`](REPLACEMENT_CHARACTER).
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for WinString {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.as_winstr().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for WinString {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::from)
+    }
+}
+
 // Safety: The pointer contained by a WinString is guaranteed to be unique.
 unsafe impl Send for WinString {}
 unsafe impl Sync for WinString {}
@@ -271,5 +542,94 @@ unsafe impl Sync for WinString {}
 #[derive(Debug)]
 enum StringAlloc {
     Com,
+    #[allow(dead_code)]
     Local,
+    Rust,
+}
+
+/// Create a `&'static WinStr` from a string literal at compile time.
+///
+/// ```
+/// use win32_coreaudio::{string::WinStr, wstr};
+///
+/// let id: &WinStr = wstr!("{0.0.0.00000000}.{5ad9b2ab-9d0a-4d3b-8c5b-6a0b1f1d7b3e}");
+/// assert_eq!(id.len(), 55);
+/// assert!(id.starts_with("{0.0.0.00000000}"));
+/// ```
+///
+/// A literal with a null character fails to compile:
+///
+/// ```compile_fail
+/// let id = win32_coreaudio::wstr!("a\0b");
+/// ```
+#[macro_export]
+macro_rules! wstr {
+    ($s:literal) => {{
+        const LEN: usize = $crate::string::utf16_len($s) + 1;
+        static WIDE: [u16; LEN] = $crate::string::encode_utf16_with_nul::<LEN>($s);
+        unsafe { $crate::string::WinStr::from_wide_with_nul(&WIDE) }
+    }};
+}
+
+/// The number of UTF-16 code units needed to encode `s`.
+#[doc(hidden)]
+pub const fn utf16_len(s: &str) -> usize {
+    let bytes = s.as_bytes();
+    let mut len = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let (ch, width) = decode_utf8(bytes, i);
+        len += if ch >= 0x10000 { 2 } else { 1 };
+        i += width;
+    }
+    len
+}
+
+/// Encode `s` as UTF-16 followed by a null terminator. `N` must be
+/// `utf16_len(s) + 1`, and `s` must not contain a null character.
+#[doc(hidden)]
+pub const fn encode_utf16_with_nul<const N: usize>(s: &str) -> [u16; N] {
+    let bytes = s.as_bytes();
+    let mut wide = [0; N];
+    let mut i = 0;
+    let mut j = 0;
+    while i < bytes.len() {
+        let (ch, width) = decode_utf8(bytes, i);
+        assert!(ch != 0, "string contains a null character");
+        if ch >= 0x10000 {
+            let ch = ch - 0x10000;
+            wide[j] = 0xD800 | (ch >> 10) as u16;
+            wide[j + 1] = 0xDC00 | (ch & 0x3FF) as u16;
+            j += 2;
+        } else {
+            wide[j] = ch as u16;
+            j += 1;
+        }
+        i += width;
+    }
+    assert!(j + 1 == N, "buffer length does not match the string");
+    wide
+}
+
+/// Decode the code point that starts at `bytes[i]`, which must be valid UTF-8.
+const fn decode_utf8(bytes: &[u8], i: usize) -> (u32, usize) {
+    let b = bytes[i] as u32;
+    if b < 0x80 {
+        (b, 1)
+    } else if b < 0xE0 {
+        (((b & 0x1F) << 6) | (bytes[i + 1] as u32 & 0x3F), 2)
+    } else if b < 0xF0 {
+        (
+            ((b & 0x0F) << 12) | ((bytes[i + 1] as u32 & 0x3F) << 6) | (bytes[i + 2] as u32 & 0x3F),
+            3,
+        )
+    } else {
+        (
+            ((b & 0x07) << 18)
+                | ((bytes[i + 1] as u32 & 0x3F) << 12)
+                | ((bytes[i + 2] as u32 & 0x3F) << 6)
+                | (bytes[i + 3] as u32 & 0x3F),
+            4,
+        )
+    }
 }
@@ -0,0 +1,323 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+
+use windows::core::GUID;
+
+use crate::{
+    bits::{ConnectorDataFlow, ConnectorType, DataFlow, PartType},
+    device::Device,
+    device_topology::{JackDescription, Part, PartControl},
+};
+
+/// Which links [`TopologyGraph::walk`] follows from a part.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalkDirection {
+    /// Follow the signal to the parts that it flows to.
+    Downstream,
+    /// Follow the signal back to the parts that it comes from.
+    Upstream,
+    /// Follow every link, which yields everything that is reachable.
+    Both,
+}
+
+/// The connector specific properties of a [`TopologyNode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConnectorDetails {
+    pub connector_type: ConnectorType,
+    pub data_flow: ConnectorDataFlow,
+}
+
+/// An owned snapshot of a [`Part`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TopologyNode {
+    /// Unique across all device topologies.
+    pub global_id: String,
+    /// The device whose topology the part belongs to.
+    pub device_id: String,
+    pub local_id: u32,
+    pub name: String,
+    pub part_type: PartType,
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            serialize_with = "crate::guid::serialize_guid",
+            deserialize_with = "crate::guid::deserialize_guid"
+        )
+    )]
+    pub sub_type: GUID,
+    /// `None` unless the part is a connector.
+    pub connector: Option<ConnectorDetails>,
+    pub controls: Vec<PartControl>,
+    pub jacks: Vec<JackDescription>,
+}
+
+impl TopologyNode {
+    /// Take a snapshot of `part`.
+    pub fn from_part(part: &Part) -> crate::Result<Self> {
+        let part_type = part.get_part_type()?;
+        let connector = match part_type {
+            PartType::Connector => {
+                let connector = part.as_connector()?;
+                Some(ConnectorDetails {
+                    connector_type: connector.get_type()?,
+                    data_flow: connector.get_data_flow()?,
+                })
+            }
+            _ => None,
+        };
+        Ok(Self {
+            global_id: part.get_global_id()?.to_string_lossy(),
+            device_id: part
+                .get_topology_object()?
+                .get_device_id()?
+                .to_string_lossy(),
+            local_id: part.get_local_id()?,
+            name: part.get_name()?.to_string_lossy(),
+            part_type,
+            sub_type: part.get_sub_type()?,
+            connector,
+            controls: part.controls()?,
+            jacks: part.jack_descriptions()?,
+        })
+    }
+}
+
+/// How two nodes of a [`TopologyGraph`] are related.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EdgeKind {
+    /// A link between two parts of the same device topology.
+    Link,
+    /// A connection between connectors of two device topologies.
+    Connection,
+}
+
+/// A directed edge of a [`TopologyGraph`], in the direction of the signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TopologyEdge {
+    /// The index of the node that the signal comes from.
+    pub from: usize,
+    /// The index of the node that the signal flows to.
+    pub to: usize,
+    pub kind: EdgeKind,
+}
+
+/// An owned graph of the parts of one or more device topologies.
+///
+/// The graph holds no COM pointers, so it can be built by hand, stored,
+/// serialized and rendered with [`to_dot`](Self::to_dot).
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TopologyGraph {
+    pub nodes: Vec<TopologyNode>,
+    /// Each edge is listed once, with indices into `nodes`.
+    pub edges: Vec<TopologyEdge>,
+}
+
+impl TopologyGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Walk the signal path from the endpoint `device` to its adapter, which
+    /// is upstream for render endpoints and downstream for capture
+    /// endpoints.
+    pub fn from_device(device: &Device) -> crate::Result<Self> {
+        let direction = match device.data_flow()? {
+            DataFlow::Render => WalkDirection::Upstream,
+            DataFlow::Capture => WalkDirection::Downstream,
+            DataFlow::Unknown(_) => WalkDirection::Both,
+        };
+        let topology = device.activate_device_topology()?;
+        let mut start = Vec::new();
+        for connector in topology.connectors()? {
+            start.push(connector.part()?);
+        }
+        for subunit in topology.subunits()? {
+            start.push(subunit.part());
+        }
+        Self::walk(&start, direction)
+    }
+
+    /// Collect the parts that are reachable from `start` by following links
+    /// and connections in `direction`.
+    pub fn walk(start: &[Part], direction: WalkDirection) -> crate::Result<Self> {
+        let mut walk = Walk {
+            graph: Self::new(),
+            indices: HashMap::new(),
+            queue: VecDeque::new(),
+        };
+        for part in start {
+            walk.visit(part)?;
+        }
+        while let Some((index, part)) = walk.queue.pop_front() {
+            if direction != WalkDirection::Upstream {
+                for next in part.outgoing_parts()? {
+                    let next = walk.visit(&next)?;
+                    walk.graph.add_edge(index, next, EdgeKind::Link);
+                }
+            }
+            if direction != WalkDirection::Downstream {
+                for previous in part.incoming_parts()? {
+                    let previous = walk.visit(&previous)?;
+                    walk.graph.add_edge(previous, index, EdgeKind::Link);
+                }
+            }
+
+            let upstream = match walk.graph.nodes[index].connector {
+                Some(details) => details.data_flow == ConnectorDataFlow::In,
+                None => continue,
+            };
+            let follow = match direction {
+                WalkDirection::Downstream => !upstream,
+                WalkDirection::Upstream => upstream,
+                WalkDirection::Both => true,
+            };
+            let connector = part.as_connector()?;
+            if follow && connector.is_connected()? {
+                let peer = walk.visit(&connector.get_connected_to()?.part()?)?;
+                if upstream {
+                    walk.graph.add_edge(peer, index, EdgeKind::Connection);
+                } else {
+                    walk.graph.add_edge(index, peer, EdgeKind::Connection);
+                }
+            }
+        }
+        Ok(walk.graph)
+    }
+
+    /// Add an edge unless the graph already has it.
+    pub fn add_edge(&mut self, from: usize, to: usize, kind: EdgeKind) {
+        let edge = TopologyEdge { from, to, kind };
+        if !self.edges.contains(&edge) {
+            self.edges.push(edge);
+        }
+    }
+
+    /// The index of the node with the given global id.
+    pub fn position(&self, global_id: &str) -> Option<usize> {
+        self.nodes.iter().position(|x| x.global_id == global_id)
+    }
+
+    /// The edges that leave the node at `index`.
+    pub fn outgoing(&self, index: usize) -> impl Iterator<Item = &TopologyEdge> + '_ {
+        self.edges.iter().filter(move |x| x.from == index)
+    }
+
+    /// The edges that enter the node at `index`.
+    pub fn incoming(&self, index: usize) -> impl Iterator<Item = &TopologyEdge> + '_ {
+        self.edges.iter().filter(move |x| x.to == index)
+    }
+
+    /// The ids of the devices that the nodes belong to, in the order in which
+    /// they first appear.
+    pub fn device_ids(&self) -> Vec<&str> {
+        let mut ids: Vec<&str> = Vec::new();
+        for node in &self.nodes {
+            if !ids.contains(&node.device_id.as_str()) {
+                ids.push(&node.device_id);
+            }
+        }
+        ids
+    }
+
+    /// Every hardware control, with the node that it belongs to.
+    pub fn controls(&self) -> impl Iterator<Item = (&TopologyNode, &PartControl)> + '_ {
+        self.nodes
+            .iter()
+            .flat_map(|node| node.controls.iter().map(move |x| (node, x)))
+    }
+
+    /// Every jack, with the connector that it belongs to.
+    pub fn jacks(&self) -> impl Iterator<Item = (&TopologyNode, &JackDescription)> + '_ {
+        self.nodes
+            .iter()
+            .flat_map(|node| node.jacks.iter().map(move |x| (node, x)))
+    }
+
+    /// Render the graph in the Graphviz DOT language, with one cluster per
+    /// device and dashed edges for connections.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph topology {\n");
+        for (cluster, device_id) in self.device_ids().into_iter().enumerate() {
+            let _ = writeln!(dot, "    subgraph cluster_{} {{", cluster);
+            let _ = writeln!(dot, "        label=\"{}\";", escape(device_id));
+            for (index, node) in self.nodes.iter().enumerate() {
+                if node.device_id != device_id {
+                    continue;
+                }
+                let shape = match node.connector {
+                    Some(_) => "box",
+                    None => "ellipse",
+                };
+                let mut label = escape(&node.name);
+                if node.name.is_empty() {
+                    label = format!("{:?}", node.sub_type);
+                }
+                for control in &node.controls {
+                    label.push_str("\\n");
+                    label.push_str(&control_label(control));
+                }
+                let _ = writeln!(
+                    dot,
+                    "        n{} [label=\"{}\", shape={}];",
+                    index, label, shape
+                );
+            }
+            dot.push_str("    }\n");
+        }
+        for edge in &self.edges {
+            let style = match edge.kind {
+                EdgeKind::Link => "",
+                EdgeKind::Connection => " [style=dashed]",
+            };
+            let _ = writeln!(dot, "    n{} -> n{}{};", edge.from, edge.to, style);
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+struct Walk {
+    graph: TopologyGraph,
+    indices: HashMap<String, usize>,
+    queue: VecDeque<(usize, Part)>,
+}
+
+impl Walk {
+    /// The index of the node of `part`, adding the node and queueing the part
+    /// if it has not been visited yet.
+    fn visit(&mut self, part: &Part) -> crate::Result<usize> {
+        let global_id = part.get_global_id()?.to_string_lossy();
+        if let Some(&index) = self.indices.get(&global_id) {
+            return Ok(index);
+        }
+        let index = self.graph.nodes.len();
+        self.graph.nodes.push(TopologyNode::from_part(part)?);
+        self.indices.insert(global_id, index);
+        self.queue.push_back((index, part.clone()));
+        Ok(index)
+    }
+}
+
+fn control_label(control: &PartControl) -> String {
+    match control {
+        PartControl::Volume(levels) => {
+            let levels: Vec<String> = levels
+                .iter()
+                .map(|x| format!("{:.1}", x.level_db))
+                .collect();
+            format!("volume {} dB", levels.join("/"))
+        }
+        PartControl::Mute(muted) => format!("mute {}", muted),
+        PartControl::AutoGainControl(enabled) => format!("agc {}", enabled),
+        PartControl::Loudness(enabled) => format!("loudness {}", enabled),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
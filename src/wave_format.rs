@@ -0,0 +1,234 @@
+//! Platform-independent `WAVEFORMATEX` and `WAVEFORMATEXTENSIBLE` layouts.
+//!
+//! The audio engine stores the formats of an endpoint as `VT_BLOB`
+//! properties that contain one of these structures, see
+//! [`Device::device_format`](crate::Device::device_format).
+
+use std::convert::TryFrom;
+
+use windows::core::GUID;
+use windows::Win32::{
+    Foundation::E_INVALIDARG,
+    Media::{
+        Audio::WAVE_FORMAT_PCM,
+        KernelStreaming::{KSDATAFORMAT_SUBTYPE_PCM, WAVE_FORMAT_EXTENSIBLE},
+    },
+};
+
+use crate::{bits::ChannelMask, Error};
+
+/// `WAVE_FORMAT_IEEE_FLOAT` from `mmreg.h`.
+const WAVE_FORMAT_IEEE_FLOAT: u32 = 3;
+
+/// `KSDATAFORMAT_SUBTYPE_IEEE_FLOAT` from `ksmedia.h`.
+const KSDATAFORMAT_SUBTYPE_IEEE_FLOAT: GUID =
+    GUID::from_u128(0x00000003_0000_0010_8000_00aa00389b71);
+
+/// The size of a `WAVEFORMATEX` including `cbSize`.
+const WAVEFORMATEX_LEN: usize = 18;
+
+/// The size of the fields that `WAVEFORMATEXTENSIBLE` adds to `WAVEFORMATEX`.
+const EXTENSIBLE_LEN: u16 = 22;
+
+/// An audio format, as described by a `WAVEFORMATEX` or
+/// `WAVEFORMATEXTENSIBLE` structure.
+///
+/// See also: [`WAVEFORMATEX`](https://docs.microsoft.com/en-us/windows/win32/api/mmreg/ns-mmreg-waveformatex),
+/// [`WAVEFORMATEXTENSIBLE`](https://docs.microsoft.com/en-us/windows/win32/api/mmreg/ns-mmreg-waveformatextensible)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WaveFormat {
+    pub channels: u16,
+    pub samples_per_sec: u32,
+    pub avg_bytes_per_sec: u32,
+    pub block_align: u16,
+    pub bits_per_sample: u16,
+    pub encoding: WaveEncoding,
+}
+
+/// The sample encoding of a [`WaveFormat`], determined by its format tag.
+///
+/// Every variant keeps the bytes that `cbSize` declares beyond the fields of
+/// its structure in `extra`, so that formats survive a round trip through
+/// [`WaveFormat::from_bytes`] and [`WaveFormat::to_bytes`] unchanged. The
+/// exception is a 16-byte `PCMWAVEFORMAT`, which comes back as an 18-byte
+/// `WAVEFORMATEX` because `cbSize` is always written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WaveEncoding {
+    /// Integer PCM samples (`WAVE_FORMAT_PCM`).
+    Pcm { extra: Vec<u8> },
+    /// Floating-point samples (`WAVE_FORMAT_IEEE_FLOAT`).
+    IeeeFloat { extra: Vec<u8> },
+    /// `WAVE_FORMAT_EXTENSIBLE`, which describes the encoding with a GUID and
+    /// assigns the channels to speakers.
+    Extensible {
+        /// The number of bits of precision in each sample, which may be less
+        /// than the container size in `bits_per_sample`.
+        valid_bits_per_sample: u16,
+        /// The raw `dwChannelMask`, including bits that have no
+        /// [`ChannelMask`] constant, such as `SPEAKER_ALL`.
+        channel_mask: ChannelMask,
+        /// The `KSDATAFORMAT_SUBTYPE_XXX` GUID of the encoding.
+        sub_format: GUID,
+        extra: Vec<u8>,
+    },
+    /// Any other format tag, together with the format-specific bytes that
+    /// follow the `WAVEFORMATEX` header.
+    Other { format_tag: u16, extra: Vec<u8> },
+}
+
+impl WaveFormat {
+    /// Parse a `WAVEFORMATEX` or `WAVEFORMATEXTENSIBLE`.
+    ///
+    /// The 16-byte `PCMWAVEFORMAT` that lacks `cbSize` is accepted as well.
+    /// Fails with `E_INVALIDARG` if the data is shorter than the structure
+    /// that it declares.
+    pub fn from_bytes(bytes: &[u8]) -> crate::Result<Self> {
+        let u16_at = |offset: usize| -> crate::Result<u16> {
+            bytes
+                .get(offset..offset + 2)
+                .map(|b| u16::from_le_bytes([b[0], b[1]]))
+                .ok_or(Error::Other(E_INVALIDARG))
+        };
+        let u32_at = |offset: usize| -> crate::Result<u32> {
+            bytes
+                .get(offset..offset + 4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .ok_or(Error::Other(E_INVALIDARG))
+        };
+
+        let format_tag = u16_at(0)?;
+        let extra_len =
+            if bytes.len() < WAVEFORMATEX_LEN && u32::from(format_tag) == WAVE_FORMAT_PCM {
+                0
+            } else {
+                u16_at(16)? as usize
+            };
+        let extra = bytes
+            .get(WAVEFORMATEX_LEN.min(bytes.len())..)
+            .and_then(|extra| extra.get(..extra_len))
+            .ok_or(Error::Other(E_INVALIDARG))?;
+
+        let encoding = match u32::from(format_tag) {
+            WAVE_FORMAT_PCM => WaveEncoding::Pcm {
+                extra: extra.to_vec(),
+            },
+            WAVE_FORMAT_EXTENSIBLE => {
+                if extra.len() < EXTENSIBLE_LEN as usize {
+                    return Err(Error::Other(E_INVALIDARG));
+                }
+                let mut data4 = [0; 8];
+                data4.copy_from_slice(&bytes[32..40]);
+                WaveEncoding::Extensible {
+                    valid_bits_per_sample: u16_at(18)?,
+                    channel_mask: ChannelMask::from_bits_retain(u32_at(20)?),
+                    sub_format: GUID {
+                        data1: u32_at(24)?,
+                        data2: u16_at(28)?,
+                        data3: u16_at(30)?,
+                        data4,
+                    },
+                    extra: extra[EXTENSIBLE_LEN as usize..].to_vec(),
+                }
+            }
+            WAVE_FORMAT_IEEE_FLOAT => WaveEncoding::IeeeFloat {
+                extra: extra.to_vec(),
+            },
+            _ => WaveEncoding::Other {
+                format_tag,
+                extra: extra.to_vec(),
+            },
+        };
+
+        Ok(Self {
+            channels: u16_at(2)?,
+            samples_per_sec: u32_at(4)?,
+            avg_bytes_per_sec: u32_at(8)?,
+            block_align: u16_at(12)?,
+            bits_per_sample: u16_at(14)?,
+            encoding,
+        })
+    }
+
+    /// Serialize as a `WAVEFORMATEX`, or a `WAVEFORMATEXTENSIBLE` for
+    /// [`WaveEncoding::Extensible`].
+    ///
+    /// `cbSize` is always written, also for formats that were parsed from a
+    /// `PCMWAVEFORMAT`.
+    ///
+    /// Fails with `E_INVALIDARG` if the extra bytes do not fit in `cbSize`.
+    pub fn to_bytes(&self) -> crate::Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(WAVEFORMATEX_LEN + EXTENSIBLE_LEN as usize);
+        bytes.extend_from_slice(&self.format_tag().to_le_bytes());
+        bytes.extend_from_slice(&self.channels.to_le_bytes());
+        bytes.extend_from_slice(&self.samples_per_sec.to_le_bytes());
+        bytes.extend_from_slice(&self.avg_bytes_per_sec.to_le_bytes());
+        bytes.extend_from_slice(&self.block_align.to_le_bytes());
+        bytes.extend_from_slice(&self.bits_per_sample.to_le_bytes());
+        let cb_size = |len: usize| u16::try_from(len).map_err(|_| Error::Other(E_INVALIDARG));
+        match &self.encoding {
+            WaveEncoding::Pcm { extra }
+            | WaveEncoding::IeeeFloat { extra }
+            | WaveEncoding::Other { extra, .. } => {
+                bytes.extend_from_slice(&cb_size(extra.len())?.to_le_bytes());
+                bytes.extend_from_slice(extra);
+            }
+            WaveEncoding::Extensible {
+                valid_bits_per_sample,
+                channel_mask,
+                sub_format,
+                extra,
+            } => {
+                let len = cb_size(EXTENSIBLE_LEN as usize + extra.len())?;
+                bytes.extend_from_slice(&len.to_le_bytes());
+                bytes.extend_from_slice(&valid_bits_per_sample.to_le_bytes());
+                bytes.extend_from_slice(&channel_mask.bits().to_le_bytes());
+                bytes.extend_from_slice(&sub_format.data1.to_le_bytes());
+                bytes.extend_from_slice(&sub_format.data2.to_le_bytes());
+                bytes.extend_from_slice(&sub_format.data3.to_le_bytes());
+                bytes.extend_from_slice(&sub_format.data4);
+                bytes.extend_from_slice(extra);
+            }
+        }
+        Ok(bytes)
+    }
+
+    /// The `wFormatTag` of the structure.
+    pub fn format_tag(&self) -> u16 {
+        match &self.encoding {
+            WaveEncoding::Pcm { .. } => WAVE_FORMAT_PCM as u16,
+            WaveEncoding::IeeeFloat { .. } => WAVE_FORMAT_IEEE_FLOAT as u16,
+            WaveEncoding::Extensible { .. } => WAVE_FORMAT_EXTENSIBLE as u16,
+            WaveEncoding::Other { format_tag, .. } => *format_tag,
+        }
+    }
+
+    /// Whether the samples are integer PCM, either directly or through the
+    /// extensible sub-format.
+    pub fn is_pcm(&self) -> bool {
+        match &self.encoding {
+            WaveEncoding::Pcm { .. } => true,
+            WaveEncoding::Extensible { sub_format, .. } => *sub_format == KSDATAFORMAT_SUBTYPE_PCM,
+            _ => false,
+        }
+    }
+
+    /// Whether the samples are floating-point, either directly or through the
+    /// extensible sub-format.
+    pub fn is_ieee_float(&self) -> bool {
+        match &self.encoding {
+            WaveEncoding::IeeeFloat { .. } => true,
+            WaveEncoding::Extensible { sub_format, .. } => {
+                *sub_format == KSDATAFORMAT_SUBTYPE_IEEE_FLOAT
+            }
+            _ => false,
+        }
+    }
+
+    /// The speaker positions of the channels, if the format assigns them.
+    pub fn channel_mask(&self) -> Option<ChannelMask> {
+        match &self.encoding {
+            WaveEncoding::Extensible { channel_mask, .. } => Some(*channel_mask),
+            _ => None,
+        }
+    }
+}
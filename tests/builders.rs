@@ -0,0 +1,221 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use win32_coreaudio::{
+    backend::fake::{FakeAudioSystem, FakeDevice, FakeSession},
+    string::WinString,
+    AudioSessionControl, AudioSessionDisconnectReason, AudioSessionEvents,
+    AudioSessionEventsBuilder, AudioSessionState, AudioVolumeDuckNotification,
+    AudioVolumeDuckNotificationBuilder, DataFlow, DeviceRole, DeviceState, Error,
+    NotificationClient, NotificationClientBuilder, NotificationData, Property,
+    DEVICE_FRIENDLY_NAME,
+};
+use windows::core::GUID;
+
+type Log = Rc<RefCell<Vec<String>>>;
+
+/// A closure that logs `name` and the debug output of its arguments.
+macro_rules! logger {
+    ($log:expr, $name:literal, |$($arg:ident),*|) => {{
+        let log = $log.clone();
+        move |$($arg),*| {
+            log.borrow_mut().push(format!("{} {:?}", $name, ($($arg,)*)));
+            Ok(())
+        }
+    }};
+}
+
+fn entries(log: &Log) -> Vec<String> {
+    log.borrow().clone()
+}
+
+#[test]
+fn notification_client_routes_each_callback() {
+    let log = Log::default();
+    let client = NotificationClientBuilder::new()
+        .on_default_device_changed(logger!(log, "default", |flow, role, id|))
+        .on_device_added(logger!(log, "added", |id|))
+        .on_device_removed(logger!(log, "removed", |id|))
+        .on_device_state_changed(logger!(log, "state", |id, state|))
+        .on_property_value_changed(logger!(log, "property", |id, key|))
+        .build();
+
+    let system = FakeAudioSystem::new();
+    let _handle = system
+        .device_enumerator()
+        .register_endpoint_notification(client)
+        .unwrap();
+    system.add_device(FakeDevice::new("usb", DataFlow::Render));
+    system.set_default_device(DataFlow::Render, DeviceRole::Console, "usb");
+    system.set_device_state("usb", DeviceState::Disabled);
+    system.set_property("usb", DEVICE_FRIENDLY_NAME, Property::Str("USB".into()));
+    system.remove_device("usb");
+
+    assert_eq!(
+        entries(&log),
+        [
+            r#"added ("usb",)"#.to_string(),
+            r#"default (Render, Console, "usb")"#.to_string(),
+            r#"state ("usb", Disabled)"#.to_string(),
+            format!(r#"property ("usb", {:?})"#, DEVICE_FRIENDLY_NAME),
+            r#"removed ("usb",)"#.to_string(),
+        ]
+    );
+}
+
+#[test]
+fn notification_client_ignores_callbacks_without_closure() {
+    let client = NotificationClientBuilder::new().build();
+    let id = WinString::from("usb");
+    assert_eq!(client.on_device_added(&id), Ok(()));
+    assert_eq!(
+        client.on_device_state_changed(&id, DeviceState::Active),
+        Ok(())
+    );
+}
+
+#[test]
+fn closure_errors_are_returned() {
+    let client = NotificationClientBuilder::new()
+        .on_device_removed(|_| Err(Error::DeviceNotFound))
+        .build();
+    assert_eq!(
+        client.on_device_removed(&WinString::from("usb")),
+        Err(Error::DeviceNotFound)
+    );
+
+    let events = AudioSessionEventsBuilder::new()
+        .on_state_changed(|_| Err(Error::DeviceInvalidated))
+        .build();
+    assert_eq!(
+        events.on_state_changed(AudioSessionState::Active),
+        Err(Error::DeviceInvalidated)
+    );
+}
+
+#[test]
+fn session_events_route_each_callback() {
+    let log = Log::default();
+    let events = AudioSessionEventsBuilder::new()
+        .on_channel_volume_changed(logger!(log, "channel", |levels, channel, context|))
+        .on_display_name_changed(logger!(log, "name", |name, context|))
+        .on_grouping_param_changed(logger!(log, "grouping", |param, context|))
+        .on_icon_path_changed(logger!(log, "icon", |path, context|))
+        .on_session_disconnected(logger!(log, "disconnected", |reason|))
+        .on_simple_volume_changed(logger!(log, "volume", |volume, mute, context|))
+        .on_state_changed(logger!(log, "state", |state|))
+        .build();
+
+    let context = GUID::from_u128(1);
+    events
+        .on_channel_volume_changed(&[0.5, 1.0], 1, None)
+        .unwrap();
+    events
+        .on_display_name_changed(&WinString::from("Game"), Some(&context))
+        .unwrap();
+    events.on_grouping_param_changed(None, None).unwrap();
+    events
+        .on_icon_path_changed(&WinString::from("game.ico"), None)
+        .unwrap();
+    events
+        .on_session_disconnected(AudioSessionDisconnectReason::DeviceRemoval)
+        .unwrap();
+    events.on_simple_volume_changed(0.25, true, None).unwrap();
+    events.on_state_changed(AudioSessionState::Expired).unwrap();
+
+    assert_eq!(
+        entries(&log),
+        [
+            "channel ([0.5, 1.0], 1, None)".to_string(),
+            format!(r#"name ("Game", Some({:?}))"#, context),
+            "grouping (None, None)".to_string(),
+            r#"icon ("game.ico", None)"#.to_string(),
+            "disconnected (DeviceRemoval,)".to_string(),
+            "volume (0.25, true, None)".to_string(),
+            "state (Expired,)".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn duck_notification_routes_each_callback() {
+    let log = Log::default();
+    let notification = AudioVolumeDuckNotificationBuilder::new()
+        .on_volume_duck_notification(logger!(log, "duck", |id, count|))
+        .on_volume_unduck_notification(logger!(log, "unduck", |id|))
+        .build();
+
+    let id = WinString::from("call");
+    notification.on_volume_duck_notification(&id, 2).unwrap();
+    notification.on_volume_unduck_notification(&id).unwrap();
+
+    assert_eq!(
+        entries(&log),
+        [r#"duck ("call", 2)"#, r#"unduck ("call",)"#]
+    );
+}
+
+#[test]
+#[allow(bare_trait_objects)]
+fn builder_entry_points_on_the_traits() {
+    let log = Log::default();
+    let client = NotificationClient::builder()
+        .on_device_added(logger!(log, "added", |id|))
+        .build();
+    client.on_device_added(&WinString::from("usb")).unwrap();
+
+    let events = <dyn AudioSessionEvents>::builder()
+        .on_state_changed(logger!(log, "state", |state|))
+        .build();
+    events.on_state_changed(AudioSessionState::Active).unwrap();
+
+    let notification = AudioVolumeDuckNotification::builder()
+        .on_volume_unduck_notification(logger!(log, "unduck", |id|))
+        .build();
+    notification
+        .on_volume_unduck_notification(&WinString::from("call"))
+        .unwrap();
+
+    assert_eq!(
+        entries(&log),
+        [
+            r#"added ("usb",)"#,
+            "state (Active,)",
+            r#"unduck ("call",)"#
+        ]
+    );
+}
+
+#[test]
+fn plain_closures_are_callbacks() {
+    let system = FakeAudioSystem::new();
+    system.add_device(FakeDevice::new("speakers", DataFlow::Render));
+    let speakers = system
+        .device_enumerator()
+        .get_device(&WinString::from("speakers"))
+        .unwrap();
+    let log = Log::default();
+
+    let volume = speakers.activate_audio_endpoint_volume().unwrap();
+    let _volume_handle = volume
+        .register_control_change_notify({
+            let log = log.clone();
+            move |data: &NotificationData| log.borrow_mut().push(format!("muted {}", data.muted))
+        })
+        .unwrap();
+    let _session_handle = speakers
+        .activate_audio_session_manager2()
+        .unwrap()
+        .register_session_notification({
+            let log = log.clone();
+            move |session: AudioSessionControl| {
+                log.borrow_mut()
+                    .push(format!("session {}", session.get_display_name().unwrap()))
+            }
+        })
+        .unwrap();
+
+    volume.set_mute(true, None).unwrap();
+    system.add_session("speakers", FakeSession::new("1").display_name("Game"));
+    assert_eq!(entries(&log), ["muted true", "session Game"]);
+}
@@ -0,0 +1,369 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use win32_coreaudio::{
+    backend::fake::{FakeAudioSystem, FakeDevice},
+    string::{WinStr, WinString},
+    BatchHandler, Clock, CoalescingClient, DataFlow, DeviceChangeBatch, DeviceRole, DeviceState,
+    ManualClock, NotificationClient, Presence, PropertyKey, Replay, DEVICE_DESCRIPTION,
+    DEVICE_FRIENDLY_NAME,
+};
+
+const WINDOW: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, PartialEq)]
+enum Delivery {
+    Batch(DeviceChangeBatch),
+    Default(DataFlow, DeviceRole, String),
+}
+
+#[derive(Clone, Default)]
+struct Recorder(Arc<Mutex<Vec<Delivery>>>);
+
+impl Recorder {
+    fn take(&self) -> Vec<Delivery> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+impl BatchHandler for Recorder {
+    fn on_device_changes(&self, batch: &DeviceChangeBatch) -> win32_coreaudio::Result<()> {
+        self.0.lock().unwrap().push(Delivery::Batch(batch.clone()));
+        Ok(())
+    }
+
+    fn on_default_device_changed(
+        &self,
+        data_flow: DataFlow,
+        role: DeviceRole,
+        device_id: &WinStr,
+    ) -> win32_coreaudio::Result<()> {
+        self.0.lock().unwrap().push(Delivery::Default(
+            data_flow,
+            role,
+            device_id.to_string_lossy(),
+        ));
+        Ok(())
+    }
+}
+
+fn client() -> (CoalescingClient<Recorder>, Recorder, ManualClock) {
+    let recorder = Recorder::default();
+    let clock = ManualClock::new();
+    let client = CoalescingClient::with_clock(recorder.clone(), WINDOW, clock.clone());
+    (client, recorder, clock)
+}
+
+fn id(id: &str) -> WinString {
+    WinString::from(id)
+}
+
+fn batch(
+    device_id: &str,
+    presence: Option<Presence>,
+    state: Option<DeviceState>,
+    property_keys: &[PropertyKey],
+    event_count: usize,
+) -> Delivery {
+    Delivery::Batch(DeviceChangeBatch {
+        device_id: device_id.into(),
+        presence,
+        state,
+        property_keys: property_keys.to_vec(),
+        event_count,
+    })
+}
+
+#[test]
+fn batch_is_delivered_when_the_window_expires() {
+    let (client, recorder, clock) = client();
+    client
+        .on_device_state_changed(&id("usb"), DeviceState::Active)
+        .unwrap();
+
+    clock.advance(WINDOW - Duration::from_millis(1));
+    client.flush_due().unwrap();
+    assert_eq!(recorder.take(), []);
+
+    clock.advance(Duration::from_millis(1));
+    client.flush_due().unwrap();
+    assert_eq!(
+        recorder.take(),
+        [batch("usb", None, Some(DeviceState::Active), &[], 1)]
+    );
+
+    // Nothing is left to deliver.
+    client.flush_due().unwrap();
+    assert_eq!(recorder.take(), []);
+    assert_eq!(client.next_deadline(), None);
+}
+
+#[test]
+fn window_starts_with_the_first_notification() {
+    let (client, recorder, clock) = client();
+    client
+        .on_device_state_changed(&id("usb"), DeviceState::Active)
+        .unwrap();
+    clock.advance(WINDOW / 2);
+    client
+        .on_device_state_changed(&id("usb"), DeviceState::Disabled)
+        .unwrap();
+
+    // Later notifications do not extend the window.
+    clock.advance(WINDOW / 2);
+    client.flush_due().unwrap();
+    assert_eq!(
+        recorder.take(),
+        [batch("usb", None, Some(DeviceState::Disabled), &[], 2)]
+    );
+}
+
+#[test]
+fn duplicate_property_keys_are_listed_once() {
+    let (client, recorder, _clock) = client();
+    let usb = id("usb");
+    client
+        .on_property_value_changed(&usb, DEVICE_FRIENDLY_NAME)
+        .unwrap();
+    client
+        .on_property_value_changed(&usb, DEVICE_DESCRIPTION)
+        .unwrap();
+    client
+        .on_property_value_changed(&usb, DEVICE_FRIENDLY_NAME)
+        .unwrap();
+    client.flush_all().unwrap();
+
+    assert_eq!(
+        recorder.take(),
+        [batch(
+            "usb",
+            None,
+            None,
+            &[DEVICE_FRIENDLY_NAME, DEVICE_DESCRIPTION],
+            3
+        )]
+    );
+}
+
+#[test]
+fn next_deadline_is_the_oldest_pending_window() {
+    let (client, recorder, clock) = client();
+    assert_eq!(client.next_deadline(), None);
+
+    let start = clock.now();
+    client.on_device_added(&id("a")).unwrap();
+    assert_eq!(client.next_deadline(), Some(start + WINDOW));
+
+    clock.advance(WINDOW / 2);
+    client.on_device_added(&id("b")).unwrap();
+    client.on_device_removed(&id("a")).unwrap();
+    assert_eq!(client.next_deadline(), Some(start + WINDOW));
+
+    clock.advance(WINDOW / 2);
+    client.flush_due().unwrap();
+    assert_eq!(
+        recorder.take(),
+        [batch("a", Some(Presence::Removed), None, &[], 2)]
+    );
+    assert_eq!(client.next_deadline(), Some(start + WINDOW + WINDOW / 2));
+}
+
+#[test]
+fn added_then_removed_within_a_window_is_one_removal() {
+    let (client, recorder, clock) = client();
+    let usb = id("usb");
+    client.on_device_added(&usb).unwrap();
+    client
+        .on_device_state_changed(&usb, DeviceState::Active)
+        .unwrap();
+    client.on_device_removed(&usb).unwrap();
+    clock.advance(WINDOW);
+    client.flush_due().unwrap();
+
+    assert_eq!(
+        recorder.take(),
+        [batch(
+            "usb",
+            Some(Presence::Removed),
+            Some(DeviceState::Active),
+            &[],
+            3
+        )]
+    );
+}
+
+#[test]
+fn due_batches_are_delivered_by_the_next_notification() {
+    let (client, recorder, clock) = client();
+    client.on_device_added(&id("a")).unwrap();
+    clock.advance(WINDOW);
+    client.on_device_added(&id("b")).unwrap();
+
+    assert_eq!(
+        recorder.take(),
+        [batch("a", Some(Presence::Added), None, &[], 1)]
+    );
+    client.flush_all().unwrap();
+    assert_eq!(
+        recorder.take(),
+        [batch("b", Some(Presence::Added), None, &[], 1)]
+    );
+}
+
+#[test]
+fn default_device_changes_pass_through() {
+    let (client, recorder, _clock) = client();
+    client.on_device_added(&id("usb")).unwrap();
+    client
+        .on_default_device_changed(DataFlow::Render, DeviceRole::Console, &id("usb"))
+        .unwrap();
+    assert_eq!(
+        recorder.take(),
+        [Delivery::Default(
+            DataFlow::Render,
+            DeviceRole::Console,
+            "usb".into()
+        )]
+    );
+}
+
+#[test]
+fn closures_handle_batches() {
+    let count = Arc::new(Mutex::new(0));
+    let handler = {
+        let count = count.clone();
+        move |batch: &DeviceChangeBatch| {
+            *count.lock().unwrap() += batch.event_count;
+            Ok(())
+        }
+    };
+    let client = CoalescingClient::with_clock(handler, WINDOW, ManualClock::new());
+    client.on_device_added(&id("usb")).unwrap();
+    client.on_device_removed(&id("usb")).unwrap();
+    client.flush_all().unwrap();
+    assert_eq!(*count.lock().unwrap(), 2);
+}
+
+#[test]
+fn notifications_from_another_thread_are_flushed_here() {
+    let (client, recorder, clock) = client();
+    let registered = client.clone();
+    thread::spawn(move || registered.on_device_added(&id("usb")).unwrap())
+        .join()
+        .unwrap();
+
+    clock.advance(WINDOW);
+    client.flush_due().unwrap();
+    assert_eq!(
+        recorder.take(),
+        [batch("usb", Some(Presence::Added), None, &[], 1)]
+    );
+}
+
+/// A plain client that is neither `Send` nor `Sync`.
+#[derive(Clone, Default)]
+struct PlainClient(Rc<RefCell<Vec<String>>>);
+
+impl NotificationClient for PlainClient {
+    fn on_default_device_changed(
+        &self,
+        _data_flow: DataFlow,
+        role: DeviceRole,
+        device_id: &WinStr,
+    ) -> win32_coreaudio::Result<()> {
+        self.0
+            .borrow_mut()
+            .push(format!("default {:?} {}", role, device_id));
+        Ok(())
+    }
+
+    fn on_device_added(&self, device_id: &WinStr) -> win32_coreaudio::Result<()> {
+        self.0.borrow_mut().push(format!("added {}", device_id));
+        Ok(())
+    }
+
+    fn on_device_removed(&self, device_id: &WinStr) -> win32_coreaudio::Result<()> {
+        self.0.borrow_mut().push(format!("removed {}", device_id));
+        Ok(())
+    }
+
+    fn on_device_state_changed(
+        &self,
+        device_id: &WinStr,
+        state: DeviceState,
+    ) -> win32_coreaudio::Result<()> {
+        self.0
+            .borrow_mut()
+            .push(format!("state {} {:?}", device_id, state));
+        Ok(())
+    }
+
+    fn on_property_value_changed(
+        &self,
+        device_id: &WinStr,
+        _property_key: PropertyKey,
+    ) -> win32_coreaudio::Result<()> {
+        self.0.borrow_mut().push(format!("property {}", device_id));
+        Ok(())
+    }
+}
+
+#[test]
+fn plain_client_receives_replayed_batches() {
+    let plain = PlainClient::default();
+    let clock = ManualClock::new();
+    let client = CoalescingClient::with_clock(Replay(plain.clone()), WINDOW, clock.clone());
+
+    let usb = id("usb");
+    client.on_device_added(&usb).unwrap();
+    client
+        .on_device_state_changed(&usb, DeviceState::Unplugged)
+        .unwrap();
+    client
+        .on_device_state_changed(&usb, DeviceState::Active)
+        .unwrap();
+    client
+        .on_property_value_changed(&usb, DEVICE_FRIENDLY_NAME)
+        .unwrap();
+    client
+        .on_property_value_changed(&usb, DEVICE_FRIENDLY_NAME)
+        .unwrap();
+    client
+        .on_default_device_changed(DataFlow::Render, DeviceRole::Console, &usb)
+        .unwrap();
+    assert_eq!(*plain.0.borrow(), ["default Console usb"]);
+
+    clock.advance(WINDOW);
+    client.flush_due().unwrap();
+    assert_eq!(
+        *plain.0.borrow(),
+        [
+            "default Console usb",
+            "added usb",
+            "state usb Active",
+            "property usb",
+        ]
+    );
+}
+
+#[test]
+fn wrapping_a_registered_plain_client() {
+    let system = FakeAudioSystem::new();
+    let plain = PlainClient::default();
+    let client = CoalescingClient::wrapping(plain.clone(), Duration::from_secs(60));
+    let _handle = system
+        .device_enumerator()
+        .register_endpoint_notification(client.clone())
+        .unwrap();
+
+    system.add_device(FakeDevice::new("usb", DataFlow::Render));
+    system.set_device_state("usb", DeviceState::Disabled);
+    system.remove_device("usb");
+    assert_eq!(*plain.0.borrow(), Vec::<String>::new());
+
+    client.flush_all().unwrap();
+    assert_eq!(*plain.0.borrow(), ["state usb Disabled", "removed usb"]);
+}
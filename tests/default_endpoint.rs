@@ -0,0 +1,186 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use win32_coreaudio::{
+    backend::{
+        fake::{FakeAudioSystem, FakeDevice},
+        CallbackRegistration, DeviceBackend, DeviceCollectionBackend, DeviceEnumeratorBackend,
+        PolicyConfigBackend,
+    },
+    string::{WinStr, WinString},
+    DataFlow, DataFlowMask, DeviceEnumerator, DeviceRole, DeviceState, DeviceStateMask, Error,
+    NotificationClientBuilder, NotificationClientHandle,
+};
+
+const ROLES: [DeviceRole; 3] = [
+    DeviceRole::Console,
+    DeviceRole::Multimedia,
+    DeviceRole::Communications,
+];
+
+fn system() -> FakeAudioSystem {
+    let system = FakeAudioSystem::new();
+    system.add_device(FakeDevice::new("speakers", DataFlow::Render));
+    system.add_device(FakeDevice::new("headset", DataFlow::Render));
+    system.add_device(FakeDevice::new("hdmi", DataFlow::Render).state(DeviceState::Unplugged));
+    for role in ROLES {
+        system.set_default_device(DataFlow::Render, role, "speakers");
+    }
+    system
+}
+
+fn default_id(enumerator: &DeviceEnumerator, role: DeviceRole) -> String {
+    enumerator
+        .get_default_audio_endpoint(DataFlow::Render, role)
+        .unwrap()
+        .get_id()
+        .unwrap()
+        .to_string_lossy()
+}
+
+type Changes = Rc<RefCell<Vec<(DeviceRole, String)>>>;
+
+/// Records the default device changes that registered clients are told of.
+fn record(enumerator: &DeviceEnumerator) -> (Changes, NotificationClientHandle) {
+    let changes = Rc::new(RefCell::new(Vec::new()));
+    let client = NotificationClientBuilder::new()
+        .on_default_device_changed({
+            let changes = changes.clone();
+            move |_, role, id| {
+                changes.borrow_mut().push((role, id.to_string_lossy()));
+                Ok(())
+            }
+        })
+        .build();
+    let handle = enumerator.register_endpoint_notification(client).unwrap();
+    (changes, handle)
+}
+
+#[test]
+fn set_default_for_one_role() {
+    let enumerator = system().device_enumerator();
+    let (changes, _handle) = record(&enumerator);
+
+    enumerator
+        .set_default_audio_endpoint(&WinString::from("headset"), DeviceRole::Communications)
+        .unwrap();
+
+    assert_eq!(
+        default_id(&enumerator, DeviceRole::Communications),
+        "headset"
+    );
+    assert_eq!(default_id(&enumerator, DeviceRole::Console), "speakers");
+    assert_eq!(default_id(&enumerator, DeviceRole::Multimedia), "speakers");
+    assert_eq!(
+        *changes.borrow(),
+        [(DeviceRole::Communications, "headset".to_string())]
+    );
+}
+
+#[test]
+fn set_default_for_all_roles() {
+    let enumerator = system().device_enumerator();
+    let (changes, _handle) = record(&enumerator);
+
+    enumerator
+        .set_default_audio_endpoint_all_roles(&WinString::from("headset"))
+        .unwrap();
+
+    for role in ROLES {
+        assert_eq!(default_id(&enumerator, role), "headset");
+    }
+    assert_eq!(
+        *changes.borrow(),
+        ROLES.map(|role| (role, "headset".to_string()))
+    );
+}
+
+#[test]
+fn inactive_or_unknown_device_is_rejected() {
+    let enumerator = system().device_enumerator();
+    let (changes, _handle) = record(&enumerator);
+
+    let inactive =
+        enumerator.set_default_audio_endpoint(&WinString::from("hdmi"), DeviceRole::Console);
+    assert!(matches!(inactive, Err(Error::Other(_))));
+    assert_eq!(
+        enumerator.set_default_audio_endpoint_all_roles(&WinString::from("hdmi")),
+        inactive
+    );
+    assert_eq!(
+        enumerator.set_default_audio_endpoint(&WinString::from("missing"), DeviceRole::Console),
+        Err(Error::DeviceNotFound)
+    );
+    assert_eq!(
+        enumerator.set_default_audio_endpoint_all_roles(&WinString::from("missing")),
+        Err(Error::DeviceNotFound)
+    );
+
+    for role in ROLES {
+        assert_eq!(default_id(&enumerator, role), "speakers");
+    }
+    assert_eq!(*changes.borrow(), []);
+}
+
+#[derive(Debug)]
+struct CountingEnumerator {
+    system: FakeAudioSystem,
+    policy_configs: Rc<Cell<usize>>,
+}
+
+impl DeviceEnumeratorBackend for CountingEnumerator {
+    fn enum_audio_endpoints(
+        &self,
+        data_flow_mask: DataFlowMask,
+        state_mask: DeviceStateMask,
+    ) -> win32_coreaudio::Result<Rc<dyn DeviceCollectionBackend>> {
+        self.system.enum_audio_endpoints(data_flow_mask, state_mask)
+    }
+
+    fn get_default_audio_endpoint(
+        &self,
+        data_flow: DataFlow,
+        role: DeviceRole,
+    ) -> win32_coreaudio::Result<Rc<dyn DeviceBackend>> {
+        self.system.get_default_audio_endpoint(data_flow, role)
+    }
+
+    fn get_device(&self, device_id: &WinStr) -> win32_coreaudio::Result<Rc<dyn DeviceBackend>> {
+        self.system.get_device(device_id)
+    }
+
+    fn register_endpoint_notification(
+        &self,
+        notification_client: Box<dyn win32_coreaudio::NotificationClient>,
+    ) -> win32_coreaudio::Result<Rc<dyn CallbackRegistration>> {
+        self.system
+            .register_endpoint_notification(notification_client)
+    }
+
+    fn policy_config(&self) -> win32_coreaudio::Result<Rc<dyn PolicyConfigBackend>> {
+        self.policy_configs.set(self.policy_configs.get() + 1);
+        self.system.policy_config()
+    }
+}
+
+#[test]
+fn policy_config_is_created_once() {
+    let policy_configs = Rc::new(Cell::new(0));
+    let enumerator = DeviceEnumerator::from_backend(CountingEnumerator {
+        system: system(),
+        policy_configs: policy_configs.clone(),
+    });
+    let clone = enumerator.clone();
+    let headset = WinString::from("headset");
+
+    enumerator
+        .set_default_audio_endpoint(&headset, DeviceRole::Console)
+        .unwrap();
+    enumerator
+        .set_default_audio_endpoint_all_roles(&headset)
+        .unwrap();
+    clone
+        .set_default_audio_endpoint(&headset, DeviceRole::Multimedia)
+        .unwrap();
+    assert_eq!(policy_configs.get(), 1);
+}
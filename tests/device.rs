@@ -0,0 +1,178 @@
+use win32_coreaudio::{
+    backend::fake::{FakeAudioSystem, FakeDevice},
+    property_keys::{
+        AUDIO_ENDPOINT_FORM_FACTOR, AUDIO_ENDPOINT_JACK_SUB_TYPE, AUDIO_ENDPOINT_PHYSICAL_SPEAKERS,
+        AUDIO_ENGINE_DEVICE_FORMAT, DEVICE_CONTAINER_ID, DEVICE_HARDWARE_IDS,
+    },
+    string::WinString,
+    ChannelMask, DataFlow, Device, EndpointFormFactor, Error, Property, DEVICE_DESCRIPTION,
+    DEVICE_FRIENDLY_NAME,
+};
+use windows::core::GUID;
+
+const CONTAINER: GUID = GUID::from_u128(0x8c1f2d3e_4a5b_6c7d_8e9f_a0b1c2d3e4f5);
+
+fn device(properties: &[(win32_coreaudio::PropertyKey, Property)]) -> Device {
+    let system = FakeAudioSystem::new();
+    let mut device = FakeDevice::new("speakers", DataFlow::Render);
+    for (key, value) in properties {
+        device = device.property(*key, value.clone());
+    }
+    system.add_device(device);
+    system
+        .device_enumerator()
+        .get_device(&WinString::from("speakers"))
+        .unwrap()
+}
+
+#[test]
+fn accessors_read_typed_properties() {
+    let device = device(&[
+        (DEVICE_FRIENDLY_NAME, Property::Str("Speakers (USB)".into())),
+        (AUDIO_ENDPOINT_FORM_FACTOR, Property::U32(3)),
+        (AUDIO_ENDPOINT_PHYSICAL_SPEAKERS, Property::U32(0x60f)),
+        (DEVICE_CONTAINER_ID, Property::Guid(CONTAINER)),
+        (
+            DEVICE_HARDWARE_IDS,
+            Property::StrVec(vec![
+                "USB\\VID_1234&PID_5678".into(),
+                "USB\\VID_1234".into(),
+            ]),
+        ),
+    ]);
+    assert_eq!(device.friendly_name().unwrap(), "Speakers (USB)");
+    assert_eq!(
+        device.form_factor().unwrap(),
+        EndpointFormFactor::Headphones
+    );
+    assert_eq!(
+        device.physical_speakers().unwrap(),
+        ChannelMask::FIVE_POINT_ONE
+    );
+    assert_eq!(device.container_id().unwrap(), CONTAINER);
+    assert_eq!(
+        device.hardware_ids().unwrap(),
+        ["USB\\VID_1234&PID_5678", "USB\\VID_1234"]
+    );
+}
+
+#[test]
+fn missing_properties_are_not_found() {
+    let device = device(&[(DEVICE_DESCRIPTION, Property::Empty)]);
+    assert_eq!(
+        device.friendly_name(),
+        Err(Error::PropertyNotFound(DEVICE_FRIENDLY_NAME))
+    );
+    // An empty value counts as missing.
+    assert_eq!(
+        device.description(),
+        Err(Error::PropertyNotFound(DEVICE_DESCRIPTION))
+    );
+    assert_eq!(
+        device.container_id(),
+        Err(Error::PropertyNotFound(DEVICE_CONTAINER_ID))
+    );
+    assert_eq!(
+        device.device_format(),
+        Err(Error::PropertyNotFound(AUDIO_ENGINE_DEVICE_FORMAT))
+    );
+}
+
+#[test]
+fn unexpected_types_are_mismatches() {
+    let device = device(&[
+        (DEVICE_FRIENDLY_NAME, Property::U32(1)),
+        (
+            AUDIO_ENDPOINT_FORM_FACTOR,
+            Property::Str("Headphones".into()),
+        ),
+        (DEVICE_CONTAINER_ID, Property::Bool(true)),
+        (DEVICE_HARDWARE_IDS, Property::U32Vec(vec![1])),
+        (AUDIO_ENGINE_DEVICE_FORMAT, Property::Blob(vec![1, 0, 2])),
+    ]);
+    assert_eq!(
+        device.friendly_name(),
+        Err(Error::PropertyTypeMismatch(DEVICE_FRIENDLY_NAME))
+    );
+    assert_eq!(
+        device.form_factor(),
+        Err(Error::PropertyTypeMismatch(AUDIO_ENDPOINT_FORM_FACTOR))
+    );
+    assert_eq!(
+        device.container_id(),
+        Err(Error::PropertyTypeMismatch(DEVICE_CONTAINER_ID))
+    );
+    assert_eq!(
+        device.hardware_ids(),
+        Err(Error::PropertyTypeMismatch(DEVICE_HARDWARE_IDS))
+    );
+    // A blob that is not a wave format is a mismatch as well.
+    assert_eq!(
+        device.device_format(),
+        Err(Error::PropertyTypeMismatch(AUDIO_ENGINE_DEVICE_FORMAT))
+    );
+}
+
+#[test]
+fn guids_can_be_braced_strings() {
+    let device = device(&[
+        (
+            DEVICE_CONTAINER_ID,
+            Property::Str("{8C1F2D3E-4A5B-6C7D-8E9F-A0B1C2D3E4F5}".into()),
+        ),
+        (
+            AUDIO_ENDPOINT_JACK_SUB_TYPE,
+            Property::Str("{8c1f2d3e-4a5b-6c7d-8e9f-a0b1c2d3e4f5}".into()),
+        ),
+    ]);
+    assert_eq!(device.container_id().unwrap(), CONTAINER);
+    assert_eq!(device.jack_subtype().unwrap(), CONTAINER);
+
+    let device = device_with_container("not a guid");
+    assert_eq!(
+        device.container_id(),
+        Err(Error::PropertyTypeMismatch(DEVICE_CONTAINER_ID))
+    );
+    let device = device_with_container("{8C1F2D3E-4A5B-6C7D-8E9F-A0B1C2D3E4F5");
+    assert_eq!(
+        device.container_id(),
+        Err(Error::PropertyTypeMismatch(DEVICE_CONTAINER_ID))
+    );
+}
+
+fn device_with_container(value: &str) -> Device {
+    device(&[(DEVICE_CONTAINER_ID, Property::Str(value.into()))])
+}
+
+#[test]
+fn single_hardware_id_string() {
+    let device = device(&[(
+        DEVICE_HARDWARE_IDS,
+        Property::Str("HDAUDIO\\FUNC_01".into()),
+    )]);
+    assert_eq!(device.hardware_ids().unwrap(), ["HDAUDIO\\FUNC_01"]);
+}
+
+#[test]
+fn unknown_form_factor_keeps_raw_value() {
+    let device = device(&[(AUDIO_ENDPOINT_FORM_FACTOR, Property::U32(42))]);
+    assert!(matches!(
+        device.form_factor().unwrap(),
+        EndpointFormFactor::Unknown(_)
+    ));
+}
+
+#[test]
+fn accessors_of_removed_device_are_invalidated() {
+    let system = FakeAudioSystem::new();
+    system.add_device(
+        FakeDevice::new("speakers", DataFlow::Render)
+            .property(DEVICE_FRIENDLY_NAME, Property::Str("Speakers".into())),
+    );
+    let device = system
+        .device_enumerator()
+        .get_device(&WinString::from("speakers"))
+        .unwrap();
+    system.remove_device("speakers");
+    assert_eq!(device.friendly_name(), Err(Error::DeviceInvalidated));
+}
@@ -0,0 +1,271 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use win32_coreaudio::{
+    backend::{
+        fake::{FakeAudioSystem, FakeDevice},
+        CallbackRegistration, DeviceBackend, DeviceCollectionBackend, DeviceEnumeratorBackend,
+        PolicyConfigBackend,
+    },
+    property_keys::AUDIO_ENDPOINT_FORM_FACTOR,
+    string::{WinStr, WinString},
+    DataFlow, DataFlowMask, DeviceEnumerator, DeviceInfo, DeviceRole, DeviceState, DeviceStateMask,
+    EndpointFormFactor, Error, HardwareSupportMask, NotificationClient, Property,
+    DEVICE_FRIENDLY_NAME,
+};
+use windows::Win32::Media::Audio::EDataFlow;
+
+fn system() -> FakeAudioSystem {
+    let system = FakeAudioSystem::new();
+    system.add_device(
+        FakeDevice::new("speakers", DataFlow::Render)
+            .property(DEVICE_FRIENDLY_NAME, Property::Str("Speakers".into()))
+            .property(AUDIO_ENDPOINT_FORM_FACTOR, Property::U32(1))
+            .hardware_support(HardwareSupportMask::VOLUME | HardwareSupportMask::MUTE),
+    );
+    system.add_device(
+        FakeDevice::new("headset", DataFlow::Render)
+            .property(DEVICE_FRIENDLY_NAME, Property::Str("Headset".into())),
+    );
+    system.add_device(FakeDevice::new("hdmi", DataFlow::Render).state(DeviceState::NotPresent));
+    system.add_device(
+        FakeDevice::new("mic", DataFlow::Capture).property(DEVICE_FRIENDLY_NAME, Property::U32(7)),
+    );
+    system.set_default_device(DataFlow::Render, DeviceRole::Console, "speakers");
+    system.set_default_device(DataFlow::Render, DeviceRole::Multimedia, "speakers");
+    system.set_default_device(DataFlow::Render, DeviceRole::Communications, "headset");
+    system.set_default_device(DataFlow::Capture, DeviceRole::Communications, "mic");
+    system
+}
+
+#[test]
+fn inventory_of_all_devices() {
+    let inventory = system()
+        .device_enumerator()
+        .inventory(DataFlowMask::All, DeviceStateMask::all())
+        .unwrap();
+    assert_eq!(
+        inventory,
+        [
+            DeviceInfo {
+                id: "speakers".into(),
+                friendly_name: Some("Speakers".into()),
+                state: DeviceState::Active,
+                data_flow: DataFlow::Render,
+                form_factor: Some(EndpointFormFactor::Speakers),
+                default_roles: vec![DeviceRole::Console, DeviceRole::Multimedia],
+                hardware_support: Some(HardwareSupportMask::VOLUME | HardwareSupportMask::MUTE),
+            },
+            DeviceInfo {
+                id: "headset".into(),
+                friendly_name: Some("Headset".into()),
+                state: DeviceState::Active,
+                data_flow: DataFlow::Render,
+                form_factor: None,
+                default_roles: vec![DeviceRole::Communications],
+                hardware_support: Some(HardwareSupportMask::empty()),
+            },
+            DeviceInfo {
+                id: "hdmi".into(),
+                friendly_name: None,
+                state: DeviceState::NotPresent,
+                data_flow: DataFlow::Render,
+                form_factor: None,
+                default_roles: vec![],
+                hardware_support: None,
+            },
+            // A malformed name is left out like a missing one.
+            DeviceInfo {
+                id: "mic".into(),
+                friendly_name: None,
+                state: DeviceState::Active,
+                data_flow: DataFlow::Capture,
+                form_factor: None,
+                default_roles: vec![DeviceRole::Communications],
+                hardware_support: Some(HardwareSupportMask::empty()),
+            },
+        ]
+    );
+    assert!(inventory[0].is_default_for(DeviceRole::Console));
+    assert!(!inventory[0].is_default_for(DeviceRole::Communications));
+}
+
+#[test]
+fn inventory_filters_by_masks() {
+    let enumerator = system().device_enumerator();
+    let ids = |flow, state| -> Vec<String> {
+        enumerator
+            .inventory(flow, state)
+            .unwrap()
+            .into_iter()
+            .map(|x| x.id)
+            .collect()
+    };
+    assert_eq!(
+        ids(DataFlowMask::Render, DeviceStateMask::ACTIVE),
+        ["speakers", "headset"]
+    );
+    assert_eq!(ids(DataFlowMask::Capture, DeviceStateMask::all()), ["mic"]);
+    assert_eq!(
+        ids(DataFlowMask::All, DeviceStateMask::NOT_PRESENT),
+        ["hdmi"]
+    );
+    assert_eq!(
+        enumerator.inventory(DataFlowMask::Unknown(EDataFlow(9)), DeviceStateMask::all()),
+        Err(Error::Other(windows::Win32::Foundation::E_INVALIDARG))
+    );
+}
+
+#[test]
+fn from_device_matches_inventory() {
+    let enumerator = system().device_enumerator();
+    let inventory = enumerator
+        .inventory(DataFlowMask::All, DeviceStateMask::all())
+        .unwrap();
+    for info in inventory {
+        let device = enumerator.get_device(&WinString::from(&info.id)).unwrap();
+        assert_eq!(DeviceInfo::from_device(&device).unwrap(), info);
+    }
+}
+
+/// Counts the default endpoint lookups of the system it forwards to.
+#[derive(Debug)]
+struct CountingEnumerator {
+    system: FakeAudioSystem,
+    default_lookups: Rc<Cell<u32>>,
+}
+
+impl DeviceEnumeratorBackend for CountingEnumerator {
+    fn enum_audio_endpoints(
+        &self,
+        data_flow_mask: DataFlowMask,
+        state_mask: DeviceStateMask,
+    ) -> win32_coreaudio::Result<Rc<dyn DeviceCollectionBackend>> {
+        self.system.enum_audio_endpoints(data_flow_mask, state_mask)
+    }
+
+    fn get_default_audio_endpoint(
+        &self,
+        data_flow: DataFlow,
+        role: DeviceRole,
+    ) -> win32_coreaudio::Result<Rc<dyn DeviceBackend>> {
+        self.default_lookups.set(self.default_lookups.get() + 1);
+        self.system.get_default_audio_endpoint(data_flow, role)
+    }
+
+    fn get_device(&self, device_id: &WinStr) -> win32_coreaudio::Result<Rc<dyn DeviceBackend>> {
+        self.system.get_device(device_id)
+    }
+
+    fn register_endpoint_notification(
+        &self,
+        notification_client: Box<dyn NotificationClient>,
+    ) -> win32_coreaudio::Result<Rc<dyn CallbackRegistration>> {
+        self.system
+            .register_endpoint_notification(notification_client)
+    }
+
+    fn policy_config(&self) -> win32_coreaudio::Result<Rc<dyn PolicyConfigBackend>> {
+        self.system.policy_config()
+    }
+}
+
+#[test]
+fn inventory_looks_up_defaults_once_per_role() {
+    let system = system();
+    for i in 0..10 {
+        system.add_device(FakeDevice::new(&format!("extra{}", i), DataFlow::Render));
+    }
+    let default_lookups = Rc::new(Cell::new(0));
+    let enumerator = DeviceEnumerator::from_backend(CountingEnumerator {
+        system,
+        default_lookups: default_lookups.clone(),
+    });
+    let inventory = enumerator
+        .inventory(DataFlowMask::All, DeviceStateMask::all())
+        .unwrap();
+    assert_eq!(inventory.len(), 14);
+    assert_eq!(default_lookups.get(), 6);
+}
+
+/// Removes a device right after the first enumeration of the system it
+/// forwards to.
+#[derive(Debug)]
+struct Vanishing {
+    system: FakeAudioSystem,
+    device_id: Cell<Option<&'static str>>,
+}
+
+impl DeviceEnumeratorBackend for Vanishing {
+    fn enum_audio_endpoints(
+        &self,
+        data_flow_mask: DataFlowMask,
+        state_mask: DeviceStateMask,
+    ) -> win32_coreaudio::Result<Rc<dyn DeviceCollectionBackend>> {
+        let devices = self.system.enum_audio_endpoints(data_flow_mask, state_mask);
+        if let Some(device_id) = self.device_id.take() {
+            self.system.remove_device(device_id);
+        }
+        devices
+    }
+
+    fn get_default_audio_endpoint(
+        &self,
+        data_flow: DataFlow,
+        role: DeviceRole,
+    ) -> win32_coreaudio::Result<Rc<dyn DeviceBackend>> {
+        self.system.get_default_audio_endpoint(data_flow, role)
+    }
+
+    fn get_device(&self, device_id: &WinStr) -> win32_coreaudio::Result<Rc<dyn DeviceBackend>> {
+        self.system.get_device(device_id)
+    }
+
+    fn register_endpoint_notification(
+        &self,
+        notification_client: Box<dyn NotificationClient>,
+    ) -> win32_coreaudio::Result<Rc<dyn CallbackRegistration>> {
+        self.system
+            .register_endpoint_notification(notification_client)
+    }
+
+    fn policy_config(&self) -> win32_coreaudio::Result<Rc<dyn PolicyConfigBackend>> {
+        self.system.policy_config()
+    }
+}
+
+#[test]
+fn inventory_skips_devices_removed_after_enumeration() {
+    let enumerator = DeviceEnumerator::from_backend(Vanishing {
+        system: system(),
+        device_id: Cell::new(Some("headset")),
+    });
+    let ids: Vec<String> = enumerator
+        .inventory(DataFlowMask::All, DeviceStateMask::all())
+        .unwrap()
+        .into_iter()
+        .map(|x| x.id)
+        .collect();
+    assert_eq!(ids, ["speakers", "hdmi", "mic"]);
+}
+
+#[test]
+fn hardware_support_of_inactive_devices_is_unknown() {
+    let system = system();
+    system.set_device_state("speakers", DeviceState::Disabled);
+    let device = system
+        .device_enumerator()
+        .get_device(&WinString::from("speakers"))
+        .unwrap();
+    assert_eq!(
+        DeviceInfo::from_device(&device).unwrap().hardware_support,
+        None
+    );
+    assert_eq!(
+        device
+            .activate_audio_endpoint_volume()
+            .unwrap()
+            .query_hardware_support(),
+        Err(Error::DeviceInvalidated)
+    );
+}
@@ -0,0 +1,235 @@
+use win32_coreaudio::{
+    DataFlow, DeviceInfo, DevicePolicy, DeviceRole, DeviceSelector, DeviceState,
+    EndpointFormFactor, PolicyDecision, PolicyEngine, PolicyEvent, TextMatch,
+};
+
+fn device(
+    id: &str,
+    friendly_name: &str,
+    form_factor: EndpointFormFactor,
+    default_roles: &[DeviceRole],
+) -> DeviceInfo {
+    DeviceInfo {
+        id: id.into(),
+        friendly_name: Some(friendly_name.into()),
+        state: DeviceState::Active,
+        data_flow: DataFlow::Render,
+        form_factor: Some(form_factor),
+        default_roles: default_roles.to_vec(),
+        hardware_support: None,
+    }
+}
+
+fn speakers() -> DeviceInfo {
+    device(
+        "speakers",
+        "Laptop Speakers",
+        EndpointFormFactor::Speakers,
+        &[DeviceRole::Console],
+    )
+}
+
+fn headset() -> DeviceInfo {
+    device("headset", "USB Headset", EndpointFormFactor::Headset, &[])
+}
+
+/// The USB headset if it is plugged in, else the speakers.
+fn policy() -> DevicePolicy {
+    DevicePolicy::new()
+        .prefer(
+            DataFlow::Render,
+            DeviceRole::Console,
+            DeviceSelector::FriendlyName(TextMatch::substring("USB")),
+        )
+        .prefer(
+            DataFlow::Render,
+            DeviceRole::Console,
+            DeviceSelector::FormFactor(EndpointFormFactor::Speakers),
+        )
+}
+
+fn decision(device_id: Option<&str>, rank: Option<usize>, is_default: bool) -> PolicyDecision {
+    PolicyDecision {
+        data_flow: DataFlow::Render,
+        role: DeviceRole::Console,
+        device_id: device_id.map(Into::into),
+        rank,
+        is_default,
+    }
+}
+
+fn default_changed(id: Option<&str>) -> PolicyEvent {
+    PolicyEvent::DefaultChanged {
+        data_flow: DataFlow::Render,
+        role: DeviceRole::Console,
+        id: id.map(Into::into),
+    }
+}
+
+#[test]
+fn usb_headset_plugged_in_and_removed() {
+    let mut engine = PolicyEngine::new(policy(), vec![speakers()]);
+    assert_eq!(
+        engine.decisions(),
+        [decision(Some("speakers"), Some(1), true)]
+    );
+    assert!(!engine.decisions()[0].needs_switch());
+
+    // Plugging in the headset makes it the preferred device.
+    let changed = engine.handle(PolicyEvent::DeviceUpdated(headset()));
+    assert_eq!(changed, [decision(Some("headset"), Some(0), false)]);
+    assert!(changed[0].needs_switch());
+
+    // Switching to it satisfies the policy.
+    let changed = engine.handle(default_changed(Some("headset")));
+    assert_eq!(changed, [decision(Some("headset"), Some(0), true)]);
+    assert!(!changed[0].needs_switch());
+
+    // Removing it falls back to the speakers, which are no longer the
+    // default until Windows or the caller switches back.
+    let changed = engine.handle(PolicyEvent::DeviceRemoved {
+        id: "headset".into(),
+    });
+    assert_eq!(changed, [decision(Some("speakers"), Some(1), false)]);
+    assert!(changed[0].needs_switch());
+    assert_eq!(
+        engine.devices().map(|x| x.id.as_str()).collect::<Vec<_>>(),
+        ["speakers"]
+    );
+
+    let changed = engine.handle(default_changed(Some("speakers")));
+    assert_eq!(changed, [decision(Some("speakers"), Some(1), true)]);
+}
+
+#[test]
+fn tie_at_the_same_rank_keeps_the_current_default() {
+    let policy = DevicePolicy::new().prefer(
+        DataFlow::Render,
+        DeviceRole::Console,
+        DeviceSelector::FormFactor(EndpointFormFactor::Speakers),
+    );
+    let dock = device("dock", "Dock Speakers", EndpointFormFactor::Speakers, &[]);
+    let laptop = device(
+        "laptop",
+        "Laptop Speakers",
+        EndpointFormFactor::Speakers,
+        &[DeviceRole::Console],
+    );
+    let mut engine = PolicyEngine::new(policy.clone(), vec![dock.clone(), laptop]);
+    // "dock" comes first, but "laptop" is the default.
+    assert_eq!(
+        engine.decisions(),
+        [decision(Some("laptop"), Some(0), true)]
+    );
+
+    // The decision follows the default among equals instead of switching back.
+    let changed = engine.handle(default_changed(Some("dock")));
+    assert_eq!(changed, [decision(Some("dock"), Some(0), true)]);
+
+    // Without a default among them, the first one is chosen.
+    let engine = PolicyEngine::new(
+        policy,
+        vec![
+            dock,
+            device(
+                "laptop",
+                "Laptop Speakers",
+                EndpointFormFactor::Speakers,
+                &[],
+            ),
+        ],
+    );
+    assert_eq!(engine.decisions(), [decision(Some("dock"), Some(0), false)]);
+}
+
+#[test]
+fn default_changed_to_an_unknown_device() {
+    let mut engine = PolicyEngine::new(policy(), vec![speakers()]);
+
+    // The old default loses the role even though the new one is not known.
+    let changed = engine.handle(default_changed(Some("bluetooth")));
+    assert_eq!(changed, [decision(Some("speakers"), Some(1), false)]);
+    assert!(engine.devices().all(|x| x.default_roles.is_empty()));
+
+    // Losing the default altogether is the same decision, so nothing changes.
+    assert_eq!(engine.handle(default_changed(None)), []);
+}
+
+#[test]
+fn state_changed_to_disabled() {
+    let mut headset = headset();
+    headset.default_roles.push(DeviceRole::Console);
+    let mut speakers = speakers();
+    speakers.default_roles.clear();
+    let mut engine = PolicyEngine::new(policy(), vec![speakers, headset]);
+    assert_eq!(
+        engine.decisions(),
+        [decision(Some("headset"), Some(0), true)]
+    );
+
+    // A disabled device is no candidate, even if it still is the default.
+    let changed = engine.handle(PolicyEvent::StateChanged {
+        id: "headset".into(),
+        state: DeviceState::Disabled,
+    });
+    assert_eq!(changed, [decision(Some("speakers"), Some(1), false)]);
+
+    // Disabling the last candidate leaves no preferred device.
+    let changed = engine.handle(PolicyEvent::StateChanged {
+        id: "speakers".into(),
+        state: DeviceState::Disabled,
+    });
+    assert_eq!(changed, [decision(None, None, false)]);
+    assert!(!changed[0].needs_switch());
+
+    // Unknown devices are ignored.
+    let changed = engine.handle(PolicyEvent::StateChanged {
+        id: "bluetooth".into(),
+        state: DeviceState::Active,
+    });
+    assert_eq!(changed, []);
+
+    let changed = engine.handle(PolicyEvent::StateChanged {
+        id: "headset".into(),
+        state: DeviceState::Active,
+    });
+    assert_eq!(changed, [decision(Some("headset"), Some(0), true)]);
+}
+
+#[test]
+fn other_lists_are_decided_independently() {
+    let policy = policy().prefer(
+        DataFlow::Capture,
+        DeviceRole::Communications,
+        DeviceSelector::Id("mic".into()),
+    );
+    let mut engine = PolicyEngine::new(policy.clone(), vec![speakers()]);
+    assert_eq!(
+        engine.decision(DataFlow::Capture, DeviceRole::Communications),
+        Some(&PolicyDecision {
+            data_flow: DataFlow::Capture,
+            role: DeviceRole::Communications,
+            device_id: None,
+            rank: None,
+            is_default: false,
+        })
+    );
+
+    let mut mic = device("mic", "Microphone", EndpointFormFactor::Microphone, &[]);
+    mic.data_flow = DataFlow::Capture;
+    let changed = engine.handle(PolicyEvent::DeviceUpdated(mic));
+    assert_eq!(
+        changed,
+        [PolicyDecision {
+            data_flow: DataFlow::Capture,
+            role: DeviceRole::Communications,
+            device_id: Some("mic".into()),
+            rank: Some(0),
+            is_default: false,
+        }]
+    );
+    assert_eq!(
+        policy.evaluate(&engine.devices().cloned().collect::<Vec<_>>()),
+        engine.decisions()
+    );
+}
@@ -0,0 +1,219 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use win32_coreaudio::{
+    backend::{
+        fake::{FakeAudioSystem, FakeDevice},
+        CallbackRegistration, DeviceBackend, DeviceCollectionBackend, DeviceEnumeratorBackend,
+        PolicyConfigBackend,
+    },
+    property_keys::DEVICE_HARDWARE_IDS,
+    string::WinStr,
+    DataFlow, DataFlowMask, DeviceEnumerator, DeviceQuery, DeviceRole, DeviceStateMask, MatchKind,
+    NotificationClient, Property, TextMatch, DEVICE_FRIENDLY_NAME,
+};
+
+#[test]
+fn glob_star_matches_any_sequence() {
+    let glob = TextMatch::glob("Speakers*Audio)");
+    assert!(glob.matches("Speakers (Realtek Audio)"));
+    assert!(glob.matches("SpeakersAudio)"));
+    assert!(!glob.matches("Speakers (Realtek Audio) 2"));
+    assert!(!glob.matches("Headphones (Realtek Audio)"));
+
+    assert!(TextMatch::glob("*").matches(""));
+    assert!(TextMatch::glob("*").matches("anything"));
+    assert!(TextMatch::glob("**a**").matches("banana"));
+    assert!(TextMatch::glob("*a*a*a*").matches("banana"));
+    assert!(!TextMatch::glob("*a*a*a*a*").matches("banana"));
+}
+
+#[test]
+fn glob_question_mark_matches_one_character() {
+    let glob = TextMatch::glob("Speakers (?)");
+    assert!(glob.matches("Speakers (2)"));
+    assert!(glob.matches("Speakers (\u{1F50A})"));
+    assert!(!glob.matches("Speakers ()"));
+    assert!(!glob.matches("Speakers (12)"));
+    assert!(TextMatch::glob("??").matches("ab"));
+    assert!(!TextMatch::glob("??").matches("a"));
+}
+
+#[test]
+fn glob_trailing_star() {
+    let glob = TextMatch::glob("USB\\VID_046D*");
+    assert!(glob.matches("USB\\VID_046D"));
+    assert!(glob.matches("USB\\VID_046D&PID_0A44"));
+    assert!(!glob.matches("USB\\VID_046"));
+    assert!(!glob.matches("HID\\USB\\VID_046D"));
+}
+
+#[test]
+fn empty_pattern() {
+    assert!(TextMatch::glob("").matches(""));
+    assert!(!TextMatch::glob("").matches("a"));
+    assert!(TextMatch::exact("").matches(""));
+    assert!(!TextMatch::exact("").matches("a"));
+    // Every text contains the empty string.
+    assert!(TextMatch::substring("").matches("a"));
+}
+
+#[test]
+fn ignore_case() {
+    assert!(!TextMatch::exact("speakers").matches("Speakers"));
+    assert!(TextMatch::exact("speakers")
+        .ignore_case()
+        .matches("SPEAKERS"));
+    assert!(TextMatch::substring("realtek")
+        .ignore_case()
+        .matches("Speakers (Realtek Audio)"));
+    assert!(TextMatch::glob("speakers (*)")
+        .ignore_case()
+        .matches("Speakers (Realtek Audio)"));
+    assert!(!TextMatch::glob("speakers (*)").matches("Speakers (Realtek Audio)"));
+
+    let text = TextMatch::new("jabra", MatchKind::Substring).ignore_case();
+    assert_eq!(text.pattern(), "jabra");
+    assert_eq!(text.kind(), MatchKind::Substring);
+    assert!(text.is_ignore_case());
+}
+
+fn enumerator(names: &[&str]) -> DeviceEnumerator {
+    let system = FakeAudioSystem::new();
+    for (i, name) in names.iter().enumerate() {
+        system.add_device(
+            FakeDevice::new(&format!("device{}", i), DataFlow::Render)
+                .property(DEVICE_FRIENDLY_NAME, Property::Str((*name).into())),
+        );
+    }
+    system.device_enumerator()
+}
+
+fn names(enumerator: &DeviceEnumerator, query: &DeviceQuery) -> Vec<String> {
+    enumerator
+        .find_devices(query)
+        .unwrap()
+        .iter()
+        .map(|x| x.device.friendly_name().unwrap().to_string_lossy())
+        .collect()
+}
+
+#[test]
+fn ranking_exact_then_prefix_then_substring() {
+    let enumerator = enumerator(&[
+        "USB Headset (Jabra)",
+        "Headset",
+        "Headset Earphone (Jabra)",
+        "Unrelated",
+        "headset",
+    ]);
+    let query = DeviceQuery::new().friendly_name(TextMatch::substring("Headset").ignore_case());
+    assert_eq!(
+        names(&enumerator, &query),
+        [
+            "Headset",
+            "headset",
+            "Headset Earphone (Jabra)",
+            "USB Headset (Jabra)",
+        ]
+    );
+
+    let matches = enumerator.find_devices(&query).unwrap();
+    assert!(matches.windows(2).all(|x| x[0].score > x[1].score));
+}
+
+#[test]
+fn ties_are_broken_by_coverage_then_enumeration_order() {
+    let enumerator = enumerator(&["A Jabra Headset", "My Jabra", "Jabra 2", "Jabra 1"]);
+    let query = DeviceQuery::new().friendly_name(TextMatch::substring("Jabra"));
+    assert_eq!(
+        names(&enumerator, &query),
+        ["Jabra 2", "Jabra 1", "My Jabra", "A Jabra Headset"]
+    );
+}
+
+#[test]
+fn every_criterion_has_to_match() {
+    let system = FakeAudioSystem::new();
+    system.add_device(
+        FakeDevice::new("a", DataFlow::Render)
+            .property(DEVICE_FRIENDLY_NAME, Property::Str("Headset".into()))
+            .property(
+                DEVICE_HARDWARE_IDS,
+                Property::StrVec(vec!["USB\\VID_0B0E&PID_0300".into()]),
+            ),
+    );
+    system.add_device(
+        FakeDevice::new("b", DataFlow::Render)
+            .property(DEVICE_FRIENDLY_NAME, Property::Str("Headset".into())),
+    );
+    let enumerator = system.device_enumerator();
+    let query = DeviceQuery::new()
+        .friendly_name(TextMatch::exact("Headset"))
+        .hardware_id(TextMatch::glob("USB\\VID_0B0E*"));
+    let matches = enumerator.find_devices(&query).unwrap();
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].device.get_id().unwrap(), "a");
+}
+
+/// Removes a device right after the system it forwards to was enumerated.
+#[derive(Debug)]
+struct Vanishing {
+    system: FakeAudioSystem,
+    device_id: Cell<Option<&'static str>>,
+}
+
+impl DeviceEnumeratorBackend for Vanishing {
+    fn enum_audio_endpoints(
+        &self,
+        data_flow_mask: DataFlowMask,
+        state_mask: DeviceStateMask,
+    ) -> win32_coreaudio::Result<Rc<dyn DeviceCollectionBackend>> {
+        let devices = self.system.enum_audio_endpoints(data_flow_mask, state_mask);
+        if let Some(device_id) = self.device_id.take() {
+            self.system.remove_device(device_id);
+        }
+        devices
+    }
+
+    fn get_default_audio_endpoint(
+        &self,
+        data_flow: DataFlow,
+        role: DeviceRole,
+    ) -> win32_coreaudio::Result<Rc<dyn DeviceBackend>> {
+        self.system.get_default_audio_endpoint(data_flow, role)
+    }
+
+    fn get_device(&self, device_id: &WinStr) -> win32_coreaudio::Result<Rc<dyn DeviceBackend>> {
+        self.system.get_device(device_id)
+    }
+
+    fn register_endpoint_notification(
+        &self,
+        notification_client: Box<dyn NotificationClient>,
+    ) -> win32_coreaudio::Result<Rc<dyn CallbackRegistration>> {
+        self.system
+            .register_endpoint_notification(notification_client)
+    }
+
+    fn policy_config(&self) -> win32_coreaudio::Result<Rc<dyn PolicyConfigBackend>> {
+        self.system.policy_config()
+    }
+}
+
+#[test]
+fn devices_removed_after_enumeration_are_skipped() {
+    let system = FakeAudioSystem::new();
+    for name in &["Headset 1", "Headset 2", "Headset 3"] {
+        system.add_device(
+            FakeDevice::new(name, DataFlow::Render)
+                .property(DEVICE_FRIENDLY_NAME, Property::Str((*name).into())),
+        );
+    }
+    let enumerator = DeviceEnumerator::from_backend(Vanishing {
+        system,
+        device_id: Cell::new(Some("Headset 2")),
+    });
+    let query = DeviceQuery::new().friendly_name(TextMatch::substring("Headset"));
+    assert_eq!(names(&enumerator, &query), ["Headset 1", "Headset 3"]);
+}
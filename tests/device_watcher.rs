@@ -0,0 +1,290 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::mpsc::Receiver;
+
+use win32_coreaudio::{
+    backend::{
+        fake::{FakeAudioSystem, FakeDevice},
+        CallbackRegistration, DeviceBackend, DeviceCollectionBackend, DeviceEnumeratorBackend,
+        PolicyConfigBackend,
+    },
+    string::WinStr,
+    DataFlow, DataFlowMask, DeviceEnumerator, DeviceEvent, DeviceRole, DeviceState,
+    DeviceStateMask, DeviceWatcher, NotificationClient, Property, DEVICE_FRIENDLY_NAME,
+};
+
+fn system() -> FakeAudioSystem {
+    let system = FakeAudioSystem::new();
+    system.add_device(FakeDevice::new("speakers", DataFlow::Render));
+    system.add_device(FakeDevice::new("mic", DataFlow::Capture).state(DeviceState::Disabled));
+    system.set_default_device(DataFlow::Render, DeviceRole::Console, "speakers");
+    system
+}
+
+fn drain(receiver: &Receiver<DeviceEvent>) -> Vec<DeviceEvent> {
+    receiver.try_iter().collect()
+}
+
+#[test]
+fn initial_table() {
+    let system = system();
+    let (watcher, receiver) = DeviceWatcher::new(&system.device_enumerator()).unwrap();
+    let table = watcher.table();
+    assert_eq!(table.ids().collect::<Vec<_>>(), ["mic", "speakers"]);
+    assert_eq!(table.state("speakers"), Some(DeviceState::Active));
+    assert_eq!(table.state("mic"), Some(DeviceState::Disabled));
+    assert_eq!(
+        table.default_device(DataFlow::Render, DeviceRole::Console),
+        Some("speakers")
+    );
+    assert_eq!(
+        table.default_device(DataFlow::Capture, DeviceRole::Console),
+        None
+    );
+    assert_eq!(drain(&receiver), []);
+}
+
+#[test]
+fn notifications_update_the_table_and_are_forwarded() {
+    let system = system();
+    let (watcher, receiver) = DeviceWatcher::new(&system.device_enumerator()).unwrap();
+
+    system.set_device_state("mic", DeviceState::Active);
+    system.set_default_device(DataFlow::Capture, DeviceRole::Communications, "mic");
+    system.set_property("mic", DEVICE_FRIENDLY_NAME, Property::Str("Mic".into()));
+    system.remove_device("speakers");
+
+    assert_eq!(
+        drain(&receiver),
+        [
+            DeviceEvent::StateChanged {
+                id: "mic".into(),
+                state: DeviceState::Active,
+            },
+            DeviceEvent::DefaultChanged {
+                data_flow: DataFlow::Capture,
+                role: DeviceRole::Communications,
+                id: Some("mic".into()),
+            },
+            DeviceEvent::PropertyChanged {
+                id: "mic".into(),
+                key: DEVICE_FRIENDLY_NAME,
+            },
+            DeviceEvent::Removed {
+                id: "speakers".into(),
+            },
+        ]
+    );
+    let table = watcher.table();
+    assert_eq!(table.state("mic"), Some(DeviceState::Active));
+    assert!(!table.contains("speakers"));
+    // Removing a device clears the roles it was the default for.
+    assert_eq!(
+        table.default_device(DataFlow::Render, DeviceRole::Console),
+        None
+    );
+    assert_eq!(
+        table.default_device(DataFlow::Capture, DeviceRole::Communications),
+        Some("mic")
+    );
+    assert_eq!(*watcher.shared_table().lock().unwrap(), table);
+}
+
+#[test]
+fn added_devices_are_queried_by_update_added() {
+    let system = system();
+    let (watcher, receiver) = DeviceWatcher::new(&system.device_enumerator()).unwrap();
+
+    system.add_device(FakeDevice::new("usb", DataFlow::Render).state(DeviceState::Unplugged));
+    system.add_device(FakeDevice::new("gone", DataFlow::Render));
+    system.remove_device("gone");
+    assert!(watcher.table().contains("usb"));
+    assert_eq!(watcher.table().state("usb"), None);
+
+    drain(&receiver);
+    watcher.update_added().unwrap();
+    assert_eq!(watcher.table().state("usb"), Some(DeviceState::Unplugged));
+    assert_eq!(
+        drain(&receiver),
+        [DeviceEvent::StateChanged {
+            id: "usb".into(),
+            state: DeviceState::Unplugged,
+        }]
+    );
+
+    // Nothing is left to query.
+    watcher.update_added().unwrap();
+    assert_eq!(drain(&receiver), []);
+}
+
+/// Runs a change on the fake system in the middle of a query, the way a
+/// notification on another thread can arrive while the watcher is busy.
+#[derive(Debug, Clone)]
+struct Interleaving {
+    system: FakeAudioSystem,
+    change: Rc<RefCell<Option<Change>>>,
+    after_enumeration: Rc<RefCell<Option<Change>>>,
+}
+
+struct Change(Box<dyn FnOnce(&FakeAudioSystem)>);
+
+impl std::fmt::Debug for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Change")
+    }
+}
+
+impl Interleaving {
+    fn run_change(&self) {
+        let change = self.change.borrow_mut().take();
+        if let Some(Change(change)) = change {
+            change(&self.system);
+        }
+    }
+
+    fn run_after_enumeration(&self) {
+        let change = self.after_enumeration.borrow_mut().take();
+        if let Some(Change(change)) = change {
+            change(&self.system);
+        }
+    }
+}
+
+impl DeviceEnumeratorBackend for Interleaving {
+    fn enum_audio_endpoints(
+        &self,
+        data_flow_mask: DataFlowMask,
+        state_mask: DeviceStateMask,
+    ) -> win32_coreaudio::Result<Rc<dyn DeviceCollectionBackend>> {
+        let devices = self.system.enum_audio_endpoints(data_flow_mask, state_mask);
+        self.run_after_enumeration();
+        devices
+    }
+
+    /// The defaults are queried after the devices were enumerated.
+    fn get_default_audio_endpoint(
+        &self,
+        data_flow: DataFlow,
+        role: DeviceRole,
+    ) -> win32_coreaudio::Result<Rc<dyn DeviceBackend>> {
+        self.run_change();
+        self.system.get_default_audio_endpoint(data_flow, role)
+    }
+
+    fn get_device(&self, device_id: &WinStr) -> win32_coreaudio::Result<Rc<dyn DeviceBackend>> {
+        let device = self.system.get_device(device_id);
+        self.run_change();
+        device
+    }
+
+    fn register_endpoint_notification(
+        &self,
+        notification_client: Box<dyn NotificationClient>,
+    ) -> win32_coreaudio::Result<Rc<dyn CallbackRegistration>> {
+        self.system
+            .register_endpoint_notification(notification_client)
+    }
+
+    fn policy_config(&self) -> win32_coreaudio::Result<Rc<dyn PolicyConfigBackend>> {
+        self.system.policy_config()
+    }
+}
+
+fn interleaving(system: &FakeAudioSystem) -> (Interleaving, DeviceEnumerator) {
+    let interleaving = Interleaving {
+        system: system.clone(),
+        change: Rc::default(),
+        after_enumeration: Rc::default(),
+    };
+    let enumerator = DeviceEnumerator::from_backend(interleaving.clone());
+    (interleaving, enumerator)
+}
+
+fn interleave(interleaving: &Interleaving, change: impl FnOnce(&FakeAudioSystem) + 'static) {
+    *interleaving.change.borrow_mut() = Some(Change(Box::new(change)));
+}
+
+#[test]
+fn device_vanishing_during_the_query_is_skipped() {
+    let system = system();
+    let (interleaving, enumerator) = interleaving(&system);
+    *interleaving.after_enumeration.borrow_mut() =
+        Some(Change(Box::new(|system| system.remove_device("mic"))));
+    let (watcher, _receiver) = DeviceWatcher::new(&enumerator).unwrap();
+    let table = watcher.table();
+    assert!(table.contains("speakers"));
+    assert!(!table.contains("mic"));
+
+    system.add_device(FakeDevice::new("mic", DataFlow::Capture));
+    *interleaving.after_enumeration.borrow_mut() =
+        Some(Change(Box::new(|system| system.remove_device("speakers"))));
+    watcher.refresh().unwrap();
+    let table = watcher.table();
+    assert!(!table.contains("speakers"));
+    assert!(table.contains("mic"));
+}
+
+#[test]
+fn removal_during_refresh_is_not_lost() {
+    let system = system();
+    let (interleaving, enumerator) = interleaving(&system);
+    let (watcher, receiver) = DeviceWatcher::new(&enumerator).unwrap();
+
+    interleave(&interleaving, |system| system.remove_device("speakers"));
+    watcher.refresh().unwrap();
+    let table = watcher.table();
+    assert!(!table.contains("speakers"));
+    assert_eq!(
+        table.default_device(DataFlow::Render, DeviceRole::Console),
+        None
+    );
+    assert_eq!(
+        drain(&receiver),
+        [DeviceEvent::Removed {
+            id: "speakers".into(),
+        }]
+    );
+}
+
+#[test]
+fn addition_during_refresh_is_not_lost() {
+    let system = system();
+    let (interleaving, enumerator) = interleaving(&system);
+    let (watcher, receiver) = DeviceWatcher::new(&enumerator).unwrap();
+
+    interleave(&interleaving, |system| {
+        system.add_device(FakeDevice::new("usb", DataFlow::Render));
+        system.set_device_state("mic", DeviceState::Active);
+    });
+    watcher.refresh().unwrap();
+    let table = watcher.table();
+    assert!(table.contains("usb"));
+    assert_eq!(table.state("mic"), Some(DeviceState::Active));
+    assert_eq!(drain(&receiver).len(), 2);
+
+    watcher.update_added().unwrap();
+    assert_eq!(watcher.table().state("usb"), Some(DeviceState::Active));
+}
+
+#[test]
+fn notifications_during_update_added_win() {
+    let system = system();
+    let (interleaving, enumerator) = interleaving(&system);
+    let (watcher, receiver) = DeviceWatcher::new(&enumerator).unwrap();
+    system.add_device(FakeDevice::new("usb", DataFlow::Render));
+
+    // The device changes state after its state was queried.
+    interleave(&interleaving, |system| {
+        system.set_device_state("usb", DeviceState::Disabled)
+    });
+    drain(&receiver);
+    watcher.update_added().unwrap();
+    assert_eq!(watcher.table().state("usb"), Some(DeviceState::Disabled));
+    assert_eq!(
+        drain(&receiver),
+        [DeviceEvent::StateChanged {
+            id: "usb".into(),
+            state: DeviceState::Disabled,
+        }]
+    );
+}
@@ -0,0 +1,73 @@
+use win32_coreaudio::{Error, DEVICE_FRIENDLY_NAME};
+use windows::core::HRESULT;
+use windows::Win32::{
+    Foundation::{
+        CO_E_NOTINITIALIZED, ERROR_NOT_FOUND, E_INVALIDARG, RPC_E_CHANGED_MODE, TYPE_E_TYPEMISMATCH,
+    },
+    Media::Audio::{
+        AUDCLNT_E_DEVICE_INVALIDATED, AUDCLNT_E_ENDPOINT_CREATE_FAILED,
+        AUDCLNT_E_SERVICE_NOT_RUNNING, AUDCLNT_E_UNSUPPORTED_FORMAT,
+    },
+};
+
+const E_NOTFOUND: HRESULT = ERROR_NOT_FOUND.to_hresult();
+
+#[test]
+fn classify_hresults() {
+    let cases = [
+        (AUDCLNT_E_DEVICE_INVALIDATED, Error::DeviceInvalidated),
+        (E_NOTFOUND, Error::DeviceNotFound),
+        (AUDCLNT_E_SERVICE_NOT_RUNNING, Error::ServiceNotRunning),
+        (AUDCLNT_E_UNSUPPORTED_FORMAT, Error::UnsupportedFormat),
+        (CO_E_NOTINITIALIZED, Error::ComInit(CO_E_NOTINITIALIZED)),
+        (RPC_E_CHANGED_MODE, Error::ComInit(RPC_E_CHANGED_MODE)),
+        (E_INVALIDARG, Error::Other(E_INVALIDARG)),
+        (
+            AUDCLNT_E_ENDPOINT_CREATE_FAILED,
+            Error::Other(AUDCLNT_E_ENDPOINT_CREATE_FAILED),
+        ),
+    ];
+    for &(code, error) in &cases {
+        assert_eq!(Error::from_hresult(code), error, "{}", code);
+        assert_eq!(Error::from(code), error);
+        assert_eq!(error.code(), code);
+    }
+}
+
+#[test]
+fn property_errors_share_codes() {
+    let not_found = Error::PropertyNotFound(DEVICE_FRIENDLY_NAME);
+    assert_eq!(not_found.code(), E_NOTFOUND);
+    assert_eq!(Error::from_hresult(not_found.code()), Error::DeviceNotFound);
+
+    let mismatch = Error::PropertyTypeMismatch(DEVICE_FRIENDLY_NAME);
+    assert_eq!(mismatch.code(), TYPE_E_TYPEMISMATCH);
+    assert_eq!(
+        Error::from_hresult(mismatch.code()),
+        Error::Other(TYPE_E_TYPEMISMATCH)
+    );
+}
+
+#[test]
+fn retryable_errors() {
+    let retryable = [
+        Error::DeviceInvalidated,
+        Error::ServiceNotRunning,
+        Error::Other(AUDCLNT_E_ENDPOINT_CREATE_FAILED),
+    ];
+    for error in &retryable {
+        assert!(error.is_retryable(), "{:?}", error);
+    }
+
+    let permanent = [
+        Error::DeviceNotFound,
+        Error::UnsupportedFormat,
+        Error::PropertyNotFound(DEVICE_FRIENDLY_NAME),
+        Error::PropertyTypeMismatch(DEVICE_FRIENDLY_NAME),
+        Error::ComInit(CO_E_NOTINITIALIZED),
+        Error::Other(E_INVALIDARG),
+    ];
+    for error in &permanent {
+        assert!(!error.is_retryable(), "{:?}", error);
+    }
+}
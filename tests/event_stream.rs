@@ -0,0 +1,308 @@
+#![cfg(feature = "async")]
+
+use std::pin::Pin;
+use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+use futures_core::Stream;
+use win32_coreaudio::{
+    backend::fake::{FakeAudioSystem, FakeDevice, FakeSession},
+    string::WinString,
+    AudioSessionDisconnectReason, AudioSessionManager2, AudioSessionState, DataFlow, Device,
+    DeviceEvent, DeviceState, DuckEvent, EventStream, OverflowPolicy, SessionCreated, SessionEvent,
+    StreamOptions,
+};
+use windows::core::GUID;
+
+fn system() -> FakeAudioSystem {
+    let system = FakeAudioSystem::new();
+    system.add_device(FakeDevice::new("speakers", DataFlow::Render));
+    system
+}
+
+/// A waker that does nothing, the tests poll again instead of waiting.
+fn noop_waker() -> Waker {
+    fn clone(_: *const ()) -> RawWaker {
+        RawWaker::new(std::ptr::null(), &VTABLE)
+    }
+    fn noop(_: *const ()) {}
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+    unsafe { Waker::from_raw(RawWaker::new(std::ptr::null(), &VTABLE)) }
+}
+
+fn poll<T>(stream: &mut EventStream<T>) -> Poll<Option<T>> {
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    Pin::new(stream).poll_next(&mut cx)
+}
+
+fn drain<T>(stream: &mut EventStream<T>) -> Vec<T> {
+    let mut events = Vec::new();
+    while let Poll::Ready(Some(event)) = poll(stream) {
+        events.push(event);
+    }
+    events
+}
+
+fn speakers(system: &FakeAudioSystem) -> Device {
+    system
+        .device_enumerator()
+        .get_device(&WinString::from("speakers"))
+        .unwrap()
+}
+
+fn session_manager(system: &FakeAudioSystem) -> AudioSessionManager2 {
+    speakers(system).activate_audio_session_manager2().unwrap()
+}
+
+fn added(id: &str) -> DeviceEvent {
+    DeviceEvent::Added { id: id.into() }
+}
+
+fn add_devices(system: &FakeAudioSystem, ids: &[&str]) {
+    for id in ids {
+        system.add_device(FakeDevice::new(id, DataFlow::Capture));
+    }
+}
+
+#[test]
+fn streams_and_events_are_send() {
+    fn assert_send<T: Send>() {}
+    assert_send::<EventStream<DeviceEvent>>();
+    assert_send::<EventStream<SessionCreated>>();
+}
+
+#[test]
+fn pending_until_an_event_arrives() {
+    let system = system();
+    let (mut stream, _handle) = system
+        .device_enumerator()
+        .endpoint_notification_stream(StreamOptions::default())
+        .unwrap();
+    assert!(poll(&mut stream).is_pending());
+
+    system.set_device_state("speakers", DeviceState::Disabled);
+    assert_eq!(stream.size_hint(), (1, None));
+    assert_eq!(
+        poll(&mut stream),
+        Poll::Ready(Some(DeviceEvent::StateChanged {
+            id: "speakers".into(),
+            state: DeviceState::Disabled,
+        }))
+    );
+    assert!(poll(&mut stream).is_pending());
+}
+
+#[test]
+fn drop_oldest_keeps_the_latest_events() {
+    let system = system();
+    let (mut stream, _handle) = system
+        .device_enumerator()
+        .endpoint_notification_stream(StreamOptions::new(2, OverflowPolicy::DropOldest))
+        .unwrap();
+
+    add_devices(&system, &["a", "b", "c", "d"]);
+    assert_eq!(stream.dropped_count(), 2);
+    assert_eq!(drain(&mut stream), [added("c"), added("d")]);
+
+    // The buffer has room again.
+    add_devices(&system, &["e"]);
+    assert_eq!(drain(&mut stream), [added("e")]);
+    assert_eq!(stream.dropped_count(), 2);
+}
+
+#[test]
+fn drop_newest_keeps_the_earliest_events() {
+    let system = system();
+    let (mut stream, _handle) = system
+        .device_enumerator()
+        .endpoint_notification_stream(StreamOptions::new(2, OverflowPolicy::DropNewest))
+        .unwrap();
+
+    add_devices(&system, &["a", "b", "c", "d"]);
+    assert_eq!(stream.dropped_count(), 2);
+    assert_eq!(drain(&mut stream), [added("a"), added("b")]);
+}
+
+#[test]
+fn zero_capacity_buffers_one_event() {
+    let options = StreamOptions::new(0, OverflowPolicy::DropOldest);
+    assert_eq!(options.capacity(), 1);
+
+    let system = system();
+    let (mut stream, _handle) = system
+        .device_enumerator()
+        .endpoint_notification_stream(options)
+        .unwrap();
+    add_devices(&system, &["a", "b"]);
+    assert_eq!(stream.dropped_count(), 1);
+    assert_eq!(drain(&mut stream), [added("b")]);
+}
+
+#[test]
+fn dropping_the_handle_unregisters_and_ends_the_stream() {
+    let system = system();
+    let (mut stream, handle) = system
+        .device_enumerator()
+        .endpoint_notification_stream(StreamOptions::default())
+        .unwrap();
+
+    add_devices(&system, &["a"]);
+    drop(handle);
+    add_devices(&system, &["b"]);
+
+    // The buffered event is still delivered, then the stream ends.
+    assert_eq!(poll(&mut stream), Poll::Ready(Some(added("a"))));
+    assert_eq!(poll(&mut stream), Poll::Ready(None));
+    assert_eq!(stream.dropped_count(), 0);
+}
+
+#[test]
+fn unregister_ends_a_pending_stream() {
+    let system = system();
+    let (mut stream, handle) = system
+        .device_enumerator()
+        .endpoint_notification_stream(StreamOptions::default())
+        .unwrap();
+    assert!(poll(&mut stream).is_pending());
+    handle.unregister();
+    assert_eq!(poll(&mut stream), Poll::Ready(None));
+}
+
+#[test]
+fn dropping_only_the_stream_stops_the_callback() {
+    let system = system();
+    let (stream, handle) = system
+        .device_enumerator()
+        .endpoint_notification_stream(StreamOptions::default())
+        .unwrap();
+    add_devices(&system, &["a"]);
+    assert!(!handle.is_stream_dropped());
+
+    drop(stream);
+    assert!(handle.is_stream_dropped());
+    // Notifications for the dropped stream are discarded.
+    add_devices(&system, &["b", "c"]);
+
+    // The owner of the handle can now unregister.
+    handle.unregister();
+    add_devices(&system, &["d"]);
+}
+
+#[test]
+fn created_sessions_are_identified() {
+    let system = system();
+    let manager = session_manager(&system);
+    let (mut stream, _handle) = manager
+        .session_notification_stream(StreamOptions::default())
+        .unwrap();
+
+    system.add_session("speakers", FakeSession::new("instance-1").identifier("app"));
+    assert_eq!(
+        drain(&mut stream),
+        [SessionCreated {
+            instance_identifier: "instance-1".into(),
+            session_identifier: "app".into(),
+        }]
+    );
+}
+
+#[test]
+fn session_events() {
+    let system = system();
+    system.add_session("speakers", FakeSession::new("instance-1"));
+    let control = session_manager(&system)
+        .get_session_enumerator()
+        .unwrap()
+        .get_session(0)
+        .unwrap();
+    let (mut stream, _handle) = control
+        .session_event_stream(StreamOptions::default())
+        .unwrap();
+
+    let context = GUID::from_u128(7);
+    control
+        .set_display_name(&WinString::from("Game"), Some(&context))
+        .unwrap();
+    control
+        .get_simple_audio_volume()
+        .unwrap()
+        .set_mute(true, None)
+        .unwrap();
+    system.set_session_state("instance-1", AudioSessionState::Active);
+    system.disconnect_session("instance-1", AudioSessionDisconnectReason::DeviceRemoval);
+
+    let events = drain(&mut stream);
+    assert_eq!(events.len(), 4);
+    assert_eq!(
+        events[0],
+        SessionEvent::DisplayNameChanged {
+            display_name: "Game".into(),
+            event_context: Some(context),
+        }
+    );
+    assert!(matches!(
+        events[1],
+        SessionEvent::SimpleVolumeChanged { mute: true, .. }
+    ));
+    assert_eq!(
+        events[2..],
+        [
+            SessionEvent::StateChanged {
+                state: AudioSessionState::Active,
+            },
+            SessionEvent::Disconnected {
+                reason: AudioSessionDisconnectReason::DeviceRemoval,
+            },
+        ]
+    );
+}
+
+#[test]
+fn duck_events() {
+    let system = system();
+    let (mut stream, _handle) = session_manager(&system)
+        .duck_notification_stream(&WinString::from("call"), StreamOptions::default())
+        .unwrap();
+
+    system.duck("call", 2);
+    // Other sessions are not reported.
+    system.duck("music", 1);
+    system.unduck("call");
+
+    assert_eq!(
+        drain(&mut stream),
+        [
+            DuckEvent::Ducked {
+                session_id: "call".into(),
+                num_communication_sessions: 2,
+            },
+            DuckEvent::Unducked {
+                session_id: "call".into(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn volume_events() {
+    let system = system();
+    let volume = speakers(&system).activate_audio_endpoint_volume().unwrap();
+    let (mut stream, _handle) = volume
+        .control_change_stream(StreamOptions::default())
+        .unwrap();
+
+    let context = GUID::from_u128(9);
+    volume.set_mute(true, Some(&context)).unwrap();
+    volume.set_master_volume_level_scalar(0.5, None).unwrap();
+
+    let events = drain(&mut stream);
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].event_context, context);
+    assert!(events[0].muted);
+    assert_eq!(events[1].event_context, GUID::zeroed());
+    assert_eq!(events[1].master_volume, 0.5);
+    assert_eq!(
+        events[1].channel_volumes.len() as u32,
+        volume.get_channel_count().unwrap()
+    );
+}
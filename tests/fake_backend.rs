@@ -0,0 +1,280 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use win32_coreaudio::{
+    backend::fake::{FakeAudioSystem, FakeDevice, FakeSession},
+    string::{WinStr, WinString},
+    AudioSessionDisconnectReason, DataFlow, DataFlowMask, Device, DeviceRole, DeviceState,
+    DeviceStateMask, Error, NotificationClient, Property, PropertyKey, StorageAccessMode,
+    DEVICE_FRIENDLY_NAME,
+};
+
+fn system() -> FakeAudioSystem {
+    let system = FakeAudioSystem::new();
+    system.add_device(
+        FakeDevice::new("speakers", DataFlow::Render)
+            .property(DEVICE_FRIENDLY_NAME, Property::Str("Speakers".into())),
+    );
+    system.add_device(FakeDevice::new("headset", DataFlow::Render).state(DeviceState::Unplugged));
+    system.add_device(FakeDevice::new("mic", DataFlow::Capture));
+    system
+}
+
+fn ids(devices: &[Device]) -> Vec<String> {
+    devices
+        .iter()
+        .map(|x| x.get_id().unwrap().to_string_lossy())
+        .collect()
+}
+
+#[test]
+fn enumerate_by_data_flow_and_state() {
+    let enumerator = system().device_enumerator();
+    let all = enumerator
+        .enum_audio_endpoints(DataFlowMask::All, DeviceStateMask::all())
+        .unwrap();
+    assert_eq!(all.get_count().unwrap(), 3);
+    assert_eq!(ids(&all.to_vec().unwrap()), ["speakers", "headset", "mic"]);
+
+    let active_render = enumerator
+        .enum_audio_endpoints(DataFlowMask::Render, DeviceStateMask::ACTIVE)
+        .unwrap();
+    assert_eq!(ids(&active_render.to_vec().unwrap()), ["speakers"]);
+
+    let capture = enumerator
+        .enum_audio_endpoints(DataFlowMask::Capture, DeviceStateMask::all())
+        .unwrap();
+    assert_eq!(ids(&capture.to_vec().unwrap()), ["mic"]);
+    assert_eq!(
+        capture.to_vec().unwrap()[0].data_flow().unwrap(),
+        DataFlow::Capture
+    );
+}
+
+#[test]
+fn get_device_by_id() {
+    let enumerator = system().device_enumerator();
+    let headset = enumerator.get_device(&WinString::from("headset")).unwrap();
+    assert_eq!(headset.get_state().unwrap(), DeviceState::Unplugged);
+    assert!(matches!(
+        enumerator.get_device(&WinString::from("missing")),
+        Err(Error::DeviceNotFound)
+    ));
+}
+
+#[test]
+fn removed_device_is_invalidated() {
+    let system = system();
+    let enumerator = system.device_enumerator();
+    let mic = enumerator.get_device(&WinString::from("mic")).unwrap();
+    system.remove_device("mic");
+    assert!(matches!(mic.get_state(), Err(Error::DeviceInvalidated)));
+}
+
+#[test]
+fn device_removed_while_iterating() {
+    let system = system();
+    let devices = system
+        .device_enumerator()
+        .enum_audio_endpoints(DataFlowMask::All, DeviceStateMask::all())
+        .unwrap();
+    system.remove_device("headset");
+
+    let items: Vec<_> = devices.iter().collect();
+    assert_eq!(items.len(), 3);
+    assert_eq!(items[0].as_ref().unwrap().get_id().unwrap(), "speakers");
+    assert!(matches!(items[1], Err(Error::DeviceInvalidated)));
+    assert_eq!(items[2].as_ref().unwrap().get_id().unwrap(), "mic");
+
+    let lossy: Vec<_> = devices.iter().lossy().collect();
+    assert_eq!(ids(&lossy), ["speakers", "mic"]);
+    assert!(matches!(devices.to_vec(), Err(Error::DeviceInvalidated)));
+}
+
+#[test]
+fn session_removed_while_iterating() {
+    let system = system();
+    for instance in &["1", "2", "3"] {
+        system.add_session(
+            "speakers",
+            FakeSession::new(instance).display_name(instance),
+        );
+    }
+    let sessions = system
+        .device_enumerator()
+        .get_device(&WinString::from("speakers"))
+        .unwrap()
+        .activate_audio_session_manager2()
+        .unwrap()
+        .get_session_enumerator()
+        .unwrap();
+    system.disconnect_session("2", AudioSessionDisconnectReason::SessionLogoff);
+
+    let items: Vec<_> = sessions.iter().collect();
+    assert_eq!(items.len(), 3);
+    assert!(items[0].is_ok());
+    assert!(matches!(items[1], Err(Error::DeviceInvalidated)));
+    assert!(items[2].is_ok());
+
+    let names: Vec<String> = sessions
+        .iter()
+        .lossy()
+        .map(|x| x.get_display_name().unwrap().to_string_lossy())
+        .collect();
+    assert_eq!(names, ["1", "3"]);
+    assert!(matches!(sessions.to_vec(), Err(Error::DeviceInvalidated)));
+}
+
+#[test]
+fn defaults() {
+    let system = system();
+    let enumerator = system.device_enumerator();
+    assert!(matches!(
+        enumerator.get_default_audio_endpoint(DataFlow::Render, DeviceRole::Console),
+        Err(Error::DeviceNotFound)
+    ));
+
+    system.set_default_device(DataFlow::Render, DeviceRole::Console, "speakers");
+    let default = enumerator
+        .get_default_audio_endpoint(DataFlow::Render, DeviceRole::Console)
+        .unwrap();
+    assert_eq!(default.get_id().unwrap(), "speakers");
+    assert!(default
+        .is_default_for(&enumerator, DeviceRole::Console)
+        .unwrap());
+    assert!(!default
+        .is_default_for(&enumerator, DeviceRole::Communications)
+        .unwrap());
+
+    // Removing the default device leaves the role without a default.
+    system.remove_device("speakers");
+    assert!(matches!(
+        enumerator.get_default_audio_endpoint(DataFlow::Render, DeviceRole::Console),
+        Err(Error::DeviceNotFound)
+    ));
+}
+
+#[test]
+fn properties() {
+    let system = system();
+    let enumerator = system.device_enumerator();
+    let speakers = enumerator.get_device(&WinString::from("speakers")).unwrap();
+    assert_eq!(speakers.friendly_name().unwrap(), "Speakers");
+
+    let store = speakers
+        .open_property_store(StorageAccessMode::Read)
+        .unwrap();
+    assert_eq!(store.get_count().unwrap(), 1);
+    assert_eq!(store.keys().unwrap(), [DEVICE_FRIENDLY_NAME]);
+
+    system.set_property(
+        "speakers",
+        DEVICE_FRIENDLY_NAME,
+        Property::Str("Desk speakers".into()),
+    );
+    assert_eq!(speakers.friendly_name().unwrap(), "Desk speakers");
+    assert_eq!(
+        store.get_value(DEVICE_FRIENDLY_NAME).unwrap(),
+        Property::Str("Desk speakers".into())
+    );
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Notification {
+    Default(DataFlow, DeviceRole, String),
+    Added(String),
+    Removed(String),
+    State(String, DeviceState),
+    Property(String, PropertyKey),
+}
+
+#[derive(Clone, Default)]
+struct Recorder(Rc<RefCell<Vec<Notification>>>);
+
+impl NotificationClient for Recorder {
+    fn on_default_device_changed(
+        &self,
+        data_flow: DataFlow,
+        role: DeviceRole,
+        device_id: &WinStr,
+    ) -> win32_coreaudio::Result<()> {
+        self.0.borrow_mut().push(Notification::Default(
+            data_flow,
+            role,
+            device_id.to_string_lossy(),
+        ));
+        Ok(())
+    }
+
+    fn on_device_added(&self, device_id: &WinStr) -> win32_coreaudio::Result<()> {
+        self.0
+            .borrow_mut()
+            .push(Notification::Added(device_id.to_string_lossy()));
+        Ok(())
+    }
+
+    fn on_device_removed(&self, device_id: &WinStr) -> win32_coreaudio::Result<()> {
+        self.0
+            .borrow_mut()
+            .push(Notification::Removed(device_id.to_string_lossy()));
+        Ok(())
+    }
+
+    fn on_device_state_changed(
+        &self,
+        device_id: &WinStr,
+        state: DeviceState,
+    ) -> win32_coreaudio::Result<()> {
+        self.0
+            .borrow_mut()
+            .push(Notification::State(device_id.to_string_lossy(), state));
+        Ok(())
+    }
+
+    fn on_property_value_changed(
+        &self,
+        device_id: &WinStr,
+        property_key: PropertyKey,
+    ) -> win32_coreaudio::Result<()> {
+        self.0.borrow_mut().push(Notification::Property(
+            device_id.to_string_lossy(),
+            property_key,
+        ));
+        Ok(())
+    }
+}
+
+#[test]
+fn notifications() {
+    let system = system();
+    let enumerator = system.device_enumerator();
+    let recorder = Recorder::default();
+    let handle = enumerator
+        .register_endpoint_notification(recorder.clone())
+        .unwrap();
+
+    system.add_device(FakeDevice::new("usb", DataFlow::Render));
+    system.set_device_state("headset", DeviceState::Active);
+    // Setting the same state again is not a change.
+    system.set_device_state("headset", DeviceState::Active);
+    system.set_default_device(DataFlow::Render, DeviceRole::Multimedia, "usb");
+    system.set_property("usb", DEVICE_FRIENDLY_NAME, Property::Str("USB".into()));
+    system.remove_device("usb");
+    // Removing an unknown device notifies nobody.
+    system.remove_device("usb");
+
+    assert_eq!(
+        *recorder.0.borrow(),
+        [
+            Notification::Added("usb".into()),
+            Notification::State("headset".into(), DeviceState::Active),
+            Notification::Default(DataFlow::Render, DeviceRole::Multimedia, "usb".into()),
+            Notification::Property("usb".into(), DEVICE_FRIENDLY_NAME),
+            Notification::Removed("usb".into()),
+        ]
+    );
+
+    handle.unregister();
+    system.add_device(FakeDevice::new("late", DataFlow::Capture));
+    assert_eq!(recorder.0.borrow().len(), 5);
+}
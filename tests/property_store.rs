@@ -0,0 +1,191 @@
+use std::collections::BTreeMap;
+
+use win32_coreaudio::{
+    backend::fake::{FakeAudioSystem, FakeDevice},
+    property_keys::{DEVICE_CLASS, DEVICE_DESCRIPTION, DEVICE_DRIVER_VERSION},
+    string::WinString,
+    DataFlow, Property, PropertyChange, PropertyKey, PropertyStore, StorageAccessMode,
+    DEVICE_FRIENDLY_NAME,
+};
+
+fn key(pid: u32) -> PropertyKey {
+    format!("{{00000000-0000-0000-0000-000000000001}} {}", pid)
+        .parse()
+        .unwrap()
+}
+
+fn snapshot(properties: &[(PropertyKey, Property)]) -> BTreeMap<PropertyKey, Property> {
+    properties.iter().cloned().collect()
+}
+
+#[test]
+fn key_display_and_parse() {
+    assert_eq!(
+        DEVICE_FRIENDLY_NAME.to_string(),
+        "{A45C254E-DF1C-4EFD-8020-67D146A850E0} 14"
+    );
+    for s in &[
+        "{A45C254E-DF1C-4EFD-8020-67D146A850E0} 14",
+        "{a45c254e-df1c-4efd-8020-67d146a850e0} 14",
+        "a45c254e-df1c-4efd-8020-67d146a850e0 14",
+        "  {a45c254e-df1c-4efd-8020-67d146a850e0}\t 14 ",
+    ] {
+        assert_eq!(s.parse::<PropertyKey>(), Ok(DEVICE_FRIENDLY_NAME), "{}", s);
+    }
+    assert_eq!(
+        DEVICE_FRIENDLY_NAME.to_string().parse::<PropertyKey>(),
+        Ok(DEVICE_FRIENDLY_NAME)
+    );
+
+    for s in &[
+        "",
+        "{a45c254e-df1c-4efd-8020-67d146a850e0}",
+        "{a45c254e-df1c-4efd-8020-67d146a850e0}14",
+        "{a45c254e-df1c-4efd-8020-67d146a850e0} -1",
+        "{a45c254e-df1c-4efd-8020-67d146a850e0} x",
+        "{a45c254e-df1c-4efd-8020-67d146a850e} 14",
+        "{g45c254e-df1c-4efd-8020-67d146a850e0} 14",
+        "FriendlyName 14",
+    ] {
+        assert!(s.parse::<PropertyKey>().is_err(), "{}", s);
+    }
+    assert_eq!(
+        "14".parse::<PropertyKey>().unwrap_err().to_string(),
+        "invalid property key, expected the form `{fmtid} pid`"
+    );
+}
+
+#[test]
+fn keys_are_ordered_by_fmtid_then_pid() {
+    let mut keys = vec![
+        "{00000000-0000-0000-0000-000000000002} 1",
+        "{00000000-0000-0000-0000-000000000001} 10",
+        "{10000000-0000-0000-0000-000000000000} 0",
+        "{00000000-0000-0000-0000-000000000001} 2",
+    ]
+    .into_iter()
+    .map(|s| s.parse::<PropertyKey>().unwrap())
+    .collect::<Vec<_>>();
+    keys.sort();
+    assert_eq!(
+        keys.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        [
+            "{00000000-0000-0000-0000-000000000001} 2",
+            "{00000000-0000-0000-0000-000000000001} 10",
+            "{00000000-0000-0000-0000-000000000002} 1",
+            "{10000000-0000-0000-0000-000000000000} 0",
+        ]
+    );
+    assert!(key(1) < key(2));
+    assert_eq!(key(1).cmp(&key(1)), std::cmp::Ordering::Equal);
+}
+
+#[test]
+fn diff_of_equal_snapshots_is_empty() {
+    let old = snapshot(&[(key(1), Property::U32(1)), (key(2), Property::Bool(true))]);
+    assert_eq!(PropertyStore::diff(&old, &old.clone()), []);
+    assert_eq!(PropertyStore::diff(&BTreeMap::new(), &BTreeMap::new()), []);
+}
+
+#[test]
+fn diff_reports_changes_in_key_order() {
+    let old = snapshot(&[
+        (key(1), Property::U32(1)),
+        (key(2), Property::Str("old".into())),
+        (key(4), Property::Bool(true)),
+    ]);
+    let new = snapshot(&[
+        (key(2), Property::Str("new".into())),
+        (key(3), Property::I32(-3)),
+        (key(4), Property::Bool(true)),
+        (key(5), Property::Empty),
+    ]);
+    assert_eq!(
+        PropertyStore::diff(&old, &new),
+        [
+            PropertyChange::Removed {
+                key: key(1),
+                old: Property::U32(1),
+            },
+            PropertyChange::Changed {
+                key: key(2),
+                old: Property::Str("old".into()),
+                new: Property::Str("new".into()),
+            },
+            PropertyChange::Added {
+                key: key(3),
+                new: Property::I32(-3),
+            },
+            PropertyChange::Added {
+                key: key(5),
+                new: Property::Empty,
+            },
+        ]
+    );
+    let keys: Vec<_> = PropertyStore::diff(&new, &old)
+        .iter()
+        .map(PropertyChange::key)
+        .collect();
+    assert_eq!(keys, [key(1), key(2), key(3), key(5)]);
+}
+
+#[test]
+fn diff_compares_floats_by_bits() {
+    let nan = snapshot(&[
+        (key(1), Property::F32(f32::NAN)),
+        (key(2), Property::F64(f64::NAN)),
+    ]);
+    assert_eq!(PropertyStore::diff(&nan, &nan.clone()), []);
+
+    let zero = snapshot(&[(key(1), Property::F32(0.0))]);
+    let negative_zero = snapshot(&[(key(1), Property::F32(-0.0))]);
+    assert_eq!(PropertyStore::diff(&zero, &negative_zero).len(), 1);
+}
+
+#[test]
+fn diff_of_a_type_change() {
+    let old = snapshot(&[(key(1), Property::U32(1))]);
+    let new = snapshot(&[(key(1), Property::I32(1))]);
+    assert_eq!(
+        PropertyStore::diff(&old, &new),
+        [PropertyChange::Changed {
+            key: key(1),
+            old: Property::U32(1),
+            new: Property::I32(1),
+        }]
+    );
+}
+
+#[test]
+fn diff_of_store_snapshots() {
+    let system = FakeAudioSystem::new();
+    system.add_device(
+        FakeDevice::new("speakers", DataFlow::Render)
+            .property(DEVICE_FRIENDLY_NAME, Property::Str("Speakers".into()))
+            .property(DEVICE_DRIVER_VERSION, Property::Str("1.0".into()))
+            .property(DEVICE_CLASS, Property::Str("MEDIA".into())),
+    );
+    let store = system
+        .device_enumerator()
+        .get_device(&WinString::from("speakers"))
+        .unwrap()
+        .open_property_store(StorageAccessMode::Read)
+        .unwrap();
+    let before = store.snapshot().unwrap();
+    assert_eq!(before.len(), 3);
+
+    system.set_property(
+        "speakers",
+        DEVICE_DRIVER_VERSION,
+        Property::Str("2.0".into()),
+    );
+    system.set_property("speakers", DEVICE_DESCRIPTION, Property::Str("Desk".into()));
+    let after = store.snapshot().unwrap();
+    let keys: Vec<_> = PropertyStore::diff(&before, &after)
+        .iter()
+        .map(PropertyChange::key)
+        .collect();
+    let mut expected = vec![DEVICE_DRIVER_VERSION, DEVICE_DESCRIPTION];
+    expected.sort();
+    assert_eq!(keys, expected);
+}
@@ -0,0 +1,175 @@
+use std::ffi::CString;
+
+use proptest::prelude::*;
+use win32_coreaudio::{string::WinString, Property};
+use windows::core::GUID;
+
+fn encode(property: &Property) -> Vec<u8> {
+    property.to_bytes().unwrap()
+}
+
+#[test]
+fn bool_layout() {
+    assert_eq!(
+        encode(&Property::Bool(true)),
+        [0x0b, 0, 0, 0, 0xff, 0xff, 0, 0]
+    );
+    assert_eq!(encode(&Property::Bool(false)), [0x0b, 0, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(
+        Property::from_bytes(&[0x0b, 0, 0, 0, 0xff, 0xff, 0, 0]).unwrap(),
+        Property::Bool(true)
+    );
+    assert_eq!(
+        Property::from_bytes(&[0x0b, 0, 0, 0, 0, 0, 0, 0]).unwrap(),
+        Property::Bool(false)
+    );
+}
+
+#[test]
+fn scalar_layout() {
+    assert_eq!(encode(&Property::Empty), [0, 0, 0, 0]);
+    assert_eq!(encode(&Property::Null), [1, 0, 0, 0]);
+    assert_eq!(encode(&Property::I8(-1)), [0x10, 0, 0, 0, 0xff, 0, 0, 0]);
+    assert_eq!(
+        encode(&Property::U16(0x1234)),
+        [0x12, 0, 0, 0, 0x34, 0x12, 0, 0]
+    );
+    assert_eq!(
+        encode(&Property::U32(0x1234_5678)),
+        [0x13, 0, 0, 0, 0x78, 0x56, 0x34, 0x12]
+    );
+    assert_eq!(
+        encode(&Property::FileTime(0x0102_0304_0506_0708)),
+        [0x40, 0, 0, 0, 8, 7, 6, 5, 4, 3, 2, 1]
+    );
+}
+
+#[test]
+fn string_layout() {
+    assert_eq!(
+        encode(&Property::Str(WinString::from("ab"))),
+        [0x1f, 0, 0, 0, 3, 0, 0, 0, b'a', 0, b'b', 0, 0, 0, 0, 0]
+    );
+    assert_eq!(
+        encode(&Property::AnsiStr(CString::new("abc").unwrap())),
+        [0x1e, 0, 0, 0, 4, 0, 0, 0, b'a', b'b', b'c', 0]
+    );
+    assert_eq!(
+        encode(&Property::StrVec(vec![
+            WinString::from("a"),
+            WinString::from("")
+        ])),
+        [0x1f, 0x10, 0, 0, 2, 0, 0, 0, 2, 0, 0, 0, b'a', 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0]
+    );
+}
+
+#[test]
+fn blob_and_guid_layout() {
+    assert_eq!(
+        encode(&Property::Blob(vec![1, 2, 3])),
+        [0x41, 0, 0, 0, 3, 0, 0, 0, 1, 2, 3, 0]
+    );
+    let guid = GUID::from_u128(0x00112233_4455_6677_8899_aabbccddeeff);
+    assert_eq!(
+        encode(&Property::Guid(guid)),
+        [
+            0x48, 0, 0, 0, 0x33, 0x22, 0x11, 0x00, 0x55, 0x44, 0x77, 0x66, 0x88, 0x99, 0xaa, 0xbb,
+            0xcc, 0xdd, 0xee, 0xff,
+        ]
+    );
+}
+
+#[test]
+fn vt_int_and_uint_decode_as_32_bit() {
+    assert_eq!(
+        Property::from_bytes(&[0x16, 0, 0, 0, 0xfe, 0xff, 0xff, 0xff]).unwrap(),
+        Property::I32(-2)
+    );
+    assert_eq!(
+        Property::from_bytes(&[0x17, 0, 0, 0, 2, 0, 0, 0]).unwrap(),
+        Property::U32(2)
+    );
+}
+
+#[test]
+fn unsupported_types() {
+    // VT_CY is a valid type that `Property` does not represent.
+    assert_eq!(
+        Property::from_bytes(&[6, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]).unwrap(),
+        Property::Unsupported { vt: 6 }
+    );
+    assert!(Property::Unsupported { vt: 6 }.to_bytes().is_err());
+}
+
+#[test]
+fn truncated_values() {
+    assert!(Property::from_bytes(&[]).is_err());
+    assert!(Property::from_bytes(&[0x13, 0]).is_err());
+    assert!(Property::from_bytes(&[0x13, 0, 0, 0, 1, 2]).is_err());
+    assert!(Property::from_bytes(&[0x41, 0, 0, 0, 4, 0, 0, 0, 1, 2, 3]).is_err());
+    assert!(Property::from_bytes(&[0x1f, 0x10, 0, 0, 0xff, 0xff, 0xff, 0xff]).is_err());
+}
+
+fn win_string() -> impl Strategy<Value = WinString> {
+    "[^\u{0}]{0,16}".prop_map(WinString::from)
+}
+
+fn property() -> impl Strategy<Value = Property> {
+    let float32 = any::<f32>().prop_filter("NaN is not equal to itself", |x| !x.is_nan());
+    let float64 = any::<f64>().prop_filter("NaN is not equal to itself", |x| !x.is_nan());
+    prop_oneof![
+        Just(Property::Empty),
+        Just(Property::Null),
+        any::<i8>().prop_map(Property::I8),
+        any::<u8>().prop_map(Property::U8),
+        any::<i16>().prop_map(Property::I16),
+        any::<u16>().prop_map(Property::U16),
+        any::<i32>().prop_map(Property::I32),
+        any::<u32>().prop_map(Property::U32),
+        any::<i64>().prop_map(Property::I64),
+        any::<u64>().prop_map(Property::U64),
+        float32.prop_map(Property::F32),
+        float64.prop_map(Property::F64),
+        any::<bool>().prop_map(Property::Bool),
+        win_string().prop_map(Property::Str),
+        prop::collection::vec(1u8.., 0..16)
+            .prop_map(|bytes| Property::AnsiStr(CString::new(bytes).unwrap())),
+        prop::collection::vec(any::<u8>(), 0..32).prop_map(Property::Blob),
+        any::<u128>().prop_map(|x| Property::Guid(GUID::from_u128(x))),
+        any::<u64>().prop_map(Property::FileTime),
+        prop::collection::vec(win_string(), 0..8).prop_map(Property::StrVec),
+        prop::collection::vec(any::<i32>(), 0..8).prop_map(Property::I32Vec),
+        prop::collection::vec(any::<u32>(), 0..8).prop_map(Property::U32Vec),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn round_trip(property in property()) {
+        let bytes = encode(&property);
+        prop_assert_eq!(bytes.len() % 4, 0);
+        prop_assert_eq!(Property::from_bytes(&bytes).unwrap(), property);
+    }
+
+    #[test]
+    fn trailing_bytes_are_ignored(property in property(), trailing in prop::collection::vec(any::<u8>(), 0..8)) {
+        let mut bytes = encode(&property);
+        bytes.extend(trailing);
+        prop_assert_eq!(Property::from_bytes(&bytes).unwrap(), property);
+    }
+
+    #[test]
+    fn truncation_never_yields_a_different_value(property in property(), cut in any::<prop::sample::Index>()) {
+        let bytes = encode(&property);
+        // Only the padding at the end may be dropped without losing data.
+        let len = cut.index(bytes.len());
+        if let Ok(decoded) = Property::from_bytes(&bytes[..len]) {
+            prop_assert_eq!(decoded, property);
+        }
+    }
+
+    #[test]
+    fn decoding_arbitrary_bytes_does_not_panic(bytes in prop::collection::vec(any::<u8>(), 0..64)) {
+        let _ = Property::from_bytes(&bytes);
+    }
+}
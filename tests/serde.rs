@@ -0,0 +1,129 @@
+#![cfg(feature = "serde")]
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+use win32_coreaudio::{
+    ChannelMask, DataFlow, DeviceRole, DeviceState, JackConnectionType, JackDescription,
+    JackGenLocation, JackGeoLocation, JackPortConnection, OwnedNotificationData, Property,
+    PropertyChange, PropertyKey, DEVICE_FRIENDLY_NAME,
+};
+use windows::core::GUID;
+use windows::Win32::Media::Audio::EDataFlow;
+
+/// Serialize `value`, check the JSON and deserialize it back.
+fn round_trip<T>(value: &T, expected: Value)
+where
+    T: Serialize + DeserializeOwned + PartialEq + std::fmt::Debug,
+{
+    let serialized = serde_json::to_value(value).unwrap();
+    assert_eq!(serialized, expected);
+    let deserialized: T = serde_json::from_value(serialized).unwrap();
+    assert_eq!(&deserialized, value);
+}
+
+const CONTEXT: GUID = GUID::from_u128(0x0a1b2c3d_4e5f_6071_8293_a4b5c6d7e8f9);
+
+#[test]
+fn notification_data() {
+    let data = OwnedNotificationData {
+        event_context: CONTEXT,
+        muted: true,
+        master_volume: 0.5,
+        channel_volumes: vec![0.25, 0.75],
+    };
+    round_trip(
+        &data,
+        json!({
+            "event_context": "{0A1B2C3D-4E5F-6071-8293-A4B5C6D7E8F9}",
+            "muted": true,
+            "master_volume": 0.5,
+            "channel_volumes": [0.25, 0.75],
+        }),
+    );
+    assert_eq!(data.as_data().to_owned_data(), data);
+    assert_eq!(
+        serde_json::to_value(data.as_data()).unwrap(),
+        serde_json::to_value(&data).unwrap()
+    );
+}
+
+#[test]
+fn guids_parse_in_any_case_with_or_without_braces() {
+    for context in [
+        "{0a1b2c3d-4e5f-6071-8293-a4b5c6d7e8f9}",
+        "0A1B2C3D-4E5F-6071-8293-A4B5C6D7E8F9",
+    ] {
+        let data: OwnedNotificationData = serde_json::from_value(json!({
+            "event_context": context,
+            "muted": false,
+            "master_volume": 1.0,
+            "channel_volumes": [],
+        }))
+        .unwrap();
+        assert_eq!(data.event_context, CONTEXT);
+    }
+    assert!(serde_json::from_value::<OwnedNotificationData>(json!({
+        "event_context": "{0A1B2C3D-4E5F-6071-8293}",
+        "muted": false,
+        "master_volume": 1.0,
+        "channel_volumes": [],
+    }))
+    .is_err());
+}
+
+#[test]
+fn property_keys() {
+    round_trip(
+        &DEVICE_FRIENDLY_NAME,
+        json!("{A45C254E-DF1C-4EFD-8020-67D146A850E0} 14"),
+    );
+    assert!(serde_json::from_value::<PropertyKey>(json!("FriendlyName")).is_err());
+
+    let change = PropertyChange::Changed {
+        key: DEVICE_FRIENDLY_NAME,
+        old: Property::U32(1),
+        new: Property::U32(2),
+    };
+    let deserialized: PropertyChange =
+        serde_json::from_value(serde_json::to_value(&change).unwrap()).unwrap();
+    assert_eq!(deserialized, change);
+}
+
+#[test]
+fn mapped_enums() {
+    round_trip(&DataFlow::Capture, json!("Capture"));
+    round_trip(&DeviceRole::Communications, json!("Communications"));
+    round_trip(&DeviceState::Unplugged, json!("Unplugged"));
+    assert!(serde_json::from_value::<DataFlow>(json!("Sideways")).is_err());
+}
+
+#[test]
+fn unknown_raw_values() {
+    round_trip(&DataFlow::Unknown(EDataFlow(7)), json!(7));
+    round_trip(&DeviceState::Unknown(0x20), json!(32));
+    // Known raw values map back to their variant.
+    assert_eq!(
+        serde_json::from_value::<DeviceState>(json!(1)).unwrap(),
+        DeviceState::Active
+    );
+    // Raw values out of the range of the underlying type are rejected.
+    assert!(serde_json::from_value::<DeviceState>(json!(-1)).is_err());
+}
+
+#[test]
+fn jack_channel_mapping_keeps_reserved_bits() {
+    let jack = JackDescription::new(
+        ChannelMask::from_bits_retain(0x8000_0003),
+        0x00ff_0000,
+        JackConnectionType::Mini,
+        JackGeoLocation::Front,
+        JackGenLocation::PrimaryBox,
+        JackPortConnection::Jack,
+        true,
+    );
+    let serialized = serde_json::to_value(&jack).unwrap();
+    assert_eq!(serialized["channel_mapping"], json!(0x8000_0003u32));
+    let deserialized: JackDescription = serde_json::from_value(serialized).unwrap();
+    assert_eq!(deserialized, jack);
+    assert_eq!(deserialized.channel_mapping.bits(), 0x8000_0003);
+}
@@ -0,0 +1,135 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::ffi::{OsStr, OsString};
+use std::hash::{Hash, Hasher};
+
+use win32_coreaudio::{
+    string::{WinStr, WinString},
+    wstr,
+};
+
+/// `s` encoded as a null-terminated wide string, like Windows returns it.
+fn with_nul(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(Some(0)).collect()
+}
+
+fn hash(value: &impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[test]
+fn from_str_truncates_at_interior_nul() {
+    let s = WinString::from("abc\0def");
+    assert_eq!(s.len(), 3);
+    assert_eq!(s, "abc");
+    assert_eq!(WinString::from(String::from("\0abc")), "");
+    assert_eq!(WinString::from(OsStr::new("ab\0c")), "ab");
+}
+
+#[test]
+fn parse_rejects_interior_nul() {
+    let error = "abc\0def".parse::<WinString>().unwrap_err();
+    assert_eq!(error.position(), 3);
+    assert_eq!(error.to_string(), "null character at byte 3");
+    assert_eq!("abc".parse::<WinString>().unwrap(), "abc");
+    assert_eq!("".parse::<WinString>().unwrap(), "");
+}
+
+#[test]
+fn wstr_matches_winstring() {
+    let literal = wstr!("Speakers (\u{1F50A})");
+    let owned = WinString::from("Speakers (\u{1F50A})");
+    assert_eq!(literal.as_slice(), owned.as_slice());
+    assert_eq!(literal.to_string_lossy(), "Speakers (\u{1F50A})");
+}
+
+#[test]
+fn borrowed_from_null_terminated_buffer() {
+    let buf = with_nul("Speakers (USB)");
+    let s = unsafe { WinStr::from_wide_with_nul(&buf) };
+    assert_eq!(s.len(), 14);
+    assert!(!s.is_empty());
+    assert_eq!(s.as_slice(), &buf[..14]);
+    assert_eq!(s.to_string().unwrap(), "Speakers (USB)");
+    assert_eq!(s.to_string_lossy(), "Speakers (USB)");
+    assert_eq!(s.to_os_string(), OsString::from("Speakers (USB)"));
+    assert_eq!(s.to_string_lossy(), format!("{}", s));
+    assert_eq!(format!("{:?}", s), "\"Speakers (USB)\"");
+    assert_eq!(s.chars().count(), 14);
+
+    let empty_buf = [0];
+    let empty = unsafe { WinStr::from_wide_with_nul(&empty_buf) };
+    assert!(empty.is_empty());
+    assert_eq!(empty, "");
+}
+
+#[test]
+fn borrowed_and_owned_hash_and_compare_equal() {
+    let buf = with_nul("{0.0.0.00000000}.{abc}");
+    let borrowed = unsafe { WinStr::from_wide_with_nul(&buf) };
+    let owned = WinString::from("{0.0.0.00000000}.{abc}");
+    assert_eq!(borrowed, owned.as_winstr());
+    assert_eq!(*borrowed, owned);
+    assert_eq!(owned, *borrowed);
+    assert_eq!(hash(&borrowed), hash(&owned.as_winstr()));
+    assert_eq!(hash(&owned), hash(&borrowed));
+    assert_eq!(borrowed.to_winstring(), owned);
+
+    let mut set = HashSet::new();
+    set.insert(owned.clone());
+    assert!(set.contains(borrowed));
+    assert!(!set.contains(wstr!("{0.0.0.00000000}.{ABC}")));
+}
+
+#[test]
+fn compare_with_str() {
+    let buf = with_nul("{0.0.1.00000000}.{ABC-def}");
+    let s = unsafe { WinStr::from_wide_with_nul(&buf) };
+    assert!(*s == *"{0.0.1.00000000}.{ABC-def}");
+    assert!(*"{0.0.1.00000000}.{ABC-def}" == *s);
+    assert_eq!(s, "{0.0.1.00000000}.{ABC-def}");
+    assert_ne!(s, "{0.0.1.00000000}.{abc-def}");
+    assert_ne!(s, "{0.0.1.00000000}");
+
+    assert!(s.eq_ignore_case("{0.0.1.00000000}.{abc-DEF}"));
+    assert!(!s.eq_ignore_case("{0.0.1.00000000}.{abc}"));
+
+    assert!(s.starts_with("{0.0.1."));
+    assert!(s.starts_with(""));
+    assert!(!s.starts_with("{0.0.0."));
+    assert!(!s.starts_with("{0.0.1.00000000}.{ABC-def}x"));
+
+    assert!(s.ends_with(".{ABC-def}"));
+    assert!(s.ends_with(""));
+    assert!(!s.ends_with(".{abc-def}"));
+    assert!(!s.ends_with("x{0.0.1.00000000}.{ABC-def}"));
+}
+
+#[test]
+fn non_ascii_characters() {
+    let buf = with_nul("Lautsprecher \u{1F50A} \u{e4}");
+    let s = unsafe { WinStr::from_wide_with_nul(&buf) };
+    // The emoji takes two UTF-16 units.
+    assert_eq!(s.len(), 17);
+    assert_eq!(s.chars().count(), 16);
+    assert_eq!(s.chars().last(), Some('\u{e4}'));
+    assert!(s.eq_ignore_case("LAUTSPRECHER \u{1F50A} \u{c4}"));
+    assert_eq!(s.to_string().unwrap(), "Lautsprecher \u{1F50A} \u{e4}");
+}
+
+#[test]
+fn unpaired_surrogate() {
+    let buf = [u16::from(b'a'), 0xD800, u16::from(b'b'), 0];
+    let s = unsafe { WinStr::from_wide_with_nul(&buf) };
+    assert_eq!(s.len(), 3);
+    assert!(s.to_string().is_err());
+    assert!(s.to_winstring().to_string().is_err());
+    assert_eq!(s.to_string_lossy(), "a\u{FFFD}b");
+    assert_eq!(format!("{}", s), "a\u{FFFD}b");
+    assert_eq!(s.chars().collect::<Vec<_>>(), ['a', '\u{FFFD}', 'b']);
+    assert_ne!(s, "a\u{FFFD}b");
+    #[cfg(not(windows))]
+    assert_eq!(s.to_os_string(), OsString::from("a\u{FFFD}b"));
+}
@@ -0,0 +1,321 @@
+use win32_coreaudio::{
+    backend::fake::{FakeAudioSystem, FakeDevice, FakePart, FakeTopology},
+    string::WinString,
+    ChannelLevel, ChannelMask, ConnectorDataFlow, ConnectorDetails, ConnectorType, DataFlow,
+    EdgeKind, JackConnectionType, JackDescription, JackGenLocation, JackGeoLocation,
+    JackPortConnection, Part, PartControl, PartType, TopologyEdge, TopologyGraph, TopologyNode,
+    VolumeRange, WalkDirection,
+};
+use windows::core::GUID;
+
+fn volume() -> PartControl {
+    PartControl::Volume(vec![ChannelLevel::new(
+        -6.0,
+        VolumeRange::new(-96.0, 0.0, 0.5),
+    )])
+}
+
+fn jack() -> JackDescription {
+    // Front left, front right and a reserved bit.
+    JackDescription::new(
+        ChannelMask::from_bits_retain(0x8000_0003),
+        0x0000_ff00,
+        JackConnectionType::Mini,
+        JackGeoLocation::Rear,
+        JackGenLocation::PrimaryBox,
+        JackPortConnection::Jack,
+        true,
+    )
+}
+
+/// A render endpoint whose connector is connected to the line out of an
+/// adapter, which mixes the wave stream into the line out and the
+/// headphones:
+///
+/// ```text
+/// adapter/0 wave -> adapter/1 volume -> adapter/2 line out => speakers/0
+///                                    -> adapter/3 headphones
+/// ```
+fn system() -> FakeAudioSystem {
+    let system = FakeAudioSystem::new();
+    system.add_device(FakeDevice::new("speakers", DataFlow::Render));
+    system.set_topology(FakeTopology::new("speakers").part(FakePart::connector(
+        0,
+        "Speakers",
+        ConnectorType::SoftwareIo,
+        ConnectorDataFlow::In,
+    )));
+    system.set_topology(
+        FakeTopology::new("adapter")
+            .part(FakePart::connector(
+                0,
+                "Wave",
+                ConnectorType::SoftwareIo,
+                ConnectorDataFlow::In,
+            ))
+            .part(
+                FakePart::subunit(1, "Master Volume")
+                    .control(volume())
+                    .control(PartControl::Mute(false)),
+            )
+            .part(
+                FakePart::connector(
+                    2,
+                    "Line Out",
+                    ConnectorType::PhysicalExternal,
+                    ConnectorDataFlow::Out,
+                )
+                .jack(jack()),
+            )
+            .part(FakePart::connector(
+                3,
+                "Headphones",
+                ConnectorType::PhysicalExternal,
+                ConnectorDataFlow::Out,
+            ))
+            .link(0, 1)
+            .link(1, 2)
+            .link(1, 3),
+    );
+    system.connect("speakers", 0, "adapter", 2);
+    system
+}
+
+fn speakers_graph(system: &FakeAudioSystem) -> TopologyGraph {
+    let speakers = system
+        .device_enumerator()
+        .get_device(&WinString::from("speakers"))
+        .unwrap();
+    TopologyGraph::from_device(&speakers).unwrap()
+}
+
+/// The part of the adapter with `local_id`, found through the connection.
+fn adapter_part(system: &FakeAudioSystem, local_id: u32) -> Part {
+    system
+        .device_enumerator()
+        .get_device(&WinString::from("speakers"))
+        .unwrap()
+        .activate_device_topology()
+        .unwrap()
+        .get_connector(0)
+        .unwrap()
+        .get_connected_to()
+        .unwrap()
+        .part()
+        .unwrap()
+        .get_topology_object()
+        .unwrap()
+        .get_part_by_id(local_id)
+        .unwrap()
+}
+
+fn node_ids(graph: &TopologyGraph) -> Vec<&str> {
+    graph.nodes.iter().map(|x| x.global_id.as_str()).collect()
+}
+
+fn edges(graph: &TopologyGraph) -> Vec<(&str, &str, EdgeKind)> {
+    graph
+        .edges
+        .iter()
+        .map(|x| {
+            (
+                graph.nodes[x.from].global_id.as_str(),
+                graph.nodes[x.to].global_id.as_str(),
+                x.kind,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn render_endpoint_walks_upstream_to_the_adapter() {
+    let graph = speakers_graph(&system());
+    assert_eq!(
+        node_ids(&graph),
+        [
+            "speakers/00000000",
+            "adapter/00000002",
+            "adapter/00000001",
+            "adapter/00000000",
+        ]
+    );
+    assert_eq!(
+        edges(&graph),
+        [
+            (
+                "adapter/00000002",
+                "speakers/00000000",
+                EdgeKind::Connection
+            ),
+            ("adapter/00000001", "adapter/00000002", EdgeKind::Link),
+            ("adapter/00000000", "adapter/00000001", EdgeKind::Link),
+        ]
+    );
+    assert_eq!(graph.device_ids(), ["speakers", "adapter"]);
+
+    let line_out = &graph.nodes[1];
+    assert_eq!(line_out.device_id, "adapter");
+    assert_eq!(line_out.local_id, 2);
+    assert_eq!(line_out.name, "Line Out");
+    assert_eq!(line_out.part_type, PartType::Connector);
+    assert_eq!(
+        line_out.connector,
+        Some(ConnectorDetails {
+            connector_type: ConnectorType::PhysicalExternal,
+            data_flow: ConnectorDataFlow::Out,
+        })
+    );
+    assert_eq!(graph.nodes[2].part_type, PartType::Subunit);
+    assert_eq!(graph.nodes[2].connector, None);
+}
+
+#[test]
+fn walk_downstream_from_the_adapter() {
+    let system = system();
+    let graph =
+        TopologyGraph::walk(&[adapter_part(&system, 0)], WalkDirection::Downstream).unwrap();
+    assert_eq!(
+        node_ids(&graph),
+        [
+            "adapter/00000000",
+            "adapter/00000001",
+            "adapter/00000002",
+            "adapter/00000003",
+            "speakers/00000000",
+        ]
+    );
+    assert_eq!(
+        edges(&graph),
+        [
+            ("adapter/00000000", "adapter/00000001", EdgeKind::Link),
+            ("adapter/00000001", "adapter/00000002", EdgeKind::Link),
+            ("adapter/00000001", "adapter/00000003", EdgeKind::Link),
+            (
+                "adapter/00000002",
+                "speakers/00000000",
+                EdgeKind::Connection
+            ),
+        ]
+    );
+}
+
+#[test]
+fn walk_both_ways_reaches_every_connected_part() {
+    let system = system();
+    let speakers = system
+        .device_enumerator()
+        .get_device(&WinString::from("speakers"))
+        .unwrap()
+        .activate_device_topology()
+        .unwrap()
+        .get_connector(0)
+        .unwrap()
+        .part()
+        .unwrap();
+    let graph = TopologyGraph::walk(&[speakers], WalkDirection::Both).unwrap();
+    assert_eq!(
+        node_ids(&graph),
+        [
+            "speakers/00000000",
+            "adapter/00000002",
+            "adapter/00000001",
+            "adapter/00000003",
+            "adapter/00000000",
+        ]
+    );
+    // The connection is found from both ends but listed once.
+    assert_eq!(
+        edges(&graph),
+        [
+            (
+                "adapter/00000002",
+                "speakers/00000000",
+                EdgeKind::Connection
+            ),
+            ("adapter/00000001", "adapter/00000002", EdgeKind::Link),
+            ("adapter/00000001", "adapter/00000003", EdgeKind::Link),
+            ("adapter/00000000", "adapter/00000001", EdgeKind::Link),
+        ]
+    );
+}
+
+#[test]
+fn controls_and_jacks() {
+    let graph = speakers_graph(&system());
+    let controls: Vec<(&str, &PartControl)> = graph
+        .controls()
+        .map(|(node, control)| (node.name.as_str(), control))
+        .collect();
+    assert_eq!(
+        controls,
+        [
+            ("Master Volume", &volume()),
+            ("Master Volume", &PartControl::Mute(false)),
+        ]
+    );
+
+    let jacks: Vec<(&str, &JackDescription)> = graph
+        .jacks()
+        .map(|(node, jack)| (node.name.as_str(), jack))
+        .collect();
+    assert_eq!(jacks, [("Line Out", &jack())]);
+    assert_eq!(jacks[0].1.channel_mapping.bits(), 0x8000_0003);
+}
+
+fn node(device_id: &str, local_id: u32, name: &str, connector: bool) -> TopologyNode {
+    TopologyNode {
+        global_id: format!("{}/{}", device_id, local_id),
+        device_id: device_id.into(),
+        local_id,
+        name: name.into(),
+        part_type: if connector {
+            PartType::Connector
+        } else {
+            PartType::Subunit
+        },
+        sub_type: GUID::from_u128(0x1234),
+        connector: connector.then_some(ConnectorDetails {
+            connector_type: ConnectorType::PhysicalExternal,
+            data_flow: ConnectorDataFlow::Out,
+        }),
+        controls: Vec::new(),
+        jacks: Vec::new(),
+    }
+}
+
+#[test]
+fn to_dot_escapes_names() {
+    let mut line_in = node(r#"usb "dock""#, 0, r#"Line "In" \ 2"#, true);
+    line_in.controls.push(PartControl::Mute(true));
+    let mut graph = TopologyGraph::new();
+    graph.nodes.push(line_in);
+    graph.nodes.push(node("adapter", 1, "", false));
+    graph.add_edge(0, 1, EdgeKind::Connection);
+    graph.add_edge(0, 1, EdgeKind::Connection);
+    assert_eq!(
+        graph.edges,
+        [TopologyEdge {
+            from: 0,
+            to: 1,
+            kind: EdgeKind::Connection,
+        }]
+    );
+
+    let expected = format!(
+        concat!(
+            "digraph topology {{\n",
+            "    subgraph cluster_0 {{\n",
+            "        label=\"usb \\\"dock\\\"\";\n",
+            "        n0 [label=\"Line \\\"In\\\" \\\\ 2\\nmute true\", shape=box];\n",
+            "    }}\n",
+            "    subgraph cluster_1 {{\n",
+            "        label=\"adapter\";\n",
+            "        n1 [label=\"{:?}\", shape=ellipse];\n",
+            "    }}\n",
+            "    n0 -> n1 [style=dashed];\n",
+            "}}\n",
+        ),
+        GUID::from_u128(0x1234)
+    );
+    assert_eq!(graph.to_dot(), expected);
+}
@@ -0,0 +1,221 @@
+use win32_coreaudio::{ChannelMask, Error, WaveEncoding, WaveFormat};
+use windows::core::GUID;
+
+/// `KSDATAFORMAT_SUBTYPE_IEEE_FLOAT`.
+const IEEE_FLOAT: GUID = GUID::from_u128(0x00000003_0000_0010_8000_00aa00389b71);
+
+/// A 16-byte `PCMWAVEFORMAT`: 44.1 kHz, 16-bit stereo, without `cbSize`.
+const PCMWAVEFORMAT_16_STEREO: [u8; 16] = [
+    0x01, 0x00, // wFormatTag = WAVE_FORMAT_PCM
+    0x02, 0x00, // nChannels = 2
+    0x44, 0xac, 0x00, 0x00, // nSamplesPerSec = 44100
+    0x10, 0xb1, 0x02, 0x00, // nAvgBytesPerSec = 176400
+    0x04, 0x00, // nBlockAlign = 4
+    0x10, 0x00, // wBitsPerSample = 16
+];
+
+/// A `WAVEFORMATEX`: 48 kHz, 16-bit stereo PCM.
+const PCM_16_STEREO: [u8; 18] = [
+    0x01, 0x00, // wFormatTag = WAVE_FORMAT_PCM
+    0x02, 0x00, // nChannels = 2
+    0x80, 0xbb, 0x00, 0x00, // nSamplesPerSec = 48000
+    0x00, 0xee, 0x02, 0x00, // nAvgBytesPerSec = 192000
+    0x04, 0x00, // nBlockAlign = 4
+    0x10, 0x00, // wBitsPerSample = 16
+    0x00, 0x00, // cbSize = 0
+];
+
+/// A `WAVEFORMATEXTENSIBLE`: 48 kHz, 32-bit float 5.1, as the audio engine
+/// reports for a 5.1 device.
+const FLOAT_5_1: [u8; 40] = [
+    0xfe, 0xff, // wFormatTag = WAVE_FORMAT_EXTENSIBLE
+    0x06, 0x00, // nChannels = 6
+    0x80, 0xbb, 0x00, 0x00, // nSamplesPerSec = 48000
+    0x00, 0x94, 0x11, 0x00, // nAvgBytesPerSec = 1152000
+    0x18, 0x00, // nBlockAlign = 24
+    0x20, 0x00, // wBitsPerSample = 32
+    0x16, 0x00, // cbSize = 22
+    0x20, 0x00, // wValidBitsPerSample = 32
+    0x0f, 0x06, 0x00, 0x00, // dwChannelMask = KSAUDIO_SPEAKER_5POINT1_SURROUND
+    0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, // SubFormat = IEEE_FLOAT
+    0x80, 0x00, 0x00, 0xaa, 0x00, 0x38, 0x9b, 0x71,
+];
+
+fn with_cb_size(bytes: &[u8], cb_size: u16) -> Vec<u8> {
+    let mut bytes = bytes.to_vec();
+    bytes[16..18].copy_from_slice(&cb_size.to_le_bytes());
+    bytes
+}
+
+#[test]
+fn pcmwaveformat_without_cb_size() {
+    let format = WaveFormat::from_bytes(&PCMWAVEFORMAT_16_STEREO).unwrap();
+    assert_eq!(
+        format,
+        WaveFormat {
+            channels: 2,
+            samples_per_sec: 44100,
+            avg_bytes_per_sec: 176_400,
+            block_align: 4,
+            bits_per_sample: 16,
+            encoding: WaveEncoding::Pcm { extra: Vec::new() },
+        }
+    );
+    // Serializing adds the missing `cbSize`.
+    let bytes = format.to_bytes().unwrap();
+    assert_eq!(bytes[..16], PCMWAVEFORMAT_16_STEREO);
+    assert_eq!(bytes[16..], [0, 0]);
+}
+
+#[test]
+fn pcm_16_bit_stereo() {
+    let format = WaveFormat::from_bytes(&PCM_16_STEREO).unwrap();
+    assert_eq!(format.channels, 2);
+    assert_eq!(format.samples_per_sec, 48000);
+    assert_eq!(format.avg_bytes_per_sec, 192_000);
+    assert_eq!(format.block_align, 4);
+    assert_eq!(format.bits_per_sample, 16);
+    assert_eq!(format.format_tag(), 1);
+    assert!(format.is_pcm());
+    assert!(!format.is_ieee_float());
+    assert_eq!(format.channel_mask(), None);
+    assert_eq!(format.to_bytes().unwrap(), PCM_16_STEREO);
+}
+
+#[test]
+fn float32_extensible_5_1() {
+    let format = WaveFormat::from_bytes(&FLOAT_5_1).unwrap();
+    assert_eq!(
+        format,
+        WaveFormat {
+            channels: 6,
+            samples_per_sec: 48000,
+            avg_bytes_per_sec: 1_152_000,
+            block_align: 24,
+            bits_per_sample: 32,
+            encoding: WaveEncoding::Extensible {
+                valid_bits_per_sample: 32,
+                channel_mask: ChannelMask::FIVE_POINT_ONE,
+                sub_format: IEEE_FLOAT,
+                extra: Vec::new(),
+            },
+        }
+    );
+    assert!(format.is_ieee_float());
+    assert!(!format.is_pcm());
+    assert_eq!(format.channel_mask().unwrap().channel_count(), 6);
+    assert_eq!(format.to_bytes().unwrap(), FLOAT_5_1);
+}
+
+#[test]
+fn extensible_keeps_reserved_mask_bits() {
+    // SPEAKER_ALL and a reserved bit that have no constant.
+    let mut bytes = FLOAT_5_1;
+    bytes[20..24].copy_from_slice(&0x8004_0003_u32.to_le_bytes());
+    let format = WaveFormat::from_bytes(&bytes).unwrap();
+    assert_eq!(format.channel_mask().unwrap().bits(), 0x8004_0003);
+    assert_eq!(format.to_bytes().unwrap(), bytes);
+}
+
+#[test]
+fn extra_bytes_are_kept() {
+    let mut bytes = with_cb_size(&PCM_16_STEREO, 2);
+    bytes.extend_from_slice(&[0xab, 0xcd]);
+    let format = WaveFormat::from_bytes(&bytes).unwrap();
+    assert_eq!(
+        format.encoding,
+        WaveEncoding::Pcm {
+            extra: vec![0xab, 0xcd],
+        }
+    );
+    assert_eq!(format.to_bytes().unwrap(), bytes);
+
+    // IEEE float with `cbSize` set.
+    bytes[0] = 0x03;
+    let format = WaveFormat::from_bytes(&bytes).unwrap();
+    assert_eq!(
+        format.encoding,
+        WaveEncoding::IeeeFloat {
+            extra: vec![0xab, 0xcd],
+        }
+    );
+    assert_eq!(format.to_bytes().unwrap(), bytes);
+
+    // Bytes after the extensible fields.
+    let mut bytes = with_cb_size(&FLOAT_5_1, 24);
+    bytes.extend_from_slice(&[1, 2]);
+    let format = WaveFormat::from_bytes(&bytes).unwrap();
+    assert!(matches!(
+        &format.encoding,
+        WaveEncoding::Extensible { extra, .. } if *extra == [1, 2]
+    ));
+    assert_eq!(format.to_bytes().unwrap(), bytes);
+
+    // An unknown tag, like WAVE_FORMAT_ADPCM.
+    let mut bytes = with_cb_size(&PCM_16_STEREO, 4);
+    bytes[0] = 0x02;
+    bytes.extend_from_slice(&[1, 2, 3, 4]);
+    let format = WaveFormat::from_bytes(&bytes).unwrap();
+    assert_eq!(
+        format.encoding,
+        WaveEncoding::Other {
+            format_tag: 2,
+            extra: vec![1, 2, 3, 4],
+        }
+    );
+    assert_eq!(format.to_bytes().unwrap(), bytes);
+}
+
+#[test]
+fn bytes_beyond_cb_size_are_ignored() {
+    let mut bytes = PCM_16_STEREO.to_vec();
+    bytes.extend_from_slice(&[0xff; 6]);
+    let format = WaveFormat::from_bytes(&bytes).unwrap();
+    assert_eq!(format.to_bytes().unwrap(), PCM_16_STEREO);
+}
+
+#[test]
+fn truncated_inputs_fail() {
+    for len in [0, 1, 2, 8, 15] {
+        assert_eq!(
+            WaveFormat::from_bytes(&PCM_16_STEREO[..len]),
+            Err(Error::Other(windows::Win32::Foundation::E_INVALIDARG)),
+            "{} bytes",
+            len
+        );
+    }
+    // Only PCM may omit `cbSize`.
+    let mut float = PCMWAVEFORMAT_16_STEREO;
+    float[0] = 0x03;
+    assert!(WaveFormat::from_bytes(&float).is_err());
+    // The extensible fields are cut off.
+    for len in [16, 18, 24, 39] {
+        assert!(WaveFormat::from_bytes(&FLOAT_5_1[..len]).is_err());
+    }
+}
+
+#[test]
+fn oversized_cb_size_fails() {
+    // `cbSize` declares more bytes than there are.
+    assert!(WaveFormat::from_bytes(&with_cb_size(&PCM_16_STEREO, 2)).is_err());
+    assert!(WaveFormat::from_bytes(&with_cb_size(&FLOAT_5_1, 23)).is_err());
+    assert!(WaveFormat::from_bytes(&with_cb_size(&FLOAT_5_1, 0xffff)).is_err());
+    // `cbSize` is too small for the extensible fields.
+    assert!(WaveFormat::from_bytes(&with_cb_size(&FLOAT_5_1, 10)).is_err());
+}
+
+#[test]
+fn extra_bytes_that_do_not_fit_cb_size_fail_to_serialize() {
+    let mut format = WaveFormat::from_bytes(&PCM_16_STEREO).unwrap();
+    format.encoding = WaveEncoding::Pcm {
+        extra: vec![0; 0x10000],
+    };
+    assert!(format.to_bytes().is_err());
+    format.encoding = WaveEncoding::Extensible {
+        valid_bits_per_sample: 16,
+        channel_mask: ChannelMask::STEREO,
+        sub_format: GUID::zeroed(),
+        extra: vec![0; 0xffff - 22 + 1],
+    };
+    assert!(format.to_bytes().is_err());
+}